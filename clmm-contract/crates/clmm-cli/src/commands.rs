@@ -0,0 +1,400 @@
+use std::{error::Error, fs, path::Path};
+
+use anchor_lang::prelude::Pubkey;
+use clmm_client::{
+    pda, sqrt_price_x64_to_price, tick_to_price, AccountView, ClmmAccount, SwapQuoter,
+    VaultSolvency,
+};
+use clmm_contract::state::{get_fee_growth_inside, TickArrayState, TickState};
+use clmm_math::{fee_math::calculate_latest_token_fees, liquidity_math::get_delta_amounts_signed};
+
+use crate::PdaCommand;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+fn read_account(path: &Path) -> Result<ClmmAccount> {
+    let (_, data) = clmm_client::read_account_dump(&fs::read_to_string(path)?)?;
+    Ok(ClmmAccount::decode(&data)?)
+}
+
+fn read_data(path: &Path) -> Result<Vec<u8>> {
+    let (_, data) = clmm_client::read_account_dump(&fs::read_to_string(path)?)?;
+    Ok(data)
+}
+
+fn read_tick_arrays(paths: &[impl AsRef<Path>]) -> Result<Vec<TickArrayState>> {
+    paths
+        .iter()
+        .map(|path| {
+            Ok(clmm_client::decode_tick_array_state(&read_data(
+                path.as_ref(),
+            )?)?)
+        })
+        .collect()
+}
+
+/// Raw token amount in UI units
+fn ui_amount(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(i32::from(decimals))
+}
+
+pub fn decode(path: &Path) -> Result<()> {
+    let account = read_account(path)?;
+    println!("{}", AccountView::from(&account).to_json()?);
+    Ok(())
+}
+
+pub fn pool(path: &Path) -> Result<()> {
+    let pool_state = clmm_client::decode_pool_state(&read_data(path)?)?;
+    let decimals_0 = pool_state.mint_decimals_0;
+    let decimals_1 = pool_state.mint_decimals_1;
+
+    println!("amm config:       {}", pool_state.amm_config);
+    println!(
+        "token 0:          {} ({} decimals)",
+        pool_state.token_mint_0, decimals_0
+    );
+    println!(
+        "token 1:          {} ({} decimals)",
+        pool_state.token_mint_1, decimals_1
+    );
+    println!("tick spacing:     {}", { pool_state.tick_spacing });
+    println!(
+        "price:            {} token 1 per token 0",
+        sqrt_price_x64_to_price(pool_state.sqrt_price_x64, decimals_0, decimals_1)
+    );
+    println!("sqrt price x64:   {}", { pool_state.sqrt_price_x64 });
+    println!("current tick:     {}", { pool_state.tick_current });
+    println!("liquidity:        {}", { pool_state.liquidity });
+    println!(
+        "total fees:       {} / {}",
+        ui_amount(pool_state.total_fees_token_0, decimals_0),
+        ui_amount(pool_state.total_fees_token_1, decimals_1)
+    );
+    println!(
+        "claimed fees:     {} / {}",
+        ui_amount(pool_state.total_fees_claimed_token_0, decimals_0),
+        ui_amount(pool_state.total_fees_claimed_token_1, decimals_1)
+    );
+    println!(
+        "protocol fees:    {} / {}",
+        ui_amount(pool_state.protocol_fees_token_0, decimals_0),
+        ui_amount(pool_state.protocol_fees_token_1, decimals_1)
+    );
+    println!(
+        "fund fees:        {} / {}",
+        ui_amount(pool_state.fund_fees_token_0, decimals_0),
+        ui_amount(pool_state.fund_fees_token_1, decimals_1)
+    );
+    Ok(())
+}
+
+pub fn ticks(paths: &[impl AsRef<Path>]) -> Result<()> {
+    let mut tick_arrays = read_tick_arrays(paths)?;
+    tick_arrays.sort_by_key(|tick_array| tick_array.start_tick_index);
+
+    println!(
+        "{:>12} {:>40} {:>40} {:>24}",
+        "tick", "liquidity_net", "liquidity_gross", "price (raw units)"
+    );
+    for tick_array in tick_arrays {
+        let ticks = tick_array.ticks;
+        for tick_state in ticks
+            .iter()
+            .filter(|tick_state| tick_state.is_initialized())
+        {
+            println!(
+                "{:>12} {:>40} {:>40} {:>24}",
+                { tick_state.tick },
+                { tick_state.liquidity_net },
+                { tick_state.liquidity_gross },
+                tick_to_price(tick_state.tick, 0, 0)
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn pda(command: PdaCommand) {
+    let (address, bump) = match command {
+        PdaCommand::AmmConfig { index } => pda::amm_config_address(index),
+        PdaCommand::Pool {
+            amm_config,
+            token_mint_0,
+            token_mint_1,
+        } => pda::pool_address(&amm_config, &token_mint_0, &token_mint_1),
+        PdaCommand::Vault { pool, token_mint } => pda::pool_vault_address(&pool, &token_mint),
+        PdaCommand::TickArray {
+            pool,
+            start_tick_index,
+        } => pda::tick_array_address(&pool, start_tick_index),
+        PdaCommand::BitmapExtension { pool } => pda::tick_array_bitmap_extension_address(&pool),
+        PdaCommand::ProtocolPosition {
+            pool,
+            tick_lower_index,
+            tick_upper_index,
+        } => pda::protocol_position_address(&pool, tick_lower_index, tick_upper_index),
+        PdaCommand::PersonalPosition { position_nft_mint } => {
+            pda::personal_position_address(&position_nft_mint)
+        }
+        PdaCommand::SupportMint { token_mint } => pda::support_mint_address(&token_mint),
+        PdaCommand::PoolCreator {
+            amm_config,
+            creator,
+        } => pda::pool_creator_address(&amm_config, &creator),
+        PdaCommand::LockedPosition { position_nft_mint } => {
+            pda::locked_position_address(&position_nft_mint)
+        }
+    };
+    println!("{} (bump {})", address, bump);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn quote(
+    pool_path: &Path,
+    amm_config_path: &Path,
+    bitmap_extension_path: Option<&Path>,
+    tick_array_paths: &[impl AsRef<Path>],
+    amount: u64,
+    zero_for_one: bool,
+    is_base_input: bool,
+    sqrt_price_limit_x64: Option<u128>,
+) -> Result<()> {
+    let pool_state = clmm_client::decode_pool_state(&read_data(pool_path)?)?;
+    let amm_config = clmm_client::decode_amm_config(&read_data(amm_config_path)?)?;
+    let bitmap_extension = match bitmap_extension_path {
+        Some(path) => Some(clmm_client::decode_tick_array_bitmap_extension(
+            &read_data(path)?,
+        )?),
+        None => None,
+    };
+    let tick_arrays = read_tick_arrays(tick_array_paths)?;
+
+    let quoter = SwapQuoter::new(
+        &pool_state,
+        &amm_config,
+        bitmap_extension.as_ref(),
+        tick_arrays,
+    )?;
+    let quote = quoter.quote(amount, sqrt_price_limit_x64, is_base_input, zero_for_one)?;
+
+    let (decimals_in, decimals_out) = if zero_for_one {
+        (pool_state.mint_decimals_0, pool_state.mint_decimals_1)
+    } else {
+        (pool_state.mint_decimals_1, pool_state.mint_decimals_0)
+    };
+    println!(
+        "amount in:        {} ({})",
+        quote.amount_in,
+        ui_amount(quote.amount_in, decimals_in)
+    );
+    println!(
+        "amount out:       {} ({})",
+        quote.amount_out,
+        ui_amount(quote.amount_out, decimals_out)
+    );
+    println!(
+        "fee:              {} ({})",
+        quote.fee_amount,
+        ui_amount(quote.fee_amount, decimals_in)
+    );
+    println!(
+        "price after:      {} token 1 per token 0",
+        sqrt_price_x64_to_price(
+            quote.sqrt_price_x64_after,
+            pool_state.mint_decimals_0,
+            pool_state.mint_decimals_1
+        )
+    );
+    println!("tick after:       {}", quote.tick_after);
+    println!("ticks crossed:    {}", quote.ticks_crossed);
+    println!("tick arrays:");
+    for (start_index, address) in quote
+        .tick_array_start_indexes
+        .iter()
+        .zip(quote.tick_arrays.iter())
+    {
+        println!("  {:>8} {}", start_index, address);
+    }
+    println!(
+        "bitmap extension: {}",
+        if quote.requires_bitmap_extension {
+            "required"
+        } else {
+            "not required"
+        }
+    );
+    Ok(())
+}
+
+pub fn position(
+    pool_path: &Path,
+    position_path: &Path,
+    tick_array_paths: &[impl AsRef<Path>],
+) -> Result<()> {
+    let pool_state = clmm_client::decode_pool_state(&read_data(pool_path)?)?;
+    let position = clmm_client::decode_personal_position(&read_data(position_path)?)?;
+    let decimals_0 = pool_state.mint_decimals_0;
+    let decimals_1 = pool_state.mint_decimals_1;
+
+    // amounts the position would withdraw now, rounded down like a liquidity decrease
+    let (amount_0, amount_1) = get_delta_amounts_signed(
+        pool_state.tick_current,
+        pool_state.sqrt_price_x64,
+        position.tick_lower_index,
+        position.tick_upper_index,
+        -i128::try_from(position.liquidity)?,
+    )?;
+
+    println!("pool:             {}", position.pool_id);
+    println!(
+        "range:            [{}, {}) = [{}, {}] token 1 per token 0",
+        position.tick_lower_index,
+        position.tick_upper_index,
+        tick_to_price(position.tick_lower_index, decimals_0, decimals_1),
+        tick_to_price(position.tick_upper_index, decimals_0, decimals_1)
+    );
+    println!("liquidity:        {}", position.liquidity);
+    println!(
+        "amount 0:         {} ({})",
+        amount_0,
+        ui_amount(amount_0, decimals_0)
+    );
+    println!(
+        "amount 1:         {} ({})",
+        amount_1,
+        ui_amount(amount_1, decimals_1)
+    );
+
+    let (fees_owed_0, fees_owed_1) = if tick_array_paths.is_empty() {
+        (position.token_fees_owed_0, position.token_fees_owed_1)
+    } else {
+        let tick_arrays = read_tick_arrays(tick_array_paths)?;
+        let tick_lower = find_tick(
+            &tick_arrays,
+            position.tick_lower_index,
+            pool_state.tick_spacing,
+        )?;
+        let tick_upper = find_tick(
+            &tick_arrays,
+            position.tick_upper_index,
+            pool_state.tick_spacing,
+        )?;
+        let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = get_fee_growth_inside(
+            &tick_lower,
+            &tick_upper,
+            pool_state.tick_current,
+            pool_state.fee_growth_global_0_x64,
+            pool_state.fee_growth_global_1_x64,
+        )?;
+        (
+            calculate_latest_token_fees(
+                position.token_fees_owed_0,
+                position.fee_growth_inside_0_last_x64,
+                fee_growth_inside_0_x64,
+                position.liquidity,
+            )?,
+            calculate_latest_token_fees(
+                position.token_fees_owed_1,
+                position.fee_growth_inside_1_last_x64,
+                fee_growth_inside_1_x64,
+                position.liquidity,
+            )?,
+        )
+    };
+    println!(
+        "fees owed 0:      {} ({})",
+        fees_owed_0,
+        ui_amount(fees_owed_0, decimals_0)
+    );
+    println!(
+        "fees owed 1:      {} ({})",
+        fees_owed_1,
+        ui_amount(fees_owed_1, decimals_1)
+    );
+    if tick_array_paths.is_empty() {
+        println!(
+            "(fees as of the last position update, pass --tick-arrays to include uncollected fees)"
+        );
+    }
+    Ok(())
+}
+
+pub fn solvency(
+    pool_path: &Path,
+    vault_paths: [&Path; 2],
+    tick_array_paths: &[impl AsRef<Path>],
+    position_paths: &[impl AsRef<Path>],
+) -> Result<()> {
+    let pool_state = clmm_client::decode_pool_state(&read_data(pool_path)?)?;
+    let vault_balances = (
+        read_vault_balance(vault_paths[0], &pool_state.token_vault_0)?,
+        read_vault_balance(vault_paths[1], &pool_state.token_vault_1)?,
+    );
+    let tick_arrays = read_tick_arrays(tick_array_paths)?;
+    let positions = position_paths
+        .iter()
+        .map(|path| {
+            Ok(clmm_client::decode_personal_position(&read_data(
+                path.as_ref(),
+            )?)?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let report = clmm_client::check_solvency(&pool_state, tick_arrays, &positions, vault_balances)?;
+
+    println!("positions:        {}", positions.len());
+    println!("liquidity:        {}", report.liquidity);
+    println!(
+        "in range:         {} (pool {})",
+        report.liquidity_in_range,
+        { pool_state.liquidity }
+    );
+    for (token, vault, decimals) in [
+        (0, report.token_0, pool_state.mint_decimals_0),
+        (1, report.token_1, pool_state.mint_decimals_1),
+    ] {
+        print_vault_solvency(token, &vault, decimals);
+    }
+
+    if report.liquidity_in_range != pool_state.liquidity {
+        return Err("liquidity in range does not match the pool, are positions missing?".into());
+    }
+    if !report.is_solvent() {
+        return Err("pool vaults do not cover what the pool owes".into());
+    }
+    Ok(())
+}
+
+fn print_vault_solvency(token: u8, vault: &VaultSolvency, decimals: u8) {
+    println!("token {}:", token);
+    println!("  vault balance:  {}", vault.vault_balance);
+    println!("  liquidity:      {}", vault.liquidity_amount);
+    println!("  fees owed:      {}", vault.fees_owed);
+    println!("  protocol fees:  {}", vault.protocol_fees);
+    println!("  fund fees:      {}", vault.fund_fees);
+    println!("  required:       {}", vault.required());
+    // negative on a shortfall
+    println!(
+        "  surplus:        {} ({})",
+        vault.surplus(),
+        vault.surplus() as f64 / 10f64.powi(i32::from(decimals))
+    );
+}
+
+fn read_vault_balance(path: &Path, vault: &Pubkey) -> Result<u64> {
+    let (address, amount) = clmm_client::read_token_account_dump(&fs::read_to_string(path)?)?;
+    if address != *vault {
+        return Err(format!("{} is not the pool vault {}", address, vault).into());
+    }
+    Ok(amount)
+}
+
+fn find_tick(tick_arrays: &[TickArrayState], tick: i32, tick_spacing: u16) -> Result<TickState> {
+    let start_index = TickArrayState::get_array_start_index(tick, tick_spacing);
+    let mut tick_array = *tick_arrays
+        .iter()
+        .find(|tick_array| tick_array.start_tick_index == start_index)
+        .ok_or_else(|| format!("missing tick array starting at {}", start_index))?;
+    Ok(*tick_array.get_tick_state_mut(tick, tick_spacing)?)
+}
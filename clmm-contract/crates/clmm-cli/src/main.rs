@@ -0,0 +1,179 @@
+//! Inspect clmm accounts and quote swaps offline
+//!
+//! Every command reads account dumps written by `solana account <address> --output json`,
+//! no RPC connection is needed.
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use clap::{Parser, Subcommand};
+
+mod commands;
+
+#[derive(Parser)]
+#[command(name = "clmm-cli", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print any program account as JSON
+    Decode { account: PathBuf },
+    /// Print price, current tick, active liquidity and fee counters of a pool
+    Pool { pool: PathBuf },
+    /// List the initialized ticks of tick arrays
+    Ticks {
+        #[arg(required = true)]
+        tick_arrays: Vec<PathBuf>,
+    },
+    /// Derive program addresses
+    Pda {
+        #[command(subcommand)]
+        pda: PdaCommand,
+    },
+    /// Quote a swap against the given pool snapshot
+    Quote {
+        #[arg(long)]
+        pool: PathBuf,
+        #[arg(long)]
+        amm_config: PathBuf,
+        #[arg(long)]
+        bitmap_extension: Option<PathBuf>,
+        #[arg(long, num_args = 1.., required = true)]
+        tick_arrays: Vec<PathBuf>,
+        /// Raw amount, the exact input unless `--exact-out` is set
+        #[arg(long)]
+        amount: u64,
+        /// Swap token_1 for token_0 instead of token_0 for token_1
+        #[arg(long)]
+        one_for_zero: bool,
+        /// Treat `--amount` as the exact output
+        #[arg(long)]
+        exact_out: bool,
+        #[arg(long)]
+        sqrt_price_limit_x64: Option<u128>,
+    },
+    /// Value a position at the current pool price, including uncollected fees when the
+    /// tick arrays holding its bounds are given
+    Position {
+        #[arg(long)]
+        pool: PathBuf,
+        #[arg(long)]
+        position: PathBuf,
+        #[arg(long, num_args = 1..)]
+        tick_arrays: Vec<PathBuf>,
+    },
+    /// Check that the vaults of a pool cover all its positions, owed fees and protocol and
+    /// fund fees, exiting with an error on a shortfall
+    Solvency {
+        #[arg(long)]
+        pool: PathBuf,
+        /// Token account dump of the token_0 vault
+        #[arg(long)]
+        vault_0: PathBuf,
+        /// Token account dump of the token_1 vault
+        #[arg(long)]
+        vault_1: PathBuf,
+        /// Every tick array holding a bound of a position with liquidity
+        #[arg(long, num_args = 1..)]
+        tick_arrays: Vec<PathBuf>,
+        /// Every personal position of the pool
+        #[arg(long, num_args = 1..)]
+        positions: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PdaCommand {
+    AmmConfig {
+        index: u16,
+    },
+    Pool {
+        amm_config: Pubkey,
+        token_mint_0: Pubkey,
+        token_mint_1: Pubkey,
+    },
+    Vault {
+        pool: Pubkey,
+        token_mint: Pubkey,
+    },
+    TickArray {
+        pool: Pubkey,
+        #[arg(allow_hyphen_values = true)]
+        start_tick_index: i32,
+    },
+    BitmapExtension {
+        pool: Pubkey,
+    },
+    ProtocolPosition {
+        pool: Pubkey,
+        #[arg(allow_hyphen_values = true)]
+        tick_lower_index: i32,
+        #[arg(allow_hyphen_values = true)]
+        tick_upper_index: i32,
+    },
+    PersonalPosition {
+        position_nft_mint: Pubkey,
+    },
+    SupportMint {
+        token_mint: Pubkey,
+    },
+    PoolCreator {
+        amm_config: Pubkey,
+        creator: Pubkey,
+    },
+    LockedPosition {
+        position_nft_mint: Pubkey,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Decode { account } => commands::decode(&account),
+        Command::Pool { pool } => commands::pool(&pool),
+        Command::Ticks { tick_arrays } => commands::ticks(&tick_arrays),
+        Command::Pda { pda } => {
+            commands::pda(pda);
+            Ok(())
+        }
+        Command::Quote {
+            pool,
+            amm_config,
+            bitmap_extension,
+            tick_arrays,
+            amount,
+            one_for_zero,
+            exact_out,
+            sqrt_price_limit_x64,
+        } => commands::quote(
+            &pool,
+            &amm_config,
+            bitmap_extension.as_deref(),
+            &tick_arrays,
+            amount,
+            !one_for_zero,
+            !exact_out,
+            sqrt_price_limit_x64,
+        ),
+        Command::Position {
+            pool,
+            position,
+            tick_arrays,
+        } => commands::position(&pool, &position, &tick_arrays),
+        Command::Solvency {
+            pool,
+            vault_0,
+            vault_1,
+            tick_arrays,
+            positions,
+        } => commands::solvency(&pool, [&vault_0, &vault_1], &tick_arrays, &positions),
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
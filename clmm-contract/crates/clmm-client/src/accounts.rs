@@ -0,0 +1,176 @@
+//! Decoding of raw program accounts
+//!
+//! Zero-copy accounts are copied out of the raw bytes without relying on alignment, so data
+//! read from RPC responses or dump files can be passed as is.
+
+use std::{mem::size_of, str::FromStr};
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, Discriminator};
+use anchor_spl::{
+    token::spl_token,
+    token_2022::spl_token_2022::{self, extension::StateWithExtensions},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clmm_contract::state::{
+    AmmConfig, LockedPositionState, PersonalPositionState, PoolCreator, PoolState,
+    ProtocolPositionState, SupportMint, TickArrayBitmapExtension, TickArrayState,
+};
+use serde::Deserialize;
+
+use crate::{ClientError, Result};
+
+/// Any account owned by the clmm program
+#[derive(Clone)]
+pub enum ClmmAccount {
+    AmmConfig(AmmConfig),
+    Pool(Box<PoolState>),
+    TickArray(Box<TickArrayState>),
+    TickArrayBitmapExtension(Box<TickArrayBitmapExtension>),
+    ProtocolPosition(ProtocolPositionState),
+    PersonalPosition(PersonalPositionState),
+    SupportMint(SupportMint),
+    PoolCreator(PoolCreator),
+    LockedPosition(LockedPositionState),
+}
+
+impl ClmmAccount {
+    /// Decode an account by its discriminator
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if has_discriminator::<PoolState>(data) {
+            decode_zero_copy(data).map(|account| ClmmAccount::Pool(Box::new(account)))
+        } else if has_discriminator::<TickArrayState>(data) {
+            decode_zero_copy(data).map(|account| ClmmAccount::TickArray(Box::new(account)))
+        } else if has_discriminator::<TickArrayBitmapExtension>(data) {
+            decode_zero_copy(data)
+                .map(|account| ClmmAccount::TickArrayBitmapExtension(Box::new(account)))
+        } else if has_discriminator::<AmmConfig>(data) {
+            decode_account(data).map(ClmmAccount::AmmConfig)
+        } else if has_discriminator::<ProtocolPositionState>(data) {
+            decode_account(data).map(ClmmAccount::ProtocolPosition)
+        } else if has_discriminator::<PersonalPositionState>(data) {
+            decode_account(data).map(ClmmAccount::PersonalPosition)
+        } else if has_discriminator::<SupportMint>(data) {
+            decode_account(data).map(ClmmAccount::SupportMint)
+        } else if has_discriminator::<PoolCreator>(data) {
+            decode_account(data).map(ClmmAccount::PoolCreator)
+        } else if has_discriminator::<LockedPositionState>(data) {
+            decode_account(data).map(ClmmAccount::LockedPosition)
+        } else {
+            Err(ClientError::UnknownAccountDiscriminator)
+        }
+    }
+}
+
+fn has_discriminator<T: Discriminator>(data: &[u8]) -> bool {
+    data.starts_with(T::DISCRIMINATOR)
+}
+
+fn check_discriminator<T: Discriminator>(data: &[u8]) -> Result<()> {
+    if !has_discriminator::<T>(data) {
+        return Err(ClientError::AccountDiscriminatorMismatch);
+    }
+    Ok(())
+}
+
+/// Decode a zero-copy account, ignoring any trailing bytes
+pub fn decode_zero_copy<T: Discriminator + bytemuck::Pod>(data: &[u8]) -> Result<T> {
+    check_discriminator::<T>(data)?;
+    let offset = T::DISCRIMINATOR.len();
+    let body = data
+        .get(offset..offset + size_of::<T>())
+        .ok_or(ClientError::AccountDataTooSmall)?;
+    Ok(bytemuck::pod_read_unaligned(body))
+}
+
+/// Decode a borsh serialized account
+pub fn decode_account<T: Discriminator + AccountDeserialize>(data: &[u8]) -> Result<T> {
+    check_discriminator::<T>(data)?;
+    Ok(T::try_deserialize(&mut &data[..])?)
+}
+
+pub fn decode_pool_state(data: &[u8]) -> Result<PoolState> {
+    decode_zero_copy(data)
+}
+
+pub fn decode_tick_array_state(data: &[u8]) -> Result<TickArrayState> {
+    decode_zero_copy(data)
+}
+
+pub fn decode_tick_array_bitmap_extension(data: &[u8]) -> Result<TickArrayBitmapExtension> {
+    decode_zero_copy(data)
+}
+
+pub fn decode_amm_config(data: &[u8]) -> Result<AmmConfig> {
+    decode_account(data)
+}
+
+pub fn decode_protocol_position(data: &[u8]) -> Result<ProtocolPositionState> {
+    decode_account(data)
+}
+
+pub fn decode_personal_position(data: &[u8]) -> Result<PersonalPositionState> {
+    decode_account(data)
+}
+
+pub fn decode_locked_position(data: &[u8]) -> Result<LockedPositionState> {
+    decode_account(data)
+}
+
+#[derive(Deserialize)]
+struct AccountDump {
+    pubkey: String,
+    account: AccountDumpData,
+}
+
+#[derive(Deserialize)]
+struct AccountDumpData {
+    /// `[data, encoding]`
+    data: (String, String),
+    owner: String,
+}
+
+/// Read the address, owner and data of an account from the output of
+/// `solana account --output json`
+fn parse_account_dump(json: &str) -> Result<(Pubkey, Pubkey, Vec<u8>)> {
+    let dump: AccountDump = serde_json::from_str(json)
+        .map_err(|err| ClientError::InvalidAccountDump(err.to_string()))?;
+    let pubkey = Pubkey::from_str(&dump.pubkey)
+        .map_err(|err| ClientError::InvalidAccountDump(err.to_string()))?;
+    let owner = Pubkey::from_str(&dump.account.owner)
+        .map_err(|err| ClientError::InvalidAccountDump(err.to_string()))?;
+
+    let (data, encoding) = dump.account.data;
+    if encoding != "base64" {
+        return Err(ClientError::InvalidAccountDump(format!(
+            "unsupported data encoding {}",
+            encoding
+        )));
+    }
+    let data = STANDARD
+        .decode(data)
+        .map_err(|err| ClientError::InvalidAccountDump(err.to_string()))?;
+    Ok((pubkey, owner, data))
+}
+
+/// Read the address and data of an account from the output of `solana account --output json`.
+/// Fails if the account is not owned by the clmm program.
+pub fn read_account_dump(json: &str) -> Result<(Pubkey, Vec<u8>)> {
+    let (pubkey, owner, data) = parse_account_dump(json)?;
+    if owner != clmm_contract::ID {
+        return Err(ClientError::AccountOwnerMismatch(pubkey));
+    }
+    Ok((pubkey, data))
+}
+
+/// Read the address and balance of an SPL Token or Token-2022 account, such as a pool vault,
+/// from the output of `solana account --output json`
+pub fn read_token_account_dump(json: &str) -> Result<(Pubkey, u64)> {
+    let (pubkey, owner, data) = parse_account_dump(json)?;
+    if owner != spl_token::ID && owner != spl_token_2022::ID {
+        return Err(ClientError::TokenAccountOwnerMismatch(pubkey));
+    }
+    // the token-2022 layout extends the token one, it unpacks both
+    let account = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)
+        .map_err(|err| ClientError::InvalidAccountDump(err.to_string()))?;
+    Ok((pubkey, account.base.amount))
+}
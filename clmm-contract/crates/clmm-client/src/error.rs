@@ -0,0 +1,84 @@
+use std::fmt;
+
+use anchor_lang::prelude::Pubkey;
+use clmm_math::MathError;
+
+pub type Result<T> = core::result::Result<T, ClientError>;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// Error returned by program code shared with the client
+    Program(anchor_lang::error::Error),
+    Math(MathError),
+    /// The swap needs a tick array that was not part of the snapshots
+    MissingTickArray(i32),
+    /// A tick array snapshot belongs to another pool
+    TickArrayPoolMismatch(i32),
+    /// A position snapshot, identified by its nft mint, belongs to another pool
+    PositionPoolMismatch(Pubkey),
+    ZeroAmountSpecified,
+    InvalidSqrtPriceLimit,
+    /// No initialized tick array is left in the swap direction
+    InsufficientLiquidity,
+    AccountDiscriminatorMismatch,
+    UnknownAccountDiscriminator,
+    AccountDataTooSmall,
+    AccountOwnerMismatch(Pubkey),
+    TokenAccountOwnerMismatch(Pubkey),
+    InvalidAccountDump(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Program(err) => write!(f, "Program error: {}", err),
+            ClientError::Math(err) => write!(f, "Math error: {}", err),
+            ClientError::MissingTickArray(start_index) => {
+                write!(f, "Missing tick array starting at {}", start_index)
+            }
+            ClientError::TickArrayPoolMismatch(start_index) => write!(
+                f,
+                "Tick array starting at {} does not belong to the pool",
+                start_index
+            ),
+            ClientError::PositionPoolMismatch(nft_mint) => write!(
+                f,
+                "Position of nft mint {} does not belong to the pool",
+                nft_mint
+            ),
+            ClientError::ZeroAmountSpecified => f.write_str("Amount specified must not be zero"),
+            ClientError::InvalidSqrtPriceLimit => f.write_str("Invalid sqrt price limit"),
+            ClientError::InsufficientLiquidity => {
+                f.write_str("Not enough liquidity in swap direction")
+            }
+            ClientError::AccountDiscriminatorMismatch => {
+                f.write_str("Account discriminator does not match the expected account")
+            }
+            ClientError::UnknownAccountDiscriminator => {
+                f.write_str("Account discriminator does not match any clmm account")
+            }
+            ClientError::AccountDataTooSmall => f.write_str("Account data is too small"),
+            ClientError::AccountOwnerMismatch(pubkey) => {
+                write!(f, "Account {} is not owned by the clmm program", pubkey)
+            }
+            ClientError::TokenAccountOwnerMismatch(pubkey) => {
+                write!(f, "Account {} is not owned by a token program", pubkey)
+            }
+            ClientError::InvalidAccountDump(err) => write!(f, "Invalid account dump: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<anchor_lang::error::Error> for ClientError {
+    fn from(err: anchor_lang::error::Error) -> Self {
+        ClientError::Program(err)
+    }
+}
+
+impl From<MathError> for ClientError {
+    fn from(err: MathError) -> Self {
+        ClientError::Math(err)
+    }
+}
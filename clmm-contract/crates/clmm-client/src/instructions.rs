@@ -0,0 +1,1198 @@
+//! Instruction builders of the clmm program
+//!
+//! Account metas are listed in the order of the matching account struct in the program's
+//! `context/` module, followed by the remaining accounts the handler reads.
+
+use anchor_lang::{
+    prelude::{borsh, AccountMeta, Pubkey},
+    solana_program::{hash::hash, instruction::Instruction, sysvar},
+    system_program, AnchorSerialize,
+};
+use anchor_spl::{associated_token, token, token_2022};
+use clmm_contract::state::{PersonalPositionState, PoolState, TickArrayState};
+
+use crate::pda;
+
+/// Anchor instruction data: the first 8 bytes of `sha256("global:<name>")` followed by the borsh encoded arguments
+fn instruction_data<T: AnchorSerialize>(name: &str, args: &T) -> Vec<u8> {
+    let mut data = hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec();
+    args.serialize(&mut data).unwrap();
+    data
+}
+
+fn instruction(name: &str, args: &impl AnchorSerialize, accounts: Vec<AccountMeta>) -> Instruction {
+    Instruction {
+        program_id: clmm_contract::ID,
+        accounts,
+        data: instruction_data(name, args),
+    }
+}
+
+/// The bitmap extension has to be passed when a tick array is outside of the pool's default bitmap
+fn tick_array_bitmap_extension_meta(
+    pool_id: &Pubkey,
+    pool_state: &PoolState,
+    tick_array_start_indexes: Vec<i32>,
+) -> Option<AccountMeta> {
+    pool_state
+        .is_overflow_default_tickarray_bitmap(tick_array_start_indexes)
+        .then(|| AccountMeta::new(pda::tick_array_bitmap_extension_address(pool_id).0, false))
+}
+
+#[derive(AnchorSerialize)]
+struct CreateAmmConfigArgs {
+    index: u16,
+    tick_spacing: u16,
+    protocol_fee_rate: u32,
+    trade_fee_rate: u32,
+    fund_fee_rate: u32,
+}
+
+pub fn create_amm_config(
+    owner: Pubkey,
+    index: u16,
+    tick_spacing: u16,
+    protocol_fee_rate: u32,
+    trade_fee_rate: u32,
+    fund_fee_rate: u32,
+) -> Instruction {
+    instruction(
+        "create_amm_config",
+        &CreateAmmConfigArgs {
+            index,
+            tick_spacing,
+            protocol_fee_rate,
+            trade_fee_rate,
+            fund_fee_rate,
+        },
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new(pda::amm_config_address(index).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct CreatePoolArgs {
+    sqrt_price_x64: u128,
+    open_time: u64,
+}
+
+/// `token_mint_0` must sort before `token_mint_1`. `create_pool_fee_receiver` is the receiver set
+/// in the amm config, required when it charges a create pool fee. `remaining_accounts` are the
+/// `SupportMint` accounts of Token-2022 mints whose extensions are not supported by default, plus
+/// the creator's `PoolCreator` account if the amm config has a creator allowlist.
+#[allow(clippy::too_many_arguments)]
+pub fn create_pool(
+    pool_creator: Pubkey,
+    amm_config: Pubkey,
+    create_pool_fee_receiver: Option<Pubkey>,
+    token_mint_0: Pubkey,
+    token_mint_1: Pubkey,
+    token_program_0: Pubkey,
+    token_program_1: Pubkey,
+    sqrt_price_x64: u128,
+    open_time: u64,
+    remaining_accounts: &[Pubkey],
+) -> Instruction {
+    let pool_id = pda::pool_address(&amm_config, &token_mint_0, &token_mint_1).0;
+    let mut accounts = vec![
+        AccountMeta::new(pool_creator, true),
+        AccountMeta::new_readonly(amm_config, false),
+        // anchor reads the program id in place of an optional account as left out
+        match create_pool_fee_receiver {
+            Some(create_pool_fee_receiver) => AccountMeta::new(create_pool_fee_receiver, false),
+            None => AccountMeta::new_readonly(clmm_contract::ID, false),
+        },
+        AccountMeta::new_readonly(token_mint_0, false),
+        AccountMeta::new_readonly(token_mint_1, false),
+        AccountMeta::new(pool_id, false),
+        AccountMeta::new(pda::pool_vault_address(&pool_id, &token_mint_0).0, false),
+        AccountMeta::new(pda::pool_vault_address(&pool_id, &token_mint_1).0, false),
+        AccountMeta::new(pda::tick_array_bitmap_extension_address(&pool_id).0, false),
+        AccountMeta::new_readonly(token_program_0, false),
+        AccountMeta::new_readonly(token_program_1, false),
+        AccountMeta::new_readonly(sysvar::rent::ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    accounts.extend(
+        remaining_accounts
+            .iter()
+            .map(|account| AccountMeta::new_readonly(*account, false)),
+    );
+
+    instruction(
+        "create_pool",
+        &CreatePoolArgs {
+            sqrt_price_x64,
+            open_time,
+        },
+        accounts,
+    )
+}
+
+/// Price band and deadline a liquidity change must execute within, the program aborts it when
+/// the pool price is outside the band or the deadline passed. `None` leaves a bound unchecked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PriceBand {
+    pub sqrt_price_min_x64: Option<u128>,
+    pub sqrt_price_max_x64: Option<u128>,
+    /// Unix timestamp after which the transaction fails
+    pub deadline: Option<i64>,
+}
+
+#[derive(AnchorSerialize)]
+struct OpenPositionWithToken22NftArgs {
+    liquidity: u128,
+    amount_0_max: u64,
+    amount_1_max: u64,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+    with_metadata: bool,
+    base_flag: Option<bool>,
+    sqrt_price_min_x64: Option<u128>,
+    sqrt_price_max_x64: Option<u128>,
+    deadline: Option<i64>,
+}
+
+/// Open a position whose NFT is a fresh Token-2022 mint. The NFT is minted to the
+/// associated token account of `position_nft_owner`.
+///
+/// With a `liquidity` of 0 the program derives it from the maximums: from the base token's
+/// maximum when `base_flag` is set, and from both maximums otherwise, in which case the position
+/// receives the most liquidity both pay for net of transfer fees. Zero liquidity and zero
+/// maximums are rejected.
+#[allow(clippy::too_many_arguments)]
+pub fn open_position_with_token22_nft(
+    payer: Pubkey,
+    position_nft_owner: Pubkey,
+    position_nft_mint: Pubkey,
+    pool_state: &PoolState,
+    token_account_0: Pubkey,
+    token_account_1: Pubkey,
+    liquidity: u128,
+    amount_0_max: u64,
+    amount_1_max: u64,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    with_metadata: bool,
+    base_flag: Option<bool>,
+    price_band: PriceBand,
+) -> Instruction {
+    let pool_id = pda::pool_address(
+        &pool_state.amm_config,
+        &pool_state.token_mint_0,
+        &pool_state.token_mint_1,
+    )
+    .0;
+    let tick_spacing = pool_state.tick_spacing;
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(tick_lower_index, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(tick_upper_index, tick_spacing);
+
+    let mut accounts = vec![
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(position_nft_owner, false),
+        AccountMeta::new(position_nft_mint, true),
+        AccountMeta::new(
+            associated_token::get_associated_token_address_with_program_id(
+                &position_nft_owner,
+                &position_nft_mint,
+                &token_2022::ID,
+            ),
+            false,
+        ),
+        AccountMeta::new(pool_id, false),
+        AccountMeta::new(
+            pda::protocol_position_address(&pool_id, tick_lower_index, tick_upper_index).0,
+            false,
+        ),
+        AccountMeta::new(pda::personal_position_address(&position_nft_mint).0, false),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_lower_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_upper_start_index).0,
+            false,
+        ),
+        AccountMeta::new(token_account_0, false),
+        AccountMeta::new(token_account_1, false),
+        AccountMeta::new(pool_state.token_vault_0, false),
+        AccountMeta::new(pool_state.token_vault_1, false),
+        AccountMeta::new_readonly(token::ID, false),
+        AccountMeta::new_readonly(sysvar::rent::ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(associated_token::ID, false),
+        AccountMeta::new_readonly(token_2022::ID, false),
+        AccountMeta::new_readonly(pool_state.token_mint_0, false),
+        AccountMeta::new_readonly(pool_state.token_mint_1, false),
+    ];
+    accounts.extend(tick_array_bitmap_extension_meta(
+        &pool_id,
+        pool_state,
+        vec![tick_array_lower_start_index, tick_array_upper_start_index],
+    ));
+
+    instruction(
+        "open_position_with_token22_nft",
+        &OpenPositionWithToken22NftArgs {
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            with_metadata,
+            base_flag,
+            sqrt_price_min_x64: price_band.sqrt_price_min_x64,
+            sqrt_price_max_x64: price_band.sqrt_price_max_x64,
+            deadline: price_band.deadline,
+        },
+        accounts,
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct IncreaseLiquidityV2Args {
+    liquidity: u128,
+    amount_0_max: u64,
+    amount_1_max: u64,
+    base_flag: Option<bool>,
+    sqrt_price_min_x64: Option<u128>,
+    sqrt_price_max_x64: Option<u128>,
+    deadline: Option<i64>,
+}
+
+/// Add liquidity to an existing position. `nft_account` is the token account holding the
+/// position NFT, `token_account_0/1` are the owner's accounts the tokens are paid from.
+/// `liquidity` of 0 is derived from the maximums as in [`open_position_with_token22_nft`].
+#[allow(clippy::too_many_arguments)]
+pub fn increase_liquidity_v2(
+    nft_owner: Pubkey,
+    nft_account: Pubkey,
+    pool_state: &PoolState,
+    personal_position: &PersonalPositionState,
+    token_account_0: Pubkey,
+    token_account_1: Pubkey,
+    liquidity: u128,
+    amount_0_max: u64,
+    amount_1_max: u64,
+    base_flag: Option<bool>,
+    price_band: PriceBand,
+) -> Instruction {
+    let pool_id = personal_position.pool_id;
+    let tick_spacing = pool_state.tick_spacing;
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_lower_index, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_upper_index, tick_spacing);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(nft_owner, true),
+        AccountMeta::new_readonly(nft_account, false),
+        AccountMeta::new(
+            pda::personal_position_address(&personal_position.nft_mint).0,
+            false,
+        ),
+        AccountMeta::new(pool_id, false),
+        AccountMeta::new(
+            pda::protocol_position_address(
+                &pool_id,
+                personal_position.tick_lower_index,
+                personal_position.tick_upper_index,
+            )
+            .0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_lower_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_upper_start_index).0,
+            false,
+        ),
+        AccountMeta::new(token_account_0, false),
+        AccountMeta::new(token_account_1, false),
+        AccountMeta::new(pool_state.token_vault_0, false),
+        AccountMeta::new(pool_state.token_vault_1, false),
+        AccountMeta::new_readonly(token::ID, false),
+        AccountMeta::new_readonly(token_2022::ID, false),
+        AccountMeta::new_readonly(pool_state.token_mint_0, false),
+        AccountMeta::new_readonly(pool_state.token_mint_1, false),
+    ];
+    accounts.extend(tick_array_bitmap_extension_meta(
+        &pool_id,
+        pool_state,
+        vec![tick_array_lower_start_index, tick_array_upper_start_index],
+    ));
+
+    instruction(
+        "increase_liquidity_v2",
+        &IncreaseLiquidityV2Args {
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            base_flag,
+            sqrt_price_min_x64: price_band.sqrt_price_min_x64,
+            sqrt_price_max_x64: price_band.sqrt_price_max_x64,
+            deadline: price_band.deadline,
+        },
+        accounts,
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct DecreaseLiquidityV2Args {
+    liquidity: u128,
+    amount_0_min: u64,
+    amount_1_min: u64,
+    sqrt_price_min_x64: Option<u128>,
+    sqrt_price_max_x64: Option<u128>,
+    deadline: Option<i64>,
+}
+
+/// Remove liquidity from a position and pay it out with all owed fees to
+/// `recipient_token_account_0/1`. The removed liquidity must be worth at least
+/// `amount_0_min`/`amount_1_min` after transfer fees.
+#[allow(clippy::too_many_arguments)]
+pub fn decrease_liquidity_v2(
+    nft_owner: Pubkey,
+    nft_account: Pubkey,
+    pool_state: &PoolState,
+    personal_position: &PersonalPositionState,
+    recipient_token_account_0: Pubkey,
+    recipient_token_account_1: Pubkey,
+    liquidity: u128,
+    amount_0_min: u64,
+    amount_1_min: u64,
+    price_band: PriceBand,
+) -> Instruction {
+    let pool_id = personal_position.pool_id;
+    let tick_spacing = pool_state.tick_spacing;
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_lower_index, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_upper_index, tick_spacing);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(nft_owner, true),
+        AccountMeta::new_readonly(nft_account, false),
+        AccountMeta::new(
+            pda::personal_position_address(&personal_position.nft_mint).0,
+            false,
+        ),
+        AccountMeta::new(pool_id, false),
+        AccountMeta::new(
+            pda::protocol_position_address(
+                &pool_id,
+                personal_position.tick_lower_index,
+                personal_position.tick_upper_index,
+            )
+            .0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_lower_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_upper_start_index).0,
+            false,
+        ),
+        AccountMeta::new(recipient_token_account_0, false),
+        AccountMeta::new(recipient_token_account_1, false),
+        AccountMeta::new(pool_state.token_vault_0, false),
+        AccountMeta::new(pool_state.token_vault_1, false),
+        AccountMeta::new_readonly(token::ID, false),
+        AccountMeta::new_readonly(token_2022::ID, false),
+        AccountMeta::new_readonly(pool_state.token_mint_0, false),
+        AccountMeta::new_readonly(pool_state.token_mint_1, false),
+    ];
+    accounts.extend(tick_array_bitmap_extension_meta(
+        &pool_id,
+        pool_state,
+        vec![tick_array_lower_start_index, tick_array_upper_start_index],
+    ));
+
+    instruction(
+        "decrease_liquidity_v2",
+        &DecreaseLiquidityV2Args {
+            liquidity,
+            amount_0_min,
+            amount_1_min,
+            sqrt_price_min_x64: price_band.sqrt_price_min_x64,
+            sqrt_price_max_x64: price_band.sqrt_price_max_x64,
+            deadline: price_band.deadline,
+        },
+        accounts,
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct ZapInArgs {
+    amount_in: u64,
+    swap_amount: u64,
+    zero_for_one: bool,
+    liquidity_min: u128,
+    sqrt_price_min_x64: Option<u128>,
+    sqrt_price_max_x64: Option<u128>,
+    deadline: Option<i64>,
+}
+
+/// Add liquidity to an existing position from token 0 when `zero_for_one`, from token 1
+/// otherwise. The program swaps `swap_amount` of the input through the pool and sends the swap
+/// output the position does not take back to `token_account_1` or `token_account_0`.
+///
+/// `swap_amount` and `swap_tick_arrays`, the `tick_array_start_indexes` of its swap, come from
+/// [`crate::SwapQuoter::quote_zap_in`], which also gives the liquidity to derive
+/// `liquidity_min` from. `price_band` bounds the pool price before the swap.
+/// [`zap_in_open_position`] opens a position from a single token.
+#[allow(clippy::too_many_arguments)]
+pub fn zap_in(
+    nft_owner: Pubkey,
+    nft_account: Pubkey,
+    pool_state: &PoolState,
+    personal_position: &PersonalPositionState,
+    token_account_0: Pubkey,
+    token_account_1: Pubkey,
+    zero_for_one: bool,
+    amount_in: u64,
+    swap_amount: u64,
+    liquidity_min: u128,
+    price_band: PriceBand,
+    swap_tick_arrays: &[i32],
+) -> Instruction {
+    let pool_id = personal_position.pool_id;
+    let tick_spacing = pool_state.tick_spacing;
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_lower_index, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_upper_index, tick_spacing);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(nft_owner, true),
+        AccountMeta::new_readonly(nft_account, false),
+        AccountMeta::new(
+            pda::personal_position_address(&personal_position.nft_mint).0,
+            false,
+        ),
+        AccountMeta::new_readonly(pool_state.amm_config, false),
+        AccountMeta::new(pool_id, false),
+        AccountMeta::new(
+            pda::protocol_position_address(
+                &pool_id,
+                personal_position.tick_lower_index,
+                personal_position.tick_upper_index,
+            )
+            .0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_lower_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_upper_start_index).0,
+            false,
+        ),
+        AccountMeta::new(token_account_0, false),
+        AccountMeta::new(token_account_1, false),
+        AccountMeta::new(pool_state.token_vault_0, false),
+        AccountMeta::new(pool_state.token_vault_1, false),
+        AccountMeta::new_readonly(token::ID, false),
+        AccountMeta::new_readonly(token_2022::ID, false),
+        AccountMeta::new_readonly(pool_state.token_mint_0, false),
+        AccountMeta::new_readonly(pool_state.token_mint_1, false),
+    ];
+    accounts.extend(zap_remaining_accounts(&pool_id, swap_tick_arrays));
+
+    instruction(
+        "zap_in",
+        &ZapInArgs {
+            amount_in,
+            swap_amount,
+            zero_for_one,
+            liquidity_min,
+            sqrt_price_min_x64: price_band.sqrt_price_min_x64,
+            sqrt_price_max_x64: price_band.sqrt_price_max_x64,
+            deadline: price_band.deadline,
+        },
+        accounts,
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct ZapInOpenPositionArgs {
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+    amount_in: u64,
+    swap_amount: u64,
+    zero_for_one: bool,
+    liquidity_min: u128,
+    with_metadata: bool,
+    sqrt_price_min_x64: Option<u128>,
+    sqrt_price_max_x64: Option<u128>,
+    deadline: Option<i64>,
+}
+
+/// Open a position from token 0 when `zero_for_one`, from token 1 otherwise, like
+/// [`zap_in`] adds to an existing one. The NFT is minted to the associated token account of
+/// `position_nft_owner` as in [`open_position_with_token22_nft`].
+#[allow(clippy::too_many_arguments)]
+pub fn zap_in_open_position(
+    payer: Pubkey,
+    position_nft_owner: Pubkey,
+    position_nft_mint: Pubkey,
+    pool_state: &PoolState,
+    token_account_0: Pubkey,
+    token_account_1: Pubkey,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    zero_for_one: bool,
+    amount_in: u64,
+    swap_amount: u64,
+    liquidity_min: u128,
+    with_metadata: bool,
+    price_band: PriceBand,
+    swap_tick_arrays: &[i32],
+) -> Instruction {
+    let pool_id = pda::pool_address(
+        &pool_state.amm_config,
+        &pool_state.token_mint_0,
+        &pool_state.token_mint_1,
+    )
+    .0;
+    let tick_spacing = pool_state.tick_spacing;
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(tick_lower_index, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(tick_upper_index, tick_spacing);
+
+    let mut accounts = vec![
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(position_nft_owner, false),
+        AccountMeta::new(position_nft_mint, true),
+        AccountMeta::new(
+            associated_token::get_associated_token_address_with_program_id(
+                &position_nft_owner,
+                &position_nft_mint,
+                &token_2022::ID,
+            ),
+            false,
+        ),
+        AccountMeta::new_readonly(pool_state.amm_config, false),
+        AccountMeta::new(pool_id, false),
+        AccountMeta::new(
+            pda::protocol_position_address(&pool_id, tick_lower_index, tick_upper_index).0,
+            false,
+        ),
+        AccountMeta::new(pda::personal_position_address(&position_nft_mint).0, false),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_lower_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_upper_start_index).0,
+            false,
+        ),
+        AccountMeta::new(token_account_0, false),
+        AccountMeta::new(token_account_1, false),
+        AccountMeta::new(pool_state.token_vault_0, false),
+        AccountMeta::new(pool_state.token_vault_1, false),
+        AccountMeta::new_readonly(token::ID, false),
+        AccountMeta::new_readonly(sysvar::rent::ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(associated_token::ID, false),
+        AccountMeta::new_readonly(token_2022::ID, false),
+        AccountMeta::new_readonly(pool_state.token_mint_0, false),
+        AccountMeta::new_readonly(pool_state.token_mint_1, false),
+    ];
+    accounts.extend(zap_remaining_accounts(&pool_id, swap_tick_arrays));
+
+    instruction(
+        "zap_in_open_position",
+        &ZapInOpenPositionArgs {
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            amount_in,
+            swap_amount,
+            zero_for_one,
+            liquidity_min,
+            with_metadata,
+            sqrt_price_min_x64: price_band.sqrt_price_min_x64,
+            sqrt_price_max_x64: price_band.sqrt_price_max_x64,
+            deadline: price_band.deadline,
+        },
+        accounts,
+    )
+}
+
+/// The remaining accounts of the zap instructions: the bitmap extension, which the swap may
+/// search even when its tick arrays are in the default bitmap, and the swap's tick arrays
+fn zap_remaining_accounts(pool_id: &Pubkey, swap_tick_arrays: &[i32]) -> Vec<AccountMeta> {
+    let mut accounts = vec![AccountMeta::new(
+        pda::tick_array_bitmap_extension_address(pool_id).0,
+        false,
+    )];
+    accounts.extend(swap_tick_arrays.iter().map(|start_index| {
+        AccountMeta::new(pda::tick_array_address(pool_id, *start_index).0, false)
+    }));
+    accounts
+}
+
+#[derive(AnchorSerialize)]
+struct RepositionArgs {
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+    swap_amount: u64,
+    zero_for_one: bool,
+    liquidity_min: u128,
+    with_metadata: bool,
+    sqrt_price_min_x64: Option<u128>,
+    sqrt_price_max_x64: Option<u128>,
+    deadline: Option<i64>,
+}
+
+/// Position that receives the liquidity of a [`reposition`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepositionTarget {
+    /// The position moves itself to the new range and keeps its nft
+    InPlace,
+    /// Another position of the nft owner that covers the new range
+    Position {
+        nft_mint: Pubkey,
+        nft_account: Pubkey,
+    },
+    /// A position opened with a fresh nft, the `nft_mint` keypair signs and the nft goes to the
+    /// associated token account of the nft owner
+    FreshNft {
+        nft_mint: Pubkey,
+        with_metadata: bool,
+    },
+}
+
+/// Move all liquidity of a position and its owed fees to `tick_lower_index..tick_upper_index`,
+/// swapping `swap_amount` of token 0 when `zero_for_one`, of token 1 otherwise, on the way.
+/// Tokens the new range does not take are sent to `token_account_0/1`.
+///
+/// `target` picks the position that receives the liquidity, see [`RepositionTarget`].
+#[allow(clippy::too_many_arguments)]
+pub fn reposition(
+    nft_owner: Pubkey,
+    nft_account: Pubkey,
+    pool_state: &PoolState,
+    personal_position: &PersonalPositionState,
+    target: RepositionTarget,
+    token_account_0: Pubkey,
+    token_account_1: Pubkey,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    zero_for_one: bool,
+    swap_amount: u64,
+    liquidity_min: u128,
+    price_band: PriceBand,
+    swap_tick_arrays: &[i32],
+) -> Instruction {
+    let pool_id = personal_position.pool_id;
+    let tick_spacing = pool_state.tick_spacing;
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_lower_index, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_upper_index, tick_spacing);
+    let new_tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(tick_lower_index, tick_spacing);
+    let new_tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(tick_upper_index, tick_spacing);
+
+    let mut accounts = vec![
+        AccountMeta::new(nft_owner, true),
+        AccountMeta::new_readonly(nft_account, false),
+        AccountMeta::new(
+            pda::personal_position_address(&personal_position.nft_mint).0,
+            false,
+        ),
+        AccountMeta::new_readonly(pool_state.amm_config, false),
+        AccountMeta::new(pool_id, false),
+        AccountMeta::new(
+            pda::protocol_position_address(
+                &pool_id,
+                personal_position.tick_lower_index,
+                personal_position.tick_upper_index,
+            )
+            .0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_lower_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_upper_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::protocol_position_address(&pool_id, tick_lower_index, tick_upper_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, new_tick_array_lower_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, new_tick_array_upper_start_index).0,
+            false,
+        ),
+    ];
+    // anchor reads the program id in place of an optional account as left out
+    let left_out = AccountMeta::new_readonly(clmm_contract::ID, false);
+    match target {
+        RepositionTarget::Position {
+            nft_mint,
+            nft_account,
+        } => accounts.extend([
+            AccountMeta::new_readonly(nft_account, false),
+            AccountMeta::new(pda::personal_position_address(&nft_mint).0, false),
+            left_out.clone(),
+            left_out.clone(),
+            left_out.clone(),
+        ]),
+        RepositionTarget::FreshNft { nft_mint, .. } => accounts.extend([
+            left_out.clone(),
+            left_out.clone(),
+            AccountMeta::new(nft_mint, true),
+            AccountMeta::new(
+                associated_token::get_associated_token_address_with_program_id(
+                    &nft_owner,
+                    &nft_mint,
+                    &token_2022::ID,
+                ),
+                false,
+            ),
+            AccountMeta::new(pda::personal_position_address(&nft_mint).0, false),
+        ]),
+        RepositionTarget::InPlace => accounts.extend(vec![left_out.clone(); 5]),
+    }
+    let (associated_token_program, rent) = match target {
+        RepositionTarget::FreshNft { .. } => (
+            AccountMeta::new_readonly(associated_token::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ),
+        _ => (left_out.clone(), left_out),
+    };
+    accounts.extend([
+        AccountMeta::new(token_account_0, false),
+        AccountMeta::new(token_account_1, false),
+        AccountMeta::new(pool_state.token_vault_0, false),
+        AccountMeta::new(pool_state.token_vault_1, false),
+        AccountMeta::new_readonly(token::ID, false),
+        AccountMeta::new_readonly(token_2022::ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        associated_token_program,
+        rent,
+        AccountMeta::new_readonly(pool_state.token_mint_0, false),
+        AccountMeta::new_readonly(pool_state.token_mint_1, false),
+        // the swap may search the bitmap extension even when its tick arrays are in the default
+        // bitmap, so it is always passed
+        AccountMeta::new(pda::tick_array_bitmap_extension_address(&pool_id).0, false),
+    ]);
+    accounts.extend(swap_tick_arrays.iter().map(|start_index| {
+        AccountMeta::new(pda::tick_array_address(&pool_id, *start_index).0, false)
+    }));
+
+    instruction(
+        "reposition",
+        &RepositionArgs {
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index: new_tick_array_lower_start_index,
+            tick_array_upper_start_index: new_tick_array_upper_start_index,
+            swap_amount,
+            zero_for_one,
+            liquidity_min,
+            with_metadata: matches!(
+                target,
+                RepositionTarget::FreshNft {
+                    with_metadata: true,
+                    ..
+                }
+            ),
+            sqrt_price_min_x64: price_band.sqrt_price_min_x64,
+            sqrt_price_max_x64: price_band.sqrt_price_max_x64,
+            deadline: price_band.deadline,
+        },
+        accounts,
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct SetPositionOperatorArgs {
+    operator: Pubkey,
+}
+
+/// Approve `operator` to compound the fees of the position held in `nft_account`, the
+/// default pubkey revokes the approval
+pub fn set_position_operator(
+    nft_owner: Pubkey,
+    nft_account: Pubkey,
+    personal_position: &PersonalPositionState,
+    operator: Pubkey,
+) -> Instruction {
+    instruction(
+        "set_position_operator",
+        &SetPositionOperatorArgs { operator },
+        vec![
+            AccountMeta::new_readonly(nft_owner, true),
+            AccountMeta::new_readonly(nft_account, false),
+            AccountMeta::new(
+                pda::personal_position_address(&personal_position.nft_mint).0,
+                false,
+            ),
+        ],
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct CompoundArgs {
+    sqrt_price_min_x64: Option<u128>,
+    sqrt_price_max_x64: Option<u128>,
+    deadline: Option<i64>,
+}
+
+/// Add the owed fees of a position to its liquidity. `authority` is the holder of
+/// `nft_account`, the account holding the position nft, or the operator that holder approved.
+pub fn compound(
+    authority: Pubkey,
+    nft_account: Pubkey,
+    pool_state: &PoolState,
+    personal_position: &PersonalPositionState,
+    price_band: PriceBand,
+) -> Instruction {
+    let pool_id = personal_position.pool_id;
+    let tick_spacing = pool_state.tick_spacing;
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_lower_index, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_upper_index, tick_spacing);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(authority, true),
+        AccountMeta::new_readonly(nft_account, false),
+        AccountMeta::new(
+            pda::personal_position_address(&personal_position.nft_mint).0,
+            false,
+        ),
+        AccountMeta::new(pool_id, false),
+        AccountMeta::new(
+            pda::protocol_position_address(
+                &pool_id,
+                personal_position.tick_lower_index,
+                personal_position.tick_upper_index,
+            )
+            .0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_lower_start_index).0,
+            false,
+        ),
+        AccountMeta::new(
+            pda::tick_array_address(&pool_id, tick_array_upper_start_index).0,
+            false,
+        ),
+    ];
+    accounts.extend(tick_array_bitmap_extension_meta(
+        &pool_id,
+        pool_state,
+        vec![tick_array_lower_start_index, tick_array_upper_start_index],
+    ));
+
+    instruction(
+        "compound",
+        &CompoundArgs {
+            sqrt_price_min_x64: price_band.sqrt_price_min_x64,
+            sqrt_price_max_x64: price_band.sqrt_price_max_x64,
+            deadline: price_band.deadline,
+        },
+        accounts,
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct LockPositionArgs {
+    unlock_time: Option<i64>,
+}
+
+/// Lock the position held in `nft_account` until `unlock_time`, for good when `None`. The
+/// new `fee_key_mint` keypair signs, its nft goes to the associated token account of
+/// `nft_owner`.
+pub fn lock_position(
+    nft_owner: Pubkey,
+    nft_account: Pubkey,
+    personal_position: &PersonalPositionState,
+    fee_key_mint: Pubkey,
+    unlock_time: Option<i64>,
+) -> Instruction {
+    let position_nft_mint = personal_position.nft_mint;
+    let locked_position = pda::locked_position_address(&position_nft_mint).0;
+    instruction(
+        "lock_position",
+        &LockPositionArgs { unlock_time },
+        vec![
+            AccountMeta::new(nft_owner, true),
+            AccountMeta::new(nft_account, false),
+            AccountMeta::new(pda::personal_position_address(&position_nft_mint).0, false),
+            AccountMeta::new_readonly(position_nft_mint, false),
+            AccountMeta::new(locked_position, false),
+            AccountMeta::new(
+                associated_token::get_associated_token_address_with_program_id(
+                    &locked_position,
+                    &position_nft_mint,
+                    &token_2022::ID,
+                ),
+                false,
+            ),
+            AccountMeta::new(fee_key_mint, true),
+            AccountMeta::new(
+                associated_token::get_associated_token_address_with_program_id(
+                    &nft_owner,
+                    &fee_key_mint,
+                    &token_2022::ID,
+                ),
+                false,
+            ),
+            AccountMeta::new_readonly(token_2022::ID, false),
+            AccountMeta::new_readonly(associated_token::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Take a position nft out of escrow after its unlock time. The fee key is burnt from the
+/// associated token account of `fee_key_owner`, which receives the position nft in its
+/// associated token account.
+pub fn unlock_position(
+    fee_key_owner: Pubkey,
+    position_nft_mint: Pubkey,
+    fee_key_mint: Pubkey,
+) -> Instruction {
+    let locked_position = pda::locked_position_address(&position_nft_mint).0;
+    instruction(
+        "unlock_position",
+        &(),
+        vec![
+            AccountMeta::new(fee_key_owner, true),
+            AccountMeta::new(
+                associated_token::get_associated_token_address_with_program_id(
+                    &fee_key_owner,
+                    &fee_key_mint,
+                    &token_2022::ID,
+                ),
+                false,
+            ),
+            AccountMeta::new(fee_key_mint, false),
+            AccountMeta::new(locked_position, false),
+            AccountMeta::new_readonly(position_nft_mint, false),
+            AccountMeta::new(
+                associated_token::get_associated_token_address_with_program_id(
+                    &locked_position,
+                    &position_nft_mint,
+                    &token_2022::ID,
+                ),
+                false,
+            ),
+            AccountMeta::new(
+                associated_token::get_associated_token_address_with_program_id(
+                    &fee_key_owner,
+                    &position_nft_mint,
+                    &token_2022::ID,
+                ),
+                false,
+            ),
+            AccountMeta::new_readonly(token_2022::ID, false),
+            AccountMeta::new_readonly(associated_token::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Pay the fees of a locked position to `recipient_token_account_0` and
+/// `recipient_token_account_1`, signed by the holder of the fee key in `fee_key_account`
+pub fn collect_locked_position_fees(
+    fee_key_owner: Pubkey,
+    fee_key_account: Pubkey,
+    pool_state: &PoolState,
+    personal_position: &PersonalPositionState,
+    recipient_token_account_0: Pubkey,
+    recipient_token_account_1: Pubkey,
+) -> Instruction {
+    let pool_id = personal_position.pool_id;
+    let tick_spacing = pool_state.tick_spacing;
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_lower_index, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(personal_position.tick_upper_index, tick_spacing);
+
+    instruction(
+        "collect_locked_position_fees",
+        &(),
+        vec![
+            AccountMeta::new_readonly(fee_key_owner, true),
+            AccountMeta::new_readonly(fee_key_account, false),
+            AccountMeta::new_readonly(
+                pda::locked_position_address(&personal_position.nft_mint).0,
+                false,
+            ),
+            AccountMeta::new(
+                pda::personal_position_address(&personal_position.nft_mint).0,
+                false,
+            ),
+            AccountMeta::new(pool_id, false),
+            AccountMeta::new(
+                pda::protocol_position_address(
+                    &pool_id,
+                    personal_position.tick_lower_index,
+                    personal_position.tick_upper_index,
+                )
+                .0,
+                false,
+            ),
+            AccountMeta::new(
+                pda::tick_array_address(&pool_id, tick_array_lower_start_index).0,
+                false,
+            ),
+            AccountMeta::new(
+                pda::tick_array_address(&pool_id, tick_array_upper_start_index).0,
+                false,
+            ),
+            AccountMeta::new(recipient_token_account_0, false),
+            AccountMeta::new(recipient_token_account_1, false),
+            AccountMeta::new(pool_state.token_vault_0, false),
+            AccountMeta::new(pool_state.token_vault_1, false),
+            AccountMeta::new_readonly(token::ID, false),
+            AccountMeta::new_readonly(token_2022::ID, false),
+            AccountMeta::new_readonly(pool_state.token_mint_0, false),
+            AccountMeta::new_readonly(pool_state.token_mint_1, false),
+        ],
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct SetCreatorAllowlistArgs {
+    enabled: bool,
+}
+
+/// Switch an amm config between permissionless and allowlisted pool creation
+pub fn set_creator_allowlist(owner: Pubkey, amm_config: Pubkey, enabled: bool) -> Instruction {
+    instruction(
+        "set_creator_allowlist",
+        &SetCreatorAllowlistArgs { enabled },
+        vec![
+            AccountMeta::new_readonly(owner, true),
+            AccountMeta::new(amm_config, false),
+        ],
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct SetCreatePoolFeeArgs {
+    create_pool_fee: u64,
+    create_pool_fee_receiver: Pubkey,
+}
+
+/// Charge every new pool of `amm_config` `create_pool_fee` lamports, paid to
+/// `create_pool_fee_receiver`
+pub fn set_create_pool_fee(
+    owner: Pubkey,
+    amm_config: Pubkey,
+    create_pool_fee: u64,
+    create_pool_fee_receiver: Pubkey,
+) -> Instruction {
+    instruction(
+        "set_create_pool_fee",
+        &SetCreatePoolFeeArgs {
+            create_pool_fee,
+            create_pool_fee_receiver,
+        },
+        vec![
+            AccountMeta::new_readonly(owner, true),
+            AccountMeta::new(amm_config, false),
+        ],
+    )
+}
+
+#[derive(AnchorSerialize)]
+struct AddPoolCreatorArgs {
+    creator: Pubkey,
+}
+
+/// Allow `creator` to create pools with `amm_config`, the admin `owner` pays the rent
+pub fn add_pool_creator(owner: Pubkey, amm_config: Pubkey, creator: Pubkey) -> Instruction {
+    instruction(
+        "add_pool_creator",
+        &AddPoolCreatorArgs { creator },
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new_readonly(amm_config, false),
+            AccountMeta::new(pda::pool_creator_address(&amm_config, &creator).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Remove `creator` from the allowlist of `amm_config`, refunding the rent to `owner`
+pub fn remove_pool_creator(owner: Pubkey, amm_config: Pubkey, creator: Pubkey) -> Instruction {
+    instruction(
+        "remove_pool_creator",
+        &(),
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new(pda::pool_creator_address(&amm_config, &creator).0, false),
+        ],
+    )
+}
+
+fn migrate(name: &str, owner: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        name,
+        &(),
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new(account, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Upgrade an amm config to the current account layout, a no-op if it already is. `owner`
+/// is the program admin and pays the rent of the larger account.
+pub fn migrate_amm_config(owner: Pubkey, amm_config: Pubkey) -> Instruction {
+    migrate("migrate_amm_config", owner, amm_config)
+}
+
+/// Upgrade a pool to the current account layout, see [`migrate_amm_config`]
+pub fn migrate_pool(owner: Pubkey, pool_state: Pubkey) -> Instruction {
+    migrate("migrate_pool", owner, pool_state)
+}
+
+/// Upgrade a protocol position to the current account layout, see [`migrate_amm_config`]
+pub fn migrate_protocol_position(owner: Pubkey, protocol_position: Pubkey) -> Instruction {
+    migrate("migrate_protocol_position", owner, protocol_position)
+}
+
+/// Upgrade a personal position to the current account layout, see [`migrate_amm_config`]
+pub fn migrate_personal_position(owner: Pubkey, personal_position: Pubkey) -> Instruction {
+    migrate("migrate_personal_position", owner, personal_position)
+}
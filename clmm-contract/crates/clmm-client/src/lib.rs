@@ -0,0 +1,20 @@
+//! Off-chain client for the clmm program
+//!
+//! Derives the program's PDAs, builds its instructions, decodes its accounts
+//! into serializable views, simulates swaps and checks pool solvency on account
+//! snapshots, so integrators do not have to reimplement seeds, layouts or account
+//! ordering.
+
+pub mod accounts;
+pub mod error;
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+pub mod solvency;
+pub mod views;
+
+pub use accounts::*;
+pub use error::*;
+pub use quote::*;
+pub use solvency::*;
+pub use views::*;
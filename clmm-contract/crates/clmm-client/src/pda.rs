@@ -0,0 +1,116 @@
+//! Program derived addresses of the clmm program
+//!
+//! Every helper returns the address together with its bump, like `Pubkey::find_program_address`.
+
+use anchor_lang::prelude::Pubkey;
+use clmm_contract::state::{
+    AMM_CONFIG_SEED, LOCKED_POSITION_SEED, PERSONAL_POSITION_SEED, POOL_CREATOR_SEED, POOL_SEED,
+    POOL_TICK_ARRAY_BITMAP_SEED, POOL_VAULT_SEED, PROTOCOL_POSITION_SEED, SUPPORT_MINT_SEED,
+    TICK_ARRAY_SEED,
+};
+
+pub fn amm_config_address(index: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[AMM_CONFIG_SEED.as_bytes(), &index.to_le_bytes()],
+        &clmm_contract::ID,
+    )
+}
+
+/// `token_mint_0` must sort before `token_mint_1`
+pub fn pool_address(
+    amm_config: &Pubkey,
+    token_mint_0: &Pubkey,
+    token_mint_1: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            POOL_SEED.as_bytes(),
+            amm_config.as_ref(),
+            token_mint_0.as_ref(),
+            token_mint_1.as_ref(),
+        ],
+        &clmm_contract::ID,
+    )
+}
+
+pub fn pool_vault_address(pool: &Pubkey, token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            POOL_VAULT_SEED.as_bytes(),
+            pool.as_ref(),
+            token_mint.as_ref(),
+        ],
+        &clmm_contract::ID,
+    )
+}
+
+/// Tick arrays are keyed by the big-endian bytes of their start index
+pub fn tick_array_address(pool: &Pubkey, start_tick_index: i32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool.as_ref(),
+            &start_tick_index.to_be_bytes(),
+        ],
+        &clmm_contract::ID,
+    )
+}
+
+pub fn tick_array_bitmap_extension_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool.as_ref()],
+        &clmm_contract::ID,
+    )
+}
+
+/// Protocol positions are keyed by the big-endian bytes of their tick range
+pub fn protocol_position_address(
+    pool: &Pubkey,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PROTOCOL_POSITION_SEED.as_bytes(),
+            pool.as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes(),
+        ],
+        &clmm_contract::ID,
+    )
+}
+
+pub fn personal_position_address(position_nft_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PERSONAL_POSITION_SEED.as_bytes(),
+            position_nft_mint.as_ref(),
+        ],
+        &clmm_contract::ID,
+    )
+}
+
+pub fn support_mint_address(token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SUPPORT_MINT_SEED.as_bytes(), token_mint.as_ref()],
+        &clmm_contract::ID,
+    )
+}
+
+pub fn pool_creator_address(amm_config: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            POOL_CREATOR_SEED.as_bytes(),
+            amm_config.as_ref(),
+            creator.as_ref(),
+        ],
+        &clmm_contract::ID,
+    )
+}
+
+pub fn locked_position_address(position_nft_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[LOCKED_POSITION_SEED.as_bytes(), position_nft_mint.as_ref()],
+        &clmm_contract::ID,
+    )
+}
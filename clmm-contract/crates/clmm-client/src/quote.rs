@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+
+use anchor_lang::prelude::Pubkey;
+use clmm_contract::{
+    state::{AmmConfig, PoolState, TickArrayBitmapExtension, TickArrayState},
+    ErrorCode,
+};
+use clmm_math::{
+    liquidity_math,
+    swap_math::{
+        compute_swap, compute_zap_swap_amount, SwapFeeRates, SwapOutcome, SwapState, SwapTicks,
+    },
+    tick_math::{self, MAX_SQRT_PRICE_X64, MIN_SQRT_PRICE_X64},
+};
+
+use crate::{pda, ClientError, Result};
+
+/// Result of a simulated swap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapQuote {
+    /// Input token taken from the user, fee included
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Part of `amount_in` charged as trade fee
+    pub fee_amount: u64,
+    pub sqrt_price_x64_after: u128,
+    pub tick_after: i32,
+    pub liquidity_after: u128,
+    /// Number of initialized ticks crossed
+    pub ticks_crossed: u32,
+    /// Start index of every tick array the swap walks through, in swap order
+    pub tick_array_start_indexes: Vec<i32>,
+    /// Tick array accounts the swap must be sent with, in swap order
+    pub tick_arrays: Vec<Pubkey>,
+    /// Whether the tick array bitmap extension account must be sent as well
+    pub requires_bitmap_extension: bool,
+}
+
+/// Result of a simulated zap into a range, see [`SwapQuoter::quote_zap_in`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZapQuote {
+    /// Part of the input swapped to the other token
+    pub swap_amount: u64,
+    /// The swap, `None` when none of the input needs to be swapped
+    pub swap: Option<SwapQuote>,
+    /// Liquidity the position receives
+    pub liquidity: u128,
+    /// Amounts the position takes, rounded up like the program does
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+/// Simulates swaps against a snapshot of a pool
+///
+/// The quoter runs the program's swap loop, [`compute_swap`]: it starts at the first initialized tick
+/// array in the swap direction, walks initialized ticks and crosses them, and looks up the next tick
+/// array through the default bitmap and the bitmap extension.
+pub struct SwapQuoter<'a> {
+    pool_id: Pubkey,
+    pool_state: &'a PoolState,
+    amm_config: &'a AmmConfig,
+    tickarray_bitmap_extension: Option<&'a TickArrayBitmapExtension>,
+    tick_arrays: BTreeMap<i32, TickArrayState>,
+}
+
+impl<'a> SwapQuoter<'a> {
+    pub fn new(
+        pool_state: &'a PoolState,
+        amm_config: &'a AmmConfig,
+        tickarray_bitmap_extension: Option<&'a TickArrayBitmapExtension>,
+        tick_arrays: impl IntoIterator<Item = TickArrayState>,
+    ) -> Result<Self> {
+        let pool_id = pda::pool_address(
+            &pool_state.amm_config,
+            &pool_state.token_mint_0,
+            &pool_state.token_mint_1,
+        )
+        .0;
+
+        let mut tick_array_states = BTreeMap::new();
+        for tick_array in tick_arrays {
+            let start_tick_index = tick_array.start_tick_index;
+            if tick_array.pool_id != pool_id {
+                return Err(ClientError::TickArrayPoolMismatch(start_tick_index));
+            }
+            tick_array_states.insert(start_tick_index, tick_array);
+        }
+
+        Ok(Self {
+            pool_id,
+            pool_state,
+            amm_config,
+            tickarray_bitmap_extension,
+            tick_arrays: tick_array_states,
+        })
+    }
+
+    /// Quote a swap of `amount_specified`, which is the exact input when `is_base_input` and the exact
+    /// output otherwise. Without a `sqrt_price_limit_x64` the swap may move the price to the end of the range.
+    pub fn quote(
+        &self,
+        amount_specified: u64,
+        sqrt_price_limit_x64: Option<u128>,
+        is_base_input: bool,
+        zero_for_one: bool,
+    ) -> Result<SwapQuote> {
+        let (outcome, tick_array_start_indexes) = self.swap(
+            amount_specified,
+            sqrt_price_limit_x64,
+            is_base_input,
+            zero_for_one,
+        )?;
+
+        let tick_arrays = tick_array_start_indexes
+            .iter()
+            .map(|start_index| pda::tick_array_address(&self.pool_id, *start_index).0)
+            .collect();
+        let requires_bitmap_extension = self
+            .pool_state
+            .is_overflow_default_tickarray_bitmap(tick_array_start_indexes.clone());
+
+        Ok(SwapQuote {
+            amount_in: outcome.amount_in,
+            amount_out: outcome.amount_out,
+            fee_amount: outcome.fee_amount,
+            sqrt_price_x64_after: outcome.state.sqrt_price_x64,
+            tick_after: outcome.state.tick,
+            liquidity_after: outcome.state.liquidity,
+            ticks_crossed: outcome.ticks_crossed,
+            tick_array_start_indexes,
+            tick_arrays,
+            requires_bitmap_extension,
+        })
+    }
+
+    /// Run the swap of [`Self::quote`], returning its outcome and the start index of every tick
+    /// array it walks through
+    fn swap(
+        &self,
+        amount_specified: u64,
+        sqrt_price_limit_x64: Option<u128>,
+        is_base_input: bool,
+        zero_for_one: bool,
+    ) -> Result<(SwapOutcome, Vec<i32>)> {
+        if amount_specified == 0 {
+            return Err(ClientError::ZeroAmountSpecified);
+        }
+        let pool_state = self.pool_state;
+        let sqrt_price_x64 = pool_state.sqrt_price_x64;
+
+        let sqrt_price_limit_x64 = sqrt_price_limit_x64.unwrap_or(if zero_for_one {
+            MIN_SQRT_PRICE_X64 + 1
+        } else {
+            MAX_SQRT_PRICE_X64 - 1
+        });
+        let is_valid_limit = if zero_for_one {
+            sqrt_price_limit_x64 < sqrt_price_x64 && sqrt_price_limit_x64 > MIN_SQRT_PRICE_X64
+        } else {
+            sqrt_price_limit_x64 > sqrt_price_x64 && sqrt_price_limit_x64 < MAX_SQRT_PRICE_X64
+        };
+        if !is_valid_limit {
+            return Err(ClientError::InvalidSqrtPriceLimit);
+        }
+
+        let (is_match_pool_current_tick_array, first_tick_array_start_index) = pool_state
+            .get_first_initialized_tick_array(self.tickarray_bitmap_extension, zero_for_one)
+            .map_err(|err| {
+                if err == ErrorCode::InsufficientLiquidityForDirection.into() {
+                    ClientError::InsufficientLiquidity
+                } else {
+                    ClientError::Program(err)
+                }
+            })?;
+        let mut ticks = QuoterTicks {
+            quoter: self,
+            tick_array: self.tick_array(first_tick_array_start_index)?,
+            is_match_pool_current_tick_array,
+            tick_array_start_indexes: vec![first_tick_array_start_index],
+        };
+
+        // fees are split like the program does, only the total is quoted
+        let fee_rates = SwapFeeRates {
+            trade_fee_rate: self.amm_config.trade_fee_rate,
+            protocol_fee_rate: self.amm_config.protocol_fee_rate,
+            fund_fee_rate: self.amm_config.fund_fee_rate,
+        };
+        let state = SwapState {
+            sqrt_price_x64: pool_state.sqrt_price_x64,
+            tick: pool_state.tick_current,
+            liquidity: pool_state.liquidity,
+            fee_growth_global_x64: 0,
+        };
+        let outcome = compute_swap(
+            &mut ticks,
+            state,
+            fee_rates,
+            amount_specified,
+            sqrt_price_limit_x64,
+            zero_for_one,
+            is_base_input,
+        )?;
+        Ok((outcome, ticks.tick_array_start_indexes))
+    }
+
+    /// Quote a zap of `amount` of token 0 when `zero_for_one`, of token 1 otherwise, into the
+    /// range from `tick_lower_index` to `tick_upper_index`. `amount` is what the pool receives,
+    /// after the transfer fee of the input mint.
+    ///
+    /// Finds the swap amount with [`compute_zap_swap_amount`]. The program swaps the quoted
+    /// `swap_amount` as it is, the search costs too many compute units to run on chain.
+    pub fn quote_zap_in(
+        &self,
+        amount: u64,
+        zero_for_one: bool,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+    ) -> Result<ZapQuote> {
+        let sqrt_price_lower_x64 = tick_math::get_sqrt_price_at_tick(tick_lower_index)?;
+        let sqrt_price_upper_x64 = tick_math::get_sqrt_price_at_tick(tick_upper_index)?;
+
+        // amounts of token 0 and 1 and the price after swapping `swap_amount`
+        let zap = |swap_amount: u64| -> Result<(Option<SwapQuote>, u64, u64, u128, i32)> {
+            if swap_amount == 0 {
+                let (amount_0, amount_1) = if zero_for_one {
+                    (amount, 0)
+                } else {
+                    (0, amount)
+                };
+                let pool_state = self.pool_state;
+                return Ok((
+                    None,
+                    amount_0,
+                    amount_1,
+                    pool_state.sqrt_price_x64,
+                    pool_state.tick_current,
+                ));
+            }
+            let swap = self.quote(swap_amount, None, true, zero_for_one)?;
+            let amount_input_left = amount - swap.amount_in;
+            let (amount_0, amount_1) = if zero_for_one {
+                (amount_input_left, swap.amount_out)
+            } else {
+                (swap.amount_out, amount_input_left)
+            };
+            let (sqrt_price_x64, tick) = (swap.sqrt_price_x64_after, swap.tick_after);
+            Ok((Some(swap), amount_0, amount_1, sqrt_price_x64, tick))
+        };
+        let swap_amount = compute_zap_swap_amount(
+            |swap_amount| match self.swap(swap_amount, None, true, zero_for_one) {
+                Ok((outcome, _)) => Ok(Some(outcome)),
+                // the pool cannot take that much, so less has to be swapped
+                Err(ClientError::InsufficientLiquidity) => Ok(None),
+                Err(err) => Err(err),
+            },
+            self.pool_state.sqrt_price_x64,
+            amount,
+            zero_for_one,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+        )?;
+
+        let (swap, amount_0, amount_1, sqrt_price_x64, tick) = zap(swap_amount)?;
+        let liquidity = liquidity_math::get_liquidity_from_amounts(
+            sqrt_price_x64,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            amount_0,
+            amount_1,
+        )?;
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            tick,
+            sqrt_price_x64,
+            tick_lower_index,
+            tick_upper_index,
+            liquidity as i128,
+        )?;
+        Ok(ZapQuote {
+            swap_amount,
+            swap,
+            liquidity,
+            amount_0,
+            amount_1,
+        })
+    }
+
+    fn tick_array(&self, start_index: i32) -> Result<TickArrayState> {
+        self.tick_arrays
+            .get(&start_index)
+            .copied()
+            .ok_or(ClientError::MissingTickArray(start_index))
+    }
+}
+
+/// The initialized ticks of the quoter's tick arrays, in the order the program walks them
+struct QuoterTicks<'q, 'a> {
+    quoter: &'q SwapQuoter<'a>,
+    tick_array: TickArrayState,
+    is_match_pool_current_tick_array: bool,
+    tick_array_start_indexes: Vec<i32>,
+}
+
+impl SwapTicks for QuoterTicks<'_, '_> {
+    type Error = ClientError;
+
+    fn next_initialized_tick(&mut self, tick: i32, zero_for_one: bool) -> Result<Option<i32>> {
+        let pool_state = self.quoter.pool_state;
+        if let Some(tick_state) =
+            self.tick_array
+                .next_initialized_tick(tick, pool_state.tick_spacing, zero_for_one)?
+        {
+            return Ok(Some(tick_state.tick));
+        }
+        // the pool's current tick array is not initialized, so the swap starts from the first
+        // initialized tick of the next one
+        if !self.is_match_pool_current_tick_array {
+            self.is_match_pool_current_tick_array = true;
+            return Ok(Some(
+                self.tick_array.first_initialized_tick(zero_for_one)?.tick,
+            ));
+        }
+
+        let next_start_index = pool_state
+            .next_initialized_tick_array_start_index(
+                self.quoter.tickarray_bitmap_extension,
+                self.tick_array.start_tick_index,
+                zero_for_one,
+            )?
+            .ok_or(ClientError::InsufficientLiquidity)?;
+        self.tick_array = self.quoter.tick_array(next_start_index)?;
+        self.tick_array_start_indexes.push(next_start_index);
+        Ok(Some(
+            self.tick_array.first_initialized_tick(zero_for_one)?.tick,
+        ))
+    }
+
+    fn cross_tick(
+        &mut self,
+        tick: i32,
+        _fee_growth_global_x64: u128,
+        _zero_for_one: bool,
+    ) -> Result<i128> {
+        let tick_spacing = self.quoter.pool_state.tick_spacing;
+        Ok(self
+            .tick_array
+            .get_tick_state_mut(tick, tick_spacing)?
+            .liquidity_net)
+    }
+}
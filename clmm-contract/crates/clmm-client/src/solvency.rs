@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+
+use clmm_contract::state::{
+    get_fee_growth_inside, PersonalPositionState, PoolState, TickArrayState, TickState,
+};
+use clmm_math::{
+    fee_math::calculate_latest_token_fees, liquidity_math::get_delta_amounts_signed, MathError,
+};
+
+use crate::{pda, ClientError, Result};
+
+/// What one vault of a pool must hold, against what it holds, in raw token units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VaultSolvency {
+    pub vault_balance: u64,
+    /// Amount all positions would withdraw at the current price, rounded down like a
+    /// liquidity decrease
+    pub liquidity_amount: u128,
+    /// Fees owed to positions, including fees accrued since their last update
+    pub fees_owed: u128,
+    pub protocol_fees: u64,
+    pub fund_fees: u64,
+}
+
+impl VaultSolvency {
+    pub fn required(&self) -> u128 {
+        self.liquidity_amount
+            + self.fees_owed
+            + u128::from(self.protocol_fees)
+            + u128::from(self.fund_fees)
+    }
+
+    /// Balance above the required amount, negative on a shortfall
+    pub fn surplus(&self) -> i128 {
+        i128::from(self.vault_balance) - self.required() as i128
+    }
+
+    pub fn is_solvent(&self) -> bool {
+        self.surplus() >= 0
+    }
+}
+
+/// Result of [`check_solvency`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolvencyReport {
+    pub token_0: VaultSolvency,
+    pub token_1: VaultSolvency,
+    /// Total liquidity of the positions checked
+    pub liquidity: u128,
+    /// Liquidity of the positions in range at the current tick, equal to the pool's liquidity
+    /// when every position was checked
+    pub liquidity_in_range: u128,
+}
+
+impl SolvencyReport {
+    pub fn is_solvent(&self) -> bool {
+        self.token_0.is_solvent() && self.token_1.is_solvent()
+    }
+}
+
+/// Recomputes what the vaults of a pool must hold to pay out every position, owed fee and
+/// protocol and fund fee at the current price, and compares it with `vault_balances`
+///
+/// `positions` must be all personal positions of the pool for the report to be meaningful.
+/// `tick_arrays` must hold the bounds of every position with liquidity, they are needed for
+/// the fees accrued since the position was last updated.
+pub fn check_solvency(
+    pool_state: &PoolState,
+    tick_arrays: impl IntoIterator<Item = TickArrayState>,
+    positions: &[PersonalPositionState],
+    vault_balances: (u64, u64),
+) -> Result<SolvencyReport> {
+    let pool_id = pda::pool_address(
+        &pool_state.amm_config,
+        &pool_state.token_mint_0,
+        &pool_state.token_mint_1,
+    )
+    .0;
+    let mut tick_array_states = BTreeMap::new();
+    for tick_array in tick_arrays {
+        let start_tick_index = tick_array.start_tick_index;
+        if tick_array.pool_id != pool_id {
+            return Err(ClientError::TickArrayPoolMismatch(start_tick_index));
+        }
+        tick_array_states.insert(start_tick_index, tick_array);
+    }
+
+    let mut report = SolvencyReport {
+        token_0: VaultSolvency {
+            vault_balance: vault_balances.0,
+            liquidity_amount: 0,
+            fees_owed: 0,
+            protocol_fees: pool_state.protocol_fees_token_0,
+            fund_fees: pool_state.fund_fees_token_0,
+        },
+        token_1: VaultSolvency {
+            vault_balance: vault_balances.1,
+            liquidity_amount: 0,
+            fees_owed: 0,
+            protocol_fees: pool_state.protocol_fees_token_1,
+            fund_fees: pool_state.fund_fees_token_1,
+        },
+        liquidity: 0,
+        liquidity_in_range: 0,
+    };
+    for position in positions {
+        if position.pool_id != pool_id {
+            return Err(ClientError::PositionPoolMismatch(position.nft_mint));
+        }
+        let (amount_0, amount_1) = get_delta_amounts_signed(
+            pool_state.tick_current,
+            pool_state.sqrt_price_x64,
+            position.tick_lower_index,
+            position.tick_upper_index,
+            -i128::try_from(position.liquidity).map_err(|_| MathError::LiquidityOverflow)?,
+        )?;
+        let (fees_owed_0, fees_owed_1) =
+            latest_fees_owed(pool_state, &tick_array_states, position)?;
+
+        report.token_0.liquidity_amount += u128::from(amount_0);
+        report.token_1.liquidity_amount += u128::from(amount_1);
+        report.token_0.fees_owed += u128::from(fees_owed_0);
+        report.token_1.fees_owed += u128::from(fees_owed_1);
+        report.liquidity += position.liquidity;
+        if position.tick_lower_index <= pool_state.tick_current
+            && pool_state.tick_current < position.tick_upper_index
+        {
+            report.liquidity_in_range += position.liquidity;
+        }
+    }
+    Ok(report)
+}
+
+/// Fees owed to `position` as of the current fee growth of the pool
+fn latest_fees_owed(
+    pool_state: &PoolState,
+    tick_arrays: &BTreeMap<i32, TickArrayState>,
+    position: &PersonalPositionState,
+) -> Result<(u64, u64)> {
+    // the bounds of an empty position may have been cleared, nothing accrues to it anyway
+    if position.liquidity == 0 {
+        return Ok((position.token_fees_owed_0, position.token_fees_owed_1));
+    }
+    let tick_lower = find_tick(
+        tick_arrays,
+        position.tick_lower_index,
+        pool_state.tick_spacing,
+    )?;
+    let tick_upper = find_tick(
+        tick_arrays,
+        position.tick_upper_index,
+        pool_state.tick_spacing,
+    )?;
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = get_fee_growth_inside(
+        &tick_lower,
+        &tick_upper,
+        pool_state.tick_current,
+        pool_state.fee_growth_global_0_x64,
+        pool_state.fee_growth_global_1_x64,
+    )?;
+    Ok((
+        calculate_latest_token_fees(
+            position.token_fees_owed_0,
+            position.fee_growth_inside_0_last_x64,
+            fee_growth_inside_0_x64,
+            position.liquidity,
+        )?,
+        calculate_latest_token_fees(
+            position.token_fees_owed_1,
+            position.fee_growth_inside_1_last_x64,
+            fee_growth_inside_1_x64,
+            position.liquidity,
+        )?,
+    ))
+}
+
+fn find_tick(
+    tick_arrays: &BTreeMap<i32, TickArrayState>,
+    tick: i32,
+    tick_spacing: u16,
+) -> Result<TickState> {
+    let start_index = TickArrayState::get_array_start_index(tick, tick_spacing);
+    let tick_array = tick_arrays
+        .get(&start_index)
+        .ok_or(ClientError::MissingTickArray(start_index))?;
+    let offset = tick_array.get_tick_offset_in_array(tick, tick_spacing)?;
+    Ok(tick_array.ticks[offset])
+}
@@ -0,0 +1,383 @@
+//! Owned, serde serializable views of program accounts
+//!
+//! 128-bit integers are rendered as decimal strings, JSON numbers lose precision past 2^53.
+
+use clmm_contract::{
+    helpers::TICK_ARRAY_BITMAP_SIZE,
+    state::{
+        AmmConfig, LockedPositionState, PersonalPositionState, PoolCreator, PoolState,
+        ProtocolPositionState, SupportMint, TickArrayBitmapExtension, TickArrayState, TickState,
+        FEE_RATE_DENOMINATOR_VALUE,
+    },
+};
+use clmm_math::fixed_point_64;
+use serde::{Deserialize, Serialize};
+
+use crate::ClmmAccount;
+
+/// Price of token_0 in token_1, in UI units
+pub fn sqrt_price_x64_to_price(
+    sqrt_price_x64: u128,
+    mint_decimals_0: u8,
+    mint_decimals_1: u8,
+) -> f64 {
+    let sqrt_price = sqrt_price_x64 as f64 / fixed_point_64::Q64 as f64;
+    sqrt_price * sqrt_price * decimals_factor(mint_decimals_0, mint_decimals_1)
+}
+
+/// Price of token_0 in token_1 at `tick`, in UI units
+pub fn tick_to_price(tick: i32, mint_decimals_0: u8, mint_decimals_1: u8) -> f64 {
+    1.0001f64.powi(tick) * decimals_factor(mint_decimals_0, mint_decimals_1)
+}
+
+fn decimals_factor(mint_decimals_0: u8, mint_decimals_1: u8) -> f64 {
+    10f64.powi(i32::from(mint_decimals_0) - i32::from(mint_decimals_1))
+}
+
+pub fn fee_rate_to_percent(fee_rate: u32) -> f64 {
+    f64::from(fee_rate) * 100.0 / f64::from(FEE_RATE_DENOMINATOR_VALUE)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AmmConfigView {
+    pub bump: u8,
+    pub index: u16,
+    pub owner: String,
+    pub tick_spacing: u16,
+    pub trade_fee_rate: u32,
+    /// Trade fee as a percent of the swap amount
+    pub trade_fee_percent: f64,
+    pub protocol_fee_rate: u32,
+    /// Protocol fee as a percent of the trade fee
+    pub protocol_fee_percent: f64,
+    pub fund_fee_rate: u32,
+    /// Fund fee as a percent of the trade fee
+    pub fund_fee_percent: f64,
+    pub fund_owner: String,
+    /// Whether only allowlisted creators may create pools
+    pub creator_allowlist: bool,
+    /// Lamports charged for creating a pool
+    pub create_pool_fee: u64,
+    pub create_pool_fee_receiver: String,
+}
+
+impl From<&AmmConfig> for AmmConfigView {
+    fn from(amm_config: &AmmConfig) -> Self {
+        Self {
+            bump: amm_config.bump,
+            index: amm_config.index,
+            owner: amm_config.owner.to_string(),
+            tick_spacing: amm_config.tick_spacing,
+            trade_fee_rate: amm_config.trade_fee_rate,
+            trade_fee_percent: fee_rate_to_percent(amm_config.trade_fee_rate),
+            protocol_fee_rate: amm_config.protocol_fee_rate,
+            protocol_fee_percent: fee_rate_to_percent(amm_config.protocol_fee_rate),
+            fund_fee_rate: amm_config.fund_fee_rate,
+            fund_fee_percent: fee_rate_to_percent(amm_config.fund_fee_rate),
+            fund_owner: amm_config.fund_owner.to_string(),
+            creator_allowlist: amm_config.creator_allowlist,
+            create_pool_fee: amm_config.create_pool_fee,
+            create_pool_fee_receiver: amm_config.create_pool_fee_receiver.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolStateView {
+    pub amm_config: String,
+    pub owner: String,
+    pub token_mint_0: String,
+    pub token_mint_1: String,
+    pub token_vault_0: String,
+    pub token_vault_1: String,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    pub liquidity: String,
+    pub sqrt_price_x64: String,
+    /// Price of token_0 in token_1, in UI units
+    pub price: f64,
+    pub tick_current: i32,
+    pub fee_growth_global_0_x64: String,
+    pub fee_growth_global_1_x64: String,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+    pub total_fees_token_0: u64,
+    pub total_fees_claimed_token_0: u64,
+    pub total_fees_token_1: u64,
+    pub total_fees_claimed_token_1: u64,
+    pub swap_in_amount_token_0: String,
+    pub swap_out_amount_token_0: String,
+    pub swap_in_amount_token_1: String,
+    pub swap_out_amount_token_1: String,
+    /// Start indexes of the initialized tick arrays tracked by the default bitmap
+    pub initialized_tick_array_start_indexes: Vec<i32>,
+    pub open_time: u64,
+    pub recent_epoch: u64,
+}
+
+impl From<&PoolState> for PoolStateView {
+    fn from(pool_state: &PoolState) -> Self {
+        let tick_array_bitmap = pool_state.tick_array_bitmap;
+        let tick_count = TickArrayState::tick_count(pool_state.tick_spacing);
+        let initialized_tick_array_start_indexes = (0..tick_array_bitmap.len() * 64)
+            .filter(|bit| tick_array_bitmap[bit / 64] & (1 << (bit % 64)) != 0)
+            .map(|bit| (bit as i32 - TICK_ARRAY_BITMAP_SIZE) * tick_count)
+            .collect();
+
+        Self {
+            amm_config: pool_state.amm_config.to_string(),
+            owner: pool_state.owner.to_string(),
+            token_mint_0: pool_state.token_mint_0.to_string(),
+            token_mint_1: pool_state.token_mint_1.to_string(),
+            token_vault_0: pool_state.token_vault_0.to_string(),
+            token_vault_1: pool_state.token_vault_1.to_string(),
+            mint_decimals_0: pool_state.mint_decimals_0,
+            mint_decimals_1: pool_state.mint_decimals_1,
+            tick_spacing: pool_state.tick_spacing,
+            liquidity: { pool_state.liquidity }.to_string(),
+            sqrt_price_x64: { pool_state.sqrt_price_x64 }.to_string(),
+            price: sqrt_price_x64_to_price(
+                pool_state.sqrt_price_x64,
+                pool_state.mint_decimals_0,
+                pool_state.mint_decimals_1,
+            ),
+            tick_current: pool_state.tick_current,
+            fee_growth_global_0_x64: { pool_state.fee_growth_global_0_x64 }.to_string(),
+            fee_growth_global_1_x64: { pool_state.fee_growth_global_1_x64 }.to_string(),
+            protocol_fees_token_0: pool_state.protocol_fees_token_0,
+            protocol_fees_token_1: pool_state.protocol_fees_token_1,
+            fund_fees_token_0: pool_state.fund_fees_token_0,
+            fund_fees_token_1: pool_state.fund_fees_token_1,
+            total_fees_token_0: pool_state.total_fees_token_0,
+            total_fees_claimed_token_0: pool_state.total_fees_claimed_token_0,
+            total_fees_token_1: pool_state.total_fees_token_1,
+            total_fees_claimed_token_1: pool_state.total_fees_claimed_token_1,
+            swap_in_amount_token_0: { pool_state.swap_in_amount_token_0 }.to_string(),
+            swap_out_amount_token_0: { pool_state.swap_out_amount_token_0 }.to_string(),
+            swap_in_amount_token_1: { pool_state.swap_in_amount_token_1 }.to_string(),
+            swap_out_amount_token_1: { pool_state.swap_out_amount_token_1 }.to_string(),
+            initialized_tick_array_start_indexes,
+            open_time: pool_state.open_time,
+            recent_epoch: pool_state.recent_epoch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickView {
+    pub tick: i32,
+    /// Price of token_0 in token_1 at this tick, in raw token units
+    pub price: f64,
+    pub liquidity_net: String,
+    pub liquidity_gross: String,
+    pub fee_growth_outside_0_x64: String,
+    pub fee_growth_outside_1_x64: String,
+}
+
+impl From<&TickState> for TickView {
+    fn from(tick_state: &TickState) -> Self {
+        Self {
+            tick: tick_state.tick,
+            price: tick_to_price(tick_state.tick, 0, 0),
+            liquidity_net: { tick_state.liquidity_net }.to_string(),
+            liquidity_gross: { tick_state.liquidity_gross }.to_string(),
+            fee_growth_outside_0_x64: { tick_state.fee_growth_outside_0_x64 }.to_string(),
+            fee_growth_outside_1_x64: { tick_state.fee_growth_outside_1_x64 }.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickArrayView {
+    pub pool_id: String,
+    pub start_tick_index: i32,
+    pub initialized_tick_count: u8,
+    pub recent_epoch: u64,
+    /// Initialized ticks only
+    pub ticks: Vec<TickView>,
+}
+
+impl From<&TickArrayState> for TickArrayView {
+    fn from(tick_array: &TickArrayState) -> Self {
+        let ticks = tick_array.ticks;
+        Self {
+            pool_id: tick_array.pool_id.to_string(),
+            start_tick_index: tick_array.start_tick_index,
+            initialized_tick_count: tick_array.initialized_tick_count,
+            recent_epoch: tick_array.recent_epoch,
+            ticks: ticks
+                .iter()
+                .filter(|tick_state| tick_state.is_initialized())
+                .map(TickView::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickArrayBitmapExtensionView {
+    pub pool_id: String,
+    pub positive_tick_array_bitmap: Vec<[u64; 8]>,
+    pub negative_tick_array_bitmap: Vec<[u64; 8]>,
+}
+
+impl From<&TickArrayBitmapExtension> for TickArrayBitmapExtensionView {
+    fn from(extension: &TickArrayBitmapExtension) -> Self {
+        Self {
+            pool_id: extension.pool_id.to_string(),
+            positive_tick_array_bitmap: { extension.positive_tick_array_bitmap }.to_vec(),
+            negative_tick_array_bitmap: { extension.negative_tick_array_bitmap }.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolPositionView {
+    pub pool_id: String,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: String,
+    pub fee_growth_inside_0_last_x64: String,
+    pub fee_growth_inside_1_last_x64: String,
+    pub token_fees_owed_0: u64,
+    pub token_fees_owed_1: u64,
+    pub recent_epoch: u64,
+}
+
+impl From<&ProtocolPositionState> for ProtocolPositionView {
+    fn from(position: &ProtocolPositionState) -> Self {
+        Self {
+            pool_id: position.pool_id.to_string(),
+            tick_lower_index: position.tick_lower_index,
+            tick_upper_index: position.tick_upper_index,
+            liquidity: position.liquidity.to_string(),
+            fee_growth_inside_0_last_x64: position.fee_growth_inside_0_last_x64.to_string(),
+            fee_growth_inside_1_last_x64: position.fee_growth_inside_1_last_x64.to_string(),
+            token_fees_owed_0: position.token_fees_owed_0,
+            token_fees_owed_1: position.token_fees_owed_1,
+            recent_epoch: position.recent_epoch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersonalPositionView {
+    pub nft_mint: String,
+    pub pool_id: String,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: String,
+    pub fee_growth_inside_0_last_x64: String,
+    pub fee_growth_inside_1_last_x64: String,
+    pub token_fees_owed_0: u64,
+    pub token_fees_owed_1: u64,
+    /// Account allowed to compound the fees of the position, the default pubkey if none
+    pub operator: String,
+}
+
+impl From<&PersonalPositionState> for PersonalPositionView {
+    fn from(position: &PersonalPositionState) -> Self {
+        Self {
+            nft_mint: position.nft_mint.to_string(),
+            pool_id: position.pool_id.to_string(),
+            tick_lower_index: position.tick_lower_index,
+            tick_upper_index: position.tick_upper_index,
+            liquidity: position.liquidity.to_string(),
+            fee_growth_inside_0_last_x64: position.fee_growth_inside_0_last_x64.to_string(),
+            fee_growth_inside_1_last_x64: position.fee_growth_inside_1_last_x64.to_string(),
+            token_fees_owed_0: position.token_fees_owed_0,
+            token_fees_owed_1: position.token_fees_owed_1,
+            operator: position.operator.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SupportMintView {
+    pub mint: String,
+}
+
+impl From<&SupportMint> for SupportMintView {
+    fn from(support_mint: &SupportMint) -> Self {
+        Self {
+            mint: support_mint.mint.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolCreatorView {
+    pub amm_config: String,
+    pub creator: String,
+}
+
+impl From<&PoolCreator> for PoolCreatorView {
+    fn from(pool_creator: &PoolCreator) -> Self {
+        Self {
+            amm_config: pool_creator.amm_config.to_string(),
+            creator: pool_creator.creator.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPositionView {
+    pub position_nft_mint: String,
+    pub pool_id: String,
+    pub fee_key_mint: String,
+    /// Unix time from which the position can be unlocked, `None` if it is locked for good
+    pub unlock_time: Option<i64>,
+}
+
+impl From<&LockedPositionState> for LockedPositionView {
+    fn from(locked_position: &LockedPositionState) -> Self {
+        Self {
+            position_nft_mint: locked_position.position_nft_mint.to_string(),
+            pool_id: locked_position.pool_id.to_string(),
+            fee_key_mint: locked_position.fee_key_mint.to_string(),
+            unlock_time: locked_position.unlock_time,
+        }
+    }
+}
+
+/// View of any program account, tagged with its account type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "account_type")]
+pub enum AccountView {
+    AmmConfig(AmmConfigView),
+    Pool(Box<PoolStateView>),
+    TickArray(TickArrayView),
+    TickArrayBitmapExtension(TickArrayBitmapExtensionView),
+    ProtocolPosition(ProtocolPositionView),
+    PersonalPosition(PersonalPositionView),
+    SupportMint(SupportMintView),
+    PoolCreator(PoolCreatorView),
+    LockedPosition(LockedPositionView),
+}
+
+impl From<&ClmmAccount> for AccountView {
+    fn from(account: &ClmmAccount) -> Self {
+        match account {
+            ClmmAccount::AmmConfig(account) => AccountView::AmmConfig(account.into()),
+            ClmmAccount::Pool(account) => AccountView::Pool(Box::new(account.as_ref().into())),
+            ClmmAccount::TickArray(account) => AccountView::TickArray(account.as_ref().into()),
+            ClmmAccount::TickArrayBitmapExtension(account) => {
+                AccountView::TickArrayBitmapExtension(account.as_ref().into())
+            }
+            ClmmAccount::ProtocolPosition(account) => AccountView::ProtocolPosition(account.into()),
+            ClmmAccount::PersonalPosition(account) => AccountView::PersonalPosition(account.into()),
+            ClmmAccount::SupportMint(account) => AccountView::SupportMint(account.into()),
+            ClmmAccount::PoolCreator(account) => AccountView::PoolCreator(account.into()),
+            ClmmAccount::LockedPosition(account) => AccountView::LockedPosition(account.into()),
+        }
+    }
+}
+
+impl AccountView {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
@@ -0,0 +1,170 @@
+use anchor_lang::prelude::Pubkey;
+use bytemuck::Zeroable;
+use clmm_client::{check_solvency, pda, ClientError};
+use clmm_contract::state::{PersonalPositionState, PoolState, TickArrayState};
+use clmm_math::{
+    fixed_point_64::Q64, liquidity_math::get_delta_amounts_signed,
+    tick_math::get_sqrt_price_at_tick,
+};
+
+const TICK_SPACING: u16 = 10;
+const LIQUIDITY: u128 = 1_000_000_000;
+
+/// Pool at tick 0 with `LIQUIDITY` in [-600, 600)
+fn pool_state() -> (Pubkey, PoolState) {
+    let mut pool_state = PoolState::zeroed();
+    pool_state.amm_config = Pubkey::new_unique();
+    pool_state.token_mint_0 = Pubkey::new_unique();
+    pool_state.token_mint_1 = Pubkey::new_unique();
+    pool_state.tick_spacing = TICK_SPACING;
+    pool_state.tick_current = 0;
+    pool_state.sqrt_price_x64 = get_sqrt_price_at_tick(0).unwrap();
+    pool_state.liquidity = LIQUIDITY;
+    let pool_id = pda::pool_address(
+        &pool_state.amm_config,
+        &pool_state.token_mint_0,
+        &pool_state.token_mint_1,
+    )
+    .0;
+    (pool_id, pool_state)
+}
+
+/// Tick arrays holding the initialized bounds of the position
+fn tick_arrays(pool_id: Pubkey) -> Vec<TickArrayState> {
+    [(-600, LIQUIDITY as i128), (600, -(LIQUIDITY as i128))]
+        .map(|(tick, liquidity_net)| {
+            let mut tick_array = TickArrayState::zeroed();
+            tick_array.pool_id = pool_id;
+            tick_array.start_tick_index = tick;
+            tick_array.initialized_tick_count = 1;
+            tick_array.ticks[0].tick = tick;
+            tick_array.ticks[0].liquidity_gross = LIQUIDITY;
+            tick_array.ticks[0].liquidity_net = liquidity_net;
+            tick_array
+        })
+        .to_vec()
+}
+
+fn position(pool_id: Pubkey) -> PersonalPositionState {
+    PersonalPositionState {
+        bump: [0],
+        nft_mint: Pubkey::new_unique(),
+        pool_id,
+        tick_lower_index: -600,
+        tick_upper_index: 600,
+        liquidity: LIQUIDITY,
+        fee_growth_inside_0_last_x64: 0,
+        fee_growth_inside_1_last_x64: 0,
+        token_fees_owed_0: 0,
+        token_fees_owed_1: 0,
+        version: PersonalPositionState::VERSION,
+        operator: Pubkey::default(),
+        operator_approver: Pubkey::default(),
+        padding: [0; 8],
+    }
+}
+
+/// Amounts the position deposited, rounded up
+fn deposit_amounts() -> (u64, u64) {
+    get_delta_amounts_signed(
+        0,
+        get_sqrt_price_at_tick(0).unwrap(),
+        -600,
+        600,
+        LIQUIDITY as i128,
+    )
+    .unwrap()
+}
+
+#[test]
+fn deposits_cover_positions() {
+    let (pool_id, pool_state) = pool_state();
+    let report = check_solvency(
+        &pool_state,
+        tick_arrays(pool_id),
+        &[position(pool_id)],
+        deposit_amounts(),
+    )
+    .unwrap();
+
+    assert!(report.is_solvent());
+    assert_eq!(report.liquidity, LIQUIDITY);
+    assert_eq!(report.liquidity_in_range, LIQUIDITY);
+    // withdrawals round down, deposits round up
+    for vault in [report.token_0, report.token_1] {
+        assert!(vault.liquidity_amount > 0);
+        assert!((0..=1).contains(&vault.surplus()));
+    }
+}
+
+#[test]
+fn reports_shortfall() {
+    let (pool_id, pool_state) = pool_state();
+    let (amount_0, amount_1) = deposit_amounts();
+    let report = check_solvency(
+        &pool_state,
+        tick_arrays(pool_id),
+        &[position(pool_id)],
+        (amount_0, amount_1 - 10),
+    )
+    .unwrap();
+
+    assert!(!report.is_solvent());
+    assert!(report.token_0.is_solvent());
+    assert!((-10..=-9).contains(&report.token_1.surplus()));
+}
+
+#[test]
+fn requires_owed_protocol_and_fund_fees() {
+    let (pool_id, mut pool_state) = pool_state();
+    // 1 token of fees per 1000 liquidity, all earned in range
+    pool_state.fee_growth_global_0_x64 = Q64 / 1000;
+    pool_state.protocol_fees_token_0 = 30;
+    pool_state.fund_fees_token_0 = 10;
+    let mut position = position(pool_id);
+    position.token_fees_owed_0 = 5;
+
+    let report = check_solvency(
+        &pool_state,
+        tick_arrays(pool_id),
+        &[position],
+        deposit_amounts(),
+    )
+    .unwrap();
+
+    let fees_owed = 5 + LIQUIDITY / 1000 - 1;
+    assert_eq!(report.token_0.fees_owed, fees_owed);
+    assert_eq!(report.token_0.protocol_fees, 30);
+    assert_eq!(report.token_0.fund_fees, 10);
+    assert_eq!(
+        report.token_0.required(),
+        report.token_0.liquidity_amount + fees_owed + 40
+    );
+    assert!(!report.token_0.is_solvent());
+    assert_eq!(report.token_1.fees_owed, 0);
+}
+
+#[test]
+fn rejects_foreign_accounts() {
+    let (pool_id, pool_state) = pool_state();
+    let other_pool_id = Pubkey::new_unique();
+
+    let foreign = position(other_pool_id);
+    assert!(matches!(
+        check_solvency(&pool_state, tick_arrays(pool_id), std::slice::from_ref(&foreign), (0, 0)),
+        Err(ClientError::PositionPoolMismatch(nft_mint)) if nft_mint == foreign.nft_mint
+    ));
+    assert!(matches!(
+        check_solvency(&pool_state, tick_arrays(other_pool_id), &[], (0, 0)),
+        Err(ClientError::TickArrayPoolMismatch(-600))
+    ));
+    assert!(matches!(
+        check_solvency(
+            &pool_state,
+            tick_arrays(pool_id).into_iter().take(1),
+            &[position(pool_id)],
+            (0, 0)
+        ),
+        Err(ClientError::MissingTickArray(600))
+    ));
+}
@@ -0,0 +1,82 @@
+use std::{fmt, io};
+
+use anchor_lang::prelude::Pubkey;
+use clmm_math::MathError;
+
+pub type Result<T> = core::result::Result<T, IndexerError>;
+
+#[derive(Debug)]
+pub enum IndexerError {
+    Math(MathError),
+    /// A transaction was fed after one from a later slot
+    SlotOutOfOrder {
+        slot: u64,
+        last_slot: u64,
+    },
+    InvalidLogs(String),
+    InvalidEventData(String),
+    UnknownAmmConfig(Pubkey),
+    UnknownPool(Pubkey),
+    UnknownPosition(Pubkey),
+    /// A tick referenced by a position is missing from the pool
+    UnknownTick(i32),
+    /// A position, identified by its nft mint, moved to a new tick range while holding liquidity
+    RepositionWithLiquidity(Pubkey),
+    /// Replaying a swap on the model does not reproduce the logged result, the model is missing
+    /// history of the pool
+    SwapReplayMismatch(Pubkey),
+    /// Nothing was indexed for the account at or before the slot
+    NotIndexedAtSlot(u64),
+    Io(io::Error),
+    InvalidSnapshot(String),
+}
+
+impl fmt::Display for IndexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexerError::Math(err) => write!(f, "Math error: {}", err),
+            IndexerError::SlotOutOfOrder { slot, last_slot } => write!(
+                f,
+                "Slot {} is older than the last indexed slot {}",
+                slot, last_slot
+            ),
+            IndexerError::InvalidLogs(err) => write!(f, "Invalid transaction logs: {}", err),
+            IndexerError::InvalidEventData(err) => write!(f, "Invalid event data: {}", err),
+            IndexerError::UnknownAmmConfig(pubkey) => write!(f, "Unknown amm config {}", pubkey),
+            IndexerError::UnknownPool(pubkey) => write!(f, "Unknown pool {}", pubkey),
+            IndexerError::UnknownPosition(pubkey) => {
+                write!(f, "Unknown position with nft mint {}", pubkey)
+            }
+            IndexerError::UnknownTick(tick) => write!(f, "Unknown tick {}", tick),
+            IndexerError::RepositionWithLiquidity(pubkey) => write!(
+                f,
+                "Position with nft mint {} was repositioned while holding liquidity",
+                pubkey
+            ),
+            IndexerError::SwapReplayMismatch(pubkey) => write!(
+                f,
+                "Replaying a swap of pool {} does not match the logged result",
+                pubkey
+            ),
+            IndexerError::NotIndexedAtSlot(slot) => {
+                write!(f, "Nothing indexed at or before slot {}", slot)
+            }
+            IndexerError::Io(err) => write!(f, "IO error: {}", err),
+            IndexerError::InvalidSnapshot(err) => write!(f, "Invalid snapshot: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for IndexerError {}
+
+impl From<MathError> for IndexerError {
+    fn from(err: MathError) -> Self {
+        IndexerError::Math(err)
+    }
+}
+
+impl From<io::Error> for IndexerError {
+    fn from(err: io::Error) -> Self {
+        IndexerError::Io(err)
+    }
+}
@@ -0,0 +1,128 @@
+//! Decoding of program events from transaction logs
+//!
+//! `emit!` writes each event as a `Program data: <base64>` log line: the event discriminator
+//! followed by its borsh encoding. Only lines logged while the clmm program is the innermost
+//! running program are decoded, so events of other programs with colliding layouts are ignored.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clmm_contract::events::{
+    ConfigChanged, FeesCollected, FeesCompounded, LiquidityDecreased, LiquidityIncreased,
+    PoolCreated, PositionOpened, PositionRepositioned, Swap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{IndexerError, Result};
+
+/// Any event emitted by the clmm program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClmmEvent {
+    ConfigChanged(ConfigChanged),
+    PoolCreated(PoolCreated),
+    PositionOpened(PositionOpened),
+    LiquidityIncreased(LiquidityIncreased),
+    LiquidityDecreased(LiquidityDecreased),
+    FeesCollected(FeesCollected),
+    PositionRepositioned(PositionRepositioned),
+    FeesCompounded(FeesCompounded),
+    Swap(Swap),
+}
+
+impl ClmmEvent {
+    /// Decode an event by its discriminator, `None` if the discriminator is not a known event
+    pub fn decode(data: &[u8]) -> Result<Option<Self>> {
+        let event = if data.starts_with(ConfigChanged::DISCRIMINATOR) {
+            ClmmEvent::ConfigChanged(decode_event(data)?)
+        } else if data.starts_with(PoolCreated::DISCRIMINATOR) {
+            ClmmEvent::PoolCreated(decode_event(data)?)
+        } else if data.starts_with(PositionOpened::DISCRIMINATOR) {
+            ClmmEvent::PositionOpened(decode_event(data)?)
+        } else if data.starts_with(LiquidityIncreased::DISCRIMINATOR) {
+            ClmmEvent::LiquidityIncreased(decode_event(data)?)
+        } else if data.starts_with(LiquidityDecreased::DISCRIMINATOR) {
+            ClmmEvent::LiquidityDecreased(decode_event(data)?)
+        } else if data.starts_with(FeesCollected::DISCRIMINATOR) {
+            ClmmEvent::FeesCollected(decode_event(data)?)
+        } else if data.starts_with(PositionRepositioned::DISCRIMINATOR) {
+            ClmmEvent::PositionRepositioned(decode_event(data)?)
+        } else if data.starts_with(FeesCompounded::DISCRIMINATOR) {
+            ClmmEvent::FeesCompounded(decode_event(data)?)
+        } else if data.starts_with(Swap::DISCRIMINATOR) {
+            ClmmEvent::Swap(decode_event(data)?)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(event))
+    }
+}
+
+fn decode_event<T: Discriminator + AnchorDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_from_slice(&data[T::DISCRIMINATOR.len()..])
+        .map_err(|err| IndexerError::InvalidEventData(err.to_string()))
+}
+
+/// Decode the events the clmm program logged in a transaction, in emission order
+pub fn parse_logs(logs: &[impl AsRef<str>]) -> Result<Vec<ClmmEvent>> {
+    let program_id = clmm_contract::ID.to_string();
+    // whether each program on the invoke stack is the clmm program
+    let mut invoke_stack: Vec<bool> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let Some(line) = line.as_ref().strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(data) = line.strip_prefix("data: ") {
+            if invoke_stack.last() == Some(&true) {
+                let data = STANDARD
+                    .decode(data)
+                    .map_err(|err| IndexerError::InvalidEventData(err.to_string()))?;
+                events.extend(ClmmEvent::decode(&data)?);
+            }
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let program = words.next();
+        match words.next() {
+            Some("invoke") => invoke_stack.push(program == Some(program_id.as_str())),
+            Some("success") | Some("failed:") => {
+                invoke_stack.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+/// Logs of one transaction, as delivered by the `logsSubscribe` RPC notification plus the slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionLogs {
+    pub slot: u64,
+    pub signature: String,
+    /// Error of a failed transaction, whose events are ignored
+    #[serde(default)]
+    pub err: Option<serde_json::Value>,
+    pub logs: Vec<String>,
+}
+
+impl TransactionLogs {
+    /// Events of the transaction, none if it failed
+    pub fn events(&self) -> Result<Vec<ClmmEvent>> {
+        if self.err.is_some() {
+            return Ok(Vec::new());
+        }
+        parse_logs(&self.logs)
+    }
+}
+
+/// Read recorded transaction logs, one JSON object per line
+pub fn read_transaction_logs(jsonl: &str) -> Result<Vec<TransactionLogs>> {
+    jsonl
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|err| IndexerError::InvalidLogs(err.to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+/// Values of a model at the end of every slot it changed in, in slot order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct History<T> {
+    entries: Vec<(u64, T)>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Record `value` as of `slot`, replacing an earlier value of the same slot.
+    /// `slot` must not be older than the latest recorded slot.
+    pub fn record(&mut self, slot: u64, value: T) {
+        match self.entries.last_mut() {
+            Some((last_slot, last_value)) if *last_slot == slot => *last_value = value,
+            _ => self.entries.push((slot, value)),
+        }
+    }
+
+    /// Value at the end of `slot`, `None` if nothing was recorded until then
+    pub fn at(&self, slot: u64) -> Option<&T> {
+        let index = self
+            .entries
+            .partition_point(|(entry_slot, _)| *entry_slot <= slot);
+        index.checked_sub(1).map(|index| &self.entries[index].1)
+    }
+
+    pub fn latest(&self) -> Option<&T> {
+        self.entries.last().map(|(_, value)| value)
+    }
+
+    /// `(slot, value)` pairs, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &T)> {
+        self.entries.iter().map(|(slot, value)| (*slot, value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use anchor_lang::prelude::Pubkey;
+use clmm_math::{fee_math, liquidity_math, MathError};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AmmConfigModel, ClmmEvent, IndexerError, PoolModel, PositionModel, PositionValue, Result,
+    TransactionLogs,
+};
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// In-memory model of every amm config, pool and position seen in the event stream
+///
+/// Events must be applied in the order the chain executed them. An error leaves the model
+/// partially updated, restore the last snapshot before indexing again.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Indexer {
+    last_slot: Option<u64>,
+    amm_configs: BTreeMap<Pubkey, AmmConfigModel>,
+    pools: BTreeMap<Pubkey, PoolModel>,
+    /// Keyed by position nft mint
+    positions: BTreeMap<Pubkey, PositionModel>,
+}
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    last_slot: Option<u64>,
+    amm_configs: Vec<&'a AmmConfigModel>,
+    pools: Vec<&'a PoolModel>,
+    positions: Vec<&'a PositionModel>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    version: u32,
+    last_slot: Option<u64>,
+    amm_configs: Vec<AmmConfigModel>,
+    pools: Vec<PoolModel>,
+    positions: Vec<PositionModel>,
+}
+
+impl Indexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Slot of the last applied transaction
+    pub fn last_slot(&self) -> Option<u64> {
+        self.last_slot
+    }
+
+    pub fn amm_config(&self, address: &Pubkey) -> Option<&AmmConfigModel> {
+        self.amm_configs.get(address)
+    }
+
+    pub fn pool(&self, address: &Pubkey) -> Option<&PoolModel> {
+        self.pools.get(address)
+    }
+
+    pub fn position(&self, position_nft_mint: &Pubkey) -> Option<&PositionModel> {
+        self.positions.get(position_nft_mint)
+    }
+
+    pub fn pools(&self) -> impl Iterator<Item = &PoolModel> {
+        self.pools.values()
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = &PositionModel> {
+        self.positions.values()
+    }
+
+    /// Apply the events of a transaction. Failed transactions only advance the slot.
+    pub fn apply_transaction(&mut self, transaction: &TransactionLogs) -> Result<()> {
+        self.check_slot(transaction.slot)?;
+        for event in transaction.events()? {
+            self.apply_event(transaction.slot, &event)?;
+        }
+        self.last_slot = Some(transaction.slot);
+        Ok(())
+    }
+
+    /// Apply a single event executed in `slot`
+    pub fn apply_event(&mut self, slot: u64, event: &ClmmEvent) -> Result<()> {
+        self.check_slot(slot)?;
+        self.last_slot = Some(slot);
+
+        match event {
+            ClmmEvent::ConfigChanged(event) => {
+                self.amm_configs
+                    .insert(event.amm_config, AmmConfigModel::from(event));
+            }
+            ClmmEvent::PoolCreated(event) => {
+                self.pools
+                    .insert(event.pool_state, PoolModel::new(slot, event));
+            }
+            ClmmEvent::PositionOpened(event) => {
+                let pool = pool_mut(&mut self.pools, &event.pool_state)?;
+                let mut position = PositionModel::new(event);
+                pool.modify_position(slot, &mut position, to_liquidity_delta(event.liquidity)?)?;
+                self.positions.insert(event.position_nft_mint, position);
+            }
+            ClmmEvent::LiquidityIncreased(event) => {
+                let pool = pool_mut(&mut self.pools, &event.pool_state)?;
+                let position = position_mut(&mut self.positions, &event.position_nft_mint)?;
+                pool.modify_position(slot, position, to_liquidity_delta(event.liquidity)?)?;
+            }
+            ClmmEvent::LiquidityDecreased(event) => {
+                let pool = pool_mut(&mut self.pools, &event.pool_state)?;
+                let position = position_mut(&mut self.positions, &event.position_nft_mint)?;
+                pool.modify_position(slot, position, -to_liquidity_delta(event.liquidity)?)?;
+                position.collect(slot, event.fee_amount_0, event.fee_amount_1);
+            }
+            ClmmEvent::FeesCollected(event) => {
+                let pool = pool_mut(&mut self.pools, &event.pool_state)?;
+                let position = position_mut(&mut self.positions, &event.position_nft_mint)?;
+                // settle the fees earned since the last update before paying out
+                pool.modify_position(slot, position, 0)?;
+                position.collect(slot, event.amount_0, event.amount_1);
+            }
+            ClmmEvent::PositionRepositioned(event) => {
+                let position = position_mut(&mut self.positions, &event.position_nft_mint)?;
+                position.reposition(slot, event.tick_lower_index, event.tick_upper_index)?;
+            }
+            ClmmEvent::FeesCompounded(event) => {
+                let pool = pool_mut(&mut self.pools, &event.pool_state)?;
+                let position = position_mut(&mut self.positions, &event.position_nft_mint)?;
+                // fees are settled before the liquidity is added, then paid out of the owed fees
+                pool.modify_position(slot, position, to_liquidity_delta(event.liquidity)?)?;
+                position.collect(slot, event.amount_0, event.amount_1);
+            }
+            ClmmEvent::Swap(event) => {
+                let pool = pool_mut(&mut self.pools, &event.pool_state)?;
+                let amm_config = self
+                    .amm_configs
+                    .get(&pool.amm_config)
+                    .ok_or(IndexerError::UnknownAmmConfig(pool.amm_config))?;
+                pool.apply_swap(slot, event, amm_config)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_slot(&self, slot: u64) -> Result<()> {
+        match self.last_slot {
+            Some(last_slot) if slot < last_slot => {
+                Err(IndexerError::SlotOutOfOrder { slot, last_slot })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Value of a position at the end of `slot`, at the pool price of that slot
+    pub fn position_value_at(
+        &self,
+        position_nft_mint: &Pubkey,
+        slot: u64,
+    ) -> Result<PositionValue> {
+        let position = self
+            .positions
+            .get(position_nft_mint)
+            .ok_or(IndexerError::UnknownPosition(*position_nft_mint))?;
+        let pool = self
+            .pools
+            .get(&position.pool_state)
+            .ok_or(IndexerError::UnknownPool(position.pool_state))?;
+        let position_state = position
+            .history
+            .at(slot)
+            .ok_or(IndexerError::NotIndexedAtSlot(slot))?;
+        let pool_state = pool
+            .history
+            .at(slot)
+            .ok_or(IndexerError::NotIndexedAtSlot(slot))?;
+
+        let (tick_lower, tick_upper) = position.range_at(slot);
+        let fee_growth_outside_lower = pool.fee_growth_outside_at(tick_lower, slot)?;
+        let fee_growth_outside_upper = pool.fee_growth_outside_at(tick_upper, slot)?;
+        let fee_growth_global = [
+            pool_state.fee_growth_global_0_x64,
+            pool_state.fee_growth_global_1_x64,
+        ];
+        let fee_growth_inside_last = [
+            position_state.fee_growth_inside_0_last_x64,
+            position_state.fee_growth_inside_1_last_x64,
+        ];
+        let fees_owed = [
+            position_state.token_fees_owed_0,
+            position_state.token_fees_owed_1,
+        ];
+        let mut fees_owed_latest = [0; 2];
+        for i in 0..2 {
+            let fee_growth_inside_x64 = fee_math::get_fee_growth_inside(
+                tick_lower,
+                fee_growth_outside_lower[i],
+                tick_upper,
+                fee_growth_outside_upper[i],
+                pool_state.tick_current,
+                fee_growth_global[i],
+            )?;
+            fees_owed_latest[i] = fee_math::calculate_latest_token_fees(
+                fees_owed[i],
+                fee_growth_inside_last[i],
+                fee_growth_inside_x64,
+                position_state.liquidity,
+            )?;
+        }
+
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            pool_state.tick_current,
+            pool_state.sqrt_price_x64,
+            tick_lower,
+            tick_upper,
+            -to_liquidity_delta(position_state.liquidity)?,
+        )?;
+
+        Ok(PositionValue {
+            slot,
+            sqrt_price_x64: pool_state.sqrt_price_x64,
+            tick_current: pool_state.tick_current,
+            liquidity: position_state.liquidity,
+            amount_0,
+            amount_1,
+            fees_owed_0: fees_owed_latest[0],
+            fees_owed_1: fees_owed_latest[1],
+        })
+    }
+
+    /// Write the whole model as JSON
+    pub fn write_snapshot(&self, writer: impl Write) -> Result<()> {
+        let snapshot = SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            last_slot: self.last_slot,
+            amm_configs: self.amm_configs.values().collect(),
+            pools: self.pools.values().collect(),
+            positions: self.positions.values().collect(),
+        };
+        serde_json::to_writer(writer, &snapshot)
+            .map_err(|err| IndexerError::InvalidSnapshot(err.to_string()))
+    }
+
+    /// Restore a model written by [`Indexer::write_snapshot`]
+    pub fn read_snapshot(reader: impl Read) -> Result<Self> {
+        let snapshot: Snapshot = serde_json::from_reader(reader)
+            .map_err(|err| IndexerError::InvalidSnapshot(err.to_string()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(IndexerError::InvalidSnapshot(format!(
+                "unsupported snapshot version {}",
+                snapshot.version
+            )));
+        }
+
+        Ok(Self {
+            last_slot: snapshot.last_slot,
+            amm_configs: snapshot
+                .amm_configs
+                .into_iter()
+                .map(|amm_config| (amm_config.address, amm_config))
+                .collect(),
+            pools: snapshot
+                .pools
+                .into_iter()
+                .map(|pool| (pool.address, pool))
+                .collect(),
+            positions: snapshot
+                .positions
+                .into_iter()
+                .map(|position| (position.position_nft_mint, position))
+                .collect(),
+        })
+    }
+
+    /// Save a snapshot to `path`. The file is replaced atomically so a crash never leaves a
+    /// truncated snapshot behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        self.write_snapshot(&mut file)?;
+        file.sync_all()?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read_snapshot(fs::File::open(path)?)
+    }
+}
+
+fn pool_mut<'a>(
+    pools: &'a mut BTreeMap<Pubkey, PoolModel>,
+    address: &Pubkey,
+) -> Result<&'a mut PoolModel> {
+    pools
+        .get_mut(address)
+        .ok_or(IndexerError::UnknownPool(*address))
+}
+
+fn position_mut<'a>(
+    positions: &'a mut BTreeMap<Pubkey, PositionModel>,
+    position_nft_mint: &Pubkey,
+) -> Result<&'a mut PositionModel> {
+    positions
+        .get_mut(position_nft_mint)
+        .ok_or(IndexerError::UnknownPosition(*position_nft_mint))
+}
+
+fn to_liquidity_delta(liquidity: u128) -> Result<i128> {
+    Ok(i128::try_from(liquidity).map_err(|_| MathError::LiquidityOverflow)?)
+}
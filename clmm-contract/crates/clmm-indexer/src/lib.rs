@@ -0,0 +1,19 @@
+//! Event-stream indexer for the clmm program
+//!
+//! Feeds transaction logs, in slot order, into an in-memory model of every amm config, pool,
+//! tick and position. Swaps are replayed tick by tick with the program's math, so fee growth and
+//! fees owed follow the chain exactly. Every change is kept as a per-slot checkpoint, which lets
+//! the model answer what a pool or position looked like at any indexed slot. The whole model can
+//! be written to disk and restored to resume indexing.
+
+pub mod error;
+pub mod events;
+pub mod history;
+pub mod indexer;
+pub mod model;
+
+pub use error::*;
+pub use events::*;
+pub use history::*;
+pub use indexer::*;
+pub use model::*;
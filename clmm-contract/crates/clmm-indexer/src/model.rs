@@ -0,0 +1,598 @@
+//! Pools, ticks and positions rebuilt from events
+//!
+//! The models mirror the program's own bookkeeping: ticks keep gross and net liquidity and the fee
+//! growth outside of them, positions keep the fee growth inside their range at their last update.
+
+use std::collections::BTreeMap;
+
+use anchor_lang::prelude::Pubkey;
+use clmm_contract::events::{ConfigChanged, PoolCreated, PositionOpened, Swap};
+use clmm_math::{
+    fee_math::{calculate_latest_token_fees, get_fee_growth_inside},
+    liquidity_math,
+    swap_math::{compute_swap, SwapFeeRates, SwapState, SwapTicks},
+    tick_math::{MAX_SQRT_PRICE_X64, MIN_SQRT_PRICE_X64},
+    MathError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{History, IndexerError, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmmConfigModel {
+    #[serde(with = "pubkey_string")]
+    pub address: Pubkey,
+    pub index: u16,
+    pub tick_spacing: u16,
+    pub trade_fee_rate: u32,
+    pub protocol_fee_rate: u32,
+    pub fund_fee_rate: u32,
+}
+
+impl From<&ConfigChanged> for AmmConfigModel {
+    fn from(event: &ConfigChanged) -> Self {
+        Self {
+            address: event.amm_config,
+            index: event.index,
+            tick_spacing: event.tick_spacing,
+            trade_fee_rate: event.trade_fee_rate,
+            protocol_fee_rate: event.protocol_fee_rate,
+            fund_fee_rate: event.fund_fee_rate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickModel {
+    pub liquidity_gross: u128,
+    pub liquidity_net: i128,
+    pub fee_growth_outside_0_x64: u128,
+    pub fee_growth_outside_1_x64: u128,
+    /// `[fee_growth_outside_0_x64, fee_growth_outside_1_x64]` after every slot they changed in
+    pub fee_growth_outside_history: History<[u128; 2]>,
+}
+
+impl TickModel {
+    pub fn is_initialized(&self) -> bool {
+        self.liquidity_gross != 0
+    }
+
+    fn record(&mut self, slot: u64) {
+        self.fee_growth_outside_history.record(
+            slot,
+            [self.fee_growth_outside_0_x64, self.fee_growth_outside_1_x64],
+        );
+    }
+}
+
+/// Mutable state of a pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolCheckpoint {
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    /// Liquidity of the positions in range
+    pub liquidity: u128,
+    pub fee_growth_global_0_x64: u128,
+    pub fee_growth_global_1_x64: u128,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolModel {
+    #[serde(with = "pubkey_string")]
+    pub address: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub amm_config: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub token_mint_0: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub token_mint_1: Pubkey,
+    pub tick_spacing: u16,
+    pub state: PoolCheckpoint,
+    /// State after every slot it changed in, which includes the pool's price history
+    pub history: History<PoolCheckpoint>,
+    /// Every tick that was ever initialized, cleared ticks keep their history
+    pub ticks: BTreeMap<i32, TickModel>,
+}
+
+/// Result of replaying a swap on a pool model
+struct SwapReplay {
+    amount_in: u64,
+    amount_out: u64,
+    sqrt_price_x64: u128,
+    tick: i32,
+    liquidity: u128,
+    /// Fee growth of the input token
+    fee_growth_global_x64: u128,
+    protocol_fee: u64,
+    fund_fee: u64,
+    /// Crossed ticks and the fee growth of the input token when they were crossed
+    crossed_ticks: Vec<(i32, u128)>,
+}
+
+impl PoolModel {
+    pub(crate) fn new(slot: u64, event: &PoolCreated) -> Self {
+        let state = PoolCheckpoint {
+            sqrt_price_x64: event.sqrt_price_x64,
+            tick_current: event.tick,
+            ..Default::default()
+        };
+        let mut history = History::default();
+        history.record(slot, state);
+        Self {
+            address: event.pool_state,
+            amm_config: event.amm_config,
+            token_mint_0: event.token_mint_0,
+            token_mint_1: event.token_mint_1,
+            tick_spacing: event.tick_spacing,
+            state,
+            history,
+            ticks: BTreeMap::new(),
+        }
+    }
+
+    /// Fee growth of both tokens inside a tick range at the current tick
+    pub fn fee_growth_inside(&self, tick_lower: i32, tick_upper: i32) -> Result<(u128, u128)> {
+        let lower = self.tick(tick_lower)?;
+        let upper = self.tick(tick_upper)?;
+        Ok((
+            get_fee_growth_inside(
+                tick_lower,
+                lower.fee_growth_outside_0_x64,
+                tick_upper,
+                upper.fee_growth_outside_0_x64,
+                self.state.tick_current,
+                self.state.fee_growth_global_0_x64,
+            )?,
+            get_fee_growth_inside(
+                tick_lower,
+                lower.fee_growth_outside_1_x64,
+                tick_upper,
+                upper.fee_growth_outside_1_x64,
+                self.state.tick_current,
+                self.state.fee_growth_global_1_x64,
+            )?,
+        ))
+    }
+
+    /// `[fee_growth_outside_0_x64, fee_growth_outside_1_x64]` of a tick at the end of `slot`
+    pub fn fee_growth_outside_at(&self, tick: i32, slot: u64) -> Result<[u128; 2]> {
+        self.tick(tick)?
+            .fee_growth_outside_history
+            .at(slot)
+            .copied()
+            .ok_or(IndexerError::NotIndexedAtSlot(slot))
+    }
+
+    fn tick(&self, tick: i32) -> Result<&TickModel> {
+        self.ticks.get(&tick).ok_or(IndexerError::UnknownTick(tick))
+    }
+
+    fn record(&mut self, slot: u64) {
+        self.history.record(slot, self.state);
+    }
+
+    /// Add `liquidity_delta` to a position, updating its ticks, its fees owed and the active liquidity
+    pub(crate) fn modify_position(
+        &mut self,
+        slot: u64,
+        position: &mut PositionModel,
+        liquidity_delta: i128,
+    ) -> Result<()> {
+        let tick_lower = position.tick_lower_index;
+        let tick_upper = position.tick_upper_index;
+
+        if liquidity_delta != 0 {
+            self.update_tick(slot, tick_lower, liquidity_delta, false)?;
+            self.update_tick(slot, tick_upper, liquidity_delta, true)?;
+        }
+
+        let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) =
+            self.fee_growth_inside(tick_lower, tick_upper)?;
+        position.update(
+            liquidity_delta,
+            fee_growth_inside_0_x64,
+            fee_growth_inside_1_x64,
+        )?;
+        position.record(slot);
+
+        if liquidity_delta != 0 {
+            if self.state.tick_current >= tick_lower && self.state.tick_current < tick_upper {
+                self.state.liquidity =
+                    liquidity_math::add_delta(self.state.liquidity, liquidity_delta)?;
+            }
+            if liquidity_delta < 0 {
+                for tick in [tick_lower, tick_upper] {
+                    let tick_model = self
+                        .ticks
+                        .get_mut(&tick)
+                        .ok_or(IndexerError::UnknownTick(tick))?;
+                    if !tick_model.is_initialized() {
+                        tick_model.liquidity_net = 0;
+                        tick_model.fee_growth_outside_0_x64 = 0;
+                        tick_model.fee_growth_outside_1_x64 = 0;
+                        tick_model.record(slot);
+                    }
+                }
+            }
+            self.record(slot);
+        }
+        Ok(())
+    }
+
+    fn update_tick(
+        &mut self,
+        slot: u64,
+        tick: i32,
+        liquidity_delta: i128,
+        upper: bool,
+    ) -> Result<()> {
+        let tick_current = self.state.tick_current;
+        let fee_growth_global_0_x64 = self.state.fee_growth_global_0_x64;
+        let fee_growth_global_1_x64 = self.state.fee_growth_global_1_x64;
+        let tick_model = self.ticks.entry(tick).or_default();
+
+        let liquidity_gross_before = tick_model.liquidity_gross;
+        tick_model.liquidity_gross =
+            liquidity_math::add_delta(liquidity_gross_before, liquidity_delta)?;
+        if liquidity_gross_before == 0 {
+            // by convention, all growth before a tick was initialized happened below it
+            if tick <= tick_current {
+                tick_model.fee_growth_outside_0_x64 = fee_growth_global_0_x64;
+                tick_model.fee_growth_outside_1_x64 = fee_growth_global_1_x64;
+            }
+            tick_model.record(slot);
+        }
+        tick_model.liquidity_net = if upper {
+            tick_model.liquidity_net.checked_sub(liquidity_delta)
+        } else {
+            tick_model.liquidity_net.checked_add(liquidity_delta)
+        }
+        .ok_or(MathError::LiquidityOverflow)?;
+        Ok(())
+    }
+
+    /// Replay a logged swap, crossing ticks and accruing fees like the program.
+    ///
+    /// The swap event does not say whether the amount was the exact input or output, nor which
+    /// price limit was used, so each combination is replayed until one reproduces the logged result.
+    pub(crate) fn apply_swap(
+        &mut self,
+        slot: u64,
+        event: &Swap,
+        amm_config: &AmmConfigModel,
+    ) -> Result<()> {
+        let zero_for_one = event.zero_for_one;
+        let (amount_in, amount_out) = if zero_for_one {
+            (event.amount_0, event.amount_1)
+        } else {
+            (event.amount_1, event.amount_0)
+        };
+
+        let candidates = [
+            (amount_in, None, true),
+            (amount_in, Some(event.sqrt_price_x64), true),
+            (amount_out, None, false),
+            (amount_out, Some(event.sqrt_price_x64), false),
+        ];
+        let replay = candidates
+            .into_iter()
+            .filter_map(|(amount_specified, sqrt_price_limit_x64, is_base_input)| {
+                self.simulate_swap(
+                    amm_config,
+                    amount_specified,
+                    sqrt_price_limit_x64,
+                    is_base_input,
+                    zero_for_one,
+                )
+                .ok()
+            })
+            .find(|replay| {
+                replay.amount_in == amount_in
+                    && replay.amount_out == amount_out
+                    && replay.sqrt_price_x64 == event.sqrt_price_x64
+                    && replay.tick == event.tick
+                    && replay.liquidity == event.liquidity
+            })
+            .ok_or(IndexerError::SwapReplayMismatch(self.address))?;
+
+        for (tick, fee_growth_global_x64) in replay.crossed_ticks {
+            let (fee_growth_global_0_x64, fee_growth_global_1_x64) = if zero_for_one {
+                (fee_growth_global_x64, self.state.fee_growth_global_1_x64)
+            } else {
+                (self.state.fee_growth_global_0_x64, fee_growth_global_x64)
+            };
+            let tick_model = self
+                .ticks
+                .get_mut(&tick)
+                .ok_or(IndexerError::UnknownTick(tick))?;
+            tick_model.fee_growth_outside_0_x64 =
+                fee_growth_global_0_x64.wrapping_sub(tick_model.fee_growth_outside_0_x64);
+            tick_model.fee_growth_outside_1_x64 =
+                fee_growth_global_1_x64.wrapping_sub(tick_model.fee_growth_outside_1_x64);
+            tick_model.record(slot);
+        }
+
+        let state = &mut self.state;
+        state.sqrt_price_x64 = replay.sqrt_price_x64;
+        state.tick_current = replay.tick;
+        state.liquidity = replay.liquidity;
+        let (fee_growth_global_x64, protocol_fees, fund_fees) = if zero_for_one {
+            (
+                &mut state.fee_growth_global_0_x64,
+                &mut state.protocol_fees_token_0,
+                &mut state.fund_fees_token_0,
+            )
+        } else {
+            (
+                &mut state.fee_growth_global_1_x64,
+                &mut state.protocol_fees_token_1,
+                &mut state.fund_fees_token_1,
+            )
+        };
+        *fee_growth_global_x64 = replay.fee_growth_global_x64;
+        *protocol_fees = protocol_fees
+            .checked_add(replay.protocol_fee)
+            .ok_or(MathError::FeeAmountOverflow)?;
+        *fund_fees = fund_fees
+            .checked_add(replay.fund_fee)
+            .ok_or(MathError::FeeAmountOverflow)?;
+        self.record(slot);
+        Ok(())
+    }
+
+    fn simulate_swap(
+        &self,
+        amm_config: &AmmConfigModel,
+        amount_specified: u64,
+        sqrt_price_limit_x64: Option<u128>,
+        is_base_input: bool,
+        zero_for_one: bool,
+    ) -> Result<SwapReplay> {
+        let sqrt_price_x64 = self.state.sqrt_price_x64;
+        let fee_growth_global_x64 = if zero_for_one {
+            self.state.fee_growth_global_0_x64
+        } else {
+            self.state.fee_growth_global_1_x64
+        };
+
+        let sqrt_price_limit_x64 = sqrt_price_limit_x64.unwrap_or(if zero_for_one {
+            MIN_SQRT_PRICE_X64 + 1
+        } else {
+            MAX_SQRT_PRICE_X64 - 1
+        });
+        let is_valid_limit = if zero_for_one {
+            sqrt_price_limit_x64 < sqrt_price_x64 && sqrt_price_limit_x64 > MIN_SQRT_PRICE_X64
+        } else {
+            sqrt_price_limit_x64 > sqrt_price_x64 && sqrt_price_limit_x64 < MAX_SQRT_PRICE_X64
+        };
+        if !is_valid_limit {
+            return Err(MathError::SqrtPriceLimitOverflow.into());
+        }
+
+        let mut ticks = ReplayTicks {
+            ticks: &self.ticks,
+            crossed_ticks: Vec::new(),
+        };
+        let state = SwapState {
+            sqrt_price_x64,
+            tick: self.state.tick_current,
+            liquidity: self.state.liquidity,
+            fee_growth_global_x64,
+        };
+        let fee_rates = SwapFeeRates {
+            trade_fee_rate: amm_config.trade_fee_rate,
+            protocol_fee_rate: amm_config.protocol_fee_rate,
+            fund_fee_rate: amm_config.fund_fee_rate,
+        };
+        let outcome = compute_swap(
+            &mut ticks,
+            state,
+            fee_rates,
+            amount_specified,
+            sqrt_price_limit_x64,
+            zero_for_one,
+            is_base_input,
+        )?;
+        Ok(SwapReplay {
+            amount_in: outcome.amount_in,
+            amount_out: outcome.amount_out,
+            sqrt_price_x64: outcome.state.sqrt_price_x64,
+            tick: outcome.state.tick,
+            liquidity: outcome.state.liquidity,
+            fee_growth_global_x64: outcome.state.fee_growth_global_x64,
+            protocol_fee: outcome.protocol_fee,
+            fund_fee: outcome.fund_fee,
+            crossed_ticks: ticks.crossed_ticks,
+        })
+    }
+}
+
+/// The initialized ticks of a pool model, recording the ticks a replayed swap crosses
+struct ReplayTicks<'a> {
+    ticks: &'a BTreeMap<i32, TickModel>,
+    /// Crossed ticks and the fee growth of the input token when they were crossed
+    crossed_ticks: Vec<(i32, u128)>,
+}
+
+impl SwapTicks for ReplayTicks<'_> {
+    type Error = IndexerError;
+
+    fn next_initialized_tick(&mut self, tick: i32, zero_for_one: bool) -> Result<Option<i32>> {
+        let next_initialized_tick = if zero_for_one {
+            self.ticks
+                .range(..=tick)
+                .rev()
+                .find(|(_, tick_model)| tick_model.is_initialized())
+        } else {
+            self.ticks
+                .range(tick + 1..)
+                .find(|(_, tick_model)| tick_model.is_initialized())
+        };
+        Ok(next_initialized_tick.map(|(index, _)| *index))
+    }
+
+    fn cross_tick(
+        &mut self,
+        tick: i32,
+        fee_growth_global_x64: u128,
+        _zero_for_one: bool,
+    ) -> Result<i128> {
+        let tick_model = self
+            .ticks
+            .get(&tick)
+            .ok_or(IndexerError::UnknownTick(tick))?;
+        self.crossed_ticks.push((tick, fee_growth_global_x64));
+        Ok(tick_model.liquidity_net)
+    }
+}
+
+/// Mutable state of a position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionCheckpoint {
+    pub liquidity: u128,
+    pub fee_growth_inside_0_last_x64: u128,
+    pub fee_growth_inside_1_last_x64: u128,
+    /// Fees owed as of the last update, fees earned since are not included
+    pub token_fees_owed_0: u64,
+    pub token_fees_owed_1: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionModel {
+    #[serde(with = "pubkey_string")]
+    pub position_nft_mint: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub pool_state: Pubkey,
+    /// Owner of the position nft when the position was opened
+    #[serde(with = "pubkey_string")]
+    pub nft_owner: Pubkey,
+    /// Current tick range
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub state: PositionCheckpoint,
+    /// State after every slot it changed in
+    pub history: History<PositionCheckpoint>,
+    /// Tick range after every slot it was repositioned in, the opening range as of slot 0.
+    /// Empty if the position never moved.
+    #[serde(default)]
+    pub range_history: History<(i32, i32)>,
+}
+
+impl PositionModel {
+    /// An empty position, the opening liquidity is added through [`PoolModel::modify_position`]
+    pub(crate) fn new(event: &PositionOpened) -> Self {
+        Self {
+            position_nft_mint: event.position_nft_mint,
+            pool_state: event.pool_state,
+            nft_owner: event.nft_owner,
+            tick_lower_index: event.tick_lower_index,
+            tick_upper_index: event.tick_upper_index,
+            state: PositionCheckpoint::default(),
+            history: History::default(),
+            range_history: History::default(),
+        }
+    }
+
+    /// Tick range at the end of `slot`
+    pub fn range_at(&self, slot: u64) -> (i32, i32) {
+        self.range_history
+            .at(slot)
+            .copied()
+            .unwrap_or((self.tick_lower_index, self.tick_upper_index))
+    }
+
+    /// Move a position without liquidity to a new tick range
+    pub(crate) fn reposition(
+        &mut self,
+        slot: u64,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+    ) -> Result<()> {
+        if self.state.liquidity != 0 {
+            return Err(IndexerError::RepositionWithLiquidity(
+                self.position_nft_mint,
+            ));
+        }
+        if self.range_history.is_empty() {
+            self.range_history
+                .record(0, (self.tick_lower_index, self.tick_upper_index));
+        }
+        self.tick_lower_index = tick_lower_index;
+        self.tick_upper_index = tick_upper_index;
+        self.range_history
+            .record(slot, (tick_lower_index, tick_upper_index));
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        liquidity_delta: i128,
+        fee_growth_inside_0_x64: u128,
+        fee_growth_inside_1_x64: u128,
+    ) -> Result<()> {
+        let state = &mut self.state;
+        state.token_fees_owed_0 = calculate_latest_token_fees(
+            state.token_fees_owed_0,
+            state.fee_growth_inside_0_last_x64,
+            fee_growth_inside_0_x64,
+            state.liquidity,
+        )?;
+        state.token_fees_owed_1 = calculate_latest_token_fees(
+            state.token_fees_owed_1,
+            state.fee_growth_inside_1_last_x64,
+            fee_growth_inside_1_x64,
+            state.liquidity,
+        )?;
+        state.fee_growth_inside_0_last_x64 = fee_growth_inside_0_x64;
+        state.fee_growth_inside_1_last_x64 = fee_growth_inside_1_x64;
+        state.liquidity = liquidity_math::add_delta(state.liquidity, liquidity_delta)?;
+        Ok(())
+    }
+
+    /// Pay out owed fees
+    pub(crate) fn collect(&mut self, slot: u64, amount_0: u64, amount_1: u64) {
+        self.state.token_fees_owed_0 = self.state.token_fees_owed_0.saturating_sub(amount_0);
+        self.state.token_fees_owed_1 = self.state.token_fees_owed_1.saturating_sub(amount_1);
+        self.record(slot);
+    }
+
+    fn record(&mut self, slot: u64) {
+        self.history.record(slot, self.state);
+    }
+}
+
+/// What a position was worth at the end of a slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionValue {
+    pub slot: u64,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    /// Tokens returned by removing all liquidity, rounded down like a liquidity decrease
+    pub amount_0: u64,
+    pub amount_1: u64,
+    /// Fees owed, including fees earned since the position's last update
+    pub fees_owed_0: u64,
+    pub fees_owed_1: u64,
+}
+
+/// Pubkeys are stored as base58 strings so snapshots stay readable
+mod pubkey_string {
+    use std::str::FromStr;
+
+    use anchor_lang::prelude::Pubkey;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let pubkey = String::deserialize(deserializer)?;
+        Pubkey::from_str(&pubkey).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,249 @@
+use anchor_lang::prelude::Pubkey;
+use clmm_contract::events::{FeesCompounded, PositionRepositioned};
+use clmm_indexer::{read_transaction_logs, ClmmEvent, Indexer, IndexerError, TransactionLogs};
+use clmm_math::tick_math::get_sqrt_price_at_tick;
+
+/// Recorded logs of one pool: config and pool creation at slots 100 and 101, positions A and B
+/// opened at 102 and 103, an unrelated program logging event data at 104, swaps at 105, 107 and
+/// 110, liquidity added to A at 106, fees of B collected at 108, A withdrawn at 109 and a failed
+/// swap at 111.
+const POOL_LIFECYCLE: &str = include_str!("fixtures/pool_lifecycle.jsonl");
+
+fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+fn pool_key() -> Pubkey {
+    key(2)
+}
+
+fn position_a() -> Pubkey {
+    key(10)
+}
+
+fn position_b() -> Pubkey {
+    key(11)
+}
+
+fn transactions() -> Vec<TransactionLogs> {
+    read_transaction_logs(POOL_LIFECYCLE).unwrap()
+}
+
+fn index(transactions: &[TransactionLogs]) -> Indexer {
+    let mut indexer = Indexer::new();
+    for transaction in transactions {
+        indexer.apply_transaction(transaction).unwrap();
+    }
+    indexer
+}
+
+#[test]
+fn parse_logs_only_decodes_clmm_events() {
+    let transactions = transactions();
+    let events: Vec<Vec<ClmmEvent>> = transactions
+        .iter()
+        .map(|transaction| transaction.events().unwrap())
+        .collect();
+
+    assert_eq!(events.len(), 12);
+    assert!(matches!(events[0][..], [ClmmEvent::ConfigChanged(_)]));
+    assert!(matches!(events[1][..], [ClmmEvent::PoolCreated(_)]));
+    assert!(matches!(events[2][..], [ClmmEvent::PositionOpened(_)]));
+    // swap data logged by another program, at the top level and through a cpi
+    assert!(events[4].is_empty());
+    assert!(matches!(events[5][..], [ClmmEvent::Swap(_)]));
+    assert!(matches!(events[9][..], [ClmmEvent::LiquidityDecreased(_)]));
+    // failed transaction
+    assert!(events[11].is_empty());
+}
+
+#[test]
+fn replays_pool_history() {
+    let indexer = index(&transactions());
+    assert_eq!(indexer.last_slot(), Some(111));
+
+    let pool = indexer.pool(&pool_key()).unwrap();
+    assert_eq!(pool.state.tick_current, -300);
+    assert_eq!(
+        pool.state.sqrt_price_x64,
+        get_sqrt_price_at_tick(-300).unwrap()
+    );
+    assert_eq!(pool.state.liquidity, 2_000_000_000_000);
+    assert!(pool.state.protocol_fees_token_0 > 0);
+    assert!(pool.state.fund_fees_token_0 > 0);
+
+    let prices: Vec<(u64, i32)> = pool
+        .history
+        .iter()
+        .map(|(slot, state)| (slot, state.tick_current))
+        .collect();
+    assert_eq!(
+        prices,
+        vec![
+            (101, 0),
+            (102, 0),
+            (103, 0),
+            (105, -149),
+            (106, -149),
+            (107, -72),
+            (109, -72),
+            (110, -300)
+        ]
+    );
+    assert_eq!(
+        pool.history.at(108).unwrap().sqrt_price_x64,
+        18381326849367263344
+    );
+    assert!(pool.history.at(100).is_none());
+
+    // the tick crossed by both swaps, and the cleared bounds of position A
+    assert!(!pool.ticks[&-100].is_initialized());
+    assert!(pool.ticks[&-500].is_initialized());
+}
+
+#[test]
+fn position_value_at_slot() {
+    let indexer = index(&transactions());
+
+    // withdrawing right after opening returns the deposit, rounded down
+    let value = indexer.position_value_at(&position_a(), 102).unwrap();
+    assert_eq!(value.liquidity, 1_000_000_000_000);
+    assert_eq!((value.amount_0, value.amount_1), (4987272070, 4987272070));
+    assert_eq!((value.fees_owed_0, value.fees_owed_1), (0, 0));
+
+    // a slot without events of the position falls back to its last update
+    let value = indexer.position_value_at(&position_a(), 108).unwrap();
+    assert_eq!(value.liquidity, 1_500_000_000_000);
+    assert_eq!((value.amount_0, value.amount_1), (12819251595, 2161495849));
+    assert_eq!((value.fees_owed_0, value.fees_owed_1), (10612444, 4576520));
+
+    // withdrawn along with its fees
+    let value = indexer.position_value_at(&position_a(), 109).unwrap();
+    assert_eq!(value.liquidity, 0);
+    assert_eq!((value.amount_0, value.amount_1), (0, 0));
+    assert_eq!((value.fees_owed_0, value.fees_owed_1), (0, 0));
+
+    let value = indexer.position_value_at(&position_b(), 107).unwrap();
+    assert_eq!((value.fees_owed_0, value.fees_owed_1), (31627557, 16376078));
+    let value = indexer.position_value_at(&position_b(), 108).unwrap();
+    assert_eq!((value.fees_owed_0, value.fees_owed_1), (0, 0));
+    let value = indexer.position_value_at(&position_b(), 111).unwrap();
+    assert_eq!(value.tick_current, -300);
+    assert_eq!((value.fees_owed_0, value.fees_owed_1), (48923903, 0));
+
+    assert!(matches!(
+        indexer.position_value_at(&position_b(), 102),
+        Err(IndexerError::NotIndexedAtSlot(102))
+    ));
+    assert!(matches!(
+        indexer.position_value_at(&key(99), 110),
+        Err(IndexerError::UnknownPosition(_))
+    ));
+}
+
+#[test]
+fn snapshot_restores_and_resumes() {
+    let transactions = transactions();
+    let indexer = index(&transactions);
+
+    let mut snapshot = Vec::new();
+    indexer.write_snapshot(&mut snapshot).unwrap();
+    assert_eq!(Indexer::read_snapshot(&snapshot[..]).unwrap(), indexer);
+
+    // index half of the stream, save to disk, restore and index the rest
+    let (head, tail) = transactions.split_at(6);
+    let path = std::env::temp_dir().join(format!("clmm-indexer-{}.json", std::process::id()));
+    index(head).save(&path).unwrap();
+    let mut resumed = Indexer::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    for transaction in tail {
+        resumed.apply_transaction(transaction).unwrap();
+    }
+    assert_eq!(resumed, indexer);
+}
+
+#[test]
+fn rejects_out_of_order_slots() {
+    let transactions = transactions();
+    let mut indexer = index(&transactions[..3]);
+    assert!(matches!(
+        indexer.apply_transaction(&transactions[1]),
+        Err(IndexerError::SlotOutOfOrder {
+            slot: 101,
+            last_slot: 102
+        })
+    ));
+}
+
+#[test]
+fn swap_without_pool_history_does_not_replay() {
+    let transactions = transactions();
+    // position B is missing, so the swap sees less liquidity than the chain did
+    let mut indexer = index(&transactions[..3]);
+    indexer.apply_transaction(&transactions[4]).unwrap();
+    assert!(matches!(
+        indexer.apply_transaction(&transactions[5]),
+        Err(IndexerError::SwapReplayMismatch(_))
+    ));
+}
+
+#[test]
+fn reposition_moves_empty_position() {
+    let mut indexer = index(&transactions());
+    let value_before = indexer.position_value_at(&position_a(), 108).unwrap();
+    let range_before = indexer.position(&position_a()).unwrap().range_at(108);
+
+    let reposition = |position_nft_mint| {
+        ClmmEvent::PositionRepositioned(PositionRepositioned {
+            pool_state: pool_key(),
+            position_nft_mint,
+            tick_lower_index: -1200,
+            tick_upper_index: 1200,
+        })
+    };
+    // A was withdrawn at 109, B still holds liquidity
+    assert!(matches!(
+        indexer.apply_event(112, &reposition(position_b())),
+        Err(IndexerError::RepositionWithLiquidity(_))
+    ));
+    indexer.apply_event(112, &reposition(position_a())).unwrap();
+
+    let position = indexer.position(&position_a()).unwrap();
+    assert_eq!(
+        (position.tick_lower_index, position.tick_upper_index),
+        (-1200, 1200)
+    );
+    assert_eq!(position.range_at(112), (-1200, 1200));
+    assert_eq!(position.range_at(111), range_before);
+    // earlier values are still computed on the range the position held then
+    assert_eq!(
+        indexer.position_value_at(&position_a(), 108).unwrap(),
+        value_before
+    );
+}
+
+#[test]
+fn compounded_fees_become_liquidity() {
+    let mut indexer = index(&transactions());
+    let before = indexer.position_value_at(&position_b(), 111).unwrap();
+
+    indexer
+        .apply_event(
+            112,
+            &ClmmEvent::FeesCompounded(FeesCompounded {
+                pool_state: pool_key(),
+                position_nft_mint: position_b(),
+                liquidity: 1_000_000,
+                amount_0: 10_000_000,
+                amount_1: 0,
+            }),
+        )
+        .unwrap();
+
+    let value = indexer.position_value_at(&position_b(), 112).unwrap();
+    assert_eq!(value.liquidity, before.liquidity + 1_000_000);
+    assert_eq!(
+        (value.fees_owed_0, value.fees_owed_1),
+        (before.fees_owed_0 - 10_000_000, before.fees_owed_1)
+    );
+}
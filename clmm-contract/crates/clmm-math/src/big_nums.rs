@@ -1,6 +1,15 @@
-///! 128 and 256 bit numbers
-///! U128 is more efficient that u128
-///! https://github.com/solana-labs/solana/issues/19549
+//! 128 to 1024 bit numbers
+//! The math runs on native u128 with a widening multiply, see `full_math`. `U256` remains for
+//! the token_0 price step whose intermediate takes 320 bits, the other types are the previous
+//! implementation and kept as a reference for it.
+
+// the code `construct_uint!` expands to is not ours to restyle
+#![allow(
+    clippy::assign_op_pattern,
+    clippy::manual_div_ceil,
+    clippy::reversed_empty_ranges
+)]
+
 use uint::construct_uint;
 construct_uint! {
     pub struct U128(2);
@@ -84,7 +93,7 @@ macro_rules! construct_bignum {
             fn try_from(u: $name) -> $crate::core_::result::Result<i128, &'static str> {
                 let err_str = "integer overflow when casting to i128";
                 let i = u128::try_from(u).map_err(|_| err_str)?;
-                if i > i128::max_value() as u128 {
+                if i > i128::MAX as u128 {
                     Err(err_str)
                 } else {
                     Ok(i as i128)
@@ -116,7 +125,7 @@ macro_rules! construct_bignum {
 
         impl $name {
 			/// Maximum value.
-			pub const MAX: $name = $name([u64::max_value(); $n_words]);
+			pub const MAX: $name = $name([u64::MAX; $n_words]);
 
             /// Conversion to usize with overflow checking
 			///
@@ -126,7 +135,7 @@ macro_rules! construct_bignum {
 			#[inline]
 			pub fn as_usize(&self) -> usize {
 				let &$name(ref arr) = self;
-				if !self.fits_word() || arr[0] > usize::max_value() as u64 {
+				if !self.fits_word() || arr[0] > usize::MAX as u64 {
 					panic!("Integer overflow when casting to usize")
 				}
 				arr[0] as usize
@@ -0,0 +1,53 @@
+//! Word-level operations on bitmaps of little-endian u64 words, bit `i` is bit `i % 64` of
+//! word `i / 64`. The tick array bitmaps of a pool and of its bitmap extension are searched
+//! one word at a time instead of shifting the whole bitmap.
+//!
+//! Indexes beyond the bitmap panic.
+
+/// Whether bit `index` is set
+#[inline]
+pub fn is_set<const N: usize>(bitmap: &[u64; N], index: usize) -> bool {
+    bitmap[index / 64] & (1 << (index % 64)) != 0
+}
+
+/// Flips bit `index`
+#[inline]
+pub fn flip_bit<const N: usize>(bitmap: &mut [u64; N], index: usize) {
+    bitmap[index / 64] ^= 1 << (index % 64);
+}
+
+/// Highest set bit at or below `index`
+#[inline]
+pub fn prev_set_bit<const N: usize>(bitmap: &[u64; N], index: usize) -> Option<usize> {
+    let mut word = index / 64;
+    // bits 0..=index % 64 of the first word
+    let mut bits = bitmap[word] & (u64::MAX >> (63 - index % 64));
+    loop {
+        if bits != 0 {
+            return Some(word * 64 + 63 - bits.leading_zeros() as usize);
+        }
+        if word == 0 {
+            return None;
+        }
+        word -= 1;
+        bits = bitmap[word];
+    }
+}
+
+/// Lowest set bit at or above `index`
+#[inline]
+pub fn next_set_bit<const N: usize>(bitmap: &[u64; N], index: usize) -> Option<usize> {
+    let mut word = index / 64;
+    // bits index % 64..64 of the first word
+    let mut bits = bitmap[word] & (u64::MAX << (index % 64));
+    loop {
+        if bits != 0 {
+            return Some(word * 64 + bits.trailing_zeros() as usize);
+        }
+        word += 1;
+        if word == N {
+            return None;
+        }
+        bits = bitmap[word];
+    }
+}
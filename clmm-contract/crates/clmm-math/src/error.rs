@@ -0,0 +1,43 @@
+use std::fmt;
+
+pub type Result<T> = core::result::Result<T, MathError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    InvalidSqrtPrice,
+    TickLowerOverflow,
+    TickUpperOverflow,
+    InvalidTick,
+    InvalidTickArrayStartIndex,
+    MaxTokenOverflow,
+    LiquidityOverflow,
+    LiquidityZero,
+    SqrtPriceX64Overflow,
+    SqrtPriceLimitOverflow,
+    InvalidFeeRate,
+    FeeAmountOverflow,
+    FeeGrowthUnderflow,
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            MathError::InvalidSqrtPrice => "Invalid sqrt price",
+            MathError::TickLowerOverflow => "Tick lower overflow",
+            MathError::TickUpperOverflow => "Tick upper overflow",
+            MathError::InvalidTick => "Invalid tick",
+            MathError::InvalidTickArrayStartIndex => "Invalid tick array start index",
+            MathError::MaxTokenOverflow => "Max token overflow",
+            MathError::LiquidityOverflow => "Liquidity overflow",
+            MathError::LiquidityZero => "Liquidity must be greater than zero",
+            MathError::SqrtPriceX64Overflow => "Sqrt price x64 overflow",
+            MathError::SqrtPriceLimitOverflow => "Sqrt price limit overflow",
+            MathError::InvalidFeeRate => "Fee rate must be less than the fee rate denominator",
+            MathError::FeeAmountOverflow => "Fee amount overflow",
+            MathError::FeeGrowthUnderflow => "Fee growth underflow",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for MathError {}
@@ -0,0 +1,55 @@
+use crate::{fixed_point_64, full_math::MulDiv, MathError, Result};
+
+pub const FEE_RATE_DENOMINATOR_VALUE: u32 = 1_000_000;
+
+/// Calculates the fee growth inside of tick_lower and tick_upper based on their positions relative to tick_current.
+/// `fee_growth_inside = fee_growth_global - fee_growth_below(lower) - fee_growth_above(upper)`
+pub fn get_fee_growth_inside(
+    tick_lower: i32,
+    tick_lower_fee_growth_outside_x64: u128,
+    tick_upper: i32,
+    tick_upper_fee_growth_outside_x64: u128,
+    tick_current: i32,
+    fee_growth_global_x64: u128,
+) -> Result<u128> {
+    // calculate fee growth below
+    let fee_growth_below_x64 = if tick_current >= tick_lower {
+        tick_lower_fee_growth_outside_x64
+    } else {
+        fee_growth_global_x64
+            .checked_sub(tick_lower_fee_growth_outside_x64)
+            .ok_or(MathError::FeeGrowthUnderflow)?
+    };
+
+    // Calculate fee growth above
+    let fee_growth_above_x64 = if tick_current < tick_upper {
+        tick_upper_fee_growth_outside_x64
+    } else {
+        fee_growth_global_x64
+            .checked_sub(tick_upper_fee_growth_outside_x64)
+            .ok_or(MathError::FeeGrowthUnderflow)?
+    };
+
+    Ok(fee_growth_global_x64
+        .wrapping_sub(fee_growth_below_x64)
+        .wrapping_sub(fee_growth_above_x64))
+}
+
+/// Adds the fees earned by `liquidity` since `fee_growth_inside_last_x64` to `last_total_fees`
+pub fn calculate_latest_token_fees(
+    last_total_fees: u64,
+    fee_growth_inside_last_x64: u128,
+    fee_growth_inside_latest_x64: u128,
+    liquidity: u128,
+) -> Result<u64> {
+    let fee_growth_delta = fixed_point_64::mul_floor(
+        fee_growth_inside_latest_x64.wrapping_sub(fee_growth_inside_last_x64),
+        liquidity,
+    )
+    .ok_or(MathError::FeeAmountOverflow)?
+    .to_underflow_u64();
+
+    last_total_fees
+        .checked_add(fee_growth_delta)
+        .ok_or(MathError::FeeAmountOverflow)
+}
@@ -0,0 +1,33 @@
+/// A library for handling Q64.64 fixed point numbers
+/// Used in sqrt_price_math.rs and liquidity_amounts.rs
+use crate::full_math::widening_mul;
+
+pub const Q64: u128 = (u64::MAX as u128) + 1; // 2^64
+pub const RESOLUTION: u8 = 64;
+
+/// Calculates floor(x * y / 2^64), `None` if the result overflows u128
+///
+/// Same result as `x.mul_div_floor(y, Q64)` with a shift in place of the division
+#[inline]
+pub fn mul_floor(x: u128, y: u128) -> Option<u128> {
+    let (low, high) = widening_mul(x, y);
+    if high >> RESOLUTION != 0 {
+        return None;
+    }
+    Some((high << RESOLUTION) | (low >> RESOLUTION))
+}
+
+/// Calculates ceil(x * y / 2^64), `None` if the result overflows u128
+#[inline]
+pub fn mul_ceil(x: u128, y: u128) -> Option<u128> {
+    let (low, high) = widening_mul(x, y);
+    if high >> RESOLUTION != 0 {
+        return None;
+    }
+    let result = (high << RESOLUTION) | (low >> RESOLUTION);
+    if low as u64 == 0 {
+        Some(result)
+    } else {
+        result.checked_add(1)
+    }
+}
@@ -0,0 +1,237 @@
+//! Multiplication and division helpers
+//! `a * b / denominator` is computed in the next wider type so the intermediate product never overflows.
+//! u64 and u128 use native integers, the 256 bit product of two u128 is kept as two halves.
+use crate::big_nums::{U128, U256, U512};
+
+const LOW_64: u128 = u64::MAX as u128;
+
+/// Full product of two u128 as `(low, high)` halves of a 256 bit number
+#[inline]
+pub const fn widening_mul(x: u128, y: u128) -> (u128, u128) {
+    let (x_0, x_1) = (x & LOW_64, x >> 64);
+    let (y_0, y_1) = (y & LOW_64, y >> 64);
+    let p_00 = x_0 * y_0;
+    let p_01 = x_0 * y_1;
+    let p_10 = x_1 * y_0;
+    // bits 64..192 of the product before carries, at most 3 * 2^64
+    let middle = (p_00 >> 64) + (p_01 & LOW_64) + (p_10 & LOW_64);
+    let low = (middle << 64) | (p_00 & LOW_64);
+    let high = x_1 * y_1 + (p_01 >> 64) + (p_10 >> 64) + (middle >> 64);
+    (low, high)
+}
+
+/// Divides the 256 bit `(low, high)` by `denom`, returns `(quotient, remainder)`
+///
+/// The quotient must fit u128, i.e. `high < denom`. Long division by 64 bit digits of a
+/// normalized divisor, see Hacker's Delight `divlu`.
+#[inline]
+pub fn div_rem_wide(low: u128, high: u128, denom: u128) -> (u128, u128) {
+    debug_assert!(high < denom);
+    if high == 0 {
+        return (low / denom, low % denom);
+    }
+    // shift the divisor up to its top bit, its high digit then approximates quotient digits
+    // within 2. `high < denom` so the dividend does not lose bits.
+    let shift = denom.leading_zeros();
+    let denom = denom << shift;
+    let high = if shift == 0 {
+        high
+    } else {
+        (high << shift) | (low >> (128 - shift))
+    };
+    let low = low << shift;
+
+    let (quotient_1, remainder) = div_rem_digit(high, low >> 64, denom);
+    let (quotient_0, remainder) = div_rem_digit(remainder, low & LOW_64, denom);
+    ((quotient_1 << 64) | quotient_0, remainder >> shift)
+}
+
+/// Divides `high * 2^64 + digit` by the normalized `denom`, where `high < denom` so the
+/// quotient is a single 64 bit digit
+#[inline]
+fn div_rem_digit(high: u128, digit: u128, denom: u128) -> (u128, u128) {
+    let (denom_1, denom_0) = (denom >> 64, denom & LOW_64);
+    let mut quotient = high / denom_1;
+    let mut remainder = high % denom_1;
+    while quotient > LOW_64 || quotient * denom_0 > ((remainder << 64) | digit) {
+        quotient -= 1;
+        remainder += denom_1;
+        if remainder > LOW_64 {
+            break;
+        }
+    }
+    // the true remainder is below `denom`, wrapping arithmetic drops the bits that cancel out
+    let remainder = ((high << 64) | digit).wrapping_sub(quotient.wrapping_mul(denom));
+    (quotient, remainder)
+}
+
+pub trait Upcast256 {
+    fn to_u256(self) -> U256;
+}
+impl Upcast256 for U128 {
+    fn to_u256(self) -> U256 {
+        U256([self.0[0], self.0[1], 0, 0])
+    }
+}
+
+pub trait Downcast256 {
+    /// Unsafe cast to U128
+    /// Bits beyond the 128th position are lost
+    fn to_u128(self) -> U128;
+}
+impl Downcast256 for U256 {
+    fn to_u128(self) -> U128 {
+        U128([self.0[0], self.0[1]])
+    }
+}
+
+pub trait Upcast512 {
+    fn to_u512(self) -> U512;
+}
+impl Upcast512 for U256 {
+    fn to_u512(self) -> U512 {
+        U512([self.0[0], self.0[1], self.0[2], self.0[3], 0, 0, 0, 0])
+    }
+}
+
+pub trait Downcast512 {
+    /// Unsafe cast to U256
+    /// Bits beyond the 256th position are lost
+    fn to_u256(self) -> U256;
+}
+impl Downcast512 for U512 {
+    fn to_u256(self) -> U256 {
+        U256([self.0[0], self.0[1], self.0[2], self.0[3]])
+    }
+}
+
+pub trait MulDiv<RHS = Self> {
+    type Output;
+
+    /// Calculates floor(self * num / denom), `None` if the result overflows `Output`
+    fn mul_div_floor(self, num: RHS, denom: RHS) -> Option<Self::Output>;
+
+    /// Calculates ceil(self * num / denom), `None` if the result overflows `Output`
+    fn mul_div_ceil(self, num: RHS, denom: RHS) -> Option<Self::Output>;
+
+    /// Returns the value as u64, or 0 if it does not fit
+    fn to_underflow_u64(self) -> u64;
+}
+
+impl MulDiv for u64 {
+    type Output = u64;
+
+    fn mul_div_floor(self, num: Self, denom: Self) -> Option<Self::Output> {
+        assert_ne!(denom, 0);
+        let r = u128::from(self) * u128::from(num) / u128::from(denom);
+        u64::try_from(r).ok()
+    }
+
+    fn mul_div_ceil(self, num: Self, denom: Self) -> Option<Self::Output> {
+        assert_ne!(denom, 0);
+        // (2^64 - 1)^2 + 2^64 - 2 still fits u128
+        let r = (u128::from(self) * u128::from(num) + u128::from(denom - 1)) / u128::from(denom);
+        u64::try_from(r).ok()
+    }
+
+    fn to_underflow_u64(self) -> u64 {
+        self
+    }
+}
+
+impl MulDiv for u128 {
+    type Output = u128;
+
+    fn mul_div_floor(self, num: Self, denom: Self) -> Option<Self::Output> {
+        assert_ne!(denom, 0);
+        let (low, high) = widening_mul(self, num);
+        if high >= denom {
+            return None;
+        }
+        Some(div_rem_wide(low, high, denom).0)
+    }
+
+    fn mul_div_ceil(self, num: Self, denom: Self) -> Option<Self::Output> {
+        assert_ne!(denom, 0);
+        let (low, high) = widening_mul(self, num);
+        if high >= denom {
+            return None;
+        }
+        match div_rem_wide(low, high, denom) {
+            (quotient, 0) => Some(quotient),
+            (quotient, _) => quotient.checked_add(1),
+        }
+    }
+
+    fn to_underflow_u64(self) -> u64 {
+        if self < u128::from(u64::MAX) {
+            self as u64
+        } else {
+            0
+        }
+    }
+}
+
+impl MulDiv for U128 {
+    type Output = U128;
+
+    fn mul_div_floor(self, num: Self, denom: Self) -> Option<Self::Output> {
+        assert_ne!(denom, U128::default());
+        let r = (self.to_u256() * num.to_u256()) / denom.to_u256();
+        if r > U128::MAX.to_u256() {
+            None
+        } else {
+            Some(r.to_u128())
+        }
+    }
+
+    fn mul_div_ceil(self, num: Self, denom: Self) -> Option<Self::Output> {
+        assert_ne!(denom, U128::default());
+        let r = (self.to_u256() * num.to_u256() + (denom - 1).to_u256()) / denom.to_u256();
+        if r > U128::MAX.to_u256() {
+            None
+        } else {
+            Some(r.to_u128())
+        }
+    }
+
+    fn to_underflow_u64(self) -> u64 {
+        if self < U128::from(u64::MAX) {
+            self.as_u64()
+        } else {
+            0
+        }
+    }
+}
+
+impl MulDiv for U256 {
+    type Output = U256;
+
+    fn mul_div_floor(self, num: Self, denom: Self) -> Option<Self::Output> {
+        assert_ne!(denom, U256::default());
+        let r = (self.to_u512() * num.to_u512()) / denom.to_u512();
+        if r > U256::MAX.to_u512() {
+            None
+        } else {
+            Some(r.to_u256())
+        }
+    }
+
+    fn mul_div_ceil(self, num: Self, denom: Self) -> Option<Self::Output> {
+        assert_ne!(denom, U256::default());
+        let r = (self.to_u512() * num.to_u512() + (denom - 1).to_u512()) / denom.to_u512();
+        if r > U256::MAX.to_u512() {
+            None
+        } else {
+            Some(r.to_u256())
+        }
+    }
+
+    fn to_underflow_u64(self) -> u64 {
+        if self < U256::from(u64::MAX) {
+            self.as_u64()
+        } else {
+            0
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Tick, sqrt price, liquidity and fee math of the clmm program
+//!
+//! This crate has no dependency on anchor so bots, backends and tests can link
+//! the exact code the program runs on chain. The program re-exports every module.
+
+pub mod big_nums;
+pub mod bit_math;
+pub mod error;
+pub mod fee_math;
+pub mod fixed_point_64;
+pub mod full_math;
+pub mod liquidity_math;
+pub mod sqrt_price_math;
+pub mod swap_math;
+pub mod tick_math;
+pub mod unsafe_math;
+
+pub use error::*;
+
+// used by `construct_bignum!`
+#[doc(hidden)]
+pub use core as core_;
@@ -0,0 +1,282 @@
+use crate::{
+    fixed_point_64,
+    full_math::{div_rem_wide, widening_mul, MulDiv},
+    tick_math::get_sqrt_price_at_tick,
+    MathError, Result,
+};
+
+/// Add a signed liquidity delta to liquidity
+pub fn add_delta(x: u128, y: i128) -> Result<u128> {
+    if y < 0 {
+        x.checked_sub(y.unsigned_abs())
+    } else {
+        x.checked_add(y as u128)
+    }
+    .ok_or(MathError::LiquidityOverflow)
+}
+
+/// Computes the maximum amount of liquidity received for a given amount of token_0, token_1, the current
+/// pool prices and the prices at the tick boundaries
+pub fn get_liquidity_from_single_amount_0(
+    sqrt_ratio_x64: u128,
+    mut sqrt_ratio_a_x64: u128,
+    mut sqrt_ratio_b_x64: u128,
+    amount_0: u64,
+) -> Result<u128> {
+    // sqrt_ratio_a_x64 should hold the smaller value
+    if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+        std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+    };
+
+    if sqrt_ratio_x64 <= sqrt_ratio_a_x64 {
+        // If P ≤ P_lower, only token_0 liquidity is active
+        get_liquidity_from_amount_0(sqrt_ratio_a_x64, sqrt_ratio_b_x64, amount_0)
+    } else if sqrt_ratio_x64 < sqrt_ratio_b_x64 {
+        // If P_lower < P < P_upper, active liquidity is the minimum of the liquidity provided
+        // by token_0 and token_1
+        get_liquidity_from_amount_0(sqrt_ratio_x64, sqrt_ratio_b_x64, amount_0)
+    } else {
+        // If P ≥ P_upper, only token_1 liquidity is active
+        Ok(0)
+    }
+}
+
+/// Computes the maximum amount of liquidity received for a given amount of token_0, token_1, the current
+/// pool prices and the prices at the tick boundaries
+pub fn get_liquidity_from_single_amount_1(
+    sqrt_ratio_x64: u128,
+    mut sqrt_ratio_a_x64: u128,
+    mut sqrt_ratio_b_x64: u128,
+    amount_1: u64,
+) -> Result<u128> {
+    // sqrt_ratio_a_x64 should hold the smaller value
+    if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+        std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+    };
+
+    if sqrt_ratio_x64 <= sqrt_ratio_a_x64 {
+        // If P ≤ P_lower, only token_0 liquidity is active
+        Ok(0)
+    } else if sqrt_ratio_x64 < sqrt_ratio_b_x64 {
+        // If P_lower < P < P_upper, active liquidity is the minimum of the liquidity provided
+        // by token_0 and token_1
+        get_liquidity_from_amount_1(sqrt_ratio_a_x64, sqrt_ratio_x64, amount_1)
+    } else {
+        // If P ≥ P_upper, only token_1 liquidity is active
+        get_liquidity_from_amount_1(sqrt_ratio_a_x64, sqrt_ratio_b_x64, amount_1)
+    }
+}
+
+/// Computes the maximum amount of liquidity received for given amounts of token_0 and token_1, the
+/// current pool price and the prices at the tick boundaries
+pub fn get_liquidity_from_amounts(
+    sqrt_ratio_x64: u128,
+    mut sqrt_ratio_a_x64: u128,
+    mut sqrt_ratio_b_x64: u128,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<u128> {
+    // sqrt_ratio_a_x64 should hold the smaller value
+    if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+        std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+    };
+
+    if sqrt_ratio_x64 <= sqrt_ratio_a_x64 {
+        get_liquidity_from_amount_0(sqrt_ratio_a_x64, sqrt_ratio_b_x64, amount_0)
+    } else if sqrt_ratio_x64 < sqrt_ratio_b_x64 {
+        // the scarcer token limits the liquidity
+        let liquidity_0 = get_liquidity_from_amount_0(sqrt_ratio_x64, sqrt_ratio_b_x64, amount_0)?;
+        let liquidity_1 = get_liquidity_from_amount_1(sqrt_ratio_a_x64, sqrt_ratio_x64, amount_1)?;
+        Ok(liquidity_0.min(liquidity_1))
+    } else {
+        get_liquidity_from_amount_1(sqrt_ratio_a_x64, sqrt_ratio_b_x64, amount_1)
+    }
+}
+
+/// Computes the amount of liquidity received for a given amount of token_0 and price range
+/// Calculates ΔL = Δx (√P_upper x √P_lower)/(√P_upper - √P_lower)
+pub fn get_liquidity_from_amount_0(
+    mut sqrt_ratio_a_x64: u128,
+    mut sqrt_ratio_b_x64: u128,
+    amount_0: u64,
+) -> Result<u128> {
+    // sqrt_ratio_a_x64 should hold the smaller value
+    if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+        std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+    };
+    if sqrt_ratio_a_x64 == sqrt_ratio_b_x64 {
+        return Err(MathError::InvalidSqrtPrice);
+    }
+    let intermediate = fixed_point_64::mul_floor(sqrt_ratio_a_x64, sqrt_ratio_b_x64)
+        .ok_or(MathError::LiquidityOverflow)?;
+
+    u128::from(amount_0)
+        .mul_div_floor(intermediate, sqrt_ratio_b_x64 - sqrt_ratio_a_x64)
+        .ok_or(MathError::LiquidityOverflow)
+}
+
+/// Computes the amount of liquidity received for a given amount of token_1 and price range
+/// Calculates ΔL = Δy / (√P_upper - √P_lower)
+pub fn get_liquidity_from_amount_1(
+    mut sqrt_ratio_a_x64: u128,
+    mut sqrt_ratio_b_x64: u128,
+    amount_1: u64,
+) -> Result<u128> {
+    // sqrt_ratio_a_x64 should hold the smaller value
+    if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+        std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+    };
+    if sqrt_ratio_a_x64 == sqrt_ratio_b_x64 {
+        return Err(MathError::InvalidSqrtPrice);
+    }
+
+    // Δy * 2^64 fits u128
+    Ok(
+        (u128::from(amount_1) << fixed_point_64::RESOLUTION)
+            / (sqrt_ratio_b_x64 - sqrt_ratio_a_x64),
+    )
+}
+
+// gets the delta amount_0 for given liquidity and price range
+/// # Formula
+///
+/// * `Δx = L * (1 / √P_lower - 1 / √P_upper)`
+/// * i.e. `L * (√P_upper - √P_lower) / (√P_upper * √P_lower)`
+pub fn get_delta_amount_0_unsigned(
+    mut sqrt_ratio_a_x64: u128,
+    mut sqrt_ratio_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<u64> {
+    // sqrt_ratio_a_x64 should hold the smaller value
+    if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+        std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+    };
+    if sqrt_ratio_a_x64 == 0 {
+        return Err(MathError::InvalidSqrtPrice);
+    }
+
+    // L * 2^64 * (√P_upper - √P_lower) takes up to 320 bits. L * (√P_upper - √P_lower) is
+    // divided by √P_upper first, then its remainder shifted by 64 bits, both quotients fit u128
+    // as √P_upper - √P_lower < √P_upper.
+    let (low, high) = widening_mul(liquidity, sqrt_ratio_b_x64 - sqrt_ratio_a_x64);
+    let (quotient, remainder) = div_rem_wide(low, high, sqrt_ratio_b_x64);
+    let (fraction, remainder) = div_rem_wide(
+        remainder << fixed_point_64::RESOLUTION,
+        remainder >> fixed_point_64::RESOLUTION,
+        sqrt_ratio_b_x64,
+    );
+    // L * 2^64 * (√P_upper - √P_lower) / √P_upper, 192 bits as (low, high)
+    let (mut low, mut high) = (
+        (quotient << fixed_point_64::RESOLUTION) | fraction,
+        quotient >> fixed_point_64::RESOLUTION,
+    );
+    if round_up && remainder != 0 {
+        (low, high) = match low.checked_add(1) {
+            Some(low) => (low, high),
+            None => (0, high + 1),
+        };
+    }
+
+    // a quotient above u128 is above u64 as well
+    if high >= sqrt_ratio_a_x64 {
+        return Err(MathError::MaxTokenOverflow);
+    }
+    let result = match div_rem_wide(low, high, sqrt_ratio_a_x64) {
+        (quotient, remainder) if round_up && remainder != 0 => quotient.checked_add(1),
+        (quotient, _) => Some(quotient),
+    };
+    result
+        .and_then(|result| u64::try_from(result).ok())
+        .ok_or(MathError::MaxTokenOverflow)
+}
+
+/// Gets the delta amount_1 for given liquidity and price range
+/// * `Δy = L (√P_upper - √P_lower)`
+pub fn get_delta_amount_1_unsigned(
+    mut sqrt_ratio_a_x64: u128,
+    mut sqrt_ratio_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<u64> {
+    // sqrt_ratio_a_x64 should hold the smaller value
+    if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+        std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+    };
+
+    let result = if round_up {
+        fixed_point_64::mul_ceil(liquidity, sqrt_ratio_b_x64 - sqrt_ratio_a_x64)
+    } else {
+        fixed_point_64::mul_floor(liquidity, sqrt_ratio_b_x64 - sqrt_ratio_a_x64)
+    };
+    result
+        .and_then(|result| u64::try_from(result).ok())
+        .ok_or(MathError::MaxTokenOverflow)
+}
+
+/// Helper function to get signed delta amount_0 for given liquidity and price range
+/// Amounts are rounded up when liquidity is added and down when it is removed
+pub fn get_delta_amount_0_signed(
+    sqrt_ratio_a_x64: u128,
+    sqrt_ratio_b_x64: u128,
+    liquidity: i128,
+) -> Result<u64> {
+    get_delta_amount_0_unsigned(
+        sqrt_ratio_a_x64,
+        sqrt_ratio_b_x64,
+        liquidity.unsigned_abs(),
+        liquidity >= 0,
+    )
+}
+
+/// Helper function to get signed delta amount_1 for given liquidity and price range
+/// Amounts are rounded up when liquidity is added and down when it is removed
+pub fn get_delta_amount_1_signed(
+    sqrt_ratio_a_x64: u128,
+    sqrt_ratio_b_x64: u128,
+    liquidity: i128,
+) -> Result<u64> {
+    get_delta_amount_1_unsigned(
+        sqrt_ratio_a_x64,
+        sqrt_ratio_b_x64,
+        liquidity.unsigned_abs(),
+        liquidity >= 0,
+    )
+}
+
+/// Gets the token amounts backing `liquidity_delta` in [tick_lower, tick_upper) at the current price
+pub fn get_delta_amounts_signed(
+    tick_current: i32,
+    sqrt_price_x64_current: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity_delta: i128,
+) -> Result<(u64, u64)> {
+    let mut amount_0 = 0;
+    let mut amount_1 = 0;
+    if tick_current < tick_lower {
+        amount_0 = get_delta_amount_0_signed(
+            get_sqrt_price_at_tick(tick_lower)?,
+            get_sqrt_price_at_tick(tick_upper)?,
+            liquidity_delta,
+        )?;
+    } else if tick_current < tick_upper {
+        amount_0 = get_delta_amount_0_signed(
+            sqrt_price_x64_current,
+            get_sqrt_price_at_tick(tick_upper)?,
+            liquidity_delta,
+        )?;
+        amount_1 = get_delta_amount_1_signed(
+            get_sqrt_price_at_tick(tick_lower)?,
+            sqrt_price_x64_current,
+            liquidity_delta,
+        )?;
+    } else {
+        amount_1 = get_delta_amount_1_signed(
+            get_sqrt_price_at_tick(tick_lower)?,
+            get_sqrt_price_at_tick(tick_upper)?,
+            liquidity_delta,
+        )?;
+    }
+    Ok((amount_0, amount_1))
+}
@@ -0,0 +1,159 @@
+use crate::{
+    big_nums::U256, fixed_point_64, full_math::MulDiv, unsafe_math::UnsafeMathTrait, MathError,
+    Result,
+};
+
+/// Gets the next sqrt price √P' given a delta of token_0
+///
+/// Always round up because
+/// 1. In the exact output case, token 0 supply decreases leading to price increase.
+///    Move price up so that exact output is met.
+/// 2. In the exact input case, token 0 supply increases leading to price decrease.
+///    Do not round down to minimize price impact. We only need to meet input
+///    change and not guarantee exact output.
+///
+/// Use function for exact input or exact output swaps for token 0
+///
+/// `L * 2^64 * √P` takes up to 320 bits, so unlike the other price and amount helpers this one
+/// still runs on `U256`.
+///
+/// # Formula
+///
+/// * `√P' = √P * L / (L + Δx * √P)`
+/// * If Δx * √P overflows, use alternate form `√P' = L / (L/√P + Δx)`
+pub fn get_next_sqrt_price_from_amount_0_rounding_up(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount: u64,
+    add: bool,
+) -> Result<u128> {
+    // we short circuit amount == 0 because the result is otherwise not
+    // guaranteed to equal the input price
+    if amount == 0 {
+        return Ok(sqrt_price_x64);
+    }
+    let numerator_1 = U256::from(liquidity) << fixed_point_64::RESOLUTION;
+
+    let result = if add {
+        if let Some(product) = U256::from(amount).checked_mul(U256::from(sqrt_price_x64)) {
+            let denominator = numerator_1 + product;
+            if denominator >= numerator_1 {
+                return downcast_sqrt_price(
+                    numerator_1.mul_div_ceil(U256::from(sqrt_price_x64), denominator),
+                );
+            };
+        }
+        // L / (L/√P + Δx)
+        Some(U256::div_rounding_up(
+            numerator_1,
+            (numerator_1 / U256::from(sqrt_price_x64))
+                .checked_add(U256::from(amount))
+                .ok_or(MathError::SqrtPriceX64Overflow)?,
+        ))
+    } else {
+        // if the product overflows, we know the denominator underflows
+        // in addition, we must check that the denominator does not underflow
+        let product = U256::from(amount)
+            .checked_mul(U256::from(sqrt_price_x64))
+            .ok_or(MathError::SqrtPriceX64Overflow)?;
+        if numerator_1 <= product {
+            return Err(MathError::SqrtPriceX64Overflow);
+        }
+        let denominator = numerator_1 - product;
+        numerator_1.mul_div_ceil(U256::from(sqrt_price_x64), denominator)
+    };
+    downcast_sqrt_price(result)
+}
+
+/// Gets the next sqrt price given a delta of token_1
+///
+/// Always round down because
+/// 1. In the exact output case, token 1 supply decreases leading to price decrease.
+///    Move price down by rounding up so that exact output of token 0 is met.
+/// 2. In the exact input case, token 1 supply increases leading to price increase.
+///    Do not round down to minimize price impact. We only need to meet input
+///    change and not gurantee exact output for token 0.
+///
+/// # Formula
+///
+/// * `√P' = √P + Δy / L`
+pub fn get_next_sqrt_price_from_amount_1_rounding_down(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount: u64,
+    add: bool,
+) -> Result<u128> {
+    // Δy * 2^64 fits u128
+    let numerator = u128::from(amount) << fixed_point_64::RESOLUTION;
+    if add {
+        // quotient - `Δy / L`
+        let quotient = numerator / liquidity;
+        sqrt_price_x64
+            .checked_add(quotient)
+            .ok_or(MathError::SqrtPriceX64Overflow)
+    } else {
+        let quotient = u128::div_rounding_up(numerator, liquidity);
+        if sqrt_price_x64 <= quotient {
+            return Err(MathError::SqrtPriceX64Overflow);
+        }
+        Ok(sqrt_price_x64 - quotient)
+    }
+}
+
+/// Gets the next sqrt price given an input amount of token_0 or token_1
+/// Throws if price or liquidity are 0, or if the next price is out of bounds
+pub fn get_next_sqrt_price_from_input(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount_in: u64,
+    zero_for_one: bool,
+) -> Result<u128> {
+    if sqrt_price_x64 == 0 {
+        return Err(MathError::SqrtPriceX64Overflow);
+    }
+    if liquidity == 0 {
+        return Err(MathError::LiquidityZero);
+    }
+
+    // round to make sure that we don't pass the target price
+    if zero_for_one {
+        get_next_sqrt_price_from_amount_0_rounding_up(sqrt_price_x64, liquidity, amount_in, true)
+    } else {
+        get_next_sqrt_price_from_amount_1_rounding_down(sqrt_price_x64, liquidity, amount_in, true)
+    }
+}
+
+/// Gets the next sqrt price given an output amount of token0 or token1
+///
+/// Throws if price or liquidity are 0 or the next price is out of bounds
+pub fn get_next_sqrt_price_from_output(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount_out: u64,
+    zero_for_one: bool,
+) -> Result<u128> {
+    if sqrt_price_x64 == 0 {
+        return Err(MathError::SqrtPriceX64Overflow);
+    }
+    if liquidity == 0 {
+        return Err(MathError::LiquidityZero);
+    }
+
+    if zero_for_one {
+        get_next_sqrt_price_from_amount_1_rounding_down(
+            sqrt_price_x64,
+            liquidity,
+            amount_out,
+            false,
+        )
+    } else {
+        get_next_sqrt_price_from_amount_0_rounding_up(sqrt_price_x64, liquidity, amount_out, false)
+    }
+}
+
+fn downcast_sqrt_price(value: Option<U256>) -> Result<u128> {
+    match value {
+        Some(v) if v <= U256::from(u128::MAX) => Ok(v.as_u128()),
+        _ => Err(MathError::SqrtPriceX64Overflow),
+    }
+}
@@ -0,0 +1,485 @@
+use crate::{
+    fee_math::FEE_RATE_DENOMINATOR_VALUE,
+    fixed_point_64,
+    full_math::MulDiv,
+    liquidity_math::{self, get_delta_amount_0_unsigned, get_delta_amount_1_unsigned},
+    sqrt_price_math::{get_next_sqrt_price_from_input, get_next_sqrt_price_from_output},
+    tick_math::{self, MAX_TICK, MIN_TICK},
+    MathError, Result,
+};
+
+/// Result of a swap step within a single tick range
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    /// The price after swapping the amount in/out, not to exceed the price target
+    pub sqrt_price_next_x64: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+}
+
+/// Computes the result of swapping some amount in, or amount out, given the parameters of the swap
+///
+/// The fee, plus the amount in, will never exceed the amount remaining if the swap's
+/// `amount_specified` is positive (base input)
+pub fn compute_swap_step(
+    sqrt_price_current_x64: u128,
+    sqrt_price_target_x64: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_rate: u32,
+    is_base_input: bool,
+    zero_for_one: bool,
+) -> Result<SwapStep> {
+    if fee_rate >= FEE_RATE_DENOMINATOR_VALUE {
+        return Err(MathError::InvalidFeeRate);
+    }
+
+    let mut swap_step = SwapStep::default();
+    if is_base_input {
+        // round up amount_in
+        // In exact input case, amount_remaining is positive
+        let amount_remaining_less_fee = amount_remaining
+            .mul_div_floor(
+                (FEE_RATE_DENOMINATOR_VALUE - fee_rate).into(),
+                u64::from(FEE_RATE_DENOMINATOR_VALUE),
+            )
+            .ok_or(MathError::MaxTokenOverflow)?;
+
+        let amount_in = calculate_amount_in_range(
+            sqrt_price_current_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            zero_for_one,
+            is_base_input,
+        )?;
+        if let Some(amount_in) = amount_in {
+            swap_step.amount_in = amount_in;
+        }
+
+        swap_step.sqrt_price_next_x64 =
+            if amount_in.is_some() && amount_remaining_less_fee >= swap_step.amount_in {
+                sqrt_price_target_x64
+            } else {
+                get_next_sqrt_price_from_input(
+                    sqrt_price_current_x64,
+                    liquidity,
+                    amount_remaining_less_fee,
+                    zero_for_one,
+                )?
+            };
+    } else {
+        // round down amount_out
+        let amount_out = calculate_amount_in_range(
+            sqrt_price_current_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            zero_for_one,
+            is_base_input,
+        )?;
+        if let Some(amount_out) = amount_out {
+            swap_step.amount_out = amount_out;
+        }
+        // In exact output case, amount_remaining is negative
+        swap_step.sqrt_price_next_x64 =
+            if amount_out.is_some() && amount_remaining >= swap_step.amount_out {
+                sqrt_price_target_x64
+            } else {
+                get_next_sqrt_price_from_output(
+                    sqrt_price_current_x64,
+                    liquidity,
+                    amount_remaining,
+                    zero_for_one,
+                )?
+            }
+    }
+
+    // whether we reached the max possible price for the given ticks
+    let max = sqrt_price_target_x64 == swap_step.sqrt_price_next_x64;
+    // get the input / output amounts when target price is not reached
+    if zero_for_one {
+        // if max is reached for exact input case, entire amount_in is needed
+        if !(max && is_base_input) {
+            swap_step.amount_in = get_delta_amount_0_unsigned(
+                swap_step.sqrt_price_next_x64,
+                sqrt_price_current_x64,
+                liquidity,
+                true,
+            )?
+        };
+        // if max is reached for exact output case, entire amount_out is needed
+        if !max || is_base_input {
+            swap_step.amount_out = get_delta_amount_1_unsigned(
+                swap_step.sqrt_price_next_x64,
+                sqrt_price_current_x64,
+                liquidity,
+                false,
+            )?;
+        };
+    } else {
+        if !(max && is_base_input) {
+            swap_step.amount_in = get_delta_amount_1_unsigned(
+                sqrt_price_current_x64,
+                swap_step.sqrt_price_next_x64,
+                liquidity,
+                true,
+            )?
+        };
+        if !max || is_base_input {
+            swap_step.amount_out = get_delta_amount_0_unsigned(
+                sqrt_price_current_x64,
+                swap_step.sqrt_price_next_x64,
+                liquidity,
+                false,
+            )?
+        };
+    }
+
+    // For exact output case, cap the output amount to not exceed the remaining output amount
+    if !is_base_input && swap_step.amount_out > amount_remaining {
+        swap_step.amount_out = amount_remaining;
+    }
+
+    swap_step.fee_amount =
+        if is_base_input && swap_step.sqrt_price_next_x64 != sqrt_price_target_x64 {
+            // we didn't reach the target, so take the remainder of the maximum input as fee
+            // swap dust is granted as fee
+            amount_remaining - swap_step.amount_in
+        } else {
+            // take pip percentage as fee
+            swap_step
+                .amount_in
+                .mul_div_ceil(
+                    fee_rate.into(),
+                    (FEE_RATE_DENOMINATOR_VALUE - fee_rate).into(),
+                )
+                .ok_or(MathError::FeeAmountOverflow)?
+        };
+
+    Ok(swap_step)
+}
+
+/// Initialized ticks a swap walks through
+///
+/// The program reads them from tick array accounts, clients from snapshots of those accounts.
+pub trait SwapTicks {
+    /// Error of the tick lookup, math errors of the swap are converted into it
+    type Error: From<MathError>;
+
+    /// The next initialized tick from `tick` in the swap direction, at or below `tick` when
+    /// `zero_for_one` and above it otherwise. `None` lets the swap run to the end of the range.
+    fn next_initialized_tick(
+        &mut self,
+        tick: i32,
+        zero_for_one: bool,
+    ) -> core::result::Result<Option<i32>, Self::Error>;
+
+    /// Cross the initialized `tick` at `fee_growth_global_x64`, the fee growth of the input
+    /// token, and return its `liquidity_net`
+    fn cross_tick(
+        &mut self,
+        tick: i32,
+        fee_growth_global_x64: u128,
+        zero_for_one: bool,
+    ) -> core::result::Result<i128, Self::Error>;
+}
+
+/// Price, liquidity and input token fee growth of a pool, before and after a swap
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapState {
+    pub sqrt_price_x64: u128,
+    pub tick: i32,
+    pub liquidity: u128,
+    pub fee_growth_global_x64: u128,
+}
+
+/// Fee rates of an amm config, see [`compute_swap`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapFeeRates {
+    pub trade_fee_rate: u32,
+    pub protocol_fee_rate: u32,
+    pub fund_fee_rate: u32,
+}
+
+/// Result of a swap across ticks
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapOutcome {
+    pub state: SwapState,
+    /// Input taken, trade fee included
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Trade fee, the sum of the protocol, fund and lp fees
+    pub fee_amount: u64,
+    pub protocol_fee: u64,
+    pub fund_fee: u64,
+    pub lp_fee: u64,
+    /// Number of initialized ticks crossed
+    pub ticks_crossed: u32,
+}
+
+/// Swap `amount_specified` from `state`, the exact input when `is_base_input` and the exact
+/// output otherwise, until it is used up or the price reaches `sqrt_price_limit_x64`
+///
+/// Steps with [`compute_swap_step`] from one initialized tick of `ticks` to the next and crosses
+/// them. Of every step's trade fee the protocol takes its share first and the fund its share of
+/// the rest, what remains grows the fee growth of the input token. The caller checks the price
+/// limit against the current price.
+pub fn compute_swap<T: SwapTicks>(
+    ticks: &mut T,
+    state: SwapState,
+    fee_rates: SwapFeeRates,
+    amount_specified: u64,
+    sqrt_price_limit_x64: u128,
+    zero_for_one: bool,
+    is_base_input: bool,
+) -> core::result::Result<SwapOutcome, T::Error> {
+    let SwapState {
+        mut sqrt_price_x64,
+        mut tick,
+        mut liquidity,
+        mut fee_growth_global_x64,
+    } = state;
+    let mut amount_specified_remaining = amount_specified;
+    let mut amount_calculated: u64 = 0;
+    let mut outcome = SwapOutcome::default();
+
+    while amount_specified_remaining != 0 && sqrt_price_x64 != sqrt_price_limit_x64 {
+        let sqrt_price_start_x64 = sqrt_price_x64;
+
+        let next_initialized_tick = ticks.next_initialized_tick(tick, zero_for_one)?;
+        let tick_next = next_initialized_tick
+            .unwrap_or(if zero_for_one { MIN_TICK } else { MAX_TICK })
+            .clamp(MIN_TICK, MAX_TICK);
+        let sqrt_price_next_x64 = tick_math::get_sqrt_price_at_tick(tick_next)?;
+        let target_price = if (zero_for_one && sqrt_price_next_x64 < sqrt_price_limit_x64)
+            || (!zero_for_one && sqrt_price_next_x64 > sqrt_price_limit_x64)
+        {
+            sqrt_price_limit_x64
+        } else {
+            sqrt_price_next_x64
+        };
+
+        let step = compute_swap_step(
+            sqrt_price_x64,
+            target_price,
+            liquidity,
+            amount_specified_remaining,
+            fee_rates.trade_fee_rate,
+            is_base_input,
+            zero_for_one,
+        )?;
+        sqrt_price_x64 = step.sqrt_price_next_x64;
+
+        let step_amount_in = step
+            .amount_in
+            .checked_add(step.fee_amount)
+            .ok_or(MathError::MaxTokenOverflow)?;
+        let (step_amount_specified, step_amount_calculated) = if is_base_input {
+            (step_amount_in, step.amount_out)
+        } else {
+            (step.amount_out, step_amount_in)
+        };
+        amount_specified_remaining = amount_specified_remaining
+            .checked_sub(step_amount_specified)
+            .ok_or(MathError::MaxTokenOverflow)?;
+        amount_calculated = amount_calculated
+            .checked_add(step_amount_calculated)
+            .ok_or(MathError::MaxTokenOverflow)?;
+
+        // the protocol takes its share of the trade fee first, the fund takes its share of the rest
+        let mut lp_fee = step.fee_amount;
+        let protocol_fee = fee_share(lp_fee, fee_rates.protocol_fee_rate);
+        lp_fee -= protocol_fee;
+        let fund_fee = fee_share(lp_fee, fee_rates.fund_fee_rate);
+        lp_fee -= fund_fee;
+        outcome.fee_amount = outcome
+            .fee_amount
+            .checked_add(step.fee_amount)
+            .ok_or(MathError::FeeAmountOverflow)?;
+        outcome.protocol_fee = outcome
+            .protocol_fee
+            .checked_add(protocol_fee)
+            .ok_or(MathError::FeeAmountOverflow)?;
+        outcome.fund_fee = outcome
+            .fund_fee
+            .checked_add(fund_fee)
+            .ok_or(MathError::FeeAmountOverflow)?;
+        outcome.lp_fee = outcome
+            .lp_fee
+            .checked_add(lp_fee)
+            .ok_or(MathError::FeeAmountOverflow)?;
+        // fees are u64, fees * 2^64 fits u128
+        if let Some(fee_growth_delta_x64) =
+            (u128::from(lp_fee) << fixed_point_64::RESOLUTION).checked_div(liquidity)
+        {
+            fee_growth_global_x64 = fee_growth_global_x64.wrapping_add(fee_growth_delta_x64);
+        }
+
+        if sqrt_price_x64 == sqrt_price_next_x64 {
+            // the price reached the next tick, cross it
+            if next_initialized_tick.is_some() {
+                let mut liquidity_net =
+                    ticks.cross_tick(tick_next, fee_growth_global_x64, zero_for_one)?;
+                if zero_for_one {
+                    liquidity_net = -liquidity_net;
+                }
+                liquidity = liquidity_math::add_delta(liquidity, liquidity_net)?;
+                outcome.ticks_crossed += 1;
+            }
+            tick = if zero_for_one {
+                tick_next - 1
+            } else {
+                tick_next
+            };
+        } else if sqrt_price_x64 != sqrt_price_start_x64 {
+            // recompute the tick unless the price did not move
+            tick = tick_math::get_tick_at_sqrt_price(sqrt_price_x64)?;
+        }
+    }
+
+    let amount_specified_used = amount_specified - amount_specified_remaining;
+    (outcome.amount_in, outcome.amount_out) = if is_base_input {
+        (amount_specified_used, amount_calculated)
+    } else {
+        (amount_calculated, amount_specified_used)
+    };
+    outcome.state = SwapState {
+        sqrt_price_x64,
+        tick,
+        liquidity,
+        fee_growth_global_x64,
+    };
+    Ok(outcome)
+}
+
+/// Precision of [`compute_zap_swap_amount`], which knows the swap amount to within
+/// `amount >> ZAP_SWAP_AMOUNT_PRECISION_BITS`
+pub const ZAP_SWAP_AMOUNT_PRECISION_BITS: u32 = 16;
+
+/// Part of `amount` a zap swaps before adding both tokens to the range from
+/// `sqrt_price_lower_x64` to `sqrt_price_upper_x64`, in a pool at `sqrt_price_x64`
+///
+/// Searches the largest swap amount after which the input left is still in excess of the ratio
+/// the range needs at the new price, which gives the most liquidity. `swap` simulates the exact
+/// input swap of the amount it is given and returns `None` when the pool cannot fill it. The
+/// search stops once the swap amount is known to within `amount >> ZAP_SWAP_AMOUNT_PRECISION_BITS`,
+/// which bounds the number of swaps the quote simulates. A range the swap would move the price
+/// away from takes the input alone, nothing is swapped for it.
+pub fn compute_zap_swap_amount<E: From<MathError>>(
+    mut swap: impl FnMut(u64) -> core::result::Result<Option<SwapOutcome>, E>,
+    sqrt_price_x64: u128,
+    amount: u64,
+    zero_for_one: bool,
+    sqrt_price_lower_x64: u128,
+    sqrt_price_upper_x64: u128,
+) -> core::result::Result<u64, E> {
+    if (zero_for_one && sqrt_price_x64 <= sqrt_price_lower_x64)
+        || (!zero_for_one && sqrt_price_x64 >= sqrt_price_upper_x64)
+    {
+        return Ok(0);
+    }
+
+    let mut input_in_excess = |swap_amount: u64| -> core::result::Result<bool, E> {
+        let Some(outcome) = swap(swap_amount)? else {
+            // the pool cannot take that much, so less has to be swapped
+            return Ok(false);
+        };
+        let amount_input_left = amount
+            .checked_sub(outcome.amount_in)
+            .ok_or(MathError::MaxTokenOverflow)?;
+        let (amount_0, amount_1) = if zero_for_one {
+            (amount_input_left, outcome.amount_out)
+        } else {
+            (outcome.amount_out, amount_input_left)
+        };
+        let sqrt_price_x64 = outcome.state.sqrt_price_x64;
+        let needs_0 = sqrt_price_x64 < sqrt_price_upper_x64;
+        let needs_1 = sqrt_price_x64 > sqrt_price_lower_x64;
+        let liquidity_0 = liquidity_math::get_liquidity_from_single_amount_0(
+            sqrt_price_x64,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            amount_0,
+        )?;
+        let liquidity_1 = liquidity_math::get_liquidity_from_single_amount_1(
+            sqrt_price_x64,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            amount_1,
+        )?;
+        let (needs_input, needs_output, liquidity_input, liquidity_output) = if zero_for_one {
+            (needs_0, needs_1, liquidity_0, liquidity_1)
+        } else {
+            (needs_1, needs_0, liquidity_1, liquidity_0)
+        };
+        Ok(!needs_input || (needs_output && liquidity_input > liquidity_output))
+    };
+
+    let tolerance = amount >> ZAP_SWAP_AMOUNT_PRECISION_BITS;
+    let (mut low, mut high) = (0, amount);
+    while high - low > tolerance {
+        let mid = low + (high - low).div_ceil(2);
+        if input_in_excess(mid)? {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// Part of `fee_amount` taken at `fee_rate`, rounded down
+fn fee_share(fee_amount: u64, fee_rate: u32) -> u64 {
+    (u128::from(fee_amount) * u128::from(fee_rate) / u128::from(FEE_RATE_DENOMINATOR_VALUE)) as u64
+}
+
+/// Pre calcumate amount_in or amount_out for the specified price range
+/// The amount maybe overflow of u64 due to the `sqrt_price_target_x64`,
+/// in that case `None` is returned and the caller falls back to the next price
+fn calculate_amount_in_range(
+    sqrt_price_current_x64: u128,
+    sqrt_price_target_x64: u128,
+    liquidity: u128,
+    zero_for_one: bool,
+    is_base_input: bool,
+) -> Result<Option<u64>> {
+    let result = if is_base_input {
+        if zero_for_one {
+            get_delta_amount_0_unsigned(
+                sqrt_price_target_x64,
+                sqrt_price_current_x64,
+                liquidity,
+                true,
+            )
+        } else {
+            get_delta_amount_1_unsigned(
+                sqrt_price_current_x64,
+                sqrt_price_target_x64,
+                liquidity,
+                true,
+            )
+        }
+    } else {
+        if zero_for_one {
+            get_delta_amount_1_unsigned(
+                sqrt_price_target_x64,
+                sqrt_price_current_x64,
+                liquidity,
+                false,
+            )
+        } else {
+            get_delta_amount_0_unsigned(
+                sqrt_price_current_x64,
+                sqrt_price_target_x64,
+                liquidity,
+                false,
+            )
+        }
+    };
+
+    match result {
+        Ok(amount) => Ok(Some(amount)),
+        Err(MathError::MaxTokenOverflow) => Ok(None),
+        Err(_) => Err(MathError::SqrtPriceLimitOverflow),
+    }
+}
@@ -0,0 +1,30 @@
+use crate::big_nums::U256;
+
+pub trait UnsafeMathTrait {
+    /// Returns ceil(x / y)
+    /// Division by 0 panics, the caller must check `y`
+    fn div_rounding_up(x: Self, y: Self) -> Self;
+}
+
+impl UnsafeMathTrait for u64 {
+    fn div_rounding_up(x: Self, y: Self) -> Self {
+        x / y + u64::from(!x.is_multiple_of(y))
+    }
+}
+
+impl UnsafeMathTrait for u128 {
+    fn div_rounding_up(x: Self, y: Self) -> Self {
+        x / y + u128::from(!x.is_multiple_of(y))
+    }
+}
+
+impl UnsafeMathTrait for U256 {
+    fn div_rounding_up(x: Self, y: Self) -> Self {
+        let (quotient, remainder) = x.div_mod(y);
+        if remainder.is_zero() {
+            quotient
+        } else {
+            quotient + U256::one()
+        }
+    }
+}
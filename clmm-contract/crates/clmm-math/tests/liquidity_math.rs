@@ -0,0 +1,87 @@
+use clmm_math::{
+    liquidity_math::{get_delta_amounts_signed, get_liquidity_from_amounts},
+    tick_math::{get_sqrt_price_at_tick, get_tick_at_sqrt_price, MAX_TICK, MIN_TICK},
+};
+use proptest::prelude::*;
+
+#[test]
+fn liquidity_from_amounts_is_limited_by_the_scarcer_token() {
+    let sqrt_price_x64 = get_sqrt_price_at_tick(0).unwrap();
+    let sqrt_price_lower_x64 = get_sqrt_price_at_tick(-600).unwrap();
+    let sqrt_price_upper_x64 = get_sqrt_price_at_tick(600).unwrap();
+    let liquidity = |amount_0, amount_1| {
+        get_liquidity_from_amounts(
+            sqrt_price_x64,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            amount_0,
+            amount_1,
+        )
+        .unwrap()
+    };
+
+    let balanced = liquidity(1_000_000, 1_000_000);
+    assert!(balanced > 0);
+    assert_eq!(liquidity(1_000_000, 2_000_000), balanced);
+    assert_eq!(liquidity(2_000_000, 1_000_000), balanced);
+    assert_eq!(liquidity(1_000_000, 0), 0);
+    assert_eq!(liquidity(0, 1_000_000), 0);
+}
+
+#[test]
+fn liquidity_from_amounts_out_of_range_takes_one_token() {
+    let sqrt_price_lower_x64 = get_sqrt_price_at_tick(-600).unwrap();
+    let sqrt_price_upper_x64 = get_sqrt_price_at_tick(600).unwrap();
+    for (tick, amounts, other_amounts) in [
+        (-1200, (1_000_000, 0), (1_000_000, u64::MAX)),
+        (1200, (0, 1_000_000), (u64::MAX, 1_000_000)),
+    ] {
+        let sqrt_price_x64 = get_sqrt_price_at_tick(tick).unwrap();
+        let liquidity = |(amount_0, amount_1)| {
+            get_liquidity_from_amounts(
+                sqrt_price_x64,
+                sqrt_price_upper_x64,
+                sqrt_price_lower_x64,
+                amount_0,
+                amount_1,
+            )
+            .unwrap()
+        };
+        assert!(liquidity(amounts) > 0);
+        assert_eq!(liquidity(amounts), liquidity(other_amounts));
+    }
+}
+
+proptest! {
+    /// Adding the liquidity never asks for more than the amounts it was computed from
+    #[test]
+    fn deposit_for_liquidity_from_amounts_fits_the_amounts(
+        tick_lower in MIN_TICK..MAX_TICK,
+        range in 1..100_000i32,
+        tick in MIN_TICK..=MAX_TICK,
+        amount_0 in any::<u64>(),
+        amount_1 in any::<u64>(),
+    ) {
+        let tick_upper = tick_lower.saturating_add(range).min(MAX_TICK);
+        let sqrt_price_x64 = get_sqrt_price_at_tick(tick).unwrap();
+        let Ok(liquidity) = get_liquidity_from_amounts(
+            sqrt_price_x64,
+            get_sqrt_price_at_tick(tick_lower).unwrap(),
+            get_sqrt_price_at_tick(tick_upper).unwrap(),
+            amount_0,
+            amount_1,
+        ) else {
+            return Ok(());
+        };
+        let (deposit_0, deposit_1) = get_delta_amounts_signed(
+            get_tick_at_sqrt_price(sqrt_price_x64).unwrap(),
+            sqrt_price_x64,
+            tick_lower,
+            tick_upper,
+            liquidity as i128,
+        )
+        .unwrap();
+        prop_assert!(deposit_0 <= amount_0);
+        prop_assert!(deposit_1 <= amount_1);
+    }
+}
@@ -0,0 +1,447 @@
+//! The native u128 math against the `big_nums` implementation it replaced. Results must match
+//! bit for bit, errors included.
+
+use clmm_math::{
+    big_nums::{U1024, U128, U256, U512},
+    bit_math::{flip_bit, is_set, next_set_bit, prev_set_bit},
+    fee_math::calculate_latest_token_fees,
+    fixed_point_64::{self, Q64},
+    full_math::{div_rem_wide, widening_mul, MulDiv},
+    liquidity_math::{
+        get_delta_amount_0_unsigned, get_delta_amount_1_unsigned, get_liquidity_from_amount_0,
+        get_liquidity_from_amount_1,
+    },
+    sqrt_price_math::get_next_sqrt_price_from_input,
+    sqrt_price_math::get_next_sqrt_price_from_output,
+    tick_math::{
+        get_sqrt_price_at_tick, MAX_SQRT_PRICE_X64, MAX_TICK, MIN_SQRT_PRICE_X64, MIN_TICK,
+    },
+    MathError, Result,
+};
+use proptest::prelude::*;
+
+/// The previous implementations, on `U128` and `U256`
+mod reference {
+    use clmm_math::unsafe_math::UnsafeMathTrait;
+
+    use super::*;
+
+    pub fn get_sqrt_price_at_tick(tick: i32) -> Result<u128> {
+        const FACTORS: [u64; 18] = [
+            0xfff97272373d4000,
+            0xfff2e50f5f657000,
+            0xffe5caca7e10f000,
+            0xffcb9843d60f7000,
+            0xff973b41fa98e800,
+            0xff2ea16466c9b000,
+            0xfe5dee046a9a3800,
+            0xfcbe86c7900bb000,
+            0xf987a7253ac65800,
+            0xf3392b0822bb6000,
+            0xe7159475a2caf000,
+            0xd097f3bdfd2f2000,
+            0xa9f746462d9f8000,
+            0x70d869a156f31c00,
+            0x31be135f97ed3200,
+            0x9aa508b5b85a500,
+            0x5d6af8dedc582c,
+            0x2216e584f5fa,
+        ];
+        let abs_tick = tick.unsigned_abs();
+        if abs_tick > MAX_TICK as u32 {
+            return Err(MathError::TickUpperOverflow);
+        }
+        let mut ratio = if abs_tick & 0x1 != 0 {
+            U128([0xfffcb933bd6fb800, 0])
+        } else {
+            U128([0, 1])
+        };
+        for (i, factor) in FACTORS.into_iter().enumerate() {
+            if abs_tick & (0x2 << i) != 0 {
+                ratio = (ratio * U128([factor, 0])) >> 64;
+            }
+        }
+        if tick > 0 {
+            ratio = U128::MAX / ratio;
+        }
+        Ok(ratio.as_u128())
+    }
+
+    pub fn get_liquidity_from_amount_0(a: u128, b: u128, amount_0: u64) -> Result<u128> {
+        let (a, b) = (a.min(b), a.max(b));
+        if a == b {
+            return Err(MathError::InvalidSqrtPrice);
+        }
+        let intermediate = U128::from(a)
+            .mul_div_floor(U128::from(b), U128::from(Q64))
+            .ok_or(MathError::LiquidityOverflow)?;
+        Ok(U128::from(amount_0)
+            .mul_div_floor(intermediate, U128::from(b - a))
+            .ok_or(MathError::LiquidityOverflow)?
+            .as_u128())
+    }
+
+    pub fn get_liquidity_from_amount_1(a: u128, b: u128, amount_1: u64) -> Result<u128> {
+        let (a, b) = (a.min(b), a.max(b));
+        if a == b {
+            return Err(MathError::InvalidSqrtPrice);
+        }
+        Ok(U128::from(amount_1)
+            .mul_div_floor(U128::from(Q64), U128::from(b - a))
+            .ok_or(MathError::LiquidityOverflow)?
+            .as_u128())
+    }
+
+    pub fn get_delta_amount_0_unsigned(
+        a: u128,
+        b: u128,
+        liquidity: u128,
+        round_up: bool,
+    ) -> Result<u64> {
+        let (a, b) = (a.min(b), a.max(b));
+        if a == 0 {
+            return Err(MathError::InvalidSqrtPrice);
+        }
+        let numerator_1 = U256::from(liquidity) << 64;
+        let numerator_2 = U256::from(b - a);
+        let result = if round_up {
+            U256::div_rounding_up(
+                numerator_1
+                    .mul_div_ceil(numerator_2, U256::from(b))
+                    .ok_or(MathError::MaxTokenOverflow)?,
+                U256::from(a),
+            )
+        } else {
+            numerator_1
+                .mul_div_floor(numerator_2, U256::from(b))
+                .ok_or(MathError::MaxTokenOverflow)?
+                / U256::from(a)
+        };
+        if result > U256::from(u64::MAX) {
+            return Err(MathError::MaxTokenOverflow);
+        }
+        Ok(result.as_u64())
+    }
+
+    pub fn get_delta_amount_1_unsigned(
+        a: u128,
+        b: u128,
+        liquidity: u128,
+        round_up: bool,
+    ) -> Result<u64> {
+        let (a, b) = (a.min(b), a.max(b));
+        let result = if round_up {
+            U256::from(liquidity).mul_div_ceil(U256::from(b - a), U256::from(Q64))
+        } else {
+            U256::from(liquidity).mul_div_floor(U256::from(b - a), U256::from(Q64))
+        }
+        .ok_or(MathError::MaxTokenOverflow)?;
+        if result > U256::from(u64::MAX) {
+            return Err(MathError::MaxTokenOverflow);
+        }
+        Ok(result.as_u64())
+    }
+
+    pub fn get_next_sqrt_price_from_amount_1_rounding_down(
+        sqrt_price_x64: u128,
+        liquidity: u128,
+        amount: u64,
+        add: bool,
+    ) -> Result<u128> {
+        if add {
+            let quotient = U256::from(u128::from(amount) << 64) / U256::from(liquidity);
+            sqrt_price_x64
+                .checked_add(quotient.as_u128())
+                .ok_or(MathError::SqrtPriceX64Overflow)
+        } else {
+            let quotient =
+                U256::div_rounding_up(U256::from(u128::from(amount) << 64), U256::from(liquidity));
+            if U256::from(sqrt_price_x64) <= quotient {
+                return Err(MathError::SqrtPriceX64Overflow);
+            }
+            Ok(sqrt_price_x64 - quotient.as_u128())
+        }
+    }
+
+    pub fn calculate_latest_token_fees(
+        last_total_fees: u64,
+        fee_growth_inside_last_x64: u128,
+        fee_growth_inside_latest_x64: u128,
+        liquidity: u128,
+    ) -> Result<u64> {
+        let fee_growth_delta =
+            U128::from(fee_growth_inside_latest_x64.wrapping_sub(fee_growth_inside_last_x64))
+                .mul_div_floor(U128::from(liquidity), U128::from(Q64))
+                .ok_or(MathError::FeeAmountOverflow)?
+                .to_underflow_u64();
+        last_total_fees
+            .checked_add(fee_growth_delta)
+            .ok_or(MathError::FeeAmountOverflow)
+    }
+}
+
+/// Any u128, with small values as likely as large ones
+fn any_u128() -> impl Strategy<Value = u128> {
+    prop_oneof![
+        any::<u128>(),
+        (any::<u128>(), 0..128u32).prop_map(|(x, shift)| x >> shift),
+        Just(0),
+        Just(u128::MAX),
+    ]
+}
+
+fn non_zero_u128() -> impl Strategy<Value = u128> {
+    any_u128().prop_map(|x| x.max(1))
+}
+
+fn any_u64() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        any::<u64>(),
+        (any::<u64>(), 0..64u32).prop_map(|(x, shift)| x >> shift),
+        Just(0),
+        Just(u64::MAX),
+    ]
+}
+
+fn sqrt_price_x64() -> impl Strategy<Value = u128> {
+    prop_oneof![
+        MIN_SQRT_PRICE_X64..MAX_SQRT_PRICE_X64,
+        (MIN_TICK..=MAX_TICK).prop_map(|tick| get_sqrt_price_at_tick(tick).unwrap()),
+        Just(MIN_SQRT_PRICE_X64),
+        Just(MAX_SQRT_PRICE_X64),
+    ]
+}
+
+/// Sparse bitmaps, so searches cross empty words
+fn bitmap<const N: usize>() -> impl Strategy<Value = [u64; N]> {
+    prop::collection::vec(
+        prop_oneof![3 => Just(0u64), 1 => any::<u64>(), 1 => (0..64u32).prop_map(|bit| 1 << bit)],
+        N,
+    )
+    .prop_map(|words| words.try_into().unwrap())
+}
+
+#[test]
+fn get_sqrt_price_at_tick_matches_on_every_tick() {
+    for tick in MIN_TICK - 1..=MAX_TICK + 1 {
+        assert_eq!(
+            get_sqrt_price_at_tick(tick),
+            reference::get_sqrt_price_at_tick(tick),
+            "tick {}",
+            tick
+        );
+    }
+}
+
+#[test]
+fn div_rem_wide_edge_cases() {
+    for (low, high, denom) in [
+        (0, 0, 1),
+        (u128::MAX, u128::MAX - 1, u128::MAX),
+        (u128::MAX, 0, 1),
+        (0, 1, 2),
+        (u128::MAX, (1 << 64) - 1, 1 << 64),
+        (u128::MAX, (1 << 64) - 1, (1 << 64) + 1),
+        // quotient digits that are overestimated by 2 before correction
+        (0, 1 << 127, (1 << 127) + 1),
+        (u128::MAX, (1 << 63) - 1, 1 << 63),
+    ] {
+        let (quotient, remainder) = div_rem_wide(low, high, denom);
+        let product = U512::from(quotient) * U512::from(denom) + U512::from(remainder);
+        let numerator = (U512::from(high) << 128) + U512::from(low);
+        assert_eq!(product, numerator);
+        assert!(remainder < denom);
+    }
+}
+
+proptest! {
+    #[test]
+    fn widening_mul_matches(x in any_u128(), y in any_u128()) {
+        let (low, high) = widening_mul(x, y);
+        let product = U256::from(x) * U256::from(y);
+        prop_assert_eq!(low, product.low_u128());
+        prop_assert_eq!(high, (product >> 128).as_u128());
+    }
+
+    #[test]
+    fn div_rem_wide_matches(low in any_u128(), high in any_u128(), denom in non_zero_u128()) {
+        let high = high % denom;
+        let (quotient, remainder) = div_rem_wide(low, high, denom);
+        let (expected_quotient, expected_remainder) =
+            ((U256::from(high) << 128) + U256::from(low)).div_mod(U256::from(denom));
+        prop_assert_eq!(quotient, expected_quotient.as_u128());
+        prop_assert_eq!(remainder, expected_remainder.as_u128());
+    }
+
+    #[test]
+    fn mul_div_u128_matches(x in any_u128(), y in any_u128(), denom in non_zero_u128()) {
+        let (x_ref, y_ref, denom_ref) = (U128::from(x), U128::from(y), U128::from(denom));
+        prop_assert_eq!(
+            x.mul_div_floor(y, denom),
+            x_ref.mul_div_floor(y_ref, denom_ref).map(|r| r.as_u128())
+        );
+        prop_assert_eq!(
+            x.mul_div_ceil(y, denom),
+            x_ref.mul_div_ceil(y_ref, denom_ref).map(|r| r.as_u128())
+        );
+        prop_assert_eq!(x.to_underflow_u64(), x_ref.to_underflow_u64());
+    }
+
+    #[test]
+    fn mul_div_u64_matches(x in any_u64(), y in any_u64(), denom in any_u64()) {
+        let denom = denom.max(1);
+        let reference = |r: Option<U128>| r.filter(|r| *r <= U128::from(u64::MAX)).map(|r| r.as_u64());
+        let (x_ref, y_ref, denom_ref) = (U128::from(x), U128::from(y), U128::from(denom));
+        prop_assert_eq!(
+            x.mul_div_floor(y, denom),
+            reference(x_ref.mul_div_floor(y_ref, denom_ref))
+        );
+        prop_assert_eq!(
+            x.mul_div_ceil(y, denom),
+            reference(x_ref.mul_div_ceil(y_ref, denom_ref))
+        );
+    }
+
+    #[test]
+    fn q64_mul_matches(x in any_u128(), y in any_u128()) {
+        let (x_ref, y_ref) = (U128::from(x), U128::from(y));
+        prop_assert_eq!(
+            fixed_point_64::mul_floor(x, y),
+            x_ref.mul_div_floor(y_ref, U128::from(Q64)).map(|r| r.as_u128())
+        );
+        prop_assert_eq!(
+            fixed_point_64::mul_ceil(x, y),
+            x_ref.mul_div_ceil(y_ref, U128::from(Q64)).map(|r| r.as_u128())
+        );
+    }
+
+    #[test]
+    fn liquidity_from_amounts_matches(
+        a in sqrt_price_x64(),
+        b in sqrt_price_x64(),
+        amount in any_u64(),
+    ) {
+        prop_assert_eq!(
+            get_liquidity_from_amount_0(a, b, amount),
+            reference::get_liquidity_from_amount_0(a, b, amount)
+        );
+        prop_assert_eq!(
+            get_liquidity_from_amount_1(a, b, amount),
+            reference::get_liquidity_from_amount_1(a, b, amount)
+        );
+    }
+
+    #[test]
+    fn delta_amounts_match(
+        a in sqrt_price_x64(),
+        b in sqrt_price_x64(),
+        liquidity in any_u128(),
+        round_up: bool,
+    ) {
+        prop_assert_eq!(
+            get_delta_amount_0_unsigned(a, b, liquidity, round_up),
+            reference::get_delta_amount_0_unsigned(a, b, liquidity, round_up)
+        );
+        prop_assert_eq!(
+            get_delta_amount_1_unsigned(a, b, liquidity, round_up),
+            reference::get_delta_amount_1_unsigned(a, b, liquidity, round_up)
+        );
+    }
+
+    #[test]
+    fn delta_amount_0_matches_on_any_price(
+        a in non_zero_u128(),
+        b in non_zero_u128(),
+        liquidity in any_u128(),
+        round_up: bool,
+    ) {
+        prop_assert_eq!(
+            get_delta_amount_0_unsigned(a, b, liquidity, round_up),
+            reference::get_delta_amount_0_unsigned(a, b, liquidity, round_up)
+        );
+    }
+
+    #[test]
+    fn next_sqrt_price_from_amount_1_matches(
+        sqrt_price_x64 in any_u128(),
+        liquidity in non_zero_u128(),
+        amount in any_u64(),
+    ) {
+        let sqrt_price_x64 = sqrt_price_x64.max(1);
+        // token_1 in moves the price up, token_1 out moves it down
+        prop_assert_eq!(
+            get_next_sqrt_price_from_input(sqrt_price_x64, liquidity, amount, false),
+            reference::get_next_sqrt_price_from_amount_1_rounding_down(
+                sqrt_price_x64,
+                liquidity,
+                amount,
+                true
+            )
+        );
+        prop_assert_eq!(
+            get_next_sqrt_price_from_output(sqrt_price_x64, liquidity, amount, true),
+            reference::get_next_sqrt_price_from_amount_1_rounding_down(
+                sqrt_price_x64,
+                liquidity,
+                amount,
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn latest_token_fees_match(
+        last_total_fees in any_u64(),
+        fee_growth_inside_last_x64 in any_u128(),
+        fee_growth_inside_latest_x64 in any_u128(),
+        liquidity in any_u128(),
+    ) {
+        prop_assert_eq!(
+            calculate_latest_token_fees(
+                last_total_fees,
+                fee_growth_inside_last_x64,
+                fee_growth_inside_latest_x64,
+                liquidity
+            ),
+            reference::calculate_latest_token_fees(
+                last_total_fees,
+                fee_growth_inside_last_x64,
+                fee_growth_inside_latest_x64,
+                liquidity
+            )
+        );
+    }
+
+    #[test]
+    fn default_bitmap_operations_match(bitmap in bitmap::<16>(), index in 0..1024usize) {
+        prop_assert_eq!(is_set(&bitmap, index), U1024(bitmap).bit(index));
+
+        let mut flipped = bitmap;
+        flip_bit(&mut flipped, index);
+        prop_assert_eq!(flipped, (U1024(bitmap) ^ (U1024::one() << index)).0);
+
+        // the searches of `next_initialized_tick_array_start_index`
+        let shifted = U1024(bitmap) << (1024 - index - 1);
+        let expected = (!shifted.is_zero()).then(|| index - shifted.leading_zeros() as usize);
+        prop_assert_eq!(prev_set_bit(&bitmap, index), expected);
+        let shifted = U1024(bitmap) >> index;
+        let expected = (!shifted.is_zero()).then(|| index + shifted.trailing_zeros() as usize);
+        prop_assert_eq!(next_set_bit(&bitmap, index), expected);
+    }
+
+    #[test]
+    fn extension_bitmap_operations_match(bitmap in bitmap::<8>(), index in 0..512usize) {
+        prop_assert_eq!(is_set(&bitmap, index), U512(bitmap).bit(index));
+
+        let mut flipped = bitmap;
+        flip_bit(&mut flipped, index);
+        prop_assert_eq!(flipped, (U512(bitmap) ^ (U512::one() << index)).0);
+
+        let shifted = U512(bitmap) << (512 - index - 1);
+        let expected = (!shifted.is_zero()).then(|| index - shifted.leading_zeros() as usize);
+        prop_assert_eq!(prev_set_bit(&bitmap, index), expected);
+        let shifted = U512(bitmap) >> index;
+        let expected = (!shifted.is_zero()).then(|| index + shifted.trailing_zeros() as usize);
+        prop_assert_eq!(next_set_bit(&bitmap, index), expected);
+    }
+}
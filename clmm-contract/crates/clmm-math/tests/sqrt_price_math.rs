@@ -0,0 +1,569 @@
+use clmm_math::{
+    fee_math::FEE_RATE_DENOMINATOR_VALUE,
+    fixed_point_64::Q64,
+    liquidity_math::{get_delta_amount_0_unsigned, get_delta_amount_1_unsigned},
+    sqrt_price_math::*,
+    swap_math::compute_swap_step,
+    tick_math::{get_sqrt_price_at_tick, MAX_SQRT_PRICE_X64, MIN_SQRT_PRICE_X64},
+};
+
+const LIQUIDITY: u128 = 1_000_000_000_000;
+
+mod get_next_sqrt_price_from_input_test {
+    use super::*;
+
+    #[test]
+    fn zero_amount_returns_input_price() {
+        for price in [MIN_SQRT_PRICE_X64, Q64, MAX_SQRT_PRICE_X64] {
+            for zero_for_one in [true, false] {
+                assert_eq!(
+                    get_next_sqrt_price_from_input(price, LIQUIDITY, 0, zero_for_one).unwrap(),
+                    price
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fails_if_price_or_liquidity_is_zero() {
+        assert!(get_next_sqrt_price_from_input(0, LIQUIDITY, 1, true).is_err());
+        assert!(get_next_sqrt_price_from_input(Q64, 0, 1, true).is_err());
+        assert!(get_next_sqrt_price_from_input(0, LIQUIDITY, 1, false).is_err());
+        assert!(get_next_sqrt_price_from_input(Q64, 0, 1, false).is_err());
+    }
+
+    #[test]
+    fn fails_if_input_overflows_price() {
+        // √P + Δy / L does not fit u128
+        assert!(get_next_sqrt_price_from_input(u128::MAX - 1, 1, 1, false).is_err());
+    }
+
+    #[test]
+    fn any_input_amount_cannot_underflow_price() {
+        // √P * L / (L + Δx * √P) is always > 0 and rounds up
+        let price = get_next_sqrt_price_from_input(1, 1, u64::MAX, true).unwrap();
+        assert_eq!(price, 1);
+    }
+
+    #[test]
+    fn input_moves_price_in_swap_direction() {
+        let down = get_next_sqrt_price_from_input(Q64, LIQUIDITY, 1_000_000, true).unwrap();
+        let up = get_next_sqrt_price_from_input(Q64, LIQUIDITY, 1_000_000, false).unwrap();
+        assert!(down < Q64);
+        assert!(up > Q64);
+    }
+
+    #[test]
+    fn input_of_token_1_at_min_price() {
+        let price =
+            get_next_sqrt_price_from_input(MIN_SQRT_PRICE_X64, LIQUIDITY, 1, false).unwrap();
+        // Δ√P = 1 * 2^64 / L, rounded down
+        assert_eq!(price, MIN_SQRT_PRICE_X64 + Q64 / LIQUIDITY);
+    }
+
+    #[test]
+    fn input_of_token_0_at_max_price() {
+        let price = get_next_sqrt_price_from_input(MAX_SQRT_PRICE_X64, LIQUIDITY, 1, true).unwrap();
+        assert!(price < MAX_SQRT_PRICE_X64);
+        // the price must move at most as far as the amount allows
+        let amount_in =
+            get_delta_amount_0_unsigned(price, MAX_SQRT_PRICE_X64, LIQUIDITY, true).unwrap();
+        assert!(amount_in <= 1);
+    }
+
+    #[test]
+    fn rounding_never_exceeds_input_amount() {
+        for price in [
+            MIN_SQRT_PRICE_X64,
+            get_sqrt_price_at_tick(-1000).unwrap(),
+            Q64,
+        ] {
+            for amount in [1u64, 7, 1_000, 999_999_999] {
+                let next = get_next_sqrt_price_from_input(price, LIQUIDITY, amount, false).unwrap();
+                let amount_in = get_delta_amount_1_unsigned(price, next, LIQUIDITY, true).unwrap();
+                assert!(amount_in <= amount);
+            }
+        }
+        for price in [
+            Q64,
+            get_sqrt_price_at_tick(1000).unwrap(),
+            MAX_SQRT_PRICE_X64,
+        ] {
+            for amount in [1u64, 7, 1_000, 999_999_999] {
+                let next = get_next_sqrt_price_from_input(price, LIQUIDITY, amount, true).unwrap();
+                let amount_in = get_delta_amount_0_unsigned(next, price, LIQUIDITY, true).unwrap();
+                assert!(amount_in <= amount);
+            }
+        }
+    }
+}
+
+mod get_next_sqrt_price_from_output_test {
+    use super::*;
+
+    #[test]
+    fn zero_amount_returns_input_price() {
+        for price in [MIN_SQRT_PRICE_X64, Q64, MAX_SQRT_PRICE_X64] {
+            for zero_for_one in [true, false] {
+                assert_eq!(
+                    get_next_sqrt_price_from_output(price, LIQUIDITY, 0, zero_for_one).unwrap(),
+                    price
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fails_if_price_or_liquidity_is_zero() {
+        assert!(get_next_sqrt_price_from_output(0, LIQUIDITY, 1, true).is_err());
+        assert!(get_next_sqrt_price_from_output(Q64, 0, 1, true).is_err());
+    }
+
+    #[test]
+    fn fails_if_output_equals_virtual_reserves_of_token_0() {
+        // x = L / √P
+        let price = Q64;
+        let reserve_0 = (LIQUIDITY * Q64 / price) as u64;
+        assert!(get_next_sqrt_price_from_output(price, LIQUIDITY, reserve_0, false).is_err());
+        assert!(get_next_sqrt_price_from_output(price, LIQUIDITY, reserve_0 - 1, false).is_ok());
+    }
+
+    #[test]
+    fn fails_if_output_equals_virtual_reserves_of_token_1() {
+        // y = L * √P
+        let price = Q64;
+        let reserve_1 = (LIQUIDITY * price / Q64) as u64;
+        assert!(get_next_sqrt_price_from_output(price, LIQUIDITY, reserve_1, true).is_err());
+        assert!(get_next_sqrt_price_from_output(price, LIQUIDITY, reserve_1 - 1, true).is_ok());
+    }
+
+    #[test]
+    fn output_at_min_price_cannot_go_below_zero() {
+        assert!(get_next_sqrt_price_from_output(MIN_SQRT_PRICE_X64, 1, u64::MAX, true).is_err());
+    }
+
+    #[test]
+    fn output_at_max_price_of_token_0() {
+        let price =
+            get_next_sqrt_price_from_output(MAX_SQRT_PRICE_X64, LIQUIDITY, 1, false).unwrap();
+        assert!(price > MAX_SQRT_PRICE_X64);
+        // the new price must deliver at least the requested output
+        let amount_out =
+            get_delta_amount_0_unsigned(MAX_SQRT_PRICE_X64, price, LIQUIDITY, false).unwrap();
+        assert!(amount_out >= 1);
+    }
+
+    #[test]
+    fn rounding_always_covers_output_amount() {
+        for amount in [1u64, 7, 1_000, 999_999_999] {
+            let next = get_next_sqrt_price_from_output(Q64, LIQUIDITY, amount, true).unwrap();
+            let amount_out = get_delta_amount_1_unsigned(next, Q64, LIQUIDITY, false).unwrap();
+            assert!(amount_out >= amount);
+
+            let next = get_next_sqrt_price_from_output(Q64, LIQUIDITY, amount, false).unwrap();
+            let amount_out = get_delta_amount_0_unsigned(Q64, next, LIQUIDITY, false).unwrap();
+            assert!(amount_out >= amount);
+        }
+    }
+}
+
+mod compute_swap_step_test {
+    use super::*;
+
+    const FEE_RATE: u32 = 3000;
+
+    #[test]
+    fn exact_input_capped_at_target_price_one_for_zero() {
+        let price = Q64;
+        let target = get_sqrt_price_at_tick(100).unwrap();
+        let amount = 1_000_000_000_000;
+        let step =
+            compute_swap_step(price, target, LIQUIDITY, amount, FEE_RATE, true, false).unwrap();
+
+        assert_eq!(step.sqrt_price_next_x64, target);
+        assert_eq!(
+            step.amount_in,
+            get_delta_amount_1_unsigned(price, target, LIQUIDITY, true).unwrap()
+        );
+        assert_eq!(
+            step.amount_out,
+            get_delta_amount_0_unsigned(price, target, LIQUIDITY, false).unwrap()
+        );
+        assert!(step.amount_in + step.fee_amount < amount);
+    }
+
+    #[test]
+    fn exact_input_fully_spent_before_target_price_zero_for_one() {
+        let price = Q64;
+        let target = get_sqrt_price_at_tick(-10_000).unwrap();
+        let amount = 1_000_000;
+        let step =
+            compute_swap_step(price, target, LIQUIDITY, amount, FEE_RATE, true, true).unwrap();
+
+        assert!(step.sqrt_price_next_x64 > target);
+        assert!(step.sqrt_price_next_x64 < price);
+        // the remainder is taken as fee
+        assert_eq!(step.amount_in + step.fee_amount, amount);
+    }
+
+    #[test]
+    fn exact_output_capped_at_target_price() {
+        let price = Q64;
+        let target = get_sqrt_price_at_tick(-100).unwrap();
+        let amount = 1_000_000_000_000;
+        let step =
+            compute_swap_step(price, target, LIQUIDITY, amount, FEE_RATE, false, true).unwrap();
+
+        assert_eq!(step.sqrt_price_next_x64, target);
+        assert!(step.amount_out < amount);
+        assert_eq!(
+            step.amount_in,
+            get_delta_amount_0_unsigned(target, price, LIQUIDITY, true).unwrap()
+        );
+    }
+
+    #[test]
+    fn exact_output_fully_received_before_target_price() {
+        let price = Q64;
+        let target = get_sqrt_price_at_tick(10_000).unwrap();
+        let amount = 1_000_000;
+        let step =
+            compute_swap_step(price, target, LIQUIDITY, amount, FEE_RATE, false, false).unwrap();
+
+        assert!(step.sqrt_price_next_x64 < target);
+        assert_eq!(step.amount_out, amount);
+    }
+
+    #[test]
+    fn fee_is_rounded_up() {
+        let step = compute_swap_step(
+            Q64,
+            get_sqrt_price_at_tick(100).unwrap(),
+            LIQUIDITY,
+            u64::MAX / 2,
+            FEE_RATE,
+            true,
+            false,
+        )
+        .unwrap();
+        let fee_floor = u128::from(step.amount_in) * u128::from(FEE_RATE)
+            / u128::from(FEE_RATE_DENOMINATOR_VALUE - FEE_RATE);
+        assert!(u128::from(step.fee_amount) >= fee_floor);
+        assert!(u128::from(step.fee_amount) <= fee_floor + 1);
+    }
+
+    #[test]
+    fn zero_fee_rate() {
+        let step = compute_swap_step(
+            Q64,
+            get_sqrt_price_at_tick(100).unwrap(),
+            LIQUIDITY,
+            u64::MAX / 2,
+            0,
+            true,
+            false,
+        )
+        .unwrap();
+        assert_eq!(step.fee_amount, 0);
+    }
+
+    #[test]
+    fn invalid_fee_rate() {
+        assert!(compute_swap_step(
+            Q64,
+            get_sqrt_price_at_tick(100).unwrap(),
+            LIQUIDITY,
+            1_000,
+            FEE_RATE_DENOMINATOR_VALUE,
+            true,
+            false,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn swap_to_min_sqrt_price() {
+        let price = get_sqrt_price_at_tick(-443_600).unwrap();
+        let step = compute_swap_step(
+            price,
+            MIN_SQRT_PRICE_X64,
+            LIQUIDITY,
+            u64::MAX,
+            FEE_RATE,
+            true,
+            true,
+        )
+        .unwrap();
+        assert_eq!(step.sqrt_price_next_x64, MIN_SQRT_PRICE_X64);
+        assert!(step.amount_in.checked_add(step.fee_amount).is_some());
+    }
+
+    #[test]
+    fn swap_to_max_sqrt_price() {
+        let price = get_sqrt_price_at_tick(443_600).unwrap();
+        let step = compute_swap_step(
+            price,
+            MAX_SQRT_PRICE_X64,
+            1,
+            u64::MAX,
+            FEE_RATE,
+            true,
+            false,
+        )
+        .unwrap();
+        assert_eq!(step.sqrt_price_next_x64, MAX_SQRT_PRICE_X64);
+        assert!(step.amount_in.checked_add(step.fee_amount).is_some());
+    }
+
+    #[test]
+    fn amount_in_overflowing_u64_falls_back_to_next_price() {
+        // the full range to the max price needs more than u64::MAX of token_1
+        let step = compute_swap_step(
+            Q64,
+            MAX_SQRT_PRICE_X64,
+            u128::from(u64::MAX) * 4,
+            u64::MAX,
+            FEE_RATE,
+            true,
+            false,
+        )
+        .unwrap();
+        assert!(step.sqrt_price_next_x64 < MAX_SQRT_PRICE_X64);
+        assert_eq!(step.amount_in + step.fee_amount, u64::MAX);
+    }
+
+    #[test]
+    fn exact_output_of_entire_remaining_amount_is_capped() {
+        let step = compute_swap_step(
+            MAX_SQRT_PRICE_X64 - 1,
+            MIN_SQRT_PRICE_X64,
+            LIQUIDITY,
+            1,
+            FEE_RATE,
+            false,
+            true,
+        )
+        .unwrap();
+        assert_eq!(step.amount_out, 1);
+        assert!(step.amount_in > 0);
+    }
+}
+
+mod compute_swap_test {
+    use std::collections::BTreeMap;
+
+    use clmm_math::{
+        swap_math::{
+            compute_swap, compute_zap_swap_amount, SwapFeeRates, SwapOutcome, SwapState, SwapTicks,
+            ZAP_SWAP_AMOUNT_PRECISION_BITS,
+        },
+        MathError,
+    };
+
+    use super::*;
+
+    const FEE_RATES: SwapFeeRates = SwapFeeRates {
+        trade_fee_rate: 3000,
+        protocol_fee_rate: 120_000,
+        fund_fee_rate: 40_000,
+    };
+
+    /// Initialized ticks and their liquidity_net, recording the crossed ones
+    #[derive(Default)]
+    struct Ticks {
+        liquidity_net: BTreeMap<i32, i128>,
+        crossed: Vec<i32>,
+    }
+
+    impl SwapTicks for Ticks {
+        type Error = MathError;
+
+        fn next_initialized_tick(
+            &mut self,
+            tick: i32,
+            zero_for_one: bool,
+        ) -> Result<Option<i32>, MathError> {
+            let next = if zero_for_one {
+                self.liquidity_net.range(..=tick).next_back()
+            } else {
+                self.liquidity_net.range(tick + 1..).next()
+            };
+            Ok(next.map(|(tick, _)| *tick))
+        }
+
+        fn cross_tick(
+            &mut self,
+            tick: i32,
+            _fee_growth_global_x64: u128,
+            _zero_for_one: bool,
+        ) -> Result<i128, MathError> {
+            self.crossed.push(tick);
+            Ok(self.liquidity_net[&tick])
+        }
+    }
+
+    fn state() -> SwapState {
+        SwapState {
+            sqrt_price_x64: Q64,
+            tick: 0,
+            liquidity: LIQUIDITY,
+            fee_growth_global_x64: 0,
+        }
+    }
+
+    #[test]
+    fn single_step_matches_compute_swap_step() {
+        let mut ticks = Ticks::default();
+        let limit = get_sqrt_price_at_tick(100).unwrap();
+        let amount = 1_000_000;
+        let outcome =
+            compute_swap(&mut ticks, state(), FEE_RATES, amount, limit, false, true).unwrap();
+        let step = compute_swap_step(
+            Q64,
+            limit,
+            LIQUIDITY,
+            amount,
+            FEE_RATES.trade_fee_rate,
+            true,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(outcome.amount_in, amount);
+        assert_eq!(outcome.amount_out, step.amount_out);
+        assert_eq!(outcome.fee_amount, step.fee_amount);
+        assert_eq!(outcome.state.sqrt_price_x64, step.sqrt_price_next_x64);
+        assert_eq!(
+            outcome.protocol_fee + outcome.fund_fee + outcome.lp_fee,
+            outcome.fee_amount
+        );
+        assert!(outcome.state.fee_growth_global_x64 > 0);
+        assert_eq!(outcome.ticks_crossed, 0);
+    }
+
+    #[test]
+    fn crossing_a_tick_applies_its_liquidity_net() {
+        let mut ticks = Ticks::default();
+        ticks.liquidity_net.insert(-60, LIQUIDITY as i128 / 2);
+        let limit = get_sqrt_price_at_tick(-120).unwrap();
+        let outcome = compute_swap(
+            &mut ticks,
+            state(),
+            FEE_RATES,
+            u64::MAX / 2,
+            limit,
+            true,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(ticks.crossed, vec![-60]);
+        assert_eq!(outcome.ticks_crossed, 1);
+        assert_eq!(outcome.state.liquidity, LIQUIDITY / 2);
+        assert_eq!(outcome.state.sqrt_price_x64, limit);
+        assert_eq!(outcome.state.tick, -120);
+    }
+
+    #[test]
+    fn exact_output_is_delivered() {
+        let mut ticks = Ticks::default();
+        ticks.liquidity_net.insert(60, -(LIQUIDITY as i128 / 2));
+        let amount_out = 5_000_000_000;
+        let outcome = compute_swap(
+            &mut ticks,
+            state(),
+            FEE_RATES,
+            amount_out,
+            MAX_SQRT_PRICE_X64 - 1,
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(outcome.amount_out, amount_out);
+        assert!(outcome.amount_in > amount_out);
+        assert_eq!(ticks.crossed, vec![60]);
+    }
+
+    /// Exact input swap of token 0 from `state`, as a zap simulates it
+    fn swap_zero_for_one(swap_amount: u64) -> Result<Option<SwapOutcome>, MathError> {
+        compute_swap(
+            &mut Ticks::default(),
+            state(),
+            FEE_RATES,
+            swap_amount,
+            MIN_SQRT_PRICE_X64 + 1,
+            true,
+            true,
+        )
+        .map(Some)
+    }
+
+    #[test]
+    fn zap_into_a_centered_range_swaps_about_half() {
+        let amount = 1_000_000;
+        let swap_amount = compute_zap_swap_amount(
+            swap_zero_for_one,
+            Q64,
+            amount,
+            true,
+            get_sqrt_price_at_tick(-600).unwrap(),
+            get_sqrt_price_at_tick(600).unwrap(),
+        )
+        .unwrap();
+
+        // the trade fee and the price impact make the swap a little larger than half
+        let tolerance = amount >> ZAP_SWAP_AMOUNT_PRECISION_BITS;
+        assert!(swap_amount + tolerance >= amount / 2);
+        assert!(swap_amount < amount / 2 + amount / 100);
+    }
+
+    #[test]
+    fn zap_into_a_range_below_the_price_swaps_all_the_pool_fills() {
+        let amount = 1_000_000;
+        let sqrt_price_lower_x64 = get_sqrt_price_at_tick(-1200).unwrap();
+        let sqrt_price_upper_x64 = get_sqrt_price_at_tick(-600).unwrap();
+        let swap_amount = compute_zap_swap_amount(
+            swap_zero_for_one,
+            Q64,
+            amount,
+            true,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+        )
+        .unwrap();
+        let tolerance = amount >> ZAP_SWAP_AMOUNT_PRECISION_BITS;
+        assert!(swap_amount + tolerance >= amount);
+
+        // swaps the pool cannot fill count as too large
+        let fillable = amount / 4;
+        let swap_amount = compute_zap_swap_amount(
+            |swap_amount| match swap_amount {
+                swap_amount if swap_amount > fillable => Ok(None),
+                swap_amount => swap_zero_for_one(swap_amount),
+            },
+            Q64,
+            amount,
+            true,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+        )
+        .unwrap();
+        assert!(swap_amount <= fillable);
+        assert!(swap_amount + tolerance >= fillable);
+    }
+
+    #[test]
+    fn zap_into_a_range_above_the_price_swaps_nothing() {
+        let swap_amount = compute_zap_swap_amount(
+            |_| -> Result<Option<SwapOutcome>, MathError> { unreachable!() },
+            Q64,
+            1_000_000,
+            true,
+            get_sqrt_price_at_tick(600).unwrap(),
+            get_sqrt_price_at_tick(1200).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(swap_amount, 0);
+    }
+}
@@ -0,0 +1,233 @@
+use clmm_math::{
+    tick_math::{
+        get_sqrt_price_at_tick, get_tick_at_sqrt_price, MAX_SQRT_PRICE_X64, MAX_TICK,
+        MIN_SQRT_PRICE_X64, MIN_TICK,
+    },
+    MathError,
+};
+use num_bigint::BigUint;
+use proptest::prelude::*;
+
+/// √1.0001^tick computed with 256 fractional bits, far beyond the 64 bits of the program
+mod reference {
+    use super::*;
+
+    const PRECISION: usize = 256;
+
+    /// √1.0001 in Q256, rounded down
+    fn sqrt_1_0001() -> BigUint {
+        ((BigUint::from(10001u32) << (2 * PRECISION)) / 10000u32).sqrt()
+    }
+
+    /// √1.0001^tick in Q256
+    pub fn sqrt_price_q256(tick: i32) -> BigUint {
+        let mut base = if tick >= 0 {
+            sqrt_1_0001()
+        } else {
+            (BigUint::from(1u8) << (2 * PRECISION)) / sqrt_1_0001()
+        };
+        let mut result = BigUint::from(1u8) << PRECISION;
+        let mut exponent = tick.unsigned_abs();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = (&result * &base) >> PRECISION;
+            }
+            base = (&base * &base) >> PRECISION;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    /// A Q64.64 price in Q256
+    pub fn x64_to_q256(sqrt_price_x64: u128) -> BigUint {
+        BigUint::from(sqrt_price_x64) << (PRECISION - 64)
+    }
+
+    /// Greatest tick whose reference price is at most `sqrt_price_x64`
+    pub fn floor_tick(sqrt_price_x64: u128) -> i32 {
+        let sqrt_price = x64_to_q256(sqrt_price_x64);
+        let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+            if sqrt_price_q256(mid) <= sqrt_price {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    }
+}
+
+/// `get_sqrt_price_at_tick` multiplies truncated 64-bit constants, the table is accurate to
+/// 2^-32 relative to the exact price
+fn within_precision(sqrt_price_x64: u128, exact_q256: &BigUint) -> bool {
+    let sqrt_price = reference::x64_to_q256(sqrt_price_x64);
+    let error = if sqrt_price > *exact_q256 {
+        &sqrt_price - exact_q256
+    } else {
+        exact_q256 - &sqrt_price
+    };
+    error <= exact_q256 >> 32usize
+}
+
+/// Any tick a price can be converted from, i.e. all valid ticks but `MAX_TICK`
+fn tick() -> impl Strategy<Value = i32> {
+    prop_oneof![
+        MIN_TICK..MAX_TICK,
+        -1000..1000,
+        MIN_TICK..MIN_TICK + 1000,
+        MAX_TICK - 1000..MAX_TICK,
+    ]
+}
+
+/// Any valid price. Uniformly drawn prices are almost all above tick 400000, so prices are
+/// also drawn within the range of a random tick.
+fn sqrt_price_x64() -> impl Strategy<Value = u128> {
+    prop_oneof![
+        MIN_SQRT_PRICE_X64..MAX_SQRT_PRICE_X64,
+        (tick(), 0.0..1.0f64).prop_map(|(tick, fraction)| {
+            let lower = get_sqrt_price_at_tick(tick).unwrap();
+            let upper = get_sqrt_price_at_tick(tick + 1).unwrap();
+            lower + ((upper - lower) as f64 * fraction) as u128
+        }),
+        tick().prop_map(|tick| get_sqrt_price_at_tick(tick).unwrap()),
+        tick().prop_map(|tick| get_sqrt_price_at_tick(tick + 1).unwrap() - 1),
+    ]
+}
+
+mod get_sqrt_price_at_tick_test {
+    use super::*;
+
+    #[test]
+    fn boundaries() {
+        assert_eq!(
+            get_sqrt_price_at_tick(MIN_TICK).unwrap(),
+            MIN_SQRT_PRICE_X64
+        );
+        assert_eq!(
+            get_sqrt_price_at_tick(MAX_TICK).unwrap(),
+            MAX_SQRT_PRICE_X64
+        );
+        assert_eq!(get_sqrt_price_at_tick(0).unwrap(), 1 << 64);
+        assert_eq!(
+            get_sqrt_price_at_tick(MIN_TICK - 1),
+            Err(MathError::TickUpperOverflow)
+        );
+        assert_eq!(
+            get_sqrt_price_at_tick(MAX_TICK + 1),
+            Err(MathError::TickUpperOverflow)
+        );
+        assert!(within_precision(
+            MIN_SQRT_PRICE_X64,
+            &reference::sqrt_price_q256(MIN_TICK)
+        ));
+        assert!(within_precision(
+            MAX_SQRT_PRICE_X64,
+            &reference::sqrt_price_q256(MAX_TICK)
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn matches_reference(tick in MIN_TICK..=MAX_TICK) {
+            let sqrt_price_x64 = get_sqrt_price_at_tick(tick).unwrap();
+            prop_assert!(within_precision(sqrt_price_x64, &reference::sqrt_price_q256(tick)));
+        }
+
+        #[test]
+        fn is_strictly_increasing(tick in tick()) {
+            prop_assert!(
+                get_sqrt_price_at_tick(tick).unwrap() < get_sqrt_price_at_tick(tick + 1).unwrap()
+            );
+        }
+
+        #[test]
+        fn fails_out_of_range(tick in prop_oneof![i32::MIN..MIN_TICK, MAX_TICK + 1..=i32::MAX]) {
+            prop_assert_eq!(get_sqrt_price_at_tick(tick), Err(MathError::TickUpperOverflow));
+        }
+    }
+}
+
+mod get_tick_at_sqrt_price_test {
+    use super::*;
+
+    #[test]
+    fn boundaries() {
+        assert_eq!(
+            get_tick_at_sqrt_price(MIN_SQRT_PRICE_X64).unwrap(),
+            MIN_TICK
+        );
+        assert_eq!(
+            get_tick_at_sqrt_price(MAX_SQRT_PRICE_X64 - 1).unwrap(),
+            MAX_TICK - 1
+        );
+        assert_eq!(
+            get_tick_at_sqrt_price(MIN_SQRT_PRICE_X64 - 1),
+            Err(MathError::InvalidSqrtPrice)
+        );
+        // the upper bound is exclusive, no tick above `MAX_TICK` can be returned
+        assert_eq!(
+            get_tick_at_sqrt_price(MAX_SQRT_PRICE_X64),
+            Err(MathError::InvalidSqrtPrice)
+        );
+        assert_eq!(get_tick_at_sqrt_price(0), Err(MathError::InvalidSqrtPrice));
+        assert_eq!(
+            get_tick_at_sqrt_price(u128::MAX),
+            Err(MathError::InvalidSqrtPrice)
+        );
+    }
+
+    /// Every tick converts back to itself, checked exhaustively since there are under a million
+    #[test]
+    fn round_trips_every_tick() {
+        for tick in MIN_TICK..MAX_TICK {
+            let sqrt_price_x64 = get_sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(get_tick_at_sqrt_price(sqrt_price_x64).unwrap(), tick);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips(tick in tick()) {
+            let sqrt_price_x64 = get_sqrt_price_at_tick(tick).unwrap();
+            prop_assert_eq!(get_tick_at_sqrt_price(sqrt_price_x64).unwrap(), tick);
+            // one below the price of a tick falls in the previous tick
+            if tick > MIN_TICK {
+                prop_assert_eq!(get_tick_at_sqrt_price(sqrt_price_x64 - 1).unwrap(), tick - 1);
+            }
+        }
+
+        /// The returned tick is the greatest tick whose price is at most the input
+        #[test]
+        fn returns_floor_tick(sqrt_price_x64 in sqrt_price_x64()) {
+            let tick = get_tick_at_sqrt_price(sqrt_price_x64).unwrap();
+            prop_assert!(get_sqrt_price_at_tick(tick).unwrap() <= sqrt_price_x64);
+            prop_assert!(sqrt_price_x64 < get_sqrt_price_at_tick(tick + 1).unwrap());
+        }
+
+        /// Same as the floor of the exact price, unless the input is so close to a tick that the
+        /// precision of `get_sqrt_price_at_tick` decides which side of it the input is on
+        #[test]
+        fn matches_reference_floor_tick(sqrt_price_x64 in sqrt_price_x64()) {
+            let tick = get_tick_at_sqrt_price(sqrt_price_x64).unwrap();
+            let reference_tick = reference::floor_tick(sqrt_price_x64);
+            if tick != reference_tick {
+                let boundary = tick.max(reference_tick);
+                prop_assert_eq!((tick - reference_tick).abs(), 1);
+                prop_assert!(within_precision(
+                    sqrt_price_x64,
+                    &reference::sqrt_price_q256(boundary)
+                ));
+            }
+        }
+
+        #[test]
+        fn is_monotonic(a in sqrt_price_x64(), b in sqrt_price_x64()) {
+            let (low, high) = (a.min(b), a.max(b));
+            prop_assert!(
+                get_tick_at_sqrt_price(low).unwrap() <= get_tick_at_sqrt_price(high).unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! `get_delta_amounts_signed` rounds deposits up and withdrawals down, it may fail on amounts
+//! that do not fit in a u64 but never panics
+#![no_main]
+
+use arbitrary::Arbitrary;
+use clmm_fuzz::assert_rounds_against_user;
+use clmm_math::{
+    liquidity_math::get_delta_amounts_signed,
+    tick_math::{get_sqrt_price_at_tick, get_tick_at_sqrt_price, MAX_TICK, MIN_TICK},
+};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    tick_a: i32,
+    tick_b: i32,
+    tick_current: i32,
+    /// Position of the price within the current tick, as a fraction of `u32::MAX`
+    price_offset: u32,
+    liquidity_delta: i128,
+}
+
+/// Maps any i32 into the valid tick range
+fn clamp_tick(tick: i32) -> i32 {
+    MIN_TICK + tick.rem_euclid(MAX_TICK - MIN_TICK + 1)
+}
+
+fuzz_target!(|input: Input| {
+    let (tick_a, tick_b) = (clamp_tick(input.tick_a), clamp_tick(input.tick_b));
+    if tick_a == tick_b {
+        return;
+    }
+    let (tick_lower, tick_upper) = (tick_a.min(tick_b), tick_a.max(tick_b));
+
+    // a price anywhere within the current tick, the last valid price is that of `MAX_TICK - 1`
+    let tick_current = clamp_tick(input.tick_current).min(MAX_TICK - 1);
+    let sqrt_price_lower_x64 = get_sqrt_price_at_tick(tick_current).unwrap();
+    let sqrt_price_upper_x64 = get_sqrt_price_at_tick(tick_current + 1).unwrap();
+    let sqrt_price_x64 = sqrt_price_lower_x64
+        + (sqrt_price_upper_x64 - sqrt_price_lower_x64 - 1) * u128::from(input.price_offset)
+            / u128::from(u32::MAX);
+    assert_eq!(
+        get_tick_at_sqrt_price(sqrt_price_x64).unwrap(),
+        tick_current
+    );
+
+    if let Ok(amounts) = get_delta_amounts_signed(
+        tick_current,
+        sqrt_price_x64,
+        tick_lower,
+        tick_upper,
+        input.liquidity_delta,
+    ) {
+        assert_rounds_against_user(
+            tick_current,
+            sqrt_price_x64,
+            tick_lower,
+            tick_upper,
+            input.liquidity_delta,
+            amounts,
+        );
+    }
+});
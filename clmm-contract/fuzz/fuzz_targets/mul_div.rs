@@ -0,0 +1,91 @@
+//! `MulDiv` never panics on a nonzero denominator and matches big integer division, returning
+//! `None` exactly when the result does not fit
+#![no_main]
+
+use arbitrary::Arbitrary;
+use clmm_math::{
+    big_nums::{U128, U256},
+    full_math::MulDiv,
+};
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigUint;
+
+#[derive(Arbitrary, Debug)]
+enum Input {
+    U64([u64; 3]),
+    Native128([u128; 3]),
+    U128([[u64; 2]; 3]),
+    U256([[u64; 4]; 3]),
+}
+
+fn from_words(words: &[u64]) -> BigUint {
+    words
+        .iter()
+        .rev()
+        .fold(BigUint::default(), |value, word| (value << 64u8) + *word)
+}
+
+/// Checks floor and ceil of `x * y / denominator` against the exact quotient
+fn check<T: MulDiv<Output = T> + Copy>(
+    x: T,
+    y: T,
+    denominator: T,
+    operands: [BigUint; 3],
+    bits: u32,
+    to_big: impl Fn(T) -> BigUint,
+) {
+    let [x_big, y_big, denominator_big] = operands;
+    if denominator_big == BigUint::default() {
+        return;
+    }
+    let product = x_big * y_big;
+    let floor = &product / &denominator_big;
+    let ceil = (&product + &denominator_big - 1u8) / &denominator_big;
+    let max = (BigUint::from(1u8) << bits) - 1u8;
+
+    match x.mul_div_floor(y, denominator) {
+        Some(result) => assert_eq!(to_big(result), floor),
+        None => assert!(floor > max, "mul_div_floor overflowed on {}", floor),
+    }
+    match x.mul_div_ceil(y, denominator) {
+        Some(result) => assert_eq!(to_big(result), ceil),
+        None => assert!(ceil > max, "mul_div_ceil overflowed on {}", ceil),
+    }
+}
+
+fuzz_target!(|input: Input| {
+    match input {
+        Input::U64([x, y, denominator]) => check(
+            x,
+            y,
+            denominator,
+            [x, y, denominator].map(BigUint::from),
+            64,
+            BigUint::from,
+        ),
+        Input::Native128([x, y, denominator]) => check(
+            x,
+            y,
+            denominator,
+            [x, y, denominator].map(BigUint::from),
+            128,
+            BigUint::from,
+        ),
+        Input::U128([x, y, denominator]) => check(
+            U128(x),
+            U128(y),
+            U128(denominator),
+            [x, y, denominator].map(|words| from_words(&words)),
+            128,
+            |value| from_words(&value.0),
+        ),
+        Input::U256([x, y, denominator]) => check(
+            U256(x),
+            U256(y),
+            U256(denominator),
+            [x, y, denominator].map(|words| from_words(&words)),
+            256,
+            |value| from_words(&value.0),
+        ),
+    }
+});
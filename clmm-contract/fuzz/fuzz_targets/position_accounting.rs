@@ -0,0 +1,489 @@
+//! Drives random sequences of liquidity changes, price moves and fee accruals through the tick
+//! and position state of the program, checking after every step that
+//!
+//! * the `liquidity_gross` and `liquidity_net` of every tick match the positions referencing
+//!   it, and `liquidity_net` sums to zero across ticks
+//! * the pool and protocol position liquidity match the personal positions
+//! * deposits round up and withdrawals round down, so the vaults never run short
+//! * no position is owed more fees than were paid
+#![no_main]
+
+use std::collections::BTreeMap;
+
+use anchor_lang::prelude::Pubkey;
+use arbitrary::Arbitrary;
+use clmm_contract::state::{
+    get_fee_growth_inside, PersonalPositionState, ProtocolPositionState, TickState,
+};
+use clmm_fuzz::assert_rounds_against_user;
+use clmm_math::{
+    fee_math::calculate_latest_token_fees,
+    fixed_point_64::RESOLUTION,
+    liquidity_math::{
+        add_delta, get_delta_amount_0_unsigned, get_delta_amount_1_unsigned,
+        get_delta_amounts_signed,
+    },
+    tick_math::{get_sqrt_price_at_tick, MAX_TICK, MIN_TICK},
+    MathError,
+};
+use libfuzzer_sys::fuzz_target;
+
+const TICK_SPACING: i32 = 10;
+const MAX_POSITIONS: usize = 8;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    /// Initial tick of the pool, in tick spacings
+    start_tick: i16,
+    actions: Vec<Action>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Action {
+    /// Opens a position over `width + 1` tick spacings, starting at `tick_lower` tick spacings
+    OpenPosition {
+        tick_lower: i16,
+        width: u16,
+        liquidity: u64,
+    },
+    AddLiquidity {
+        position: u8,
+        liquidity: u64,
+    },
+    /// Removes at most the liquidity of the position
+    RemoveLiquidity {
+        position: u8,
+        liquidity: u64,
+    },
+    /// Swaps the price to the start of tick `tick * TICK_SPACING + offset % TICK_SPACING`
+    MovePrice {
+        tick: i16,
+        offset: u8,
+    },
+    /// Swap fees paid to the liquidity in range
+    AccrueFees {
+        amount_0: u32,
+        amount_1: u32,
+    },
+}
+
+/// In-memory pool, with the ticks and positions it references
+#[derive(Clone)]
+struct Model {
+    tick_current: i32,
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    fee_growth_global_0_x64: u128,
+    fee_growth_global_1_x64: u128,
+    ticks: BTreeMap<i32, TickState>,
+    protocol_positions: BTreeMap<(i32, i32), ProtocolPositionState>,
+    positions: Vec<PersonalPositionState>,
+    vault_0: u128,
+    vault_1: u128,
+    fees_paid_0: u128,
+    fees_paid_1: u128,
+}
+
+impl Model {
+    fn new(start_tick: i16) -> Self {
+        let tick_current = (i32::from(start_tick) * TICK_SPACING).clamp(MIN_TICK, MAX_TICK - 1);
+        Self {
+            tick_current,
+            sqrt_price_x64: get_sqrt_price_at_tick(tick_current).unwrap(),
+            liquidity: 0,
+            fee_growth_global_0_x64: 0,
+            fee_growth_global_1_x64: 0,
+            ticks: BTreeMap::new(),
+            protocol_positions: BTreeMap::new(),
+            positions: Vec::new(),
+            vault_0: 0,
+            vault_1: 0,
+            fees_paid_0: 0,
+            fees_paid_1: 0,
+        }
+    }
+
+    /// Returns the state after `action`, or the error of the token amount math if it fails
+    fn apply(&self, action: &Action) -> Result<Self, MathError> {
+        let mut next = self.clone();
+        match *action {
+            Action::OpenPosition {
+                tick_lower,
+                width,
+                liquidity,
+            } => {
+                if next.positions.len() < MAX_POSITIONS {
+                    let tick_lower_index = i32::from(tick_lower) * TICK_SPACING;
+                    let tick_upper_index = (tick_lower_index
+                        + (i32::from(width) + 1) * TICK_SPACING)
+                        .min(MAX_TICK / TICK_SPACING * TICK_SPACING);
+                    next.positions.push(PersonalPositionState {
+                        bump: [0],
+                        nft_mint: Pubkey::new_unique(),
+                        pool_id: Pubkey::default(),
+                        tick_lower_index,
+                        tick_upper_index,
+                        liquidity: 0,
+                        fee_growth_inside_0_last_x64: 0,
+                        fee_growth_inside_1_last_x64: 0,
+                        token_fees_owed_0: 0,
+                        token_fees_owed_1: 0,
+                        version: PersonalPositionState::VERSION,
+                        operator: Pubkey::default(),
+                        operator_approver: Pubkey::default(),
+                        padding: [0; 8],
+                    });
+                    next.modify_position(next.positions.len() - 1, i128::from(liquidity))?;
+                }
+            }
+            Action::AddLiquidity {
+                position,
+                liquidity,
+            } => {
+                if !next.positions.is_empty() {
+                    let index = usize::from(position) % next.positions.len();
+                    next.modify_position(index, i128::from(liquidity))?;
+                }
+            }
+            Action::RemoveLiquidity {
+                position,
+                liquidity,
+            } => {
+                if !next.positions.is_empty() {
+                    let index = usize::from(position) % next.positions.len();
+                    let liquidity = u128::from(liquidity).min(next.positions[index].liquidity);
+                    next.modify_position(index, -(liquidity as i128))?;
+                }
+            }
+            Action::MovePrice { tick, offset } => {
+                let tick_target = (i32::from(tick) * TICK_SPACING
+                    + i32::from(offset) % TICK_SPACING)
+                    .clamp(MIN_TICK, MAX_TICK - 1);
+                next.move_price(tick_target)?;
+            }
+            Action::AccrueFees { amount_0, amount_1 } => {
+                if next.liquidity > 0 {
+                    next.fee_growth_global_0_x64 = next
+                        .fee_growth_global_0_x64
+                        .wrapping_add(next.fee_growth(amount_0));
+                    next.fee_growth_global_1_x64 = next
+                        .fee_growth_global_1_x64
+                        .wrapping_add(next.fee_growth(amount_1));
+                    next.vault_0 += u128::from(amount_0);
+                    next.vault_1 += u128::from(amount_1);
+                    next.fees_paid_0 += u128::from(amount_0);
+                    next.fees_paid_1 += u128::from(amount_1);
+                }
+            }
+        }
+        Ok(next)
+    }
+
+    /// Fee growth per unit of the liquidity in range for `amount` of fees
+    fn fee_growth(&self, amount: u32) -> u128 {
+        (u128::from(amount) << RESOLUTION) / self.liquidity
+    }
+
+    fn tick(&self, tick: i32) -> TickState {
+        self.ticks.get(&tick).copied().unwrap_or(TickState {
+            tick,
+            ..Default::default()
+        })
+    }
+
+    /// Adds `liquidity_delta` to a position the way the liquidity instructions do, removing
+    /// liquidity when it is negative
+    fn modify_position(&mut self, index: usize, liquidity_delta: i128) -> Result<(), MathError> {
+        if liquidity_delta == 0 {
+            return Ok(());
+        }
+        let tick_lower_index = self.positions[index].tick_lower_index;
+        let tick_upper_index = self.positions[index].tick_upper_index;
+        let amounts = get_delta_amounts_signed(
+            self.tick_current,
+            self.sqrt_price_x64,
+            tick_lower_index,
+            tick_upper_index,
+            liquidity_delta,
+        )?;
+
+        let mut tick_lower = self.tick(tick_lower_index);
+        let mut tick_upper = self.tick(tick_upper_index);
+        let flipped_lower = tick_lower
+            .update(
+                self.tick_current,
+                liquidity_delta,
+                self.fee_growth_global_0_x64,
+                self.fee_growth_global_1_x64,
+                false,
+            )
+            .unwrap();
+        let flipped_upper = tick_upper
+            .update(
+                self.tick_current,
+                liquidity_delta,
+                self.fee_growth_global_0_x64,
+                self.fee_growth_global_1_x64,
+                true,
+            )
+            .unwrap();
+        let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = get_fee_growth_inside(
+            &tick_lower,
+            &tick_upper,
+            self.tick_current,
+            self.fee_growth_global_0_x64,
+            self.fee_growth_global_1_x64,
+        )
+        .unwrap();
+
+        self.protocol_positions
+            .entry((tick_lower_index, tick_upper_index))
+            .or_insert_with(|| ProtocolPositionState {
+                bump: 0,
+                pool_id: Pubkey::default(),
+                tick_lower_index,
+                tick_upper_index,
+                liquidity: 0,
+                fee_growth_inside_0_last_x64: 0,
+                fee_growth_inside_1_last_x64: 0,
+                token_fees_owed_0: 0,
+                token_fees_owed_1: 0,
+                recent_epoch: 0,
+                version: ProtocolPositionState::VERSION,
+                padding: [0; 8],
+            })
+            .update(
+                tick_lower_index,
+                tick_upper_index,
+                liquidity_delta,
+                fee_growth_inside_0_x64,
+                fee_growth_inside_1_x64,
+            )
+            .unwrap();
+
+        let position = &mut self.positions[index];
+        position.token_fees_owed_0 = calculate_latest_token_fees(
+            position.token_fees_owed_0,
+            position.fee_growth_inside_0_last_x64,
+            fee_growth_inside_0_x64,
+            position.liquidity,
+        )
+        .unwrap();
+        position.token_fees_owed_1 = calculate_latest_token_fees(
+            position.token_fees_owed_1,
+            position.fee_growth_inside_1_last_x64,
+            fee_growth_inside_1_x64,
+            position.liquidity,
+        )
+        .unwrap();
+        position.fee_growth_inside_0_last_x64 = fee_growth_inside_0_x64;
+        position.fee_growth_inside_1_last_x64 = fee_growth_inside_1_x64;
+        position.liquidity = add_delta(position.liquidity, liquidity_delta).unwrap();
+
+        // ticks no longer referenced by any position are cleared
+        for (tick, flipped) in [(tick_lower, flipped_lower), (tick_upper, flipped_upper)] {
+            if flipped && liquidity_delta < 0 {
+                self.ticks.remove(&{ tick.tick });
+            } else {
+                self.ticks.insert(tick.tick, tick);
+            }
+        }
+        if tick_lower_index <= self.tick_current && self.tick_current < tick_upper_index {
+            self.liquidity = add_delta(self.liquidity, liquidity_delta).unwrap();
+        }
+
+        assert_rounds_against_user(
+            self.tick_current,
+            self.sqrt_price_x64,
+            tick_lower_index,
+            tick_upper_index,
+            liquidity_delta,
+            amounts,
+        );
+        if liquidity_delta > 0 {
+            self.vault_0 += u128::from(amounts.0);
+            self.vault_1 += u128::from(amounts.1);
+        } else {
+            self.withdraw(amounts.0, amounts.1);
+        }
+        Ok(())
+    }
+
+    /// Swaps to the price of `tick_target`, crossing the initialized ticks on the way
+    fn move_price(&mut self, tick_target: i32) -> Result<(), MathError> {
+        let sqrt_price_target_x64 = get_sqrt_price_at_tick(tick_target).unwrap();
+        if sqrt_price_target_x64 > self.sqrt_price_x64 {
+            let crossed: Vec<i32> = self
+                .ticks
+                .range(self.tick_current + 1..=tick_target)
+                .map(|(tick, _)| *tick)
+                .collect();
+            for tick in crossed {
+                self.swap_to(get_sqrt_price_at_tick(tick).unwrap())?;
+                let liquidity_net = self.cross(tick);
+                self.liquidity = add_delta(self.liquidity, liquidity_net).unwrap();
+                self.tick_current = tick;
+            }
+        } else if tick_target < self.tick_current {
+            let crossed: Vec<i32> = self
+                .ticks
+                .range(tick_target + 1..=self.tick_current)
+                .rev()
+                .map(|(tick, _)| *tick)
+                .collect();
+            for tick in crossed {
+                self.swap_to(get_sqrt_price_at_tick(tick).unwrap())?;
+                let liquidity_net = self.cross(tick);
+                self.liquidity = add_delta(self.liquidity, -liquidity_net).unwrap();
+                self.tick_current = tick - 1;
+            }
+        }
+        self.swap_to(sqrt_price_target_x64)?;
+        self.tick_current = tick_target;
+        Ok(())
+    }
+
+    fn cross(&mut self, tick: i32) -> i128 {
+        self.ticks
+            .get_mut(&tick)
+            .unwrap()
+            .cross(self.fee_growth_global_0_x64, self.fee_growth_global_1_x64)
+    }
+
+    /// Moves the price without crossing a tick, the vaults receive the input rounded up and pay
+    /// the output rounded down
+    fn swap_to(&mut self, sqrt_price_x64: u128) -> Result<(), MathError> {
+        if sqrt_price_x64 > self.sqrt_price_x64 {
+            let amount_1_in = get_delta_amount_1_unsigned(
+                self.sqrt_price_x64,
+                sqrt_price_x64,
+                self.liquidity,
+                true,
+            )?;
+            let amount_0_out = get_delta_amount_0_unsigned(
+                self.sqrt_price_x64,
+                sqrt_price_x64,
+                self.liquidity,
+                false,
+            )?;
+            self.vault_1 += u128::from(amount_1_in);
+            self.withdraw(amount_0_out, 0);
+        } else {
+            let amount_0_in = get_delta_amount_0_unsigned(
+                self.sqrt_price_x64,
+                sqrt_price_x64,
+                self.liquidity,
+                true,
+            )?;
+            let amount_1_out = get_delta_amount_1_unsigned(
+                self.sqrt_price_x64,
+                sqrt_price_x64,
+                self.liquidity,
+                false,
+            )?;
+            self.vault_0 += u128::from(amount_0_in);
+            self.withdraw(0, amount_1_out);
+        }
+        self.sqrt_price_x64 = sqrt_price_x64;
+        Ok(())
+    }
+
+    fn withdraw(&mut self, amount_0: u64, amount_1: u64) {
+        self.vault_0 = self
+            .vault_0
+            .checked_sub(u128::from(amount_0))
+            .expect("vault 0 underflow");
+        self.vault_1 = self
+            .vault_1
+            .checked_sub(u128::from(amount_1))
+            .expect("vault 1 underflow");
+    }
+
+    fn check_invariants(&self) {
+        let mut liquidity_gross = BTreeMap::<i32, u128>::new();
+        let mut liquidity_net = BTreeMap::<i32, i128>::new();
+        let mut range_liquidity = BTreeMap::<(i32, i32), u128>::new();
+        let mut liquidity_in_range = 0;
+        for position in self
+            .positions
+            .iter()
+            .filter(|position| position.liquidity > 0)
+        {
+            let (tick_lower, tick_upper) = (position.tick_lower_index, position.tick_upper_index);
+            *liquidity_gross.entry(tick_lower).or_default() += position.liquidity;
+            *liquidity_gross.entry(tick_upper).or_default() += position.liquidity;
+            *liquidity_net.entry(tick_lower).or_default() += position.liquidity as i128;
+            *liquidity_net.entry(tick_upper).or_default() -= position.liquidity as i128;
+            *range_liquidity.entry((tick_lower, tick_upper)).or_default() += position.liquidity;
+            if tick_lower <= self.tick_current && self.tick_current < tick_upper {
+                liquidity_in_range += position.liquidity;
+            }
+        }
+
+        assert_eq!(
+            self.ticks.keys().collect::<Vec<_>>(),
+            liquidity_gross.keys().collect::<Vec<_>>(),
+            "initialized ticks"
+        );
+        for (index, tick) in &self.ticks {
+            assert_eq!(
+                { tick.liquidity_gross },
+                liquidity_gross[index],
+                "tick {}",
+                index
+            );
+            assert_eq!(
+                { tick.liquidity_net },
+                liquidity_net[index],
+                "tick {}",
+                index
+            );
+        }
+        assert_eq!(
+            self.ticks
+                .values()
+                .map(|tick| tick.liquidity_net)
+                .sum::<i128>(),
+            0
+        );
+
+        assert_eq!(self.liquidity, liquidity_in_range);
+        for (range, protocol_position) in &self.protocol_positions {
+            assert_eq!(
+                protocol_position.liquidity,
+                range_liquidity.get(range).copied().unwrap_or_default(),
+                "range {:?}",
+                range
+            );
+        }
+
+        let protocol_fees_owed = self
+            .protocol_positions
+            .values()
+            .map(|position| (position.token_fees_owed_0, position.token_fees_owed_1));
+        let personal_fees_owed = self
+            .positions
+            .iter()
+            .map(|position| (position.token_fees_owed_0, position.token_fees_owed_1));
+        for fees_owed in [
+            protocol_fees_owed.collect::<Vec<_>>(),
+            personal_fees_owed.collect(),
+        ] {
+            let fees_owed_0: u128 = fees_owed.iter().map(|fees| u128::from(fees.0)).sum();
+            let fees_owed_1: u128 = fees_owed.iter().map(|fees| u128::from(fees.1)).sum();
+            assert!(fees_owed_0 <= self.fees_paid_0);
+            assert!(fees_owed_1 <= self.fees_paid_1);
+        }
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mut model = Model::new(input.start_tick);
+    for action in &input.actions {
+        // an action whose token amounts do not fit in a u64 is rejected by the program
+        if let Ok(next) = model.apply(action) {
+            model = next;
+        }
+        model.check_invariants();
+    }
+});
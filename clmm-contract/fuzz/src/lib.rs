@@ -0,0 +1,121 @@
+//! Exact reference math shared by the fuzz targets
+//!
+//! Token amounts are computed as exact fractions of big integers, so the rounding direction
+//! of the fixed point math under test can be checked.
+
+use clmm_math::{fixed_point_64::RESOLUTION, tick_math::get_sqrt_price_at_tick};
+use num_bigint::BigUint;
+
+/// A non-negative fraction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fraction {
+    pub numerator: BigUint,
+    pub denominator: BigUint,
+}
+
+impl Fraction {
+    pub fn integer(value: impl Into<BigUint>) -> Self {
+        Self {
+            numerator: value.into(),
+            denominator: BigUint::from(1u8),
+        }
+    }
+
+    pub fn floor(&self) -> BigUint {
+        &self.numerator / &self.denominator
+    }
+
+    pub fn ceil(&self) -> BigUint {
+        (&self.numerator + &self.denominator - 1u8) / &self.denominator
+    }
+}
+
+/// `Δx = L * (√P_upper - √P_lower) / (√P_upper * √P_lower)`, with prices in Q64.64
+pub fn amount_0(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128, liquidity: u128) -> Fraction {
+    let (low, high) = (
+        sqrt_price_a_x64.min(sqrt_price_b_x64),
+        sqrt_price_a_x64.max(sqrt_price_b_x64),
+    );
+    Fraction {
+        numerator: (BigUint::from(liquidity) << RESOLUTION) * (high - low),
+        denominator: BigUint::from(low) * high,
+    }
+}
+
+/// `Δy = L * (√P_upper - √P_lower)`, with prices in Q64.64
+pub fn amount_1(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128, liquidity: u128) -> Fraction {
+    let (low, high) = (
+        sqrt_price_a_x64.min(sqrt_price_b_x64),
+        sqrt_price_a_x64.max(sqrt_price_b_x64),
+    );
+    Fraction {
+        numerator: BigUint::from(liquidity) * (high - low),
+        denominator: BigUint::from(1u8) << RESOLUTION,
+    }
+}
+
+/// Exact amounts backing `liquidity` in [tick_lower, tick_upper), mirroring the cases of
+/// `liquidity_math::get_delta_amounts_signed`
+pub fn delta_amounts(
+    tick_current: i32,
+    sqrt_price_x64: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+) -> (Fraction, Fraction) {
+    let sqrt_price_lower_x64 = get_sqrt_price_at_tick(tick_lower).unwrap();
+    let sqrt_price_upper_x64 = get_sqrt_price_at_tick(tick_upper).unwrap();
+    if tick_current < tick_lower {
+        (
+            amount_0(sqrt_price_lower_x64, sqrt_price_upper_x64, liquidity),
+            Fraction::integer(0u8),
+        )
+    } else if tick_current < tick_upper {
+        (
+            amount_0(sqrt_price_x64, sqrt_price_upper_x64, liquidity),
+            amount_1(sqrt_price_lower_x64, sqrt_price_x64, liquidity),
+        )
+    } else {
+        (
+            Fraction::integer(0u8),
+            amount_1(sqrt_price_lower_x64, sqrt_price_upper_x64, liquidity),
+        )
+    }
+}
+
+/// Panics unless `amounts` round against the user: deposits (`liquidity_delta > 0`) cover the
+/// exact amounts and withdrawals do not exceed them
+pub fn assert_rounds_against_user(
+    tick_current: i32,
+    sqrt_price_x64: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity_delta: i128,
+    amounts: (u64, u64),
+) {
+    let (exact_0, exact_1) = delta_amounts(
+        tick_current,
+        sqrt_price_x64,
+        tick_lower,
+        tick_upper,
+        liquidity_delta.unsigned_abs(),
+    );
+    for (amount, exact) in [(amounts.0, exact_0), (amounts.1, exact_1)] {
+        let amount = BigUint::from(amount);
+        if liquidity_delta > 0 {
+            assert!(
+                amount >= exact.ceil(),
+                "deposit of {} is below the exact amount {:?}",
+                amount,
+                exact
+            );
+        } else {
+            assert!(
+                amount <= exact.floor(),
+                "withdrawal of {} is above the exact amount {:?}",
+                amount,
+                exact
+            );
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Compute units of the hot paths of the clmm program, measured in LiteSVM
+//!
+//! Build both programs with `anchor build`, then run `cargo bench -p clmm-bench`. Results are
+//! compared with `benches/compute_units.txt` and the bench fails when a path got more
+//! expensive. After an intended change, run it with `UPDATE_BASELINE=1` and commit the
+//! rewritten baseline, so the new costs show up in review.
+//!
+//! Per-call costs are the difference between running a `clmm-bench` instruction over
+//! `CALLS` inputs and over none, divided by `CALLS`. They include the borsh decoding of one
+//! input. Instruction costs are those of a whole transaction holding only that instruction.
+//! `zap_in` is measured crossing 1, 10 and 100 initialized ticks, its cost grows with the ticks
+//! its swap crosses.
+
+#[path = "../../clmm-contract/tests/common/mod.rs"]
+mod common;
+
+use std::{collections::BTreeMap, env, fs, path::PathBuf, process::ExitCode};
+
+use anchor_lang::{solana_program::instruction::Instruction, InstructionData, ToAccountMetas};
+use clmm_bench::SwapStepArgs;
+use clmm_client::{
+    instructions::{self, PriceBand},
+    SwapQuoter,
+};
+use clmm_math::tick_math::{get_sqrt_price_at_tick, MAX_TICK, MIN_TICK};
+use common::*;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+const CALLS: i32 = 16;
+const LIQUIDITY: u128 = 1_000_000_000;
+
+type Results = BTreeMap<String, u64>;
+
+fn baseline_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("benches/compute_units.txt")
+}
+
+fn bench_program_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/clmm_bench.so")
+}
+
+/// Compute units of a transaction holding `instruction`, after a compute unit limit raise
+/// that costs the same in every transaction
+fn compute_units(env: &mut TestEnv, instruction: Instruction, signers: &[&Keypair]) -> u64 {
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+        instruction,
+    ];
+    env.send(&instructions, signers)
+        .unwrap_or_else(|failed| panic!("{:?}", failed))
+        .compute_units_consumed
+}
+
+fn bench_instruction(data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: clmm_bench::ID,
+        accounts: clmm_bench::accounts::Bench {}.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Compute units of one call, over `inputs`
+fn per_call<T>(env: &mut TestEnv, inputs: Vec<T>, build: impl Fn(Vec<T>) -> Instruction) -> u64 {
+    let calls = inputs.len() as u64;
+    let empty = compute_units(env, build(Vec::new()), &[]);
+    let full = compute_units(env, build(inputs), &[]);
+    (full - empty) / calls
+}
+
+/// `CALLS` ticks spread over the whole tick range
+fn spread_ticks() -> Vec<i32> {
+    (0..CALLS)
+        .map(|i| MIN_TICK + (MAX_TICK - MIN_TICK) / (CALLS - 1) * i)
+        .collect()
+}
+
+fn bench_math(env: &mut TestEnv, results: &mut Results) {
+    results.insert(
+        "get_sqrt_price_at_tick".to_string(),
+        per_call(env, spread_ticks(), |ticks| {
+            bench_instruction(clmm_bench::instruction::GetSqrtPriceAtTick { ticks })
+        }),
+    );
+
+    // prices between two ticks, `get_tick_at_sqrt_price` then has to search for the tick
+    let sqrt_prices_x64 = spread_ticks()
+        .into_iter()
+        .map(|tick| get_sqrt_price_at_tick(tick.min(MAX_TICK - 1)).unwrap() + 1)
+        .collect();
+    results.insert(
+        "get_tick_at_sqrt_price".to_string(),
+        per_call(env, sqrt_prices_x64, |sqrt_prices_x64| {
+            bench_instruction(clmm_bench::instruction::GetTickAtSqrtPrice { sqrt_prices_x64 })
+        }),
+    );
+
+    // tick arrays spread over the default bitmap, which covers 512 tick arrays on each side
+    let tick_count = 60 * i32::from(TICK_SPACING);
+    let tick_array_start_indexes = (0..CALLS)
+        .map(|i| (-512 + 1023 / (CALLS - 1) * i) * tick_count)
+        .collect();
+    results.insert(
+        "flip_tick_array_bit".to_string(),
+        per_call(env, tick_array_start_indexes, |tick_array_start_indexes| {
+            bench_instruction(clmm_bench::instruction::FlipTickArrayBit {
+                tick_spacing: TICK_SPACING,
+                tick_array_start_indexes,
+            })
+        }),
+    );
+
+    // steps ending on their target price and steps running out of amount, in both directions
+    let sqrt_price_x64 = get_sqrt_price_at_tick(0).unwrap();
+    let steps = (0..CALLS)
+        .map(|i| {
+            let zero_for_one = i % 2 == 0;
+            let target_tick = if zero_for_one { -600 } else { 600 };
+            SwapStepArgs {
+                sqrt_price_current_x64: sqrt_price_x64,
+                sqrt_price_target_x64: get_sqrt_price_at_tick(target_tick).unwrap(),
+                liquidity: LIQUIDITY,
+                amount_remaining: if i % 4 < 2 { 1_000 } else { u64::MAX / 2 },
+                fee_rate: TRADE_FEE_RATE,
+                is_base_input: i % 8 < 4,
+                zero_for_one,
+            }
+        })
+        .collect();
+    results.insert(
+        "compute_swap_step".to_string(),
+        per_call(env, steps, |steps| {
+            bench_instruction(clmm_bench::instruction::ComputeSwapStep { steps })
+        }),
+    );
+}
+
+fn open_position(env: &mut TestEnv, pool: &TestPool, tick_lower: i32, tick_upper: i32) -> u64 {
+    let nft_mint = Keypair::new();
+    let pool_state = env.pool_state(&pool.address);
+    let instruction = instructions::open_position_with_token22_nft(
+        env.user.pubkey(),
+        env.user.pubkey(),
+        nft_mint.pubkey(),
+        &pool_state,
+        pool.mint_0.user_account,
+        pool.mint_1.user_account,
+        LIQUIDITY,
+        u64::MAX,
+        u64::MAX,
+        tick_lower,
+        tick_upper,
+        false,
+        None,
+        PriceBand::default(),
+    );
+    compute_units(env, instruction, &[&nft_mint])
+}
+
+fn bench_open_position(env: &mut TestEnv, results: &mut Results) {
+    let amm_config = env.create_amm_config(0);
+    let sqrt_price_x64 = get_sqrt_price_at_tick(0).unwrap();
+
+    let pool = env.create_pool(amm_config, [MintKind::Spl, MintKind::Spl], sqrt_price_x64);
+    results.insert(
+        "open_position_with_token22_nft/new_tick_arrays".to_string(),
+        open_position(env, &pool, -600, 600),
+    );
+    results.insert(
+        "open_position_with_token22_nft/initialized_ticks".to_string(),
+        open_position(env, &pool, -600, 600),
+    );
+
+    let transfer_fee = MintKind::Token2022TransferFee {
+        basis_points: 100,
+        maximum_fee: u64::MAX,
+    };
+    let pool = env.create_pool(amm_config, [MintKind::Spl, transfer_fee], sqrt_price_x64);
+    results.insert(
+        "open_position_with_token22_nft/transfer_fee_mint".to_string(),
+        open_position(env, &pool, -600, 600),
+    );
+}
+
+/// Compute units of a `zap_in` into a wide position whose swap crosses `ticks_crossed`
+/// initialized ticks, in a fresh pool at tick 0
+fn zap_in(env: &mut TestEnv, amm_config: Pubkey, ticks_crossed: i32) -> u64 {
+    let sqrt_price_x64 = get_sqrt_price_at_tick(0).unwrap();
+    let pool = env.create_pool(amm_config, [MintKind::Spl, MintKind::Spl], sqrt_price_x64);
+    let wide = env
+        .open_position(&pool, -6000, 6000, LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    // one initialized tick every `TICK_SPACING` below the price, the upper tick is shared
+    let tick_spacing = i32::from(TICK_SPACING);
+    for i in 1..=ticks_crossed {
+        env.open_position(
+            &pool,
+            -i * tick_spacing,
+            6000,
+            LIQUIDITY,
+            u64::MAX,
+            u64::MAX,
+        )
+        .unwrap();
+    }
+
+    // swap down to halfway past the last of those ticks
+    let pool_state = env.pool_state(&pool.address);
+    let amm_config_state = env.amm_config(&amm_config);
+    let tickarray_bitmap_extension = env.tick_array_bitmap_extension(&pool.address);
+    let swap_tick_arrays = env.swap_tick_arrays(&pool, true);
+    let swap = SwapQuoter::new(
+        &pool_state,
+        &amm_config_state,
+        Some(&tickarray_bitmap_extension),
+        swap_tick_arrays
+            .iter()
+            .map(|start_index| env.tick_array(&pool.address, *start_index)),
+    )
+    .unwrap()
+    .quote(
+        u64::MAX / 2,
+        Some(get_sqrt_price_at_tick(-ticks_crossed * tick_spacing - tick_spacing / 2).unwrap()),
+        true,
+        true,
+    )
+    .unwrap();
+    assert_eq!(swap.ticks_crossed, ticks_crossed as u32);
+
+    let personal_position = env.personal_position(&wide.nft_mint);
+    let instruction = instructions::zap_in(
+        env.user.pubkey(),
+        wide.nft_account,
+        &pool_state,
+        &personal_position,
+        pool.mint_0.user_account,
+        pool.mint_1.user_account,
+        true,
+        2 * swap.amount_in,
+        swap.amount_in,
+        0,
+        PriceBand::default(),
+        &swap.tick_array_start_indexes,
+    );
+    compute_units(env, instruction, &[])
+}
+
+fn bench_zap_in(env: &mut TestEnv, results: &mut Results) {
+    let amm_config = env.create_amm_config(1);
+    for ticks_crossed in [1, 10, 100] {
+        results.insert(
+            format!("zap_in/cross_{}_ticks", ticks_crossed),
+            zap_in(env, amm_config, ticks_crossed),
+        );
+    }
+}
+
+/// `<name> <compute units>` per line
+fn read_baseline() -> Results {
+    let Ok(baseline) = fs::read_to_string(baseline_path()) else {
+        return Results::new();
+    };
+    baseline
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(name, units)| (name.to_string(), units.trim().parse().unwrap()))
+        .collect()
+}
+
+fn write_baseline(results: &Results) {
+    let baseline: String = results
+        .iter()
+        .map(|(name, units)| format!("{} {}\n", name, units))
+        .collect();
+    fs::write(baseline_path(), baseline).unwrap();
+}
+
+fn main() -> ExitCode {
+    // without its SBF build `TestEnv` runs the clmm program natively, which meters nothing
+    for path in [program_path(), bench_program_path()] {
+        assert!(
+            path.exists(),
+            "{} not found, build the programs with `anchor build` first",
+            path.display()
+        );
+    }
+    let mut env = TestEnv::new();
+    env.svm
+        .add_program_from_file(clmm_bench::ID, bench_program_path())
+        .unwrap();
+
+    let mut results = Results::new();
+    bench_math(&mut env, &mut results);
+    bench_open_position(&mut env, &mut results);
+    bench_zap_in(&mut env, &mut results);
+
+    let baseline = read_baseline();
+    let mut regressed = false;
+    println!(
+        "{:<50} {:>10} {:>10} {:>9}",
+        "", "baseline", "current", "change"
+    );
+    for (name, units) in &results {
+        let change = match baseline.get(name) {
+            Some(&baseline_units) => {
+                regressed |= *units > baseline_units;
+                format!(
+                    "{:+.2}%",
+                    (*units as f64 - baseline_units as f64) * 100.0 / baseline_units as f64
+                )
+            }
+            None => "new".to_string(),
+        };
+        let baseline_units = baseline
+            .get(name)
+            .map_or("-".to_string(), |units| units.to_string());
+        println!(
+            "{:<50} {:>10} {:>10} {:>9}",
+            name, baseline_units, units, change
+        );
+    }
+
+    if env::var_os("UPDATE_BASELINE").is_some() {
+        write_baseline(&results);
+        println!("baseline written to {}", baseline_path().display());
+    } else if regressed {
+        println!("compute units regressed, run with UPDATE_BASELINE=1 if this is intended");
+        return ExitCode::FAILURE;
+    } else if baseline.is_empty() {
+        println!(
+            "no baseline at {}, run with UPDATE_BASELINE=1 and commit it",
+            baseline_path().display()
+        );
+        return ExitCode::FAILURE;
+    } else if results != baseline {
+        println!("baseline is out of date, run with UPDATE_BASELINE=1 to update it");
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,85 @@
+//! Calls hot paths of the clmm program over a list of inputs, so `benches/compute_units.rs`
+//! can measure the compute units of one call
+//!
+//! Only ever loaded into LiteSVM, it is not meant to be deployed.
+
+use std::hint::black_box;
+
+use anchor_lang::prelude::*;
+use clmm_contract::{
+    error::ErrorCode,
+    helpers::{swap_math, tick_math},
+    state::PoolState,
+};
+
+declare_id!("BwSS9sdQmv6CKLMMmaPGpRY1UTGhAMKk2mXWSMxpSPQo");
+
+#[program]
+pub mod clmm_bench {
+    use super::*;
+
+    pub fn get_sqrt_price_at_tick(_ctx: Context<Bench>, ticks: Vec<i32>) -> Result<()> {
+        for tick in ticks {
+            black_box(tick_math::get_sqrt_price_at_tick(black_box(tick)).map_err(ErrorCode::from)?);
+        }
+        Ok(())
+    }
+
+    pub fn get_tick_at_sqrt_price(_ctx: Context<Bench>, sqrt_prices_x64: Vec<u128>) -> Result<()> {
+        for sqrt_price_x64 in sqrt_prices_x64 {
+            black_box(
+                tick_math::get_tick_at_sqrt_price(black_box(sqrt_price_x64))
+                    .map_err(ErrorCode::from)?,
+            );
+        }
+        Ok(())
+    }
+
+    /// Flips bits of the default tick array bitmap of an in-memory pool
+    pub fn flip_tick_array_bit(
+        _ctx: Context<Bench>,
+        tick_spacing: u16,
+        tick_array_start_indexes: Vec<i32>,
+    ) -> Result<()> {
+        let mut pool_state: PoolState = bytemuck::Zeroable::zeroed();
+        pool_state.tick_spacing = tick_spacing;
+        for tick_array_start_index in tick_array_start_indexes {
+            pool_state.flip_tick_array_bit(None, black_box(tick_array_start_index))?;
+        }
+        black_box(pool_state.tick_array_bitmap);
+        Ok(())
+    }
+
+    pub fn compute_swap_step(_ctx: Context<Bench>, steps: Vec<SwapStepArgs>) -> Result<()> {
+        for step in steps {
+            black_box(
+                swap_math::compute_swap_step(
+                    step.sqrt_price_current_x64,
+                    step.sqrt_price_target_x64,
+                    step.liquidity,
+                    step.amount_remaining,
+                    step.fee_rate,
+                    step.is_base_input,
+                    step.zero_for_one,
+                )
+                .map_err(ErrorCode::from)?,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Bench {}
+
+/// Arguments of `swap_math::compute_swap_step`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SwapStepArgs {
+    pub sqrt_price_current_x64: u128,
+    pub sqrt_price_target_x64: u128,
+    pub liquidity: u128,
+    pub amount_remaining: u64,
+    pub fee_rate: u32,
+    pub is_base_input: bool,
+    pub zero_for_one: bool,
+}
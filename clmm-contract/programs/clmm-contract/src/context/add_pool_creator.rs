@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    context::create_amm_config::ID as ADMIN,
+    events::PoolCreatorChanged,
+    state::{AmmConfig, PoolCreator},
+};
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct AddPoolCreator<'info> {
+    #[account(
+        mut,
+        address = ADMIN,
+    )]
+    pub owner: Signer<'info>,
+
+    pub amm_config: Account<'info, AmmConfig>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PoolCreator::INIT_SPACE,
+        seeds = [b"pool_creator", amm_config.key().as_ref(), creator.as_ref()],
+        bump,
+    )]
+    pub pool_creator: Account<'info, PoolCreator>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AddPoolCreator<'info> {
+    pub fn add_pool_creator(&mut self, creator: Pubkey, bumps: &AddPoolCreatorBumps) -> Result<()> {
+        let pool_creator = &mut self.pool_creator;
+        pool_creator.bump = bumps.pool_creator;
+        pool_creator.amm_config = self.amm_config.key();
+        pool_creator.creator = creator;
+
+        emit!(PoolCreatorChanged {
+            amm_config: pool_creator.amm_config,
+            creator,
+            allowed: true,
+        });
+
+        Ok(())
+    }
+}
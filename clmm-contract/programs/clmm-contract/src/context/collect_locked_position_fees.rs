@@ -0,0 +1,174 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{
+    error::ErrorCode,
+    events::FeesCollected,
+    helpers::{
+        settle_personal_position_fees, transfer_from_pool_vault_to_user,
+        update_protocol_position_fees,
+    },
+    state::{
+        LockedPositionState, PersonalPositionState, PoolState, ProtocolPositionState,
+        TickArrayState,
+    },
+    util::AccountLoad,
+};
+
+#[derive(Accounts)]
+pub struct CollectLockedPositionFees<'info> {
+    pub fee_key_owner: Signer<'info>,
+
+    #[account(
+        constraint = fee_key_account.mint == locked_position.fee_key_mint,
+        constraint = fee_key_account.amount == 1,
+        token::authority = fee_key_owner
+    )]
+    pub fee_key_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub locked_position: Box<Account<'info, LockedPositionState>>,
+
+    #[account(
+        mut,
+        seeds = [b"personal_position", locked_position.position_nft_mint.as_ref()],
+        bump,
+        constraint = personal_position.pool_id == pool_state.key()
+    )]
+    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"protocol_position",
+            pool_state.key().as_ref(),
+            &personal_position.tick_lower_index.to_be_bytes(),
+            &personal_position.tick_upper_index.to_be_bytes()
+        ],
+        bump,
+        constraint = protocol_position.pool_id == pool_state.key()
+    )]
+    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+
+    #[account(
+        mut,
+        constraint = tick_array_lower.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        mut,
+        constraint = tick_array_upper.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint
+    )]
+    pub recipient_token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint
+    )]
+    pub recipient_token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_program_2022: Program<'info, Token2022>,
+
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> CollectLockedPositionFees<'info> {
+    /// Pay all fees a locked position earned to the recipient accounts of the fee key holder
+    pub fn collect_locked_position_fees(&mut self) -> Result<()> {
+        let (amount_0, amount_1) = {
+            let pool_state = &mut self.pool_state.load_mut()?;
+            update_protocol_position_fees(
+                &AccountLoad::try_from(&self.tick_array_lower.to_account_info())?,
+                &AccountLoad::try_from(&self.tick_array_upper.to_account_info())?,
+                &mut self.protocol_position,
+                pool_state,
+            )?;
+            let personal_position = &mut self.personal_position;
+            settle_personal_position_fees(personal_position, &self.protocol_position)?;
+
+            let amount_0 = personal_position.token_fees_owed_0;
+            let amount_1 = personal_position.token_fees_owed_1;
+            personal_position.token_fees_owed_0 = 0;
+            personal_position.token_fees_owed_1 = 0;
+            pool_state.total_fees_claimed_token_0 = pool_state
+                .total_fees_claimed_token_0
+                .checked_add(amount_0)
+                .ok_or(ErrorCode::MaxTokenOverflow)?;
+            pool_state.total_fees_claimed_token_1 = pool_state
+                .total_fees_claimed_token_1
+                .checked_add(amount_1)
+                .ok_or(ErrorCode::MaxTokenOverflow)?;
+            (amount_0, amount_1)
+        };
+
+        for (amount, vault, account, mint) in [
+            (
+                amount_0,
+                &self.token_vault_0,
+                &self.recipient_token_account_0,
+                &self.vault_0_mint,
+            ),
+            (
+                amount_1,
+                &self.token_vault_1,
+                &self.recipient_token_account_1,
+                &self.vault_1_mint,
+            ),
+        ] {
+            transfer_from_pool_vault_to_user(
+                &self.pool_state,
+                &vault.to_account_info(),
+                &account.to_account_info(),
+                Some(mint.clone()),
+                &self.token_program.to_account_info(),
+                Some(self.token_program_2022.to_account_info()),
+                amount,
+            )?;
+        }
+
+        emit!(FeesCollected {
+            pool_state: self.pool_state.key(),
+            position_nft_mint: self.personal_position.nft_mint,
+            recipient_token_account_0: self.recipient_token_account_0.key(),
+            recipient_token_account_1: self.recipient_token_account_1.key(),
+            amount_0,
+            amount_1,
+        });
+
+        Ok(())
+    }
+}
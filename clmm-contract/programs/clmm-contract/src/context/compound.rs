@@ -0,0 +1,195 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::{
+    error::ErrorCode,
+    events::FeesCompounded,
+    helpers::{
+        add_liquidity_to_range, add_personal_position_liquidity, check_price_band,
+        get_delta_amounts_signed, get_liquidity_from_amounts, get_sqrt_price_at_tick,
+        settle_personal_position_fees, update_protocol_position_fees,
+    },
+    state::{
+        PersonalPositionState, PoolState, ProtocolPositionState, TickArrayBitmapExtension,
+        TickArrayState,
+    },
+    util::AccountLoad,
+};
+
+/// Add the owed fees of a position to its liquidity
+///
+/// Remaining accounts: the tick array bitmap extension, required when a tick array of the
+/// range is outside the default bitmap.
+#[derive(Accounts)]
+pub struct Compound<'info> {
+    /// The position holder or the operator it approved
+    pub authority: Signer<'info>,
+
+    /// Holds the position nft, its owner is the current holder of the position
+    #[account(
+        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.amount == 1
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = personal_position.pool_id == pool_state.key()
+    )]
+    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"protocol_position",
+            pool_state.key().as_ref(),
+            &personal_position.tick_lower_index.to_be_bytes(),
+            &personal_position.tick_upper_index.to_be_bytes()
+        ],
+        bump,
+        constraint = protocol_position.pool_id == pool_state.key()
+    )]
+    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+
+    #[account(
+        mut,
+        constraint = tick_array_lower.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        mut,
+        constraint = tick_array_upper.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+}
+
+impl<'info> Compound<'info> {
+    /// Settle the fees the position earned, then turn as much of its owed fees into liquidity of
+    /// its range as the pool price allows. What is left stays owed. The fees are already in the
+    /// vaults, so no tokens move. The price band and deadline guard the price the fees are added
+    /// at, as for any other liquidity change.
+    pub fn compound(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        let authority = self.authority.key();
+        let holder = self.nft_account.owner;
+        // an approval given by an earlier holder of the nft no longer counts
+        let operator = self.personal_position.operator;
+        let is_approved_operator = operator != Pubkey::default()
+            && operator == authority
+            && self.personal_position.operator_approver == holder;
+        require!(
+            holder == authority || is_approved_operator,
+            ErrorCode::NotApprovedOperator
+        );
+
+        let pool_id = self.pool_state.key();
+        let tick_lower_index = self.personal_position.tick_lower_index;
+        let tick_upper_index = self.personal_position.tick_upper_index;
+        let pool_state = &mut self.pool_state.load_mut()?;
+        check_price_band(pool_state, sqrt_price_min_x64, sqrt_price_max_x64, deadline)?;
+
+        update_protocol_position_fees(
+            &AccountLoad::try_from(&self.tick_array_lower.to_account_info())?,
+            &AccountLoad::try_from(&self.tick_array_upper.to_account_info())?,
+            &mut self.protocol_position,
+            pool_state,
+        )?;
+        settle_personal_position_fees(&mut self.personal_position, &self.protocol_position)?;
+
+        let fees_owed_0 = self.personal_position.token_fees_owed_0;
+        let fees_owed_1 = self.personal_position.token_fees_owed_1;
+        let sqrt_price_lower_x64 =
+            get_sqrt_price_at_tick(tick_lower_index).map_err(ErrorCode::from)?;
+        let sqrt_price_upper_x64 =
+            get_sqrt_price_at_tick(tick_upper_index).map_err(ErrorCode::from)?;
+        let mut liquidity = get_liquidity_from_amounts(
+            pool_state.sqrt_price_x64,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            fees_owed_0,
+            fees_owed_1,
+        )
+        .map_err(ErrorCode::from)?;
+        // the amounts of a liquidity round up and can overshoot the fees it was derived from,
+        // derive it again from the fees less the overshoot
+        let (amount_0, amount_1) = get_delta_amounts_signed(
+            pool_state.tick_current,
+            pool_state.sqrt_price_x64,
+            tick_lower_index,
+            tick_upper_index,
+            i128::try_from(liquidity).map_err(|_| ErrorCode::LiquidityOverflow)?,
+        )
+        .map_err(ErrorCode::from)?;
+        if amount_0 > fees_owed_0 || amount_1 > fees_owed_1 {
+            liquidity = get_liquidity_from_amounts(
+                pool_state.sqrt_price_x64,
+                sqrt_price_lower_x64,
+                sqrt_price_upper_x64,
+                fees_owed_0.saturating_sub(amount_0.saturating_sub(fees_owed_0)),
+                fees_owed_1.saturating_sub(amount_1.saturating_sub(fees_owed_1)),
+            )
+            .map_err(ErrorCode::from)?;
+        }
+        require!(liquidity > 0, ErrorCode::FeesTooSmallToCompound);
+
+        let use_tickarray_bitmap_extension = pool_state
+            .is_overflow_default_tickarray_bitmap(vec![tick_lower_index, tick_upper_index]);
+        let (amount_0, amount_1) = add_liquidity_to_range(
+            &AccountLoad::try_from(&self.tick_array_lower.to_account_info())?,
+            &AccountLoad::try_from(&self.tick_array_upper.to_account_info())?,
+            &mut self.protocol_position,
+            if use_tickarray_bitmap_extension {
+                let tickarray_bitmap_extension = remaining_accounts
+                    .first()
+                    .ok_or(error!(ErrorCode::MissingTickArrayBitmapExtensionAccount))?;
+                require_keys_eq!(
+                    tickarray_bitmap_extension.key(),
+                    TickArrayBitmapExtension::key(pool_id)
+                );
+                Some(tickarray_bitmap_extension)
+            } else {
+                None
+            },
+            pool_state,
+            liquidity,
+            tick_lower_index,
+            tick_upper_index,
+        )?;
+        // the owed fees back the deposit, the pool must not pay for rounding
+        require_gte!(fees_owed_0, amount_0, ErrorCode::CompoundExceedsOwedFees);
+        require_gte!(fees_owed_1, amount_1, ErrorCode::CompoundExceedsOwedFees);
+        // the compounded fees leave the owed fees as if they were collected
+        pool_state.total_fees_claimed_token_0 = pool_state
+            .total_fees_claimed_token_0
+            .checked_add(amount_0)
+            .ok_or(ErrorCode::MaxTokenOverflow)?;
+        pool_state.total_fees_claimed_token_1 = pool_state
+            .total_fees_claimed_token_1
+            .checked_add(amount_1)
+            .ok_or(ErrorCode::MaxTokenOverflow)?;
+
+        let personal_position = &mut self.personal_position;
+        personal_position.token_fees_owed_0 = fees_owed_0 - amount_0;
+        personal_position.token_fees_owed_1 = fees_owed_1 - amount_1;
+        add_personal_position_liquidity(personal_position, &self.protocol_position, liquidity)?;
+
+        emit!(FeesCompounded {
+            pool_state: pool_id,
+            position_nft_mint: personal_position.nft_mint,
+            liquidity,
+            amount_0,
+            amount_1,
+        });
+
+        Ok(())
+    }
+}
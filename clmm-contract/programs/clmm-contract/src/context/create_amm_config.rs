@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
 
-use crate::state::AmmConfig;
+use crate::{events::ConfigChanged, state::AmmConfig};
 
 pub const ID: Pubkey = pubkey!("GThUX1Atko4tqhN2NaiTazWSeFWMuiUvfFnyJyUghFMJ");
 
@@ -44,6 +44,18 @@ impl<'info> CreateAmmConfig<'info> {
         amm_config.protocol_fee_rate = protocol_fee_rate;
         amm_config.tick_spacing = tick_spacing;
         amm_config.fund_owner = self.owner.key();
+        amm_config.version = AmmConfig::VERSION;
+
+        emit!(ConfigChanged {
+            amm_config: amm_config.key(),
+            index,
+            owner: amm_config.owner,
+            protocol_fee_rate,
+            trade_fee_rate,
+            fund_fee_rate,
+            tick_spacing,
+            fund_owner: amm_config.fund_owner,
+        });
 
         Ok(())
     }
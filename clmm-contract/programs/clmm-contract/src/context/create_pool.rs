@@ -1,15 +1,23 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
 use anchor_spl::{
     token::Token,
-    token_2022::spl_token_2022::extension::{
-        BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
     },
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 use crate::{
-    helpers::{create_token_vault_account, tick::get_tick_at_sqrt_price, token::get_token_vault},
-    state::{AmmConfig, PoolState, SupportMint, TickArrayBitmapExtension},
+    error::ErrorCode,
+    events::PoolCreated,
+    helpers::get_tick_at_sqrt_price,
+    state::{
+        AmmConfig, PoolCreator, PoolState, SupportMint, TickArrayBitmapExtension, POOL_CREATOR_SEED,
+    },
 };
 
 #[derive(Accounts)]
@@ -19,6 +27,14 @@ pub struct CreatePool<'info> {
 
     pub amm_config: Box<Account<'info, AmmConfig>>,
 
+    /// CHECK: the receiver set in the amm config, required when the config charges a create
+    /// pool fee and left out otherwise
+    #[account(
+        mut,
+        address = amm_config.create_pool_fee_receiver @ ErrorCode::InvalidCreatePoolFeeReceiver
+    )]
+    pub create_pool_fee_receiver: Option<UncheckedAccount<'info>>,
+
     #[account(
         constraint = token_mint_0.key() < token_mint_1.key(),
         mint::token_program = token_program_0
@@ -98,10 +114,11 @@ pub fn support_mint_associated_is_initialized(
     remaining_accounts: &[AccountInfo], // it may include SupportMint account
     token_mint: &InterfaceAccount<Mint>,
 ) -> Result<bool> {
-    if remaining_accounts.len() == 0 {
+    if remaining_accounts.is_empty() {
         return Ok(false);
     }
-    let seeds = &[b"support_mint", token_mint.key().as_ref()];
+    let token_mint_key = token_mint.key();
+    let seeds = &[b"support_mint", token_mint_key.as_ref()];
     let (if_initialized_mint_account, _bump) = Pubkey::find_program_address(seeds, &crate::id());
     let mut is_mint_initialized = false;
 
@@ -119,7 +136,33 @@ pub fn support_mint_associated_is_initialized(
             break;
         }
     }
-    return Ok(is_mint_initialized);
+    Ok(is_mint_initialized)
+}
+
+/// Whether `remaining_accounts` include the `PoolCreator` account allowing `creator` to create
+/// pools with `amm_config`
+pub fn pool_creator_is_allowed(
+    remaining_accounts: &[AccountInfo],
+    amm_config: &Pubkey,
+    creator: &Pubkey,
+) -> Result<bool> {
+    let seeds = &[
+        POOL_CREATOR_SEED.as_bytes(),
+        amm_config.as_ref(),
+        creator.as_ref(),
+    ];
+    let (pool_creator_account, _bump) = Pubkey::find_program_address(seeds, &crate::id());
+
+    for account_info in remaining_accounts.iter() {
+        if *account_info.owner != crate::id() || account_info.key() != pool_creator_account {
+            continue;
+        }
+        let pool_creator = PoolCreator::try_deserialize(&mut account_info.data.borrow().as_ref())?;
+        if pool_creator.amm_config == *amm_config && pool_creator.creator == *creator {
+            return Ok(true);
+        }
+    }
+    Ok(false)
 }
 
 pub fn is_mint_supported(
@@ -128,7 +171,7 @@ pub fn is_mint_supported(
 ) -> Result<bool> {
     let mint_info = mint_account.to_account_info();
     // legacy token account is supported at first place, should check for token2022
-    if mint_info.owner == Token::id() {
+    if *mint_info.owner == Token::id() {
         return Ok(true);
     }
 
@@ -156,23 +199,54 @@ pub fn is_mint_supported(
 impl<'info> CreatePool<'info> {
     pub fn create_pool(
         &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
         sqrt_price_x64: u128,
         open_time: u64,
-        bumps: CreatePoolBumps,
+        bumps: &CreatePoolBumps,
     ) -> Result<()> {
+        // configs in allowlist mode only accept vetted creators
+        if self.amm_config.creator_allowlist {
+            require!(
+                pool_creator_is_allowed(
+                    remaining_accounts,
+                    &self.amm_config.key(),
+                    &self.pool_creator.key(),
+                )?,
+                ErrorCode::PoolCreatorNotAllowed
+            );
+        }
+
+        // spam protection, paid in lamports to the protocol
+        if self.amm_config.create_pool_fee > 0 {
+            let create_pool_fee_receiver = self
+                .create_pool_fee_receiver
+                .as_ref()
+                .ok_or(ErrorCode::InvalidCreatePoolFeeReceiver)?;
+            transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.pool_creator.to_account_info(),
+                        to: create_pool_fee_receiver.to_account_info(),
+                    },
+                ),
+                self.amm_config.create_pool_fee,
+            )?;
+        }
+
         // check if mints are initialized
         // if its not initialized, check if it is supported
         let mint0_is_initialized =
-            support_mint_associated_is_initialized(&self.remaining_accounts, &self.token_mint_0)?;
+            support_mint_associated_is_initialized(remaining_accounts, &self.token_mint_0)?;
 
         let mint1_is_initialized =
-            support_mint_associated_is_initialized(&self.remaining_accounts, &self.token_mint_1)?;
+            support_mint_associated_is_initialized(remaining_accounts, &self.token_mint_1)?;
 
-        if (is_mint_supported(&self.token_mint_0, mint0_is_initialized)?
-            && is_mint_supported(&self.token_mint_1, mint1_is_initialized)?)
-        {
-            return Err(ErrorCode::MintNotSupported);
-        }
+        require!(
+            is_mint_supported(&self.token_mint_0, mint0_is_initialized)?
+                && is_mint_supported(&self.token_mint_1, mint1_is_initialized)?,
+            ErrorCode::MintNotSupported
+        );
 
         let block_timestamp = Clock::get()?.unix_timestamp as u64;
         require_gt!(block_timestamp, open_time);
@@ -181,39 +255,8 @@ impl<'info> CreatePool<'info> {
         // load_int if first time initilized and mut ref
         // load_mut if already initialized
         let mut pool_state = self.pool_state.load_init()?;
-        let tick = get_tick_at_sqrt_price(sqrt_price_x64)?;
-
-        msg!("tick: {} price: {}", tick, sqrt_price_x64);
-
-        // init token vault accounts
-        create_token_vault_account(
-            &self.pool_creator,
-            &self.pool_state.to_account_info(),
-            &self.token_vault_0.to_account_info(),
-            &self.token_mint_0,
-            &self.system_program,
-            &self.token_program_0,
-            &[
-                b"vault",
-                self.pool_state.key().as_ref(),
-                self.token_mint_0.key().as_ref(),
-                &[bumps.token_vault_0][..],
-            ],
-        )?;
-        create_token_vault_account(
-            &self.pool_creator,
-            &self.pool_state.to_account_info(),
-            &self.token_vault_1.to_account_info(),
-            &self.token_mint_1,
-            &self.system_program,
-            &self.token_program_1,
-            &[
-                b"vault",
-                self.pool_state.key().as_ref(),
-                self.token_mint_1.key().as_ref(),
-                &[bumps.token_vault_1][..],
-            ],
-        )?;
+        let tick = get_tick_at_sqrt_price(sqrt_price_x64).map_err(ErrorCode::from)?;
+
         pool_state.amm_config = self.amm_config.key();
         pool_state.token_mint_0 = self.token_mint_0.key();
         pool_state.token_mint_1 = self.token_mint_1.key();
@@ -244,24 +287,27 @@ impl<'info> CreatePool<'info> {
         pool_state.total_fees_claimed_token_1 = 0;
         pool_state.fund_fees_token_0 = 0;
         pool_state.fund_fees_token_1 = 0;
+        pool_state.version = PoolState::VERSION;
+        pool_state.padding = [0; 32];
 
-        &self
-            .tick_array_bitmap_extension
+        self.tick_array_bitmap_extension
             .load_init()?
             .initialize(pool_id);
 
-        msg!("pool_state: {:?}", pool_id);
-        msg!(
-            "tick_array_bitmap_extension: {:?}",
-            &self.tick_array_bitmap_extension.key()
-        );
+        emit!(PoolCreated {
+            pool_state: pool_id,
+            pool_creator: self.pool_creator.key(),
+            amm_config: self.amm_config.key(),
+            token_mint_0: self.token_mint_0.key(),
+            token_mint_1: self.token_mint_1.key(),
+            token_vault_0: self.token_vault_0.key(),
+            token_vault_1: self.token_vault_1.key(),
+            tick_spacing: self.amm_config.tick_spacing,
+            sqrt_price_x64,
+            tick,
+            open_time,
+        });
 
         Ok(())
     }
 }
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Mint is not supported")]
-    MintNotSupported,
-}
@@ -0,0 +1,244 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{
+    error::ErrorCode,
+    events::LiquidityDecreased,
+    helpers::{
+        check_price_band, get_transfer_fee, remove_liquidity_from_range,
+        remove_personal_position_liquidity, transfer_from_pool_vault_to_user,
+    },
+    state::{
+        PersonalPositionState, PoolState, ProtocolPositionState, TickArrayBitmapExtension,
+        TickArrayState,
+    },
+    util::AccountLoad,
+};
+
+/// Remove liquidity from a position
+///
+/// Remaining accounts: the tick array bitmap extension when a tick array of the position is
+/// outside of the pool's default bitmap.
+#[derive(Accounts)]
+pub struct DecreaseLiquidity<'info> {
+    pub nft_owner: Signer<'info>,
+
+    #[account(
+        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = personal_position.pool_id == pool_state.key()
+    )]
+    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"protocol_position",
+            pool_state.key().as_ref(),
+            &personal_position.tick_lower_index.to_be_bytes(),
+            &personal_position.tick_upper_index.to_be_bytes()
+        ],
+        bump,
+        constraint = protocol_position.pool_id == pool_state.key()
+    )]
+    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+
+    #[account(
+        mut,
+        constraint = tick_array_lower.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        mut,
+        constraint = tick_array_upper.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint
+    )]
+    pub recipient_token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint
+    )]
+    pub recipient_token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_program_2022: Program<'info, Token2022>,
+
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> DecreaseLiquidity<'info> {
+    /// Remove `liquidity` from the position and pay it out along with all owed fees. Fails when
+    /// the removed liquidity is worth less than `amount_0_min`/`amount_1_min` after transfer
+    /// fees, or when the pool price is outside the band, see [`check_price_band`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn decrease_liquidity_v2(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        liquidity: u128,
+        amount_0_min: u64,
+        amount_1_min: u64,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        check_price_band(
+            &*self.pool_state.load()?,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )?;
+
+        let tick_lower = self.personal_position.tick_lower_index;
+        let tick_upper = self.personal_position.tick_upper_index;
+        let (decrease_amount_0, decrease_amount_1, fee_amount_0, fee_amount_1) = {
+            let pool_state = &mut self.pool_state.load_mut()?;
+            let tick_array_bitmap_extension =
+                if pool_state.is_overflow_default_tickarray_bitmap(vec![tick_lower, tick_upper]) {
+                    let tick_array_bitmap_extension = remaining_accounts
+                        .first()
+                        .ok_or(ErrorCode::MissingTickArrayBitmapExtensionAccount)?;
+                    require_keys_eq!(
+                        tick_array_bitmap_extension.key(),
+                        TickArrayBitmapExtension::key(self.pool_state.key())
+                    );
+                    Some(tick_array_bitmap_extension)
+                } else {
+                    None
+                };
+
+            let (decrease_amount_0, decrease_amount_1) = remove_liquidity_from_range(
+                &AccountLoad::try_from(&self.tick_array_lower.to_account_info())?,
+                &AccountLoad::try_from(&self.tick_array_upper.to_account_info())?,
+                &mut self.protocol_position,
+                tick_array_bitmap_extension,
+                pool_state,
+                liquidity,
+                tick_lower,
+                tick_upper,
+            )?;
+            let personal_position = &mut self.personal_position;
+            remove_personal_position_liquidity(
+                personal_position,
+                &self.protocol_position,
+                liquidity,
+            )?;
+
+            // the owed fees are collected along with the liquidity
+            let fee_amount_0 = personal_position.token_fees_owed_0;
+            let fee_amount_1 = personal_position.token_fees_owed_1;
+            personal_position.token_fees_owed_0 = 0;
+            personal_position.token_fees_owed_1 = 0;
+            pool_state.total_fees_claimed_token_0 = pool_state
+                .total_fees_claimed_token_0
+                .checked_add(fee_amount_0)
+                .ok_or(ErrorCode::MaxTokenOverflow)?;
+            pool_state.total_fees_claimed_token_1 = pool_state
+                .total_fees_claimed_token_1
+                .checked_add(fee_amount_1)
+                .ok_or(ErrorCode::MaxTokenOverflow)?;
+            (
+                decrease_amount_0,
+                decrease_amount_1,
+                fee_amount_0,
+                fee_amount_1,
+            )
+        };
+
+        let mut transfer_fees = [0; 2];
+        for (i, (decrease_amount, fee_amount, amount_min, vault, account, mint)) in [
+            (
+                decrease_amount_0,
+                fee_amount_0,
+                amount_0_min,
+                &self.token_vault_0,
+                &self.recipient_token_account_0,
+                &self.vault_0_mint,
+            ),
+            (
+                decrease_amount_1,
+                fee_amount_1,
+                amount_1_min,
+                &self.token_vault_1,
+                &self.recipient_token_account_1,
+                &self.vault_1_mint,
+            ),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let amount = decrease_amount
+                .checked_add(fee_amount)
+                .ok_or(ErrorCode::MaxTokenOverflow)?;
+            transfer_fees[i] = get_transfer_fee(mint, amount)?;
+            // the whole transfer fee is charged to the liquidity, the fees cannot make up for it
+            require_gte!(
+                decrease_amount.saturating_sub(transfer_fees[i]),
+                amount_min,
+                ErrorCode::PriceSlippageCheck
+            );
+            transfer_from_pool_vault_to_user(
+                &self.pool_state,
+                &vault.to_account_info(),
+                &account.to_account_info(),
+                Some(mint.clone()),
+                &self.token_program.to_account_info(),
+                Some(self.token_program_2022.to_account_info()),
+                amount,
+            )?;
+        }
+
+        emit!(LiquidityDecreased {
+            pool_state: self.pool_state.key(),
+            position_nft_mint: self.personal_position.nft_mint,
+            liquidity,
+            decrease_amount_0,
+            decrease_amount_1,
+            fee_amount_0,
+            fee_amount_1,
+            transfer_fee_0: transfer_fees[0],
+            transfer_fee_1: transfer_fees[1],
+        });
+
+        Ok(())
+    }
+}
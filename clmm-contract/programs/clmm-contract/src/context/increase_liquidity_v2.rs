@@ -1,8 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{token::Token, token_2022::Token2022, token_interface::TokenAccount};
+use anchor_spl::{
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
 
 use crate::{
-    helpers::increase_liquidity,
+    helpers::{check_price_band, increase_liquidity},
     state::{PersonalPositionState, PoolState, ProtocolPositionState, TickArrayState},
 };
 
@@ -90,29 +94,40 @@ pub struct IncreaseLiquidity<'info> {
 }
 
 impl<'info> IncreaseLiquidity<'info> {
-    pub fn increase_liquidity_v2<'info>(
+    #[allow(clippy::too_many_arguments)]
+    pub fn increase_liquidity_v2(
         &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
         liquidity: u128,
         amount_0_max: u64,
         amount_1_max: u64,
         base_flag: Option<bool>,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
     ) -> Result<()> {
+        check_price_band(
+            &*self.pool_state.load()?,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )?;
         increase_liquidity(
-            &ctx.accounts.nft_owner,
-            &ctx.accounts.pool_state,
-            &mut ctx.accounts.protocol_position,
-            &mut ctx.accounts.personal_position,
-            &ctx.accounts.tick_array_lower,
-            &ctx.accounts.tick_array_upper,
-            &ctx.accounts.token_account_0.to_account_info(),
-            &ctx.accounts.token_account_1.to_account_info(),
-            &ctx.accounts.token_vault_0.to_account_info(),
-            &ctx.accounts.token_vault_1.to_account_info(),
-            &ctx.accounts.token_program,
-            Some(&ctx.accounts.token_program_2022),
-            Some(ctx.accounts.vault_0_mint.clone()),
-            Some(ctx.accounts.vault_1_mint.clone()),
-            &ctx.remaining_accounts,
+            &self.nft_owner,
+            &self.pool_state,
+            &mut self.protocol_position,
+            &mut self.personal_position,
+            &self.tick_array_lower,
+            &self.tick_array_upper,
+            &self.token_account_0.to_account_info(),
+            &self.token_account_1.to_account_info(),
+            &self.token_vault_0.to_account_info(),
+            &self.token_vault_1.to_account_info(),
+            &self.token_program,
+            Some(&self.token_program_2022),
+            Some(self.vault_0_mint.clone()),
+            Some(self.vault_1_mint.clone()),
+            remaining_accounts,
             liquidity,
             amount_0_max,
             amount_1_max,
@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_2022::{self, spl_token_2022::instruction::AuthorityType, Token2022},
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{
+    error::ErrorCode,
+    events::{PositionLocked, PositionOperatorChanged},
+    state::{LockedPositionState, PersonalPositionState},
+};
+
+#[derive(Accounts)]
+pub struct LockPosition<'info> {
+    /// Holds the position nft and pays for the escrow and the fee key
+    #[account(mut)]
+    pub nft_owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+
+    #[account(address = personal_position.nft_mint)]
+    pub position_nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init,
+        seeds = [b"locked_position", position_nft_mint.key().as_ref()],
+        bump,
+        payer = nft_owner,
+        space = 8 + LockedPositionState::INIT_SPACE
+    )]
+    pub locked_position: Box<Account<'info, LockedPositionState>>,
+
+    /// Holds the position nft while it is locked. Anyone can create the associated token
+    /// account ahead of the lock, so an existing one is taken as is.
+    #[account(
+        init_if_needed,
+        payer = nft_owner,
+        associated_token::mint = position_nft_mint,
+        associated_token::authority = locked_position,
+        associated_token::token_program = token_program_2022
+    )]
+    pub escrow_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = nft_owner,
+        mint::decimals = 0,
+        mint::authority = locked_position,
+        mint::token_program = token_program_2022
+    )]
+    pub fee_key_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = nft_owner,
+        associated_token::mint = fee_key_mint,
+        associated_token::authority = nft_owner,
+        associated_token::token_program = token_program_2022
+    )]
+    pub fee_key_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program_2022: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> LockPosition<'info> {
+    /// Move the position nft into escrow and mint the fee key nft to its owner. Without the
+    /// position nft nobody can change the liquidity of the position until the fee key holder
+    /// unlocks it at `unlock_time`, or ever when `unlock_time` is `None`.
+    pub fn lock_position(
+        &mut self,
+        unlock_time: Option<i64>,
+        bumps: &LockPositionBumps,
+    ) -> Result<()> {
+        if let Some(unlock_time) = unlock_time {
+            require_gt!(
+                unlock_time,
+                Clock::get()?.unix_timestamp,
+                ErrorCode::InvalidUnlockTime
+            );
+        }
+
+        let locked_position = &mut self.locked_position;
+        locked_position.bump = [bumps.locked_position];
+        locked_position.position_nft_mint = self.position_nft_mint.key();
+        locked_position.pool_id = self.personal_position.pool_id;
+        locked_position.fee_key_mint = self.fee_key_mint.key();
+        locked_position.unlock_time = unlock_time;
+
+        // the approval of the holder does not carry over to the locked position
+        let personal_position = &mut self.personal_position;
+        if personal_position.operator != Pubkey::default() {
+            personal_position.operator = Pubkey::default();
+            personal_position.operator_approver = Pubkey::default();
+            emit!(PositionOperatorChanged {
+                position_nft_mint: personal_position.nft_mint,
+                operator: Pubkey::default(),
+            });
+        }
+
+        token_2022::transfer_checked(
+            CpiContext::new(
+                self.token_program_2022.to_account_info(),
+                token_2022::TransferChecked {
+                    from: self.nft_account.to_account_info(),
+                    mint: self.position_nft_mint.to_account_info(),
+                    to: self.escrow_nft_account.to_account_info(),
+                    authority: self.nft_owner.to_account_info(),
+                },
+            ),
+            1,
+            0,
+        )?;
+
+        // the fee key is an nft as well, nothing can be minted after it
+        let seeds = self.locked_position.seeds();
+        token_2022::mint_to(
+            CpiContext::new_with_signer(
+                self.token_program_2022.to_account_info(),
+                token_2022::MintTo {
+                    mint: self.fee_key_mint.to_account_info(),
+                    to: self.fee_key_account.to_account_info(),
+                    authority: self.locked_position.to_account_info(),
+                },
+                &[&seeds],
+            ),
+            1,
+        )?;
+        token_2022::set_authority(
+            CpiContext::new_with_signer(
+                self.token_program_2022.to_account_info(),
+                token_2022::SetAuthority {
+                    current_authority: self.locked_position.to_account_info(),
+                    account_or_mint: self.fee_key_mint.to_account_info(),
+                },
+                &[&seeds],
+            ),
+            AuthorityType::MintTokens,
+            None,
+        )?;
+
+        emit!(PositionLocked {
+            pool_state: self.locked_position.pool_id,
+            position_nft_mint: self.locked_position.position_nft_mint,
+            fee_key_mint: self.locked_position.fee_key_mint,
+            nft_owner: self.nft_owner.key(),
+            unlock_time,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    context::create_amm_config::ID as ADMIN,
+    state::{migrate_account, AmmConfig},
+};
+
+#[derive(Accounts)]
+pub struct MigrateAmmConfig<'info> {
+    #[account(
+        mut,
+        address = ADMIN,
+    )]
+    pub owner: Signer<'info>,
+
+    /// CHECK: may still be on an older layout, checked by `migrate_account`
+    #[account(
+        mut,
+        owner = crate::ID,
+    )]
+    pub amm_config: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateAmmConfig<'info> {
+    pub fn migrate_amm_config(&mut self) -> Result<()> {
+        migrate_account::<AmmConfig>(
+            &self.amm_config.to_account_info(),
+            &self.owner.to_account_info(),
+            &self.system_program.to_account_info(),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    context::create_amm_config::ID as ADMIN,
+    state::{migrate_account, PersonalPositionState},
+};
+
+#[derive(Accounts)]
+pub struct MigratePersonalPosition<'info> {
+    #[account(
+        mut,
+        address = ADMIN,
+    )]
+    pub owner: Signer<'info>,
+
+    /// CHECK: may still be on an older layout, checked by `migrate_account`
+    #[account(
+        mut,
+        owner = crate::ID,
+    )]
+    pub personal_position: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigratePersonalPosition<'info> {
+    pub fn migrate_personal_position(&mut self) -> Result<()> {
+        migrate_account::<PersonalPositionState>(
+            &self.personal_position.to_account_info(),
+            &self.owner.to_account_info(),
+            &self.system_program.to_account_info(),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    context::create_amm_config::ID as ADMIN,
+    state::{migrate_account, PoolState},
+};
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    #[account(
+        mut,
+        address = ADMIN,
+    )]
+    pub owner: Signer<'info>,
+
+    /// CHECK: may still be on an older layout, checked by `migrate_account`
+    #[account(
+        mut,
+        owner = crate::ID,
+    )]
+    pub pool_state: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigratePool<'info> {
+    pub fn migrate_pool(&mut self) -> Result<()> {
+        migrate_account::<PoolState>(
+            &self.pool_state.to_account_info(),
+            &self.owner.to_account_info(),
+            &self.system_program.to_account_info(),
+        )?;
+        Ok(())
+    }
+}
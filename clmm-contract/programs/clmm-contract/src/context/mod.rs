@@ -9,3 +9,54 @@ pub use open_position_with_token22_nft::*;
 
 pub mod increase_liquidity_v2;
 pub use increase_liquidity_v2::*;
+
+pub mod decrease_liquidity_v2;
+pub use decrease_liquidity_v2::*;
+
+pub mod migrate_amm_config;
+pub use migrate_amm_config::*;
+
+pub mod migrate_pool;
+pub use migrate_pool::*;
+
+pub mod migrate_protocol_position;
+pub use migrate_protocol_position::*;
+
+pub mod migrate_personal_position;
+pub use migrate_personal_position::*;
+
+pub mod set_creator_allowlist;
+pub use set_creator_allowlist::*;
+
+pub mod add_pool_creator;
+pub use add_pool_creator::*;
+
+pub mod remove_pool_creator;
+pub use remove_pool_creator::*;
+
+pub mod set_create_pool_fee;
+pub use set_create_pool_fee::*;
+
+pub mod zap_in;
+pub use zap_in::*;
+
+pub mod zap_in_open_position;
+pub use zap_in_open_position::*;
+
+pub mod reposition;
+pub use reposition::*;
+
+pub mod set_position_operator;
+pub use set_position_operator::*;
+
+pub mod compound;
+pub use compound::*;
+
+pub mod lock_position;
+pub use lock_position::*;
+
+pub mod unlock_position;
+pub use unlock_position::*;
+
+pub mod collect_locked_position_fees;
+pub use collect_locked_position_fees::*;
@@ -1,13 +1,26 @@
-use std::ops::DerefMut;
-
-use crate::{helpers::{add_liquidity, check_tick_array_start_index, mint_nft_and_remove_mint_authority}, state::{personal_position, PersonalPositionState, PoolState, ProtocolPositionState, TickArrayState}};
-use anchor_lang::{prelude::*, system_program::{create_account, CreateAccount}};
+use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::{create, AssociatedToken, Create}, token::initialize_mint2, token_2022::{ spl_token_2022::{self, extension::{metadata_pointer, ExtensionType}, instruction::initialize_mint_close_authority}, Token2022}, token_interface::{Mint, TokenAccount}
+    associated_token::AssociatedToken,
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{
+    error::ErrorCode,
+    events::PositionOpened,
+    helpers::{
+        add_liquidity, check_price_band, create_position_nft, init_personal_position,
+        mint_nft_and_remove_mint_authority, prepare_range,
+    },
+    state::{PersonalPositionState, PoolState, ProtocolPositionState, TickArrayBitmapExtension},
 };
 
 #[derive(Accounts)]
 #[instruction(
+    liquidity: u128,
+    amount_0_max: u64,
+    amount_1_max: u64,
     tick_lower_index: i32,
     tick_upper_index: i32,
     tick_array_lower_start_index: i32,
@@ -17,12 +30,13 @@ pub struct OpenPositionWithToken22Nft<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
+    /// CHECK: receives the position nft
     pub position_nft_owner: UncheckedAccount<'info>,
 
     #[account(mut)]
     pub position_nft_mint: Signer<'info>,
 
-    /// ATA address where position NFT will be minted, initialize in contract
+    /// CHECK: ATA address where position NFT will be minted, initialize in contract
     #[account(mut)]
     pub position_nft_account: UncheckedAccount<'info>,
 
@@ -52,6 +66,7 @@ pub struct OpenPositionWithToken22Nft<'info> {
     )]
     pub personal_position: Box<Account<'info, PersonalPositionState>>,
 
+    /// CHECK: created in the instruction if needed
     #[account(
         mut,
         seeds = [
@@ -63,6 +78,7 @@ pub struct OpenPositionWithToken22Nft<'info> {
     )]
     pub tick_array_lower: UncheckedAccount<'info>,
 
+    /// CHECK: created in the instruction if needed
     #[account(
         mut,
         seeds = [
@@ -74,6 +90,18 @@ pub struct OpenPositionWithToken22Nft<'info> {
     )]
     pub tick_array_upper: UncheckedAccount<'info>,
 
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint
+    )]
+    pub token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint
+    )]
+    pub token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
     #[account(
         mut,
         constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
@@ -89,7 +117,6 @@ pub struct OpenPositionWithToken22Nft<'info> {
     pub token_program: Program<'info, Token>,
 
     pub rent: Sysvar<'info, Rent>,
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>, // NFT ATA
     pub token_program_2022: Program<'info, Token2022>,             // for token22 mint
@@ -105,263 +132,12 @@ pub struct OpenPositionWithToken22Nft<'info> {
     pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
 }
 
-pub fn create_position_nft_mint_with_extensions<'info>(
-    payer: &Signer<'info>,
-    position_nft_mint: &AccountInfo<'info>,
-    mint_authority: &AccountInfo<'info>,
-    mint_close_authority: &AccountInfo<'info>,
-    system_program: &Program<'info, System>,
-    token_2022_program: &Program<'info, Token2022>,
-    with_metadata: bool,
-) -> Result<()> {
-    let extensions = if with_metadata {
-        [
-            ExtensionType::MintCloseAuthority,
-            ExtensionType::MetadataPointer,
-        ]
-        .to_vec()
-    } else {
-        [ExtensionType::MintCloseAuthority].to_vec()
-    };
-    let space = ExtensionType::try_calculate_account_len<spl_token_2022::state::Mint>(&extensions)?;
-    let lamports = Rent::get()?.minimum_balance(space);
-
-    let create_account_cpi_context = CpiContext::new(
-        system_program.to_account_info(),
-        CreateAccount{
-            from: payer.to_account_info(),
-            to: position_nft_mint.to_account_info()
-        }
-    );
-    // create MINT ACCOUNT
-    create_account(create_account_cpi_context, lamports, space.try_into().unwrap()?, token_2022_program.key )?;
-
-    // Initialize token extensions
-    for e in extensions {
-        match e {
-            ExtensionType::MetadataPointer => {
-                let ix = metadata_pointer::instruction::initialize(
-                    token_2022_program.key,
-                    position_nft_mint.key,
-                    None,
-                    Some(position_nft_mint.key())
-                )?;
-                invoke(
-                    &ix,
-                    &[   
-                        token_2022_program.to_account_info(),
-                        position_nft_mint.to_account_info()
-                    ]
-                )?;
-
-            }
-            ExtensionType::MintCloseAuthority => {
-                let ix = initialize_mint_close_authority(
-                    token_2022_program.key,
-                    position_nft_mint.key,
-                    Some(mint_close_authority.key)
-                )?;
-                invoke(
-                    &ix,
-                    &[
-                        token_2022_program.to_account_info(),
-                        position_nft_mint.to_account_info()
-                    ]
-                )?;
-            }
-            _ => {
-                return err!(ErrorCode::InvalidExtensionType);
-            }
-        }
-    }
-
-    initialize_mint2(
-        CpiContext::new(
-            token_2022_program.to_account_info(),
-            InitializeMint2 {
-                mint: position_nft_mint.to_account_info()
-            }
-        ),
-        0,
-        &mint_authority.key,
-        None
-    )?;
-
-
-}
-
-pub fn open_position<'b, 'info>(
-   payer: &'b Signer<'info>,
-   position_nft_owner: &'b UncheckedAccount<'info>,
-   position_nft_mint: &'b AccountInfo<'info>,
-   position_nft_account: &'b AccountInfo<'info>,
-   metadata_account: Option<&'b UncheckedAccount<'info>>,
-   pool_state_loader: &'b AccountLoader<'info, PoolState>,
-   tick_array_lower_loader: &'b UncheckedAccount<'info>,
-   tick_array_upper_loader: &'b UncheckedAccount<'info>,
-   protocol_position: &'b mut Box<Account<'info, ProtocolPositionState>>,
-   personal_position: &'b mut Box<Account<'info, PersonalPositionState>>,
-   token_account_0: &'b AccountInfo<'info>,
-   token_account_1: &'b AccountInfo<'info>,
-   token_vault_0: &'b AccountInfo<'info>,
-   token_vault_1: &'b AccountInfo<'info>,
-   rent: &'b Sysvar<'info, Rent>,
-   system_program: &'b Program<'info, System>,
-   token_program: &'b Program<'info, Token>,
-   _associated_token_program: &'b Program<'info, AssociatedToken>,
-   metadata_program: Option<&'b Program<'info, Metadata>>,
-   token_program_2022: Option<&'b Program<'info, Token2022>>,
-   vault_0_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
-   vault_1_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
-   remaining_accounts: &'b [AccountInfo<'info>],
-   protocol_position_bump: u8,
-   personal_position_bump: u8,
-   liquidity: u128,
-   amount_0_max: u64,
-   amount_1_max: u64,
-   tick_lower_index: i32,
-   tick_upper_index: i32,
-   tick_array_lower_start_index: i32,
-   tick_array_upper_start_index: i32,
-   with_metadata: bool,
-   base_flag: Option<bool>,
-   use_metadata_extension: bool
-) -> Result<()> {
-    let mut liquidity = liquidity;
-
-    {
-        let pool_state = &mut pool_state_loader.load_mut()?;
-        // check ticks order
-        require!(tick_lower_index < tick_upper_index, ErrorCode::InvalidTickOrder);
-        // check tick array start index
-        check_tick_array_start_index(
-            tick_array_lower_start_index,
-            tick_lower_index,
-            pool_state.tick_spacing
-        )?;
-        check_tick_array_start_index(
-            tick_array_upper_start_index,
-            tick_upper_index,
-            pool_state.tick_spacing
-        )?;
-
-        let tick_array_lower_loader = TickArrayState::get_or_create_tick_array(
-            payer.to_account_info(),
-            tick_array_lower_loader.to_account_info(),
-            tick_array_lower_start_index,
-            pool_state.tick_spacing,
-            &pool_state_loader,
-            system_program.to_account_info(),
-        )?;
-
-        let tick_array_upper_loader = 
-        if tick_array_lower_start_index == tick_array_upper_start_index {
-            AccountLoader::<TickArrayState>::try_from(&tick_array_upper_loader.to_account_info())?;
-        } else {
-            TickArrayState::get_or_create_tick_array(
-                payer.to_account_info(),
-                tick_array_upper_loader.to_account_info(),
-                tick_array_upper_start_index,
-                pool_state.tick_spacing,
-                &pool_state_loader,
-                system_program.to_account_info(),
-            )?;
-        };
-
-        // check if protocol position is initialized , protocol initialize also add ticks to tick array
-        let protocol_position = protocol_position.deref_mut();
-        if protocol_position.pool_id == Pubkey::default() {
-            protocol_position.bump = protocol_position_bump;
-            protocol_position.pool_id = pool_state_loader.key();
-            protocol_position.tick_lower_index = tick_lower_index;
-            protocol_position.tick_upper_index = tick_upper_index;
-            
-            tick_array_lower_loader
-                .load_mut()?
-                .get_tick_state_mut(tick_lower_index, pool_state.tick_spacing)?
-                .tick = tick_lower_index;
-
-            tick_array_upper_loader
-                .load_mut()?
-                .get_tick_state_mut(tick_upper_index, pool_state.tick_spacing)?
-                .tick = tick_upper_index;
-        }
-
-        let use_tickarray_bitmap_extension = 
-            pool_state.is_overflow_default_tickarray_bitmap(
-                vec![tick_array_lower_start_index, tick_array_upper_start_index]
-            );
-
-        // Checkpoint: tick_array is loaded, protocol position is initialized, lets now add liquidity
-        let (amount_0, amount_1, amount_0_transfer_fee, amount_1_transfer_fee) = add_liquidity(
-            payer,
-            token_account_0,
-            token_account_1,
-            token_vault_0,
-            token_vault_1,
-            &tick_array_lower_loader,
-            &tick_array_upper_loader,
-            protocol_position,
-            token_program_2022,
-            token_program,
-            vault_0_mint,
-            vault_1_mint,
-            if use_tickarray_bitmap_extension {
-                require_keys_eq!(
-                    remaining_accounts[0].key(),
-                    TickArrayBitmapExtension::key(pool_state_loader.key())
-                );
-                Some(&remaining_accounts[0])
-            } else {
-                None
-            },
-            pool_state,
-            &mut liquidity,
-            amount_0_max,
-            amount_1_max,
-            tick_lower_index,
-            tick_upper_index,
-            base_flag,
-        )?;
-        
-        // initialize personal position
-        personal_position.bump = [personal_position_bump];
-        personal_position.nft_mint = position_nft_mint.key();
-        personal_position.pool_id = pool_state_loader.key();
-        personal_position.tick_lower_index = tick_lower_index;
-        personal_position.tick_upper_index = tick_upper_index;
-        personal_position.fee_growth_inside_0_last_x64 =
-            protocol_position.fee_growth_inside_0_last_x64;
-        personal_position.fee_growth_inside_1_last_x64 =
-            protocol_position.fee_growth_inside_1_last_x64;
- 
-        personal_position.liquidity = liquidity;
-    }
-
-    mint_nft_and_remove_mint_authority(
-        payer,
-        pool_state_loader,
-        personal_position,
-        position_nft_mint,
-        position_nft_account,
-        metadata_account,
-        metadata_program,
-        token_program,
-        token_program_2022,
-        system_program,
-        rent,
-        with_metadata,
-        use_metadata_extension,
-    )
-
-
-}
-
-
-
 impl<'info> OpenPositionWithToken22Nft<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn open_position_with_token22_nft(
         &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        bumps: &OpenPositionWithToken22NftBumps,
         liquidity: u128,
         amount_0_max: u64,
         amount_1_max: u64,
@@ -371,79 +147,126 @@ impl<'info> OpenPositionWithToken22Nft<'info> {
         tick_array_upper_start_index: i32,
         with_metadata: bool,
         base_flag: Option<bool>,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
     ) -> Result<()> {
-        // create nft mint with extensions
-        // create user position nft account
-        // open position
+        check_price_band(
+            &*self.pool_state.load()?,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )?;
 
-        create_position_nft_mint_with_extensions(
+        create_position_nft(
             &self.payer,
+            &self.position_nft_owner.to_account_info(),
             &self.position_nft_mint.to_account_info(),
+            &self.position_nft_account.to_account_info(),
             &self.pool_state.to_account_info(),
             &self.personal_position.to_account_info(),
             &self.system_program,
             &self.token_program_2022,
-            with_metadata
+            &self.associated_token_program,
+            with_metadata,
         )?;
 
-        create(CpiContext::new(
-            &self.associated_token_program.to_account_info(),
-            Create {
-                payer: &self.payer.to_account_info(),
-                associated_token: &self.position_nft_account.to_account_info(),
-                authority: &self.position_nft_owner.to_account_info(),
-                mint: &self.position_nft_mint.to_account_info(),
-                system_program: &self.system_program.to_account_info(),
-                token_program: &self.token_program_2022.to_account_info()
-            }
-        ))?;
-
-        
-        // update the pool state and personal position and protocol position
-        open_position(
-            &ctx.accounts.payer,
-            &ctx.accounts.position_nft_owner,
-            &ctx.accounts.position_nft_mint,
-            &ctx.accounts.position_nft_account,
-            None,
-            &ctx.accounts.pool_state,
-            &ctx.accounts.tick_array_lower,
-            &ctx.accounts.tick_array_upper,
-            &mut ctx.accounts.protocol_position,
-            &mut ctx.accounts.personal_position,
-            &ctx.accounts.token_account_0.to_account_info(),
-            &ctx.accounts.token_account_1.to_account_info(),
-            &ctx.accounts.token_vault_0.to_account_info(),
-            &ctx.accounts.token_vault_1.to_account_info(),
-            &ctx.accounts.rent,
-            &ctx.accounts.system_program,
-            &ctx.accounts.token_program,
-            &ctx.accounts.associated_token_program,
-            None,
-            Some(&ctx.accounts.token_program_2022),
-            Some(ctx.accounts.vault_0_mint.clone()),
-            Some(ctx.accounts.vault_1_mint.clone()),
-            &ctx.remaining_accounts,
-            ctx.bumps.protocol_position,
-            ctx.bumps.personal_position,
-            liquidity,
-            amount_0_max,
-            amount_1_max,
+        let pool_id = self.pool_state.key();
+        let (tick_array_lower_loader, tick_array_upper_loader) = prepare_range(
+            &self.payer.to_account_info(),
+            &self.pool_state,
+            &self.tick_array_lower.to_account_info(),
+            &self.tick_array_upper.to_account_info(),
+            &mut self.protocol_position,
+            bumps.protocol_position,
+            &self.system_program.to_account_info(),
             tick_lower_index,
             tick_upper_index,
             tick_array_lower_start_index,
             tick_array_upper_start_index,
+        )?;
+
+        let mut liquidity = liquidity;
+        let (amount_0, amount_1, amount_0_transfer_fee, amount_1_transfer_fee) = {
+            let pool_state = &mut self.pool_state.load_mut()?;
+            let tick_array_bitmap_extension =
+                if pool_state.is_overflow_default_tickarray_bitmap(vec![
+                    tick_array_lower_start_index,
+                    tick_array_upper_start_index,
+                ]) {
+                    let tick_array_bitmap_extension = remaining_accounts
+                        .first()
+                        .ok_or(ErrorCode::MissingTickArrayBitmapExtensionAccount)?;
+                    require_keys_eq!(
+                        tick_array_bitmap_extension.key(),
+                        TickArrayBitmapExtension::key(pool_id)
+                    );
+                    Some(tick_array_bitmap_extension)
+                } else {
+                    None
+                };
+
+            // Checkpoint: tick_array is loaded, protocol position is initialized, lets now add liquidity
+            add_liquidity(
+                &self.payer,
+                &self.token_account_0.to_account_info(),
+                &self.token_account_1.to_account_info(),
+                &self.token_vault_0.to_account_info(),
+                &self.token_vault_1.to_account_info(),
+                &tick_array_lower_loader,
+                &tick_array_upper_loader,
+                &mut self.protocol_position,
+                Some(&self.token_program_2022),
+                &self.token_program,
+                Some(self.vault_0_mint.clone()),
+                Some(self.vault_1_mint.clone()),
+                tick_array_bitmap_extension,
+                pool_state,
+                &mut liquidity,
+                amount_0_max,
+                amount_1_max,
+                tick_lower_index,
+                tick_upper_index,
+                base_flag,
+            )?
+        };
+
+        init_personal_position(
+            &mut self.personal_position,
+            bumps.personal_position,
+            self.position_nft_mint.key(),
+            &self.protocol_position,
+            liquidity,
+        );
+
+        emit!(PositionOpened {
+            pool_state: pool_id,
+            minter: self.payer.key(),
+            nft_owner: self.position_nft_owner.key(),
+            position_nft_mint: self.position_nft_mint.key(),
+            tick_lower_index,
+            tick_upper_index,
+            liquidity,
+            deposit_amount_0: amount_0,
+            deposit_amount_1: amount_1,
+            deposit_amount_0_transfer_fee: amount_0_transfer_fee,
+            deposit_amount_1_transfer_fee: amount_1_transfer_fee,
+        });
+
+        mint_nft_and_remove_mint_authority(
+            &self.payer,
+            &self.pool_state,
+            &self.personal_position,
+            &self.position_nft_mint.to_account_info(),
+            &self.position_nft_account.to_account_info(),
+            None,
+            None,
+            &self.token_program,
+            Some(&self.token_program_2022),
+            &self.system_program,
+            &self.rent,
             with_metadata,
-            base_flag,
             true,
         )
     }
 }
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Invalid extension type")]
-    InvalidExtensionType,
-    #[msg("Invalid tick order")]
-    InvalidTickOrder
-}
\ No newline at end of file
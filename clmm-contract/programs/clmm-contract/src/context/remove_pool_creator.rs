@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    context::create_amm_config::ID as ADMIN, events::PoolCreatorChanged, state::PoolCreator,
+};
+
+#[derive(Accounts)]
+pub struct RemovePoolCreator<'info> {
+    #[account(
+        mut,
+        address = ADMIN,
+    )]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+    )]
+    pub pool_creator: Account<'info, PoolCreator>,
+}
+
+impl<'info> RemovePoolCreator<'info> {
+    pub fn remove_pool_creator(&mut self) -> Result<()> {
+        emit!(PoolCreatorChanged {
+            amm_config: self.pool_creator.amm_config,
+            creator: self.pool_creator.creator,
+            allowed: false,
+        });
+
+        Ok(())
+    }
+}
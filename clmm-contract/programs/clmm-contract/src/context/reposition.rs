@@ -0,0 +1,601 @@
+use std::collections::VecDeque;
+
+use anchor_lang::{error::ErrorCode as AnchorErrorCode, prelude::*};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{
+    error::ErrorCode,
+    events::{LiquidityDecreased, LiquidityIncreased, PositionOpened, PositionRepositioned, Swap},
+    helpers::{
+        add_liquidity_to_range, add_personal_position_liquidity, check_price_band,
+        create_position_nft, get_liquidity_from_amounts, get_sqrt_price_at_tick, get_transfer_fee,
+        init_personal_position, mint_nft_and_remove_mint_authority, prepare_range,
+        remove_liquidity_from_range, remove_personal_position_liquidity, swap_internal,
+        transfer_from_pool_vault_to_user,
+    },
+    state::{
+        AmmConfig, PersonalPositionState, PoolState, ProtocolPositionState,
+        TickArrayBitmapExtension, TickArrayState,
+    },
+    util::AccountLoad,
+};
+
+/// Move all liquidity of a position to a new tick range
+///
+/// Remaining accounts: the tick array bitmap extension, which may be left out when neither the
+/// swap nor the tick arrays of the two ranges need it, followed by the tick arrays the swap
+/// walks through in swap order.
+#[derive(Accounts)]
+#[instruction(
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32
+)]
+pub struct Reposition<'info> {
+    /// Pays for the tick arrays and the protocol position of the new range
+    #[account(mut)]
+    pub nft_owner: Signer<'info>,
+
+    #[account(
+        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = personal_position.pool_id == pool_state.key()
+    )]
+    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"protocol_position",
+            pool_state.key().as_ref(),
+            &personal_position.tick_lower_index.to_be_bytes(),
+            &personal_position.tick_upper_index.to_be_bytes()
+        ],
+        bump,
+        constraint = protocol_position.pool_id == pool_state.key()
+    )]
+    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+
+    #[account(
+        mut,
+        constraint = tick_array_lower.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        mut,
+        constraint = tick_array_upper.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"protocol_position",
+            pool_state.key().as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes()
+        ],
+        bump,
+        space = 8 + ProtocolPositionState::INIT_SPACE,
+        payer = nft_owner
+    )]
+    pub new_protocol_position: Box<Account<'info, ProtocolPositionState>>,
+
+    /// Created in the instruction if needed
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool_state.key().as_ref(),
+            &tick_array_lower_start_index.to_be_bytes()
+        ],
+        bump
+    )]
+    pub new_tick_array_lower: UncheckedAccount<'info>,
+
+    /// Created in the instruction if needed
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool_state.key().as_ref(),
+            &tick_array_upper_start_index.to_be_bytes()
+        ],
+        bump
+    )]
+    pub new_tick_array_upper: UncheckedAccount<'info>,
+
+    /// Nft account of `new_personal_position`, which must be held by the signer as well
+    #[account(
+        constraint = new_nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub new_nft_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Position of the new range that receives the liquidity. Left out along with the fresh nft
+    /// accounts, the position itself moves to the new range and keeps its nft.
+    #[account(
+        mut,
+        constraint = new_personal_position.pool_id == pool_state.key(),
+        constraint = new_personal_position.key() != personal_position.key()
+    )]
+    pub new_personal_position: Option<Box<Account<'info, PersonalPositionState>>>,
+
+    /// Mint of a fresh nft opening a position of the new range that receives the liquidity,
+    /// instead of `new_personal_position`
+    #[account(mut)]
+    pub fresh_position_nft_mint: Option<Signer<'info>>,
+
+    /// CHECK: ATA of the signer the fresh nft is minted to, initialize in contract
+    #[account(mut)]
+    pub fresh_position_nft_account: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        init,
+        seeds = [
+            b"personal_position",
+            fresh_position_nft_mint
+                .as_ref()
+                .map(|mint| mint.key())
+                .unwrap_or_default()
+                .as_ref()
+        ],
+        bump,
+        payer = nft_owner,
+        space = 8 + PersonalPositionState::INIT_SPACE
+    )]
+    pub fresh_personal_position: Option<Box<Account<'info, PersonalPositionState>>>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint
+    )]
+    pub token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint
+    )]
+    pub token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_program_2022: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+    /// Needed with a fresh nft only
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub rent: Option<Sysvar<'info, Rent>>,
+
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> Reposition<'info> {
+    /// Remove all liquidity of the position and collect its fees, swap `swap_amount` of token 0
+    /// when `zero_for_one`, of token 1 otherwise, and add the proceeds to the new range. Tokens
+    /// the new range does not take are sent back to the user. The swap and the deposit never
+    /// leave the vaults, so only the tokens sent back are charged a transfer fee.
+    ///
+    /// The liquidity goes to `new_personal_position` when given, to a position opened with a
+    /// fresh nft when `fresh_position_nft_mint` is given, and stays with the position otherwise.
+    #[allow(clippy::too_many_arguments)]
+    pub fn reposition(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        bumps: &RepositionBumps,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        tick_array_lower_start_index: i32,
+        tick_array_upper_start_index: i32,
+        swap_amount: u64,
+        zero_for_one: bool,
+        liquidity_min: u128,
+        with_metadata: bool,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        let pool_id = self.pool_state.key();
+        let old_tick_lower = self.personal_position.tick_lower_index;
+        let old_tick_upper = self.personal_position.tick_upper_index;
+        let old_liquidity = self.personal_position.liquidity;
+        require!(
+            (tick_lower_index, tick_upper_index) != (old_tick_lower, old_tick_upper),
+            ErrorCode::RepositionToSameRange
+        );
+        require!(old_liquidity > 0, ErrorCode::LiquidityZero);
+        if let Some(new_personal_position) = &self.new_personal_position {
+            // the liquidity must not leave the holder of the position
+            let new_nft_account = self
+                .new_nft_account
+                .as_ref()
+                .ok_or(ErrorCode::NewPositionNotHeld)?;
+            require_keys_eq!(
+                new_nft_account.mint,
+                new_personal_position.nft_mint,
+                ErrorCode::NewPositionNotHeld
+            );
+            require!(
+                new_personal_position.tick_lower_index == tick_lower_index
+                    && new_personal_position.tick_upper_index == tick_upper_index,
+                ErrorCode::InvalidTickRange
+            );
+        }
+
+        check_price_band(
+            &*self.pool_state.load()?,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )?;
+        if let Some(fresh_position_nft_mint) = &self.fresh_position_nft_mint {
+            require!(
+                self.new_personal_position.is_none(),
+                ErrorCode::RepositionTargetConflict
+            );
+            create_position_nft(
+                &self.nft_owner,
+                &self.nft_owner.to_account_info(),
+                &fresh_position_nft_mint.to_account_info(),
+                &fresh_nft_account(&self.fresh_position_nft_account)?.to_account_info(),
+                &self.pool_state.to_account_info(),
+                &fresh_nft_account(&self.fresh_personal_position)?.to_account_info(),
+                &self.system_program,
+                &self.token_program_2022,
+                fresh_nft_account(&self.associated_token_program)?,
+                with_metadata,
+            )?;
+        }
+
+        let (new_tick_array_lower, new_tick_array_upper) = prepare_range(
+            &self.nft_owner.to_account_info(),
+            &self.pool_state,
+            &self.new_tick_array_lower.to_account_info(),
+            &self.new_tick_array_upper.to_account_info(),
+            &mut self.new_protocol_position,
+            bumps.new_protocol_position,
+            &self.system_program.to_account_info(),
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+        )?;
+
+        let (tickarray_bitmap_extension_info, swap_tick_arrays) =
+            match remaining_accounts.split_first() {
+                Some((first, rest)) if first.key() == TickArrayBitmapExtension::key(pool_id) => {
+                    (Some(first), rest)
+                }
+                _ => (None, remaining_accounts),
+            };
+
+        let (removed_0, removed_1) = {
+            let pool_state = &mut self.pool_state.load_mut()?;
+            remove_liquidity_from_range(
+                &AccountLoad::try_from(&self.tick_array_lower.to_account_info())?,
+                &AccountLoad::try_from(&self.tick_array_upper.to_account_info())?,
+                &mut self.protocol_position,
+                tickarray_bitmap_extension_info,
+                pool_state,
+                old_liquidity,
+                old_tick_lower,
+                old_tick_upper,
+            )?
+        };
+        remove_personal_position_liquidity(
+            &mut self.personal_position,
+            &self.protocol_position,
+            old_liquidity,
+        )?;
+
+        // the owed fees are collected into the new range along with the liquidity
+        let fee_amount_0 = self.personal_position.token_fees_owed_0;
+        let fee_amount_1 = self.personal_position.token_fees_owed_1;
+        self.personal_position.token_fees_owed_0 = 0;
+        self.personal_position.token_fees_owed_1 = 0;
+        let withdrawn_0 = removed_0
+            .checked_add(fee_amount_0)
+            .ok_or(ErrorCode::MaxTokenOverflow)?;
+        let withdrawn_1 = removed_1
+            .checked_add(fee_amount_1)
+            .ok_or(ErrorCode::MaxTokenOverflow)?;
+        let (withdrawn_in, withdrawn_out) = if zero_for_one {
+            (withdrawn_0, withdrawn_1)
+        } else {
+            (withdrawn_1, withdrawn_0)
+        };
+        require_gte!(
+            withdrawn_in,
+            swap_amount,
+            ErrorCode::InvalidRepositionSwapAmount
+        );
+
+        let (swap_amount_in, swap_amount_out, swap_state, liquidity, amounts_available, amounts) = {
+            let pool_state = &mut self.pool_state.load_mut()?;
+            pool_state.total_fees_claimed_token_0 = pool_state
+                .total_fees_claimed_token_0
+                .checked_add(fee_amount_0)
+                .ok_or(ErrorCode::MaxTokenOverflow)?;
+            pool_state.total_fees_claimed_token_1 = pool_state
+                .total_fees_claimed_token_1
+                .checked_add(fee_amount_1)
+                .ok_or(ErrorCode::MaxTokenOverflow)?;
+
+            let (swap_amount_in, swap_amount_out) = if swap_amount == 0 {
+                (0, 0)
+            } else {
+                let tickarray_bitmap_extension = match tickarray_bitmap_extension_info {
+                    Some(account_info) => Some(
+                        *AccountLoader::<TickArrayBitmapExtension>::try_from(account_info)?
+                            .load()?,
+                    ),
+                    None => None,
+                };
+                let tick_array_loaders = swap_tick_arrays
+                    .iter()
+                    .map(AccountLoader::<TickArrayState>::try_from)
+                    .collect::<Result<Vec<_>>>()?;
+                let mut tick_array_states = tick_array_loaders
+                    .iter()
+                    .map(|loader| loader.load_mut())
+                    .collect::<Result<VecDeque<_>>>()?;
+                swap_internal(
+                    &self.amm_config,
+                    pool_state,
+                    &mut tick_array_states,
+                    tickarray_bitmap_extension.as_ref(),
+                    swap_amount,
+                    0,
+                    zero_for_one,
+                    true,
+                    Clock::get()?.unix_timestamp as u64,
+                )?
+            };
+            let swap_state = (
+                pool_state.sqrt_price_x64,
+                pool_state.liquidity,
+                pool_state.tick_current,
+            );
+
+            let amount_in_left = withdrawn_in - swap_amount_in;
+            let amount_out_total = withdrawn_out
+                .checked_add(swap_amount_out)
+                .ok_or(ErrorCode::MaxTokenOverflow)?;
+            let (amount_0_available, amount_1_available) = if zero_for_one {
+                (amount_in_left, amount_out_total)
+            } else {
+                (amount_out_total, amount_in_left)
+            };
+            let liquidity = get_liquidity_from_amounts(
+                pool_state.sqrt_price_x64,
+                get_sqrt_price_at_tick(tick_lower_index).map_err(ErrorCode::from)?,
+                get_sqrt_price_at_tick(tick_upper_index).map_err(ErrorCode::from)?,
+                amount_0_available,
+                amount_1_available,
+            )
+            .map_err(ErrorCode::from)?;
+            require!(liquidity > 0, ErrorCode::LiquidityZero);
+            require_gte!(liquidity, liquidity_min, ErrorCode::PriceSlippageCheck);
+
+            let (amount_0, amount_1) = add_liquidity_to_range(
+                &new_tick_array_lower,
+                &new_tick_array_upper,
+                &mut self.new_protocol_position,
+                tickarray_bitmap_extension_info,
+                pool_state,
+                liquidity,
+                tick_lower_index,
+                tick_upper_index,
+            )?;
+            // the withdrawn amounts back the deposit, the pool must not pay for rounding
+            require_gte!(amount_0_available, amount_0, ErrorCode::PriceSlippageCheck);
+            require_gte!(amount_1_available, amount_1, ErrorCode::PriceSlippageCheck);
+
+            (
+                swap_amount_in,
+                swap_amount_out,
+                swap_state,
+                liquidity,
+                (amount_0_available, amount_1_available),
+                (amount_0, amount_1),
+            )
+        };
+        let (amount_0, amount_1) = amounts;
+
+        let position_nft_mint = match (
+            &mut self.new_personal_position,
+            &self.fresh_position_nft_mint,
+            &mut self.fresh_personal_position,
+        ) {
+            (Some(new_personal_position), _, _) => {
+                add_personal_position_liquidity(
+                    new_personal_position,
+                    &self.new_protocol_position,
+                    liquidity,
+                )?;
+                new_personal_position.nft_mint
+            }
+            (None, Some(fresh_position_nft_mint), Some(fresh_personal_position)) => {
+                init_personal_position(
+                    fresh_personal_position,
+                    bumps.fresh_personal_position.unwrap_or_default(),
+                    fresh_position_nft_mint.key(),
+                    &self.new_protocol_position,
+                    liquidity,
+                );
+                fresh_position_nft_mint.key()
+            }
+            _ => {
+                // the position is empty, so it takes the fee growth of its new range as is
+                let personal_position = &mut self.personal_position;
+                personal_position.tick_lower_index = tick_lower_index;
+                personal_position.tick_upper_index = tick_upper_index;
+                add_personal_position_liquidity(
+                    personal_position,
+                    &self.new_protocol_position,
+                    liquidity,
+                )?;
+                personal_position.nft_mint
+            }
+        };
+
+        // what the new range does not take goes back to the user
+        let (amount_0_available, amount_1_available) = amounts_available;
+        let (amount_0_surplus, amount_1_surplus) =
+            (amount_0_available - amount_0, amount_1_available - amount_1);
+        let transfer_fee_0 = get_transfer_fee(&self.vault_0_mint, amount_0_surplus)?;
+        let transfer_fee_1 = get_transfer_fee(&self.vault_1_mint, amount_1_surplus)?;
+        for (amount, vault, account, mint) in [
+            (
+                amount_0_surplus,
+                &self.token_vault_0,
+                &self.token_account_0,
+                &self.vault_0_mint,
+            ),
+            (
+                amount_1_surplus,
+                &self.token_vault_1,
+                &self.token_account_1,
+                &self.vault_1_mint,
+            ),
+        ] {
+            if amount > 0 {
+                transfer_from_pool_vault_to_user(
+                    &self.pool_state,
+                    &vault.to_account_info(),
+                    &account.to_account_info(),
+                    Some(mint.clone()),
+                    &self.token_program.to_account_info(),
+                    Some(self.token_program_2022.to_account_info()),
+                    amount,
+                )?;
+            }
+        }
+
+        emit!(LiquidityDecreased {
+            pool_state: pool_id,
+            position_nft_mint: self.personal_position.nft_mint,
+            liquidity: old_liquidity,
+            decrease_amount_0: removed_0,
+            decrease_amount_1: removed_1,
+            fee_amount_0,
+            fee_amount_1,
+            transfer_fee_0,
+            transfer_fee_1,
+        });
+        if self.new_personal_position.is_none() && self.fresh_position_nft_mint.is_none() {
+            emit!(PositionRepositioned {
+                pool_state: pool_id,
+                position_nft_mint,
+                tick_lower_index,
+                tick_upper_index,
+            });
+        }
+        if swap_amount_in > 0 {
+            let (sqrt_price_x64, liquidity, tick) = swap_state;
+            let (amount_0, amount_1) = if zero_for_one {
+                (swap_amount_in, swap_amount_out)
+            } else {
+                (swap_amount_out, swap_amount_in)
+            };
+            emit!(Swap {
+                pool_state: pool_id,
+                sender: self.nft_owner.key(),
+                token_account_0: self.token_account_0.key(),
+                token_account_1: self.token_account_1.key(),
+                amount_0,
+                transfer_fee_0: 0,
+                amount_1,
+                transfer_fee_1: 0,
+                zero_for_one,
+                sqrt_price_x64,
+                liquidity,
+                tick,
+            });
+        }
+        let Some(fresh_position_nft_mint) = &self.fresh_position_nft_mint else {
+            emit!(LiquidityIncreased {
+                pool_state: pool_id,
+                position_nft_mint,
+                liquidity,
+                amount_0,
+                amount_1,
+                amount_0_transfer_fee: 0,
+                amount_1_transfer_fee: 0,
+            });
+            return Ok(());
+        };
+        emit!(PositionOpened {
+            pool_state: pool_id,
+            minter: self.nft_owner.key(),
+            nft_owner: self.nft_owner.key(),
+            position_nft_mint,
+            tick_lower_index,
+            tick_upper_index,
+            liquidity,
+            deposit_amount_0: amount_0,
+            deposit_amount_1: amount_1,
+            deposit_amount_0_transfer_fee: 0,
+            deposit_amount_1_transfer_fee: 0,
+        });
+        mint_nft_and_remove_mint_authority(
+            &self.nft_owner,
+            &self.pool_state,
+            fresh_nft_account(&self.fresh_personal_position)?,
+            &fresh_position_nft_mint.to_account_info(),
+            &fresh_nft_account(&self.fresh_position_nft_account)?.to_account_info(),
+            None,
+            None,
+            &self.token_program,
+            Some(&self.token_program_2022),
+            &self.system_program,
+            fresh_nft_account(&self.rent)?,
+            with_metadata,
+            true,
+        )
+    }
+}
+
+/// An optional account the fresh nft needs
+fn fresh_nft_account<T>(account: &Option<T>) -> Result<&T> {
+    account
+        .as_ref()
+        .ok_or(error!(AnchorErrorCode::AccountNotEnoughKeys))
+}
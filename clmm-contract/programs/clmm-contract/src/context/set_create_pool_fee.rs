@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    context::create_amm_config::ID as ADMIN, error::ErrorCode, events::CreatePoolFeeChanged,
+    state::AmmConfig,
+};
+
+#[derive(Accounts)]
+pub struct SetCreatePoolFee<'info> {
+    #[account(
+        address = ADMIN,
+    )]
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub amm_config: Account<'info, AmmConfig>,
+}
+
+impl<'info> SetCreatePoolFee<'info> {
+    /// Set the lamports every new pool of the amm config pays to `create_pool_fee_receiver`,
+    /// zero disables the fee
+    pub fn set_create_pool_fee(
+        &mut self,
+        create_pool_fee: u64,
+        create_pool_fee_receiver: Pubkey,
+    ) -> Result<()> {
+        require!(
+            create_pool_fee == 0 || create_pool_fee_receiver != Pubkey::default(),
+            ErrorCode::InvalidCreatePoolFeeReceiver
+        );
+
+        let amm_config = &mut self.amm_config;
+        amm_config.create_pool_fee = create_pool_fee;
+        amm_config.create_pool_fee_receiver = create_pool_fee_receiver;
+
+        emit!(CreatePoolFeeChanged {
+            amm_config: amm_config.key(),
+            create_pool_fee,
+            create_pool_fee_receiver,
+        });
+
+        Ok(())
+    }
+}
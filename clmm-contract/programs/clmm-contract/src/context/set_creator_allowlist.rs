@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    context::create_amm_config::ID as ADMIN, events::CreatorAllowlistChanged, state::AmmConfig,
+};
+
+#[derive(Accounts)]
+pub struct SetCreatorAllowlist<'info> {
+    #[account(
+        address = ADMIN,
+    )]
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub amm_config: Account<'info, AmmConfig>,
+}
+
+impl<'info> SetCreatorAllowlist<'info> {
+    /// Switch the amm config between permissionless and allowlisted pool creation. Pools that
+    /// already exist are not affected.
+    pub fn set_creator_allowlist(&mut self, enabled: bool) -> Result<()> {
+        self.amm_config.creator_allowlist = enabled;
+
+        emit!(CreatorAllowlistChanged {
+            amm_config: self.amm_config.key(),
+            enabled,
+        });
+
+        Ok(())
+    }
+}
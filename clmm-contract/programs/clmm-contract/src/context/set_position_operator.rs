@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::{events::PositionOperatorChanged, state::PersonalPositionState};
+
+#[derive(Accounts)]
+pub struct SetPositionOperator<'info> {
+    pub nft_owner: Signer<'info>,
+
+    #[account(
+        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+}
+
+impl<'info> SetPositionOperator<'info> {
+    /// Approve `operator` to compound the fees of the position, the default pubkey revokes it.
+    /// The approval is given by the current holder and lapses when the nft changes hands.
+    pub fn set_position_operator(&mut self, operator: Pubkey) -> Result<()> {
+        self.personal_position.operator = operator;
+        self.personal_position.operator_approver = self.nft_owner.key();
+
+        emit!(PositionOperatorChanged {
+            position_nft_mint: self.personal_position.nft_mint,
+            operator,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_2022::{self, Token2022},
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{error::ErrorCode, events::PositionUnlocked, state::LockedPositionState};
+
+#[derive(Accounts)]
+pub struct UnlockPosition<'info> {
+    /// Holds the fee key and receives the position nft and the rent of the escrow
+    #[account(mut)]
+    pub fee_key_owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = fee_key_account.mint == locked_position.fee_key_mint,
+        constraint = fee_key_account.amount == 1,
+        token::authority = fee_key_owner
+    )]
+    pub fee_key_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        address = locked_position.fee_key_mint
+    )]
+    pub fee_key_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"locked_position", position_nft_mint.key().as_ref()],
+        bump = locked_position.bump[0],
+        close = fee_key_owner
+    )]
+    pub locked_position: Box<Account<'info, LockedPositionState>>,
+
+    #[account(address = locked_position.position_nft_mint)]
+    pub position_nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = position_nft_mint,
+        associated_token::authority = locked_position,
+        associated_token::token_program = token_program_2022
+    )]
+    pub escrow_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = fee_key_owner,
+        associated_token::mint = position_nft_mint,
+        associated_token::authority = fee_key_owner,
+        associated_token::token_program = token_program_2022
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program_2022: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> UnlockPosition<'info> {
+    /// Once the unlock time has passed, burn the fee key and hand the position nft to its
+    /// holder. The escrow accounts are closed.
+    pub fn unlock_position(&mut self) -> Result<()> {
+        require!(
+            self.locked_position
+                .is_unlocked(Clock::get()?.unix_timestamp),
+            ErrorCode::PositionStillLocked
+        );
+
+        let seeds = self.locked_position.seeds();
+        token_2022::transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program_2022.to_account_info(),
+                token_2022::TransferChecked {
+                    from: self.escrow_nft_account.to_account_info(),
+                    mint: self.position_nft_mint.to_account_info(),
+                    to: self.nft_account.to_account_info(),
+                    authority: self.locked_position.to_account_info(),
+                },
+                &[&seeds],
+            ),
+            1,
+            0,
+        )?;
+        token_2022::close_account(CpiContext::new_with_signer(
+            self.token_program_2022.to_account_info(),
+            token_2022::CloseAccount {
+                account: self.escrow_nft_account.to_account_info(),
+                destination: self.fee_key_owner.to_account_info(),
+                authority: self.locked_position.to_account_info(),
+            },
+            &[&seeds],
+        ))?;
+
+        token_2022::burn(
+            CpiContext::new(
+                self.token_program_2022.to_account_info(),
+                token_2022::Burn {
+                    mint: self.fee_key_mint.to_account_info(),
+                    from: self.fee_key_account.to_account_info(),
+                    authority: self.fee_key_owner.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        token_2022::close_account(CpiContext::new(
+            self.token_program_2022.to_account_info(),
+            token_2022::CloseAccount {
+                account: self.fee_key_account.to_account_info(),
+                destination: self.fee_key_owner.to_account_info(),
+                authority: self.fee_key_owner.to_account_info(),
+            },
+        ))?;
+
+        emit!(PositionUnlocked {
+            pool_state: self.locked_position.pool_id,
+            position_nft_mint: self.locked_position.position_nft_mint,
+            nft_owner: self.fee_key_owner.key(),
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{
+    events::LiquidityIncreased,
+    helpers::{add_personal_position_liquidity, check_price_band, zap_into_range},
+    state::{AmmConfig, PersonalPositionState, PoolState, ProtocolPositionState, TickArrayState},
+    util::AccountLoad,
+};
+
+/// Add liquidity to a position from a single token
+///
+/// Remaining accounts: the tick array bitmap extension, which may be left out when neither the
+/// swap nor the position's tick arrays need it, followed by the tick arrays the swap walks
+/// through in swap order.
+#[derive(Accounts)]
+pub struct ZapIn<'info> {
+    pub nft_owner: Signer<'info>,
+
+    #[account(
+        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = personal_position.pool_id == pool_state.key()
+    )]
+    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"protocol_position",
+            pool_state.key().as_ref(),
+            &personal_position.tick_lower_index.to_be_bytes(),
+            &personal_position.tick_upper_index.to_be_bytes()
+        ],
+        bump,
+        constraint = protocol_position.pool_id == pool_state.key()
+    )]
+    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+
+    #[account(
+        mut,
+        constraint = tick_array_lower.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        mut,
+        constraint = tick_array_upper.load()?.pool_id == pool_state.key()
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint
+    )]
+    pub token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint
+    )]
+    pub token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_program_2022: Program<'info, Token2022>,
+
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> ZapIn<'info> {
+    /// Take `amount_in` of token 0 when `zero_for_one`, of token 1 otherwise, swap
+    /// `swap_amount` of it through the pool and add the liquidity both parts pay for to the
+    /// position, see [`zap_into_range`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn zap_in(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        swap_amount: u64,
+        zero_for_one: bool,
+        liquidity_min: u128,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        check_price_band(
+            &*self.pool_state.load()?,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )?;
+        let (liquidity, amount_0, amount_1, amount_0_transfer_fee, amount_1_transfer_fee) =
+            zap_into_range(
+                &self.nft_owner,
+                &self.token_account_0,
+                &self.token_account_1,
+                &self.token_vault_0,
+                &self.token_vault_1,
+                &self.vault_0_mint,
+                &self.vault_1_mint,
+                &self.token_program,
+                &self.token_program_2022,
+                &self.amm_config,
+                &self.pool_state,
+                &mut self.protocol_position,
+                &AccountLoad::try_from(&self.tick_array_lower.to_account_info())?,
+                &AccountLoad::try_from(&self.tick_array_upper.to_account_info())?,
+                remaining_accounts,
+                amount_in,
+                swap_amount,
+                zero_for_one,
+                liquidity_min,
+                self.personal_position.tick_lower_index,
+                self.personal_position.tick_upper_index,
+            )?;
+
+        add_personal_position_liquidity(
+            &mut self.personal_position,
+            &self.protocol_position,
+            liquidity,
+        )?;
+
+        emit!(LiquidityIncreased {
+            pool_state: self.pool_state.key(),
+            position_nft_mint: self.personal_position.nft_mint,
+            liquidity,
+            amount_0,
+            amount_1,
+            amount_0_transfer_fee,
+            amount_1_transfer_fee,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{
+    events::PositionOpened,
+    helpers::{
+        check_price_band, create_position_nft, init_personal_position,
+        mint_nft_and_remove_mint_authority, prepare_range, zap_into_range,
+    },
+    state::{AmmConfig, PersonalPositionState, PoolState, ProtocolPositionState},
+};
+
+/// Open a position from a single token
+///
+/// Remaining accounts: the tick array bitmap extension, which may be left out when neither the
+/// swap nor the position's tick arrays need it, followed by the tick arrays the swap walks
+/// through in swap order.
+#[derive(Accounts)]
+#[instruction(
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32
+)]
+pub struct ZapInOpenPosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: receives the position nft
+    pub position_nft_owner: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub position_nft_mint: Signer<'info>,
+
+    /// CHECK: ATA address where position NFT will be minted, initialize in contract
+    #[account(mut)]
+    pub position_nft_account: UncheckedAccount<'info>,
+
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"protocol_position",
+            pool_state.key().as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes()
+        ],
+        bump,
+        space = 8 + ProtocolPositionState::INIT_SPACE,
+        payer = payer
+    )]
+    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+
+    #[account(
+        init,
+        seeds = [b"personal_position", position_nft_mint.key().as_ref()],
+        bump,
+        payer = payer,
+        space = 8 + PersonalPositionState::INIT_SPACE
+    )]
+    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+
+    /// CHECK: created in the instruction if needed
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool_state.key().as_ref(),
+            &tick_array_lower_start_index.to_be_bytes()
+        ],
+        bump
+    )]
+    pub tick_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: created in the instruction if needed
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool_state.key().as_ref(),
+            &tick_array_upper_start_index.to_be_bytes()
+        ],
+        bump
+    )]
+    pub tick_array_upper: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint
+    )]
+    pub token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint
+    )]
+    pub token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>, // NFT ATA
+    pub token_program_2022: Program<'info, Token2022>,             // for token22 mint
+
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> ZapInOpenPosition<'info> {
+    /// Open a position in the range from `tick_lower_index` to `tick_upper_index` with
+    /// `amount_in` of token 0 when `zero_for_one`, of token 1 otherwise, swapping `swap_amount`
+    /// of it through the pool, see [`zap_into_range`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn zap_in_open_position(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        bumps: &ZapInOpenPositionBumps,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        tick_array_lower_start_index: i32,
+        tick_array_upper_start_index: i32,
+        amount_in: u64,
+        swap_amount: u64,
+        zero_for_one: bool,
+        liquidity_min: u128,
+        with_metadata: bool,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        check_price_band(
+            &*self.pool_state.load()?,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )?;
+
+        create_position_nft(
+            &self.payer,
+            &self.position_nft_owner.to_account_info(),
+            &self.position_nft_mint.to_account_info(),
+            &self.position_nft_account.to_account_info(),
+            &self.pool_state.to_account_info(),
+            &self.personal_position.to_account_info(),
+            &self.system_program,
+            &self.token_program_2022,
+            &self.associated_token_program,
+            with_metadata,
+        )?;
+
+        let pool_id = self.pool_state.key();
+        let (tick_array_lower_loader, tick_array_upper_loader) = prepare_range(
+            &self.payer.to_account_info(),
+            &self.pool_state,
+            &self.tick_array_lower.to_account_info(),
+            &self.tick_array_upper.to_account_info(),
+            &mut self.protocol_position,
+            bumps.protocol_position,
+            &self.system_program.to_account_info(),
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+        )?;
+
+        let (liquidity, amount_0, amount_1, amount_0_transfer_fee, amount_1_transfer_fee) =
+            zap_into_range(
+                &self.payer,
+                &self.token_account_0,
+                &self.token_account_1,
+                &self.token_vault_0,
+                &self.token_vault_1,
+                &self.vault_0_mint,
+                &self.vault_1_mint,
+                &self.token_program,
+                &self.token_program_2022,
+                &self.amm_config,
+                &self.pool_state,
+                &mut self.protocol_position,
+                &tick_array_lower_loader,
+                &tick_array_upper_loader,
+                remaining_accounts,
+                amount_in,
+                swap_amount,
+                zero_for_one,
+                liquidity_min,
+                tick_lower_index,
+                tick_upper_index,
+            )?;
+
+        init_personal_position(
+            &mut self.personal_position,
+            bumps.personal_position,
+            self.position_nft_mint.key(),
+            &self.protocol_position,
+            liquidity,
+        );
+
+        emit!(PositionOpened {
+            pool_state: pool_id,
+            minter: self.payer.key(),
+            nft_owner: self.position_nft_owner.key(),
+            position_nft_mint: self.position_nft_mint.key(),
+            tick_lower_index,
+            tick_upper_index,
+            liquidity,
+            deposit_amount_0: amount_0,
+            deposit_amount_1: amount_1,
+            deposit_amount_0_transfer_fee: amount_0_transfer_fee,
+            deposit_amount_1_transfer_fee: amount_1_transfer_fee,
+        });
+
+        mint_nft_and_remove_mint_authority(
+            &self.payer,
+            &self.pool_state,
+            &self.personal_position,
+            &self.position_nft_mint.to_account_info(),
+            &self.position_nft_account.to_account_info(),
+            None,
+            None,
+            &self.token_program,
+            Some(&self.token_program_2022),
+            &self.system_program,
+            &self.rent,
+            with_metadata,
+            true,
+        )
+    }
+}
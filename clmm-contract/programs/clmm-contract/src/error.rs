@@ -0,0 +1,156 @@
+use anchor_lang::prelude::*;
+use clmm_math::MathError;
+
+/// Errors of the clmm program
+///
+/// Anchor numbers the variants from 6000 in declaration order and clients match on those codes,
+/// so variants are never reordered or removed. New variants go at the end.
+#[error_code]
+pub enum ErrorCode {
+    // math, 6000
+    #[msg("Invalid sqrt price")]
+    InvalidSqrtPrice,
+    #[msg("Tick lower overflow")]
+    TickLowerOverflow,
+    #[msg("Tick upper overflow")]
+    TickUpperOverflow,
+    #[msg("Invalid tick")]
+    InvalidTick,
+    #[msg("Invalid tick array start index")]
+    InvalidTickArrayStartIndex,
+    #[msg("Max token overflow")]
+    MaxTokenOverflow,
+    #[msg("Liquidity overflow")]
+    LiquidityOverflow,
+    #[msg("Liquidity must be greater than zero")]
+    LiquidityZero,
+    #[msg("Sqrt price x64 overflow")]
+    SqrtPriceX64Overflow,
+    #[msg("Sqrt price limit overflow")]
+    SqrtPriceLimitOverflow,
+    #[msg("Fee rate must be less than the fee rate denominator")]
+    InvalidFeeRate,
+    #[msg("Fee amount overflow")]
+    FeeAmountOverflow,
+    #[msg("Fee growth underflow")]
+    FeeGrowthUnderflow,
+
+    // ticks and tick arrays, 6013
+    #[msg("Invalid tick index")]
+    InvalidTickIndex,
+    #[msg("Invalid tick array")]
+    InvalidTickArray,
+    #[msg("Invalid tick array boundary")]
+    InvalidTickArrayBoundary,
+    #[msg("Invalid tick range")]
+    InvalidTickRange,
+    #[msg("Invalid tick order")]
+    InvalidTickOrder,
+
+    // pool, 6018
+    #[msg("Tick array bitmap extension account is required")]
+    MissingTickArrayBitmapExtensionAccount,
+    #[msg("No initialized tick array left in swap direction")]
+    InsufficientLiquidityForDirection,
+    #[msg("Mint is not supported")]
+    MintNotSupported,
+
+    // liquidity, 6021
+    #[msg("Invalid amount")]
+    ForbidBothZeroForSupplyLiquidity,
+    #[msg("Price slippage check")]
+    PriceSlippageCheck,
+    #[msg("Liquidity underflow")]
+    LiquidityUnderflow,
+    #[msg("Liquidity net overflow")]
+    LiquidityNetOverflow,
+
+    // tokens, 6025
+    #[msg("Transfer fee calculate not match")]
+    TransferFeeCalculateNotMatch,
+    #[msg("Transfer fee overflow")]
+    TransferFeeOverflow,
+    #[msg("Invalid extension type")]
+    InvalidExtensionType,
+
+    // account migration, 6028
+    #[msg("Account data does not match any known layout")]
+    InvalidAccountLayout,
+    #[msg("Account layout is newer than the program")]
+    UnsupportedLayoutVersion,
+
+    // pool creation, 6030
+    #[msg("Pool creator is not on the allowlist of the amm config")]
+    PoolCreatorNotAllowed,
+    #[msg("Create pool fee receiver does not match the amm config")]
+    InvalidCreatePoolFeeReceiver,
+
+    // swap, 6032
+    #[msg("Pool is not open yet")]
+    PoolNotOpen,
+    #[msg("Swap amount must be greater than zero")]
+    ZeroAmountSpecified,
+    #[msg("Tick arrays of the swap are missing or out of order")]
+    NotEnoughTickArrayAccount,
+
+    // zap, 6035
+    #[msg("Swap amount of the zap exceeds its input")]
+    InvalidZapSwapAmount,
+
+    // liquidity slippage, 6036
+    #[msg("Pool price is outside the accepted price band")]
+    PriceOutOfBand,
+    #[msg("Transaction deadline has passed")]
+    DeadlineExceeded,
+
+    // reposition, 6038
+    #[msg("Position already has this tick range")]
+    RepositionToSameRange,
+    #[msg("Swap amount of the reposition exceeds the withdrawn amount")]
+    InvalidRepositionSwapAmount,
+
+    // compound, 6040
+    #[msg("Signer is neither the position holder nor its approved operator")]
+    NotApprovedOperator,
+    #[msg("Owed fees are too small to add any liquidity")]
+    FeesTooSmallToCompound,
+
+    // position lock, 6042
+    #[msg("Unlock time must be in the future")]
+    InvalidUnlockTime,
+    #[msg("Position is still locked")]
+    PositionStillLocked,
+
+    // reposition, 6044
+    #[msg("Signer does not hold the nft of the new position")]
+    NewPositionNotHeld,
+
+    // compound, 6045
+    #[msg("Compounded liquidity needs more than the owed fees")]
+    CompoundExceedsOwedFees,
+
+    // reposition, 6046
+    #[msg("Reposition takes either a new position or a fresh nft, not both")]
+    RepositionTargetConflict,
+}
+
+/// Failures of the shared `clmm-math` functions, convert with `.map_err(ErrorCode::from)?`
+impl From<MathError> for ErrorCode {
+    fn from(e: MathError) -> Self {
+        match e {
+            MathError::InvalidSqrtPrice => ErrorCode::InvalidSqrtPrice,
+            MathError::TickLowerOverflow => ErrorCode::TickLowerOverflow,
+            MathError::TickUpperOverflow => ErrorCode::TickUpperOverflow,
+            MathError::InvalidTick => ErrorCode::InvalidTick,
+            MathError::InvalidTickArrayStartIndex => ErrorCode::InvalidTickArrayStartIndex,
+            MathError::MaxTokenOverflow => ErrorCode::MaxTokenOverflow,
+            MathError::LiquidityOverflow => ErrorCode::LiquidityOverflow,
+            MathError::LiquidityZero => ErrorCode::LiquidityZero,
+            MathError::SqrtPriceX64Overflow => ErrorCode::SqrtPriceX64Overflow,
+            MathError::SqrtPriceLimitOverflow => ErrorCode::SqrtPriceLimitOverflow,
+            MathError::InvalidFeeRate => ErrorCode::InvalidFeeRate,
+            MathError::FeeAmountOverflow => ErrorCode::FeeAmountOverflow,
+            MathError::FeeGrowthUnderflow => ErrorCode::FeeGrowthUnderflow,
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use anchor_lang::prelude::*;
+
+/// Emitted when an amm config is created or one of its fields changes
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigChanged {
+    pub amm_config: Pubkey,
+    pub index: u16,
+    pub owner: Pubkey,
+    pub protocol_fee_rate: u32,
+    pub trade_fee_rate: u32,
+    pub fund_fee_rate: u32,
+    pub tick_spacing: u16,
+    pub fund_owner: Pubkey,
+}
+
+/// Emitted when an amm config switches between permissionless and allowlisted pool creation
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatorAllowlistChanged {
+    pub amm_config: Pubkey,
+    pub enabled: bool,
+}
+
+/// Emitted when the admin changes the pool creation fee of an amm config
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatePoolFeeChanged {
+    pub amm_config: Pubkey,
+    pub create_pool_fee: u64,
+    pub create_pool_fee_receiver: Pubkey,
+}
+
+/// Emitted when a creator is added to or removed from the allowlist of an amm config
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolCreatorChanged {
+    pub amm_config: Pubkey,
+    pub creator: Pubkey,
+    pub allowed: bool,
+}
+
+/// Emitted when a pool is created
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolCreated {
+    pub pool_state: Pubkey,
+    pub pool_creator: Pubkey,
+    pub amm_config: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub tick_spacing: u16,
+    // initial price
+    pub sqrt_price_x64: u128,
+    pub tick: i32,
+    pub open_time: u64,
+}
+
+/// Emitted when a personal position is opened, with the liquidity it was opened with
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionOpened {
+    pub pool_state: Pubkey,
+    pub minter: Pubkey,
+    pub nft_owner: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: u128,
+    // amounts credited to the pool, transfer fees excluded
+    pub deposit_amount_0: u64,
+    pub deposit_amount_1: u64,
+    pub deposit_amount_0_transfer_fee: u64,
+    pub deposit_amount_1_transfer_fee: u64,
+}
+
+/// Emitted when liquidity is added to an existing position
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiquidityIncreased {
+    pub pool_state: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub liquidity: u128,
+    // amounts credited to the pool, transfer fees excluded
+    pub amount_0: u64,
+    pub amount_1: u64,
+    pub amount_0_transfer_fee: u64,
+    pub amount_1_transfer_fee: u64,
+}
+
+/// Emitted when liquidity is removed from a position
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiquidityDecreased {
+    pub pool_state: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub liquidity: u128,
+    // amounts debited from the pool, transfer fees included
+    pub decrease_amount_0: u64,
+    pub decrease_amount_1: u64,
+    // fees collected along with the liquidity
+    pub fee_amount_0: u64,
+    pub fee_amount_1: u64,
+    pub transfer_fee_0: u64,
+    pub transfer_fee_1: u64,
+}
+
+/// Emitted when a position's owed fees are paid out
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeesCollected {
+    pub pool_state: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub recipient_token_account_0: Pubkey,
+    pub recipient_token_account_1: Pubkey,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+/// Emitted when a position without liquidity moves to a new tick range, keeping its nft
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionRepositioned {
+    pub pool_state: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+}
+
+/// Emitted when the holder of a position approves or revokes its operator
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionOperatorChanged {
+    pub position_nft_mint: Pubkey,
+    /// The default pubkey when the operator is revoked
+    pub operator: Pubkey,
+}
+
+/// Emitted when owed fees of a position are added to its liquidity, `amount_0` and
+/// `amount_1` are taken from the owed fees
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeesCompounded {
+    pub pool_state: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub liquidity: u128,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+/// Emitted when a position nft moves into escrow and its fee key is minted
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionLocked {
+    pub pool_state: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub fee_key_mint: Pubkey,
+    pub nft_owner: Pubkey,
+    pub unlock_time: Option<i64>,
+}
+
+/// Emitted when the fee key holder takes a position nft back out of escrow
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionUnlocked {
+    pub pool_state: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub nft_owner: Pubkey,
+}
+
+/// Emitted after every swap with the pool state it left behind
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Swap {
+    pub pool_state: Pubkey,
+    pub sender: Pubkey,
+    pub token_account_0: Pubkey,
+    pub token_account_1: Pubkey,
+    // amounts moved between the user and the pool, transfer fees excluded
+    pub amount_0: u64,
+    pub transfer_fee_0: u64,
+    pub amount_1: u64,
+    pub transfer_fee_1: u64,
+    pub zero_for_one: bool,
+    pub sqrt_price_x64: u128,
+    pub liquidity: u128,
+    pub tick: i32,
+}
@@ -0,0 +1,100 @@
+use std::cell::RefMut;
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode,
+    helpers::{modify_position, settle_personal_position_fees},
+    state::{PersonalPositionState, PoolState, ProtocolPositionState, TickArrayState},
+    util::AccountLoad,
+};
+
+/// Remove `liquidity` from the ticks and the protocol position of a range and flip the tick
+/// arrays that lose their last initialized tick. Returns the amounts the liquidity is worth,
+/// rounded down, without transferring them.
+#[allow(clippy::too_many_arguments)]
+pub fn remove_liquidity_from_range<'b, 'info>(
+    tick_array_lower_loader: &'b AccountLoad<'info, TickArrayState>,
+    tick_array_upper_loader: &'b AccountLoad<'info, TickArrayState>,
+    protocol_position: &mut ProtocolPositionState,
+    tick_array_bitmap_extension: Option<&'info AccountInfo<'info>>,
+    pool_state: &mut RefMut<PoolState>,
+    liquidity: u128,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+) -> Result<(u64, u64)> {
+    require!(liquidity > 0, ErrorCode::LiquidityZero);
+    require_keys_eq!(tick_array_lower_loader.load()?.pool_id, pool_state.key()?);
+    require_keys_eq!(tick_array_upper_loader.load()?.pool_id, pool_state.key()?);
+
+    let mut tick_lower_state = *tick_array_lower_loader
+        .load_mut()?
+        .get_tick_state_mut(tick_lower_index, pool_state.tick_spacing)?;
+    let mut tick_upper_state = *tick_array_upper_loader
+        .load_mut()?
+        .get_tick_state_mut(tick_upper_index, pool_state.tick_spacing)?;
+
+    let liquidity_delta = i128::try_from(liquidity).map_err(|_| ErrorCode::LiquidityOverflow)?;
+    let (amount_0, amount_1, flip_tick_lower, flip_tick_upper) = modify_position(
+        -liquidity_delta,
+        pool_state,
+        protocol_position,
+        &mut tick_lower_state,
+        &mut tick_upper_state,
+    )?;
+
+    // ticks without liquidity left were cleared by `update_position`
+    tick_array_lower_loader.load_mut()?.update_tick_state(
+        tick_lower_index,
+        pool_state.tick_spacing,
+        tick_lower_state,
+    )?;
+    tick_array_upper_loader.load_mut()?.update_tick_state(
+        tick_upper_index,
+        pool_state.tick_spacing,
+        tick_upper_state,
+    )?;
+
+    if flip_tick_lower {
+        let mut tick_array_lower = tick_array_lower_loader.load_mut()?;
+        tick_array_lower.update_initialized_tick_count(false)?;
+
+        if tick_array_lower.initialized_tick_count == 0 {
+            pool_state.flip_tick_array_bit(
+                tick_array_bitmap_extension,
+                tick_array_lower.start_tick_index,
+            )?;
+        }
+    }
+
+    if flip_tick_upper {
+        let mut tick_array_upper = tick_array_upper_loader.load_mut()?;
+        tick_array_upper.update_initialized_tick_count(false)?;
+
+        if tick_array_upper.initialized_tick_count == 0 {
+            pool_state.flip_tick_array_bit(
+                tick_array_bitmap_extension,
+                tick_array_upper.start_tick_index,
+            )?;
+        }
+    }
+
+    Ok((amount_0, amount_1))
+}
+
+/// Settle the fees a personal position earned since its last update, then remove `liquidity`
+/// from it
+pub fn remove_personal_position_liquidity(
+    personal_position: &mut PersonalPositionState,
+    protocol_position: &ProtocolPositionState,
+    liquidity: u128,
+) -> Result<()> {
+    settle_personal_position_fees(personal_position, protocol_position)?;
+
+    personal_position.liquidity = personal_position
+        .liquidity
+        .checked_sub(liquidity)
+        .ok_or(ErrorCode::LiquidityUnderflow)?;
+
+    Ok(())
+}
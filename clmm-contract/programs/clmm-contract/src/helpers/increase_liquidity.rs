@@ -2,13 +2,17 @@ use anchor_lang::prelude::*;
 use anchor_spl::{token::Token, token_2022::Token2022, token_interface::Mint};
 
 use crate::{
-    helpers::{add_liquidity, fixed_point_64, U128},
+    error::ErrorCode,
+    events::LiquidityIncreased,
+    helpers::{add_liquidity, calculate_latest_token_fees},
     state::{
-        personal_position, protocol_position, PersonalPositionState, PoolState,
-        ProtocolPositionState, TickArrayBitmapExtension, TickArrayState,
+        PersonalPositionState, PoolState, ProtocolPositionState, TickArrayBitmapExtension,
+        TickArrayState,
     },
+    util::AccountLoad,
 };
 
+#[allow(clippy::too_many_arguments)]
 pub fn increase_liquidity<'b, 'info>(
     nft_owner: &'b Signer<'info>,
     pool_state_loader: &'b AccountLoader<'info, PoolState>,
@@ -21,10 +25,10 @@ pub fn increase_liquidity<'b, 'info>(
     token_vault_0: &'b AccountInfo<'info>,
     token_vault_1: &'b AccountInfo<'info>,
     token_program: &'b Program<'info, Token>,
-    token_program_2022: &'b Program<'info, Token2022>,
+    token_program_2022: Option<&'b Program<'info, Token2022>>,
     vault_0_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
     vault_1_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
-    remaining_account: &'c [AccountInfo<'info>],
+    remaining_accounts: &'info [AccountInfo<'info>],
     liquidity: u128,
     amount_0_max: u64,
     amount_1_max: u64,
@@ -39,8 +43,21 @@ pub fn increase_liquidity<'b, 'info>(
     let use_tickarray_bitmap_extension =
         pool_state.is_overflow_default_tickarray_bitmap(vec![tick_lower, tick_upper]);
 
+    let tick_array_bitmap_extension = if use_tickarray_bitmap_extension {
+        let tick_array_bitmap_extension = remaining_accounts
+            .first()
+            .ok_or(ErrorCode::MissingTickArrayBitmapExtensionAccount)?;
+        require_keys_eq!(
+            tick_array_bitmap_extension.key(),
+            TickArrayBitmapExtension::key(pool_state_loader.key())
+        );
+        Some(tick_array_bitmap_extension)
+    } else {
+        None
+    };
+
     let (amount_0, amount_1, amount_0_transfer_fee, amount_1_transfer_fee) = add_liquidity(
-        &nft_owner,
+        nft_owner,
         token_account_0,
         token_account_1,
         token_vault_0,
@@ -52,15 +69,7 @@ pub fn increase_liquidity<'b, 'info>(
         token_program,
         vault_0_mint,
         vault_1_mint,
-        if use_tickarray_bitmap_extension {
-            require_keys_eq!(
-                remaining_accounts[0].key(),
-                TickArrayBitmapExtension::key(pool_state_loader.key())
-            );
-            Some(&remaining_accounts[0])
-        } else {
-            None
-        },
+        tick_array_bitmap_extension,
         pool_state,
         &mut liquidity,
         amount_0_max,
@@ -70,38 +79,60 @@ pub fn increase_liquidity<'b, 'info>(
         base_flag,
     )?;
 
+    add_personal_position_liquidity(personal_position, protocol_position, liquidity)?;
+
+    emit!(LiquidityIncreased {
+        pool_state: pool_state_loader.key(),
+        position_nft_mint: personal_position.nft_mint,
+        liquidity,
+        amount_0,
+        amount_1,
+        amount_0_transfer_fee,
+        amount_1_transfer_fee,
+    });
+
+    Ok(())
+}
+
+/// Settle the fees a personal position earned since its last update, then add `liquidity` to it
+pub fn add_personal_position_liquidity(
+    personal_position: &mut PersonalPositionState,
+    protocol_position: &ProtocolPositionState,
+    liquidity: u128,
+) -> Result<()> {
+    settle_personal_position_fees(personal_position, protocol_position)?;
+
+    personal_position.liquidity = personal_position
+        .liquidity
+        .checked_add(liquidity)
+        .ok_or(ErrorCode::LiquidityOverflow)?;
+
+    Ok(())
+}
+
+/// Add the fees a personal position earned since its last update to its owed fees. The
+/// protocol position must have been updated first.
+pub fn settle_personal_position_fees(
+    personal_position: &mut PersonalPositionState,
+    protocol_position: &ProtocolPositionState,
+) -> Result<()> {
     personal_position.token_fees_owed_0 = calculate_latest_token_fees(
         personal_position.token_fees_owed_0,
         personal_position.fee_growth_inside_0_last_x64,
         protocol_position.fee_growth_inside_0_last_x64,
         personal_position.liquidity,
-    );
+    )
+    .map_err(ErrorCode::from)?;
     personal_position.token_fees_owed_1 = calculate_latest_token_fees(
         personal_position.token_fees_owed_1,
         personal_position.fee_growth_inside_1_last_x64,
         protocol_position.fee_growth_inside_1_last_x64,
         personal_position.liquidity,
-    );
+    )
+    .map_err(ErrorCode::from)?;
 
     personal_position.fee_growth_inside_0_last_x64 = protocol_position.fee_growth_inside_0_last_x64;
     personal_position.fee_growth_inside_1_last_x64 = protocol_position.fee_growth_inside_1_last_x64;
 
-    personal_position.liquidity = personal_position.liquidity.checked_add(liquidity).unwrap();
-
     Ok(())
 }
-
-pub fn calculate_latest_token_fees(
-    last_total_fees: u64,
-    fee_growth_inside_last_x64: u128,
-    fee_growth_inside_latest_x64: u128,
-    liquidity: u128,
-) -> u64 {
-    let fee_growth_delta =
-        U128::from(fee_growth_inside_latest_x64.wrapping_sub(fee_growth_inside_last_x64))
-            .mul_div_floor(U128::from(liquidity), U128::from(fixed_point_64::Q64))
-            .unwrap()
-            .to_underflow_u64();
-
-    last_total_fees.checked_add(fee_growth_delta).unwrap()
-}
@@ -1,5 +1,17 @@
-pub mod tick;
-pub use tick::*;
+pub use clmm_math::{
+    big_nums, bit_math, fee_math, fixed_point_64, full_math, liquidity_math, sqrt_price_math,
+    swap_math, tick_math, unsafe_math,
+};
+
+pub use big_nums::*;
+pub use bit_math::*;
+pub use fee_math::*;
+pub use full_math::*;
+pub use liquidity_math::*;
+pub use sqrt_price_math::*;
+pub use swap_math::*;
+pub use tick_math::*;
+pub use unsafe_math::*;
 
 pub mod token;
 pub use token::*;
@@ -10,11 +22,17 @@ pub use tick_array_bit_map::*;
 pub mod add_liquidity;
 pub use add_liquidity::*;
 
-pub mod big_nums;
-pub use big_nums::*;
+pub mod increase_liquidity;
+pub use increase_liquidity::*;
 
-pub mod fixed_point_64;
-pub use fixed_point_64::*;
+pub mod open_position;
+pub use open_position::*;
 
-pub mod increase_liquidity;
-pub use increase_liquidity::*;
\ No newline at end of file
+pub mod swap;
+pub use swap::*;
+
+pub mod decrease_liquidity;
+pub use decrease_liquidity::*;
+
+pub mod zap;
+pub use zap::*;
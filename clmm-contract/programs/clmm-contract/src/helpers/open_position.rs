@@ -0,0 +1,241 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::program::invoke,
+    system_program::{create_account, CreateAccount},
+};
+use anchor_spl::{
+    associated_token::{create, AssociatedToken, Create},
+    token_2022::{
+        initialize_mint2,
+        spl_token_2022::{
+            self,
+            extension::{metadata_pointer, ExtensionType},
+            instruction::initialize_mint_close_authority,
+        },
+        InitializeMint2, Token2022,
+    },
+};
+
+use crate::{
+    error::ErrorCode,
+    helpers::check_tick_array_start_index,
+    state::{PersonalPositionState, PoolState, ProtocolPositionState, TickArrayState},
+    util::AccountLoad,
+};
+
+/// Create the position nft mint with its Token-2022 extensions, the pool is the mint authority
+/// and the personal position may close the mint
+pub fn create_position_nft_mint_with_extensions<'info>(
+    payer: &Signer<'info>,
+    position_nft_mint: &AccountInfo<'info>,
+    mint_authority: &AccountInfo<'info>,
+    mint_close_authority: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    token_2022_program: &Program<'info, Token2022>,
+    with_metadata: bool,
+) -> Result<()> {
+    let extensions = if with_metadata {
+        [
+            ExtensionType::MintCloseAuthority,
+            ExtensionType::MetadataPointer,
+        ]
+        .to_vec()
+    } else {
+        [ExtensionType::MintCloseAuthority].to_vec()
+    };
+    let space =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&extensions)?;
+    let lamports = Rent::get()?.minimum_balance(space);
+
+    // create MINT ACCOUNT
+    create_account(
+        CpiContext::new(
+            system_program.to_account_info(),
+            CreateAccount {
+                from: payer.to_account_info(),
+                to: position_nft_mint.to_account_info(),
+            },
+        ),
+        lamports,
+        u64::try_from(space).map_err(|_| ErrorCode::MaxTokenOverflow)?,
+        token_2022_program.key,
+    )?;
+
+    // Initialize token extensions
+    for e in extensions {
+        match e {
+            ExtensionType::MetadataPointer => {
+                let ix = metadata_pointer::instruction::initialize(
+                    token_2022_program.key,
+                    position_nft_mint.key,
+                    None,
+                    Some(position_nft_mint.key()),
+                )?;
+                invoke(
+                    &ix,
+                    &[
+                        token_2022_program.to_account_info(),
+                        position_nft_mint.to_account_info(),
+                    ],
+                )?;
+            }
+            ExtensionType::MintCloseAuthority => {
+                let ix = initialize_mint_close_authority(
+                    token_2022_program.key,
+                    position_nft_mint.key,
+                    Some(mint_close_authority.key),
+                )?;
+                invoke(
+                    &ix,
+                    &[
+                        token_2022_program.to_account_info(),
+                        position_nft_mint.to_account_info(),
+                    ],
+                )?;
+            }
+            _ => {
+                return err!(ErrorCode::InvalidExtensionType);
+            }
+        }
+    }
+
+    initialize_mint2(
+        CpiContext::new(
+            token_2022_program.to_account_info(),
+            InitializeMint2 {
+                mint: position_nft_mint.to_account_info(),
+            },
+        ),
+        0,
+        mint_authority.key,
+        None,
+    )
+}
+
+/// Create the position nft mint, see [`create_position_nft_mint_with_extensions`], and the
+/// associated token account of `position_nft_owner` the nft is minted to
+#[allow(clippy::too_many_arguments)]
+pub fn create_position_nft<'info>(
+    payer: &Signer<'info>,
+    position_nft_owner: &AccountInfo<'info>,
+    position_nft_mint: &AccountInfo<'info>,
+    position_nft_account: &AccountInfo<'info>,
+    pool_state: &AccountInfo<'info>,
+    personal_position: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    token_program_2022: &Program<'info, Token2022>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+    with_metadata: bool,
+) -> Result<()> {
+    create_position_nft_mint_with_extensions(
+        payer,
+        position_nft_mint,
+        pool_state,
+        personal_position,
+        system_program,
+        token_program_2022,
+        with_metadata,
+    )?;
+    create(CpiContext::new(
+        associated_token_program.to_account_info(),
+        Create {
+            payer: payer.to_account_info(),
+            associated_token: position_nft_account.clone(),
+            authority: position_nft_owner.clone(),
+            mint: position_nft_mint.clone(),
+            system_program: system_program.to_account_info(),
+            token_program: token_program_2022.to_account_info(),
+        },
+    ))
+}
+
+/// Check the tick order and the tick array start indexes of the range from `tick_lower_index`
+/// to `tick_upper_index`, create its tick arrays if needed and initialize its protocol position
+/// when new, which also sets the ticks of the range. Returns the lower and upper tick array.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_range<'info>(
+    payer: &AccountInfo<'info>,
+    pool_state_loader: &AccountLoader<'info, PoolState>,
+    tick_array_lower: &AccountInfo<'info>,
+    tick_array_upper: &AccountInfo<'info>,
+    protocol_position: &mut ProtocolPositionState,
+    protocol_position_bump: u8,
+    system_program: &AccountInfo<'info>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+) -> Result<(
+    AccountLoad<'info, TickArrayState>,
+    AccountLoad<'info, TickArrayState>,
+)> {
+    require!(
+        tick_lower_index < tick_upper_index,
+        ErrorCode::InvalidTickOrder
+    );
+    let tick_spacing = pool_state_loader.load()?.tick_spacing;
+    check_tick_array_start_index(tick_array_lower_start_index, tick_lower_index, tick_spacing)
+        .map_err(ErrorCode::from)?;
+    check_tick_array_start_index(tick_array_upper_start_index, tick_upper_index, tick_spacing)
+        .map_err(ErrorCode::from)?;
+
+    let tick_array_lower_loader = TickArrayState::get_or_create_tick_array(
+        payer.clone(),
+        tick_array_lower.clone(),
+        tick_array_lower_start_index,
+        tick_spacing,
+        pool_state_loader,
+        system_program.clone(),
+    )?;
+    let tick_array_upper_loader = if tick_array_lower_start_index == tick_array_upper_start_index {
+        AccountLoad::<TickArrayState>::try_from(tick_array_upper)?
+    } else {
+        TickArrayState::get_or_create_tick_array(
+            payer.clone(),
+            tick_array_upper.clone(),
+            tick_array_upper_start_index,
+            tick_spacing,
+            pool_state_loader,
+            system_program.clone(),
+        )?
+    };
+
+    if protocol_position.pool_id == Pubkey::default() {
+        protocol_position.bump = protocol_position_bump;
+        protocol_position.pool_id = pool_state_loader.key();
+        protocol_position.tick_lower_index = tick_lower_index;
+        protocol_position.tick_upper_index = tick_upper_index;
+        protocol_position.version = ProtocolPositionState::VERSION;
+
+        tick_array_lower_loader
+            .load_mut()?
+            .get_tick_state_mut(tick_lower_index, tick_spacing)?
+            .tick = tick_lower_index;
+        tick_array_upper_loader
+            .load_mut()?
+            .get_tick_state_mut(tick_upper_index, tick_spacing)?
+            .tick = tick_upper_index;
+    }
+
+    Ok((tick_array_lower_loader, tick_array_upper_loader))
+}
+
+/// Initialize a new personal position holding `liquidity` of the range of `protocol_position`,
+/// starting from the fee growth the protocol position has now
+pub fn init_personal_position(
+    personal_position: &mut PersonalPositionState,
+    bump: u8,
+    nft_mint: Pubkey,
+    protocol_position: &ProtocolPositionState,
+    liquidity: u128,
+) {
+    personal_position.bump = [bump];
+    personal_position.nft_mint = nft_mint;
+    personal_position.pool_id = protocol_position.pool_id;
+    personal_position.tick_lower_index = protocol_position.tick_lower_index;
+    personal_position.tick_upper_index = protocol_position.tick_upper_index;
+    personal_position.fee_growth_inside_0_last_x64 = protocol_position.fee_growth_inside_0_last_x64;
+    personal_position.fee_growth_inside_1_last_x64 = protocol_position.fee_growth_inside_1_last_x64;
+    personal_position.liquidity = liquidity;
+    personal_position.version = PersonalPositionState::VERSION;
+}
@@ -0,0 +1,267 @@
+use std::{cell::RefMut, collections::VecDeque};
+
+use anchor_lang::prelude::*;
+use clmm_math::MathError;
+
+use crate::{
+    error::ErrorCode,
+    helpers::{
+        compute_swap, SwapFeeRates, SwapOutcome, SwapState, SwapTicks, MAX_SQRT_PRICE_X64,
+        MIN_SQRT_PRICE_X64,
+    },
+    state::{AmmConfig, PoolState, TickArrayBitmapExtension, TickArrayState},
+};
+
+/// Swap `amount_specified` against the liquidity of the pool, the exact input when
+/// `is_base_input` and the exact output otherwise. Moves the price, crosses ticks and books the
+/// trade fee on the pool, tokens are not transferred.
+///
+/// `tick_array_states` are the tick arrays the swap walks through in swap order, starting with the
+/// first initialized one in the swap direction. A `sqrt_price_limit_x64` of 0 lets the swap move
+/// the price to the end of the range. Returns the input taken, trade fee included, and the output.
+#[allow(clippy::too_many_arguments)]
+pub fn swap_internal(
+    amm_config: &AmmConfig,
+    pool_state: &mut RefMut<PoolState>,
+    tick_array_states: &mut VecDeque<RefMut<TickArrayState>>,
+    tickarray_bitmap_extension: Option<&TickArrayBitmapExtension>,
+    amount_specified: u64,
+    sqrt_price_limit_x64: u128,
+    zero_for_one: bool,
+    is_base_input: bool,
+    block_timestamp: u64,
+) -> Result<(u64, u64)> {
+    require!(amount_specified != 0, ErrorCode::ZeroAmountSpecified);
+    require!(
+        block_timestamp > pool_state.open_time,
+        ErrorCode::PoolNotOpen
+    );
+    let pool_id = pool_state.key()?;
+
+    let sqrt_price_limit_x64 = match sqrt_price_limit_x64 {
+        0 if zero_for_one => MIN_SQRT_PRICE_X64 + 1,
+        0 => MAX_SQRT_PRICE_X64 - 1,
+        limit => limit,
+    };
+    let is_valid_limit = if zero_for_one {
+        sqrt_price_limit_x64 < pool_state.sqrt_price_x64
+            && sqrt_price_limit_x64 > MIN_SQRT_PRICE_X64
+    } else {
+        sqrt_price_limit_x64 > pool_state.sqrt_price_x64
+            && sqrt_price_limit_x64 < MAX_SQRT_PRICE_X64
+    };
+    require!(is_valid_limit, ErrorCode::SqrtPriceLimitOverflow);
+
+    let (is_match_pool_current_tick_array, first_tick_array_start_index) =
+        pool_state.get_first_initialized_tick_array(tickarray_bitmap_extension, zero_for_one)?;
+    let tick_array_current =
+        next_tick_array(tick_array_states, pool_id, first_tick_array_start_index)?;
+
+    let fee_growth_global_x64 = if zero_for_one {
+        pool_state.fee_growth_global_0_x64
+    } else {
+        pool_state.fee_growth_global_1_x64
+    };
+    let state = SwapState {
+        sqrt_price_x64: pool_state.sqrt_price_x64,
+        tick: pool_state.tick_current,
+        liquidity: pool_state.liquidity,
+        fee_growth_global_x64,
+    };
+    let fee_rates = SwapFeeRates {
+        trade_fee_rate: amm_config.trade_fee_rate,
+        protocol_fee_rate: amm_config.protocol_fee_rate,
+        fund_fee_rate: amm_config.fund_fee_rate,
+    };
+    let mut ticks = TickArrayTicks {
+        pool_id,
+        pool_state,
+        tickarray_bitmap_extension,
+        tick_array_states,
+        tick_array_current,
+        is_match_pool_current_tick_array,
+    };
+    let SwapOutcome {
+        state,
+        amount_in,
+        amount_out,
+        protocol_fee,
+        fund_fee,
+        lp_fee,
+        ..
+    } = compute_swap(
+        &mut ticks,
+        state,
+        fee_rates,
+        amount_specified,
+        sqrt_price_limit_x64,
+        zero_for_one,
+        is_base_input,
+    )
+    .map_err(|SwapError(error)| error)?;
+    let SwapState {
+        sqrt_price_x64,
+        tick,
+        liquidity,
+        fee_growth_global_x64,
+    } = state;
+
+    pool_state.sqrt_price_x64 = sqrt_price_x64;
+    pool_state.tick_current = tick;
+    pool_state.liquidity = liquidity;
+    if zero_for_one {
+        pool_state.fee_growth_global_0_x64 = fee_growth_global_x64;
+        pool_state.protocol_fees_token_0 = pool_state
+            .protocol_fees_token_0
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::FeeAmountOverflow)?;
+        pool_state.fund_fees_token_0 = pool_state
+            .fund_fees_token_0
+            .checked_add(fund_fee)
+            .ok_or(ErrorCode::FeeAmountOverflow)?;
+        pool_state.total_fees_token_0 = pool_state
+            .total_fees_token_0
+            .checked_add(lp_fee)
+            .ok_or(ErrorCode::FeeAmountOverflow)?;
+        pool_state.swap_in_amount_token_0 = pool_state
+            .swap_in_amount_token_0
+            .checked_add(u128::from(amount_in))
+            .ok_or(ErrorCode::MaxTokenOverflow)?;
+        pool_state.swap_out_amount_token_1 = pool_state
+            .swap_out_amount_token_1
+            .checked_add(u128::from(amount_out))
+            .ok_or(ErrorCode::MaxTokenOverflow)?;
+    } else {
+        pool_state.fee_growth_global_1_x64 = fee_growth_global_x64;
+        pool_state.protocol_fees_token_1 = pool_state
+            .protocol_fees_token_1
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::FeeAmountOverflow)?;
+        pool_state.fund_fees_token_1 = pool_state
+            .fund_fees_token_1
+            .checked_add(fund_fee)
+            .ok_or(ErrorCode::FeeAmountOverflow)?;
+        pool_state.total_fees_token_1 = pool_state
+            .total_fees_token_1
+            .checked_add(lp_fee)
+            .ok_or(ErrorCode::FeeAmountOverflow)?;
+        pool_state.swap_in_amount_token_1 = pool_state
+            .swap_in_amount_token_1
+            .checked_add(u128::from(amount_in))
+            .ok_or(ErrorCode::MaxTokenOverflow)?;
+        pool_state.swap_out_amount_token_0 = pool_state
+            .swap_out_amount_token_0
+            .checked_add(u128::from(amount_out))
+            .ok_or(ErrorCode::MaxTokenOverflow)?;
+    }
+
+    Ok((amount_in, amount_out))
+}
+
+/// Take the next tick array of the swap, which must be the one of the pool starting at
+/// `start_tick_index`
+fn next_tick_array<'a>(
+    tick_array_states: &mut VecDeque<RefMut<'a, TickArrayState>>,
+    pool_id: Pubkey,
+    start_tick_index: i32,
+) -> Result<RefMut<'a, TickArrayState>> {
+    let tick_array = tick_array_states
+        .pop_front()
+        .ok_or(ErrorCode::NotEnoughTickArrayAccount)?;
+    require_keys_eq!(tick_array.pool_id, pool_id);
+    require!(
+        tick_array.start_tick_index == start_tick_index,
+        ErrorCode::NotEnoughTickArrayAccount
+    );
+    Ok(tick_array)
+}
+
+/// Error of [`compute_swap`], which needs an error type math errors convert into
+struct SwapError(Error);
+
+impl From<Error> for SwapError {
+    fn from(error: Error) -> Self {
+        SwapError(error)
+    }
+}
+
+impl From<MathError> for SwapError {
+    fn from(error: MathError) -> Self {
+        SwapError(ErrorCode::from(error).into())
+    }
+}
+
+/// The initialized ticks of the tick arrays passed to the swap
+struct TickArrayTicks<'a, 'b> {
+    pool_id: Pubkey,
+    pool_state: &'a PoolState,
+    tickarray_bitmap_extension: Option<&'a TickArrayBitmapExtension>,
+    tick_array_states: &'a mut VecDeque<RefMut<'b, TickArrayState>>,
+    tick_array_current: RefMut<'b, TickArrayState>,
+    is_match_pool_current_tick_array: bool,
+}
+
+impl SwapTicks for TickArrayTicks<'_, '_> {
+    type Error = SwapError;
+
+    fn next_initialized_tick(
+        &mut self,
+        tick: i32,
+        zero_for_one: bool,
+    ) -> std::result::Result<Option<i32>, SwapError> {
+        let tick_spacing = self.pool_state.tick_spacing;
+        if let Some(tick_state) =
+            self.tick_array_current
+                .next_initialized_tick(tick, tick_spacing, zero_for_one)?
+        {
+            return Ok(Some(tick_state.tick));
+        }
+        // the pool's current tick array is not initialized, so the swap starts from the first
+        // initialized tick of the next one
+        if !self.is_match_pool_current_tick_array {
+            self.is_match_pool_current_tick_array = true;
+            return Ok(Some(
+                self.tick_array_current
+                    .first_initialized_tick(zero_for_one)?
+                    .tick,
+            ));
+        }
+
+        let next_start_index = self
+            .pool_state
+            .next_initialized_tick_array_start_index(
+                self.tickarray_bitmap_extension,
+                self.tick_array_current.start_tick_index,
+                zero_for_one,
+            )?
+            .ok_or(error!(ErrorCode::InsufficientLiquidityForDirection))?;
+        self.tick_array_current =
+            next_tick_array(self.tick_array_states, self.pool_id, next_start_index)?;
+        Ok(Some(
+            self.tick_array_current
+                .first_initialized_tick(zero_for_one)?
+                .tick,
+        ))
+    }
+
+    fn cross_tick(
+        &mut self,
+        tick: i32,
+        fee_growth_global_x64: u128,
+        zero_for_one: bool,
+    ) -> std::result::Result<i128, SwapError> {
+        let pool_state = self.pool_state;
+        let (fee_growth_global_0_x64, fee_growth_global_1_x64) = if zero_for_one {
+            (fee_growth_global_x64, pool_state.fee_growth_global_1_x64)
+        } else {
+            (pool_state.fee_growth_global_0_x64, fee_growth_global_x64)
+        };
+        let mut tick_state = *self
+            .tick_array_current
+            .get_tick_state_mut(tick, pool_state.tick_spacing)?;
+        let liquidity_net = tick_state.cross(fee_growth_global_0_x64, fee_growth_global_1_x64);
+        self.tick_array_current
+            .update_tick_state(tick, pool_state.tick_spacing, tick_state)?;
+        Ok(liquidity_net)
+    }
+}
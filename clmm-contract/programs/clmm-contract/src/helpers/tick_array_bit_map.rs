@@ -1,8 +1,11 @@
+use crate::{
+    error::ErrorCode,
+    helpers::{is_set, next_set_bit, prev_set_bit},
+    state::{TickArrayState, TickState, TICK_ARRAY_SIZE},
+};
 use anchor_lang::prelude::*;
-use crate::state::TICK_ARRAY_SIZE;
-
-pub const TICK_ARRAY_BITMAP_SIZE: i32 = 8*64;
 
+pub const TICK_ARRAY_BITMAP_SIZE: i32 = 8 * 64;
 
 pub fn max_tick_in_tickarray_bitmap(tick_spacing: u16) -> i32 {
     i32::from(tick_spacing) * TICK_ARRAY_SIZE * TICK_ARRAY_BITMAP_SIZE
@@ -15,9 +18,89 @@ pub fn get_bitmap_tick_boundary(tick_array_start_index: i32, tick_spacing: u16)
         m += 1;
     }
     let min_value: i32 = ticks_in_one_bitmap * m;
-    if tick_array_start_index < 0 { 
+    if tick_array_start_index < 0 {
         (-min_value, -min_value + ticks_in_one_bitmap)
     } else {
         (min_value, min_value + ticks_in_one_bitmap)
     }
-}
\ No newline at end of file
+}
+
+/// Given a tick, check whether the tick array containing it is initialized in the default bitmap.
+/// Returns the start index of that tick array either way.
+pub fn check_current_tick_array_is_initialized(
+    bit_map: [u64; 16],
+    tick_current: i32,
+    tick_spacing: u16,
+) -> Result<(bool, i32)> {
+    if TickState::check_is_out_of_boundary(tick_current) {
+        return err!(ErrorCode::InvalidTickIndex);
+    }
+    let multiplier = TickArrayState::tick_count(tick_spacing);
+    let mut compressed = tick_current / multiplier + TICK_ARRAY_BITMAP_SIZE;
+    if tick_current < 0 && tick_current % multiplier != 0 {
+        // round towards negative infinity
+        compressed -= 1;
+    }
+    let bit_pos = compressed.abs();
+    let initialized = is_set(&bit_map, bit_pos as usize);
+    Ok((
+        initialized,
+        (compressed - TICK_ARRAY_BITMAP_SIZE) * multiplier,
+    ))
+}
+
+/// Search the default bitmap for the next initialized tick array after `last_tick_array_start_index`
+/// in the swap direction. When nothing is found, the boundary of the default bitmap is returned
+/// so the search can continue in the bitmap extension.
+pub fn next_initialized_tick_array_start_index(
+    bit_map: [u64; 16],
+    last_tick_array_start_index: i32,
+    tick_spacing: u16,
+    zero_for_one: bool,
+) -> (bool, i32) {
+    assert!(TickArrayState::check_is_valid_start_index(
+        last_tick_array_start_index,
+        tick_spacing
+    ));
+    let tick_boundary = max_tick_in_tickarray_bitmap(tick_spacing);
+    let multiplier = TickArrayState::tick_count(tick_spacing);
+    let next_tick_array_start_index = if zero_for_one {
+        last_tick_array_start_index - multiplier
+    } else {
+        last_tick_array_start_index + multiplier
+    };
+
+    if next_tick_array_start_index < -tick_boundary || next_tick_array_start_index >= tick_boundary
+    {
+        return (false, last_tick_array_start_index);
+    }
+
+    let mut compressed = next_tick_array_start_index / multiplier + TICK_ARRAY_BITMAP_SIZE;
+    if next_tick_array_start_index < 0 && next_tick_array_start_index % multiplier != 0 {
+        // round towards negative infinity
+        compressed -= 1;
+    }
+    let bit_pos = compressed.abs();
+
+    if zero_for_one {
+        // tick from upper to lower, find from higher bits to lower bits
+        match prev_set_bit(&bit_map, bit_pos as usize) {
+            Some(next_bit) => (
+                true,
+                (next_bit as i32 - TICK_ARRAY_BITMAP_SIZE) * multiplier,
+            ),
+            // not found til to the end
+            None => (false, -tick_boundary),
+        }
+    } else {
+        // tick from lower to upper, find from lower bits to higher bits
+        match next_set_bit(&bit_map, bit_pos as usize) {
+            Some(next_bit) => (
+                true,
+                (next_bit as i32 - TICK_ARRAY_BITMAP_SIZE) * multiplier,
+            ),
+            // not found til to the end
+            None => (false, tick_boundary - multiplier),
+        }
+    }
+}
@@ -1,12 +1,16 @@
-use std::fs::Metadata;
-
 use anchor_lang::{
+    error::ErrorCode as AnchorErrorCode,
     prelude::*,
-    system_program::{allocate, assign, Allocate, Assign, CreateAccount, Transfer},
+    solana_program::program::invoke_signed,
+    system_program::{
+        self, allocate, assign, create_account, Allocate, Assign, CreateAccount, Transfer,
+    },
 };
 use anchor_spl::{
     metadata::{
-        create_metadata_accounts_v3, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3,
+        create_metadata_accounts_v3,
+        mpl_token_metadata::types::{Creator, DataV2},
+        CreateMetadataAccountsV3, Metadata,
     },
     token::{self, Token},
     token_2022::{
@@ -15,10 +19,9 @@ use anchor_spl::{
             self,
             extension::{
                 transfer_fee::{TransferFeeConfig, MAX_FEE_BASIS_POINTS},
-                BaseStateWithExtensions,
-                ExtensionType::ImmutableOwner,
-                StateWithExtensions,
+                BaseStateWithExtensions, StateWithExtensions,
             },
+            instruction::AuthorityType,
         },
         GetAccountDataSize, InitializeAccount3, Token2022,
     },
@@ -26,6 +29,7 @@ use anchor_spl::{
 };
 
 use crate::{
+    error::ErrorCode,
     state::{PersonalPositionState, PoolState},
     util::get_recent_epoch,
 };
@@ -55,7 +59,7 @@ pub fn create_or_allocate_account<'a>(
         create_account(
             cpi_context.with_signer(&[signer_seed]),
             lamports,
-            u64::try_from(space).unwrap(),
+            u64::try_from(space).map_err(|_| ErrorCode::MaxTokenOverflow)?,
             program_id,
         )?;
     } else {
@@ -79,7 +83,7 @@ pub fn create_or_allocate_account<'a>(
         );
         allocate(
             cpi_context.with_signer(&[signer_seed]),
-            u64::try_from(space).unwrap(),
+            u64::try_from(space).map_err(|_| ErrorCode::MaxTokenOverflow)?,
         )?;
 
         let cpi_context2 = CpiContext::new(
@@ -88,10 +92,7 @@ pub fn create_or_allocate_account<'a>(
                 account_to_assign: target_account.clone(),
             },
         );
-        assign(
-            cpi_context2.with_signer(&[signer_seed]),
-            u64::try_from(space).unwrap(),
-        )?;
+        assign(cpi_context2.with_signer(&[signer_seed]), program_id)?;
     }
     Ok(())
 }
@@ -105,7 +106,6 @@ pub fn create_token_vault_account<'info>(
     token_2022_program: &Interface<'info, TokenInterface>,
     signer_seeds: &[&[u8]],
 ) -> Result<()> {
-    let immutable_owner_required = false;
     // support both spl_token_program and token_program_2022
     let cpi_context = CpiContext::new(
         token_2022_program.to_account_info(),
@@ -114,8 +114,6 @@ pub fn create_token_vault_account<'info>(
         },
     );
 
-    let extension_type = ImmutableOwner;
-
     let space = get_account_data_size(cpi_context, &[])?;
 
     create_or_allocate_account(
@@ -124,7 +122,7 @@ pub fn create_token_vault_account<'info>(
         system_program.to_account_info(),
         token_account.to_account_info(),
         signer_seeds,
-        space.try_into().unwrap(),
+        usize::try_from(space).map_err(|_| ErrorCode::MaxTokenOverflow)?,
     )?;
 
     // call initializeAccount3
@@ -135,14 +133,11 @@ pub fn create_token_vault_account<'info>(
             mint: token_mint.to_account_info(),
             authority: pool_state.to_account_info(),
         },
-    ))?;
+    ))
 }
 
 // fee for input amount
-pub fn get_transfer_fee(
-    mint_account: Box<InterfaceAccount<Mint>>,
-    pre_fee_amount: u64,
-) -> Result<u64> {
+pub fn get_transfer_fee(mint_account: &InterfaceAccount<Mint>, pre_fee_amount: u64) -> Result<u64> {
     let mint_info = mint_account.to_account_info();
     if *mint_info.owner == Token::id() {
         return Ok(0);
@@ -151,10 +146,10 @@ pub fn get_transfer_fee(
     let mint_data = mint_info.try_borrow_data()?;
     let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
 
-    let fee = if let transfer_fee_config = mint.get_extension::<TransferFeeConfig>()? {
+    let fee = if let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() {
         transfer_fee_config
             .calculate_epoch_fee(get_recent_epoch()?, pre_fee_amount)
-            .unwrap()
+            .ok_or(ErrorCode::TransferFeeOverflow)?
     } else {
         0
     };
@@ -163,7 +158,7 @@ pub fn get_transfer_fee(
 }
 
 pub fn get_transfer_inverse_fee(
-    mint_account: Box<InterfaceAccount<Mint>>,
+    mint_account: &InterfaceAccount<Mint>,
     post_fee_amount: u64,
 ) -> Result<u64> {
     let mint_info = mint_account.to_account_info();
@@ -182,10 +177,13 @@ pub fn get_transfer_inverse_fee(
         } else {
             let transfer_fee = transfer_fee_config
                 .calculate_inverse_epoch_fee(epoch, post_fee_amount)
-                .unwrap();
+                .ok_or(ErrorCode::TransferFeeOverflow)?;
+            let pre_fee_amount = post_fee_amount
+                .checked_add(transfer_fee)
+                .ok_or(ErrorCode::TransferFeeOverflow)?;
             let transfer_fee_for_check = transfer_fee_config
-                .calculate_epoch_fee(epoch, post_fee_amount.checked_add(transfer_fee).unwrap())
-                .unwrap();
+                .calculate_epoch_fee(epoch, pre_fee_amount)
+                .ok_or(ErrorCode::TransferFeeOverflow)?;
             if transfer_fee != transfer_fee_for_check {
                 return err!(ErrorCode::TransferFeeCalculateNotMatch);
             }
@@ -244,23 +242,77 @@ pub fn transfer_from_user_to_pool_vault<'info>(
     }
 }
 
+/// Transfer `amount` out of a pool vault, signed by the pool
+pub fn transfer_from_pool_vault_to_user<'info>(
+    pool_state_loader: &AccountLoader<'info, PoolState>,
+    from_vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    token_program: &AccountInfo<'info>,
+    token_program_2022: Option<AccountInfo<'info>>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let pool_state = pool_state_loader.load()?;
+    let seeds = pool_state.seeds();
+    let mut token_program_info = token_program.to_account_info();
+    let from_vault_info = from_vault.to_account_info();
+    match (mint, token_program_2022) {
+        (Some(mint), Some(token_program_2022)) => {
+            if from_vault_info.owner == token_program_2022.key {
+                token_program_info = token_program_2022.to_account_info()
+            }
+            token_2022::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program_info,
+                    token_2022::TransferChecked {
+                        from: from_vault_info,
+                        to: to.to_account_info(),
+                        authority: pool_state_loader.to_account_info(),
+                        mint: mint.to_account_info(),
+                    },
+                    &[&seeds],
+                ),
+                amount,
+                mint.decimals,
+            )
+        }
+        _ => token::transfer(
+            CpiContext::new_with_signer(
+                token_program_info,
+                token::Transfer {
+                    from: from_vault_info,
+                    to: to.to_account_info(),
+                    authority: pool_state_loader.to_account_info(),
+                },
+                &[&seeds],
+            ),
+            amount,
+        ),
+    }
+}
+
 fn get_metadata_data(personal_position_id: Pubkey) -> (String, String, String) {
-    return (
+    (
         String::from("Raydium Concentrated Liquidity"),
         String::from("RCL"),
         format!(
             "https://dynamic-ipfs.raydium.io/clmm/position?id={}",
-            personal_position_id.to_string()
+            personal_position_id
         ),
-    );
+    )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn initialize_token_metadata_extension<'info>(
     payer: &Signer<'info>,
     position_nft_mint: &AccountInfo<'info>,
     mint_authority: &AccountInfo<'info>,
     metadata_update_authority: &AccountInfo<'info>,
     token_2022_program: &Program<'info, Token2022>,
+    system_program: &Program<'info, System>,
     name: String,
     symbol: String,
     uri: String,
@@ -284,15 +336,15 @@ pub fn initialize_token_metadata_extension<'info>(
     drop(mint_data);
 
     let cpi_context = CpiContext::new(
-        token_2022_program.to_account_info(),
+        system_program.to_account_info(),
         Transfer {
             from: payer.to_account_info(),
             to: position_nft_mint.to_account_info(),
         },
     );
-    transfer(cpi_context, additional_lamports)?;
+    system_program::transfer(cpi_context, additional_lamports)?;
 
-    solana_program::program::invoke_signed(
+    invoke_signed(
         &spl_token_metadata_interface::instruction::initialize(
             token_2022_program.key,
             position_nft_mint.key,
@@ -315,6 +367,7 @@ pub fn initialize_token_metadata_extension<'info>(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn initialize_metadata_account<'info>(
     payer: &Signer<'info>,
     authority: &AccountInfo<'info>,
@@ -362,6 +415,7 @@ fn initialize_metadata_account<'info>(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn mint_nft_and_remove_mint_authority<'info>(
     payer: &Signer<'info>,
     pool_state_loader: &AccountLoader<'info, PoolState>,
@@ -385,7 +439,9 @@ pub fn mint_nft_and_remove_mint_authority<'info>(
     let token_program_info = if position_nft_mint_info.owner == token_program.key {
         token_program.to_account_info()
     } else {
-        token_program_2022.unwrap().to_account_info()
+        token_program_2022
+            .ok_or(error!(AnchorErrorCode::AccountNotEnoughKeys))?
+            .to_account_info()
     };
 
     if with_metadata {
@@ -396,7 +452,8 @@ pub fn mint_nft_and_remove_mint_authority<'info>(
                 &position_nft_mint_info,
                 &pool_state_info,
                 &personal_position.to_account_info(),
-                token_program_2022.unwrap(),
+                token_program_2022.ok_or(error!(AnchorErrorCode::AccountNotEnoughKeys))?,
+                system_program,
                 name,
                 symbol,
                 uri,
@@ -407,8 +464,8 @@ pub fn mint_nft_and_remove_mint_authority<'info>(
                 payer,
                 &pool_state_info,
                 &position_nft_mint_info,
-                metadata_account.unwrap(),
-                metadata_program.unwrap(),
+                metadata_account.ok_or(error!(AnchorErrorCode::AccountNotEnoughKeys))?,
+                metadata_program.ok_or(error!(AnchorErrorCode::AccountNotEnoughKeys))?,
                 system_program,
                 rent,
                 name,
@@ -446,9 +503,3 @@ pub fn mint_nft_and_remove_mint_authority<'info>(
         None,
     )
 }
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Transfer fee calculate not match")]
-    TransferFeeCalculateNotMatch,
-}
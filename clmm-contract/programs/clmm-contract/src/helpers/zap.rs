@@ -0,0 +1,276 @@
+use std::{cell::RefMut, collections::VecDeque};
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{
+    error::ErrorCode,
+    events::Swap,
+    helpers::{
+        add_liquidity_to_range, get_liquidity_from_amounts, get_sqrt_price_at_tick,
+        get_transfer_fee, get_transfer_inverse_fee, swap_internal,
+        transfer_from_pool_vault_to_user, transfer_from_user_to_pool_vault,
+    },
+    state::{
+        AmmConfig, PoolState, ProtocolPositionState, TickArrayBitmapExtension, TickArrayState,
+    },
+    util::AccountLoad,
+};
+
+/// Take `amount_in` of token 0 when `zero_for_one`, of token 1 otherwise, swap `swap_amount` of
+/// it through the pool and add the liquidity both parts pay for to the range. Output of the swap
+/// the range does not take is sent back to the user, input the range does not take is not
+/// transferred.
+///
+/// The swap amount is quoted off chain with `SwapQuoter::quote_zap_in` of the client, a quote
+/// the pool has moved away from by the time the zap lands is caught by `liquidity_min`.
+///
+/// `remaining_accounts` are the tick array bitmap extension, which may be left out when neither
+/// the swap nor the range's tick arrays need it, followed by the tick arrays the swap walks
+/// through in swap order. Emits the `Swap` event and returns the liquidity added, the amounts
+/// credited to the range and the part of the input transfer fee paid for them.
+#[allow(clippy::too_many_arguments)]
+pub fn zap_into_range<'info>(
+    payer: &Signer<'info>,
+    token_account_0: &InterfaceAccount<'info, TokenAccount>,
+    token_account_1: &InterfaceAccount<'info, TokenAccount>,
+    token_vault_0: &InterfaceAccount<'info, TokenAccount>,
+    token_vault_1: &InterfaceAccount<'info, TokenAccount>,
+    vault_0_mint: &InterfaceAccount<'info, Mint>,
+    vault_1_mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Program<'info, Token>,
+    token_program_2022: &Program<'info, Token2022>,
+    amm_config: &AmmConfig,
+    pool_state_loader: &AccountLoader<'info, PoolState>,
+    protocol_position: &mut ProtocolPositionState,
+    tick_array_lower_loader: &AccountLoad<'info, TickArrayState>,
+    tick_array_upper_loader: &AccountLoad<'info, TickArrayState>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    amount_in: u64,
+    swap_amount: u64,
+    zero_for_one: bool,
+    liquidity_min: u128,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+) -> Result<(u128, u64, u64, u64, u64)> {
+    let pool_id = pool_state_loader.key();
+    let (input_mint, output_mint) = if zero_for_one {
+        (vault_0_mint, vault_1_mint)
+    } else {
+        (vault_1_mint, vault_0_mint)
+    };
+
+    // the transfer fee of the input is paid out of `amount_in`
+    let amount_in_transfer_fee = get_transfer_fee(input_mint, amount_in)?;
+    let amount_available = amount_in
+        .checked_sub(amount_in_transfer_fee)
+        .ok_or(ErrorCode::TransferFeeOverflow)?;
+    require_gte!(
+        amount_available,
+        swap_amount,
+        ErrorCode::InvalidZapSwapAmount
+    );
+
+    let (tickarray_bitmap_extension_info, swap_tick_arrays) = match remaining_accounts.split_first()
+    {
+        Some((first, rest)) if first.key() == TickArrayBitmapExtension::key(pool_id) => {
+            (Some(first), rest)
+        }
+        _ => (None, remaining_accounts),
+    };
+    let tickarray_bitmap_extension = match tickarray_bitmap_extension_info {
+        Some(account_info) => {
+            Some(*AccountLoader::<TickArrayBitmapExtension>::try_from(account_info)?.load()?)
+        }
+        None => None,
+    };
+    let tick_array_loaders = swap_tick_arrays
+        .iter()
+        .map(AccountLoader::<TickArrayState>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+
+    let (swap_amount_in, swap_amount_out, swap_state, liquidity, amount_0, amount_1) = {
+        let pool_state = &mut pool_state_loader.load_mut()?;
+
+        // the swap tick arrays are released before the range's ticks are loaded, they may be
+        // the same accounts
+        let (swap_amount_in, swap_amount_out) = {
+            let mut tick_array_states = tick_array_loaders
+                .iter()
+                .map(|loader| loader.load_mut())
+                .collect::<Result<VecDeque<RefMut<TickArrayState>>>>()?;
+            if swap_amount == 0 {
+                (0, 0)
+            } else {
+                swap_internal(
+                    amm_config,
+                    pool_state,
+                    &mut tick_array_states,
+                    tickarray_bitmap_extension.as_ref(),
+                    swap_amount,
+                    0,
+                    zero_for_one,
+                    true,
+                    Clock::get()?.unix_timestamp as u64,
+                )?
+            }
+        };
+        let swap_state = (
+            pool_state.sqrt_price_x64,
+            pool_state.liquidity,
+            pool_state.tick_current,
+        );
+
+        let amount_input_left = amount_available - swap_amount_in;
+        let (amount_0_available, amount_1_available) = if zero_for_one {
+            (amount_input_left, swap_amount_out)
+        } else {
+            (swap_amount_out, amount_input_left)
+        };
+        let liquidity = get_liquidity_from_amounts(
+            pool_state.sqrt_price_x64,
+            get_sqrt_price_at_tick(tick_lower_index).map_err(ErrorCode::from)?,
+            get_sqrt_price_at_tick(tick_upper_index).map_err(ErrorCode::from)?,
+            amount_0_available,
+            amount_1_available,
+        )
+        .map_err(ErrorCode::from)?;
+        require!(liquidity > 0, ErrorCode::LiquidityZero);
+        require_gte!(liquidity, liquidity_min, ErrorCode::PriceSlippageCheck);
+
+        let (amount_0, amount_1) = add_liquidity_to_range(
+            tick_array_lower_loader,
+            tick_array_upper_loader,
+            protocol_position,
+            tickarray_bitmap_extension_info,
+            pool_state,
+            liquidity,
+            tick_lower_index,
+            tick_upper_index,
+        )?;
+        // the swap output backs the deposit, the pool must not pay for rounding
+        require_gte!(amount_0_available, amount_0, ErrorCode::PriceSlippageCheck);
+        require_gte!(amount_1_available, amount_1, ErrorCode::PriceSlippageCheck);
+
+        (
+            swap_amount_in,
+            swap_amount_out,
+            swap_state,
+            liquidity,
+            amount_0,
+            amount_1,
+        )
+    };
+
+    let (deposit_in, deposit_out, input_account, output_account, input_vault, output_vault) =
+        if zero_for_one {
+            (
+                amount_0,
+                amount_1,
+                token_account_0,
+                token_account_1,
+                token_vault_0,
+                token_vault_1,
+            )
+        } else {
+            (
+                amount_1,
+                amount_0,
+                token_account_1,
+                token_account_0,
+                token_vault_1,
+                token_vault_0,
+            )
+        };
+
+    let pool_amount_in = swap_amount_in
+        .checked_add(deposit_in)
+        .ok_or(ErrorCode::MaxTokenOverflow)?;
+    let pool_amount_in_transfer_fee = get_transfer_inverse_fee(input_mint, pool_amount_in)?;
+    let user_amount_in = pool_amount_in
+        .checked_add(pool_amount_in_transfer_fee)
+        .ok_or(ErrorCode::TransferFeeOverflow)?;
+    require_gte!(amount_in, user_amount_in, ErrorCode::PriceSlippageCheck);
+    // the swap output the range does not take goes back to the user
+    let amount_out = swap_amount_out - deposit_out;
+    let amount_out_transfer_fee = get_transfer_fee(output_mint, amount_out)?;
+
+    transfer_from_user_to_pool_vault(
+        payer,
+        &input_account.to_account_info(),
+        &input_vault.to_account_info(),
+        Some(Box::new(input_mint.clone())),
+        &token_program.to_account_info(),
+        Some(token_program_2022.to_account_info()),
+        user_amount_in,
+    )?;
+    transfer_from_pool_vault_to_user(
+        pool_state_loader,
+        &output_vault.to_account_info(),
+        &output_account.to_account_info(),
+        Some(Box::new(output_mint.clone())),
+        &token_program.to_account_info(),
+        Some(token_program_2022.to_account_info()),
+        amount_out,
+    )?;
+
+    // one transfer pays the input of the swap and of the deposit, its fee is split between them
+    // by amount, the output transfer fee is paid on the part sent back
+    let swap_transfer_fee_in = if pool_amount_in == 0 {
+        0
+    } else {
+        (u128::from(pool_amount_in_transfer_fee) * u128::from(swap_amount_in)
+            / u128::from(pool_amount_in)) as u64
+    };
+    let deposit_transfer_fee_in = pool_amount_in_transfer_fee - swap_transfer_fee_in;
+
+    if swap_amount_in > 0 {
+        let (sqrt_price_x64, liquidity, tick) = swap_state;
+        let (amount_0, transfer_fee_0, amount_1, transfer_fee_1) = if zero_for_one {
+            (
+                swap_amount_in,
+                swap_transfer_fee_in,
+                swap_amount_out,
+                amount_out_transfer_fee,
+            )
+        } else {
+            (
+                swap_amount_out,
+                amount_out_transfer_fee,
+                swap_amount_in,
+                swap_transfer_fee_in,
+            )
+        };
+        emit!(Swap {
+            pool_state: pool_id,
+            sender: payer.key(),
+            token_account_0: token_account_0.key(),
+            token_account_1: token_account_1.key(),
+            amount_0,
+            transfer_fee_0,
+            amount_1,
+            transfer_fee_1,
+            zero_for_one,
+            sqrt_price_x64,
+            liquidity,
+            tick,
+        });
+    }
+
+    let (amount_0_transfer_fee, amount_1_transfer_fee) = if zero_for_one {
+        (deposit_transfer_fee_in, 0)
+    } else {
+        (0, deposit_transfer_fee_in)
+    };
+    Ok((
+        liquidity,
+        amount_0,
+        amount_1,
+        amount_0_transfer_fee,
+        amount_1_transfer_fee,
+    ))
+}
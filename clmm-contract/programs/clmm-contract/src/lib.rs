@@ -1,6 +1,10 @@
 use anchor_lang::prelude::*;
 pub mod context;
 pub use context::*;
+pub mod error;
+pub use error::ErrorCode;
+pub mod events;
+pub use events::*;
 pub mod helpers;
 pub mod state;
 pub mod util;
@@ -31,5 +35,253 @@ pub mod clmm_contract {
         Ok(())
     }
 
-    
+    pub fn create_pool<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreatePool<'info>>,
+        sqrt_price_x64: u128,
+        open_time: u64,
+    ) -> Result<()> {
+        ctx.accounts.create_pool(
+            ctx.remaining_accounts,
+            sqrt_price_x64,
+            open_time,
+            &ctx.bumps,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn open_position_with_token22_nft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, OpenPositionWithToken22Nft<'info>>,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        tick_array_lower_start_index: i32,
+        tick_array_upper_start_index: i32,
+        with_metadata: bool,
+        base_flag: Option<bool>,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.open_position_with_token22_nft(
+            ctx.remaining_accounts,
+            &ctx.bumps,
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            with_metadata,
+            base_flag,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn increase_liquidity_v2<'info>(
+        ctx: Context<'_, '_, 'info, 'info, IncreaseLiquidity<'info>>,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        base_flag: Option<bool>,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.increase_liquidity_v2(
+            ctx.remaining_accounts,
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            base_flag,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn decrease_liquidity_v2<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DecreaseLiquidity<'info>>,
+        liquidity: u128,
+        amount_0_min: u64,
+        amount_1_min: u64,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.decrease_liquidity_v2(
+            ctx.remaining_accounts,
+            liquidity,
+            amount_0_min,
+            amount_1_min,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )
+    }
+
+    pub fn set_creator_allowlist(ctx: Context<SetCreatorAllowlist>, enabled: bool) -> Result<()> {
+        ctx.accounts.set_creator_allowlist(enabled)
+    }
+
+    pub fn set_create_pool_fee(
+        ctx: Context<SetCreatePoolFee>,
+        create_pool_fee: u64,
+        create_pool_fee_receiver: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_create_pool_fee(create_pool_fee, create_pool_fee_receiver)
+    }
+
+    pub fn add_pool_creator(ctx: Context<AddPoolCreator>, creator: Pubkey) -> Result<()> {
+        ctx.accounts.add_pool_creator(creator, &ctx.bumps)
+    }
+
+    pub fn remove_pool_creator(ctx: Context<RemovePoolCreator>) -> Result<()> {
+        ctx.accounts.remove_pool_creator()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn zap_in<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ZapIn<'info>>,
+        amount_in: u64,
+        swap_amount: u64,
+        zero_for_one: bool,
+        liquidity_min: u128,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.zap_in(
+            ctx.remaining_accounts,
+            amount_in,
+            swap_amount,
+            zero_for_one,
+            liquidity_min,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn zap_in_open_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ZapInOpenPosition<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        tick_array_lower_start_index: i32,
+        tick_array_upper_start_index: i32,
+        amount_in: u64,
+        swap_amount: u64,
+        zero_for_one: bool,
+        liquidity_min: u128,
+        with_metadata: bool,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.zap_in_open_position(
+            ctx.remaining_accounts,
+            &ctx.bumps,
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            amount_in,
+            swap_amount,
+            zero_for_one,
+            liquidity_min,
+            with_metadata,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn reposition<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Reposition<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        tick_array_lower_start_index: i32,
+        tick_array_upper_start_index: i32,
+        swap_amount: u64,
+        zero_for_one: bool,
+        liquidity_min: u128,
+        with_metadata: bool,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.reposition(
+            ctx.remaining_accounts,
+            &ctx.bumps,
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            swap_amount,
+            zero_for_one,
+            liquidity_min,
+            with_metadata,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )
+    }
+
+    pub fn set_position_operator(
+        ctx: Context<SetPositionOperator>,
+        operator: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.set_position_operator(operator)
+    }
+
+    pub fn compound<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Compound<'info>>,
+        sqrt_price_min_x64: Option<u128>,
+        sqrt_price_max_x64: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.compound(
+            ctx.remaining_accounts,
+            sqrt_price_min_x64,
+            sqrt_price_max_x64,
+            deadline,
+        )
+    }
+
+    pub fn lock_position(ctx: Context<LockPosition>, unlock_time: Option<i64>) -> Result<()> {
+        ctx.accounts.lock_position(unlock_time, &ctx.bumps)
+    }
+
+    pub fn unlock_position(ctx: Context<UnlockPosition>) -> Result<()> {
+        ctx.accounts.unlock_position()
+    }
+
+    pub fn collect_locked_position_fees(ctx: Context<CollectLockedPositionFees>) -> Result<()> {
+        ctx.accounts.collect_locked_position_fees()
+    }
+
+    pub fn migrate_amm_config(ctx: Context<MigrateAmmConfig>) -> Result<()> {
+        ctx.accounts.migrate_amm_config()
+    }
+
+    pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+        ctx.accounts.migrate_pool()
+    }
+
+    pub fn migrate_protocol_position(ctx: Context<MigrateProtocolPosition>) -> Result<()> {
+        ctx.accounts.migrate_protocol_position()
+    }
+
+    pub fn migrate_personal_position(ctx: Context<MigratePersonalPosition>) -> Result<()> {
+        ctx.accounts.migrate_personal_position()
+    }
 }
@@ -1,5 +1,9 @@
 use anchor_lang::prelude::*;
 
+pub use crate::helpers::FEE_RATE_DENOMINATOR_VALUE;
+
+pub const AMM_CONFIG_SEED: &str = "amm_config";
+
 #[account]
 #[derive(InitSpace)]
 pub struct AmmConfig {
@@ -14,4 +18,26 @@ pub struct AmmConfig {
 
     pub tick_spacing: u16,
     pub fund_owner: Pubkey,
+
+    // layout version, see the upgrade path in `state`
+    pub version: u8,
+
+    // only creators with a `PoolCreator` account of this config may create pools
+    pub creator_allowlist: bool,
+    pub padding_1: [u8; 7],
+
+    // lamports charged to pool creators, paid to `create_pool_fee_receiver`
+    pub create_pool_fee: u64,
+    pub create_pool_fee_receiver: Pubkey,
+
+    // reserved for new fields
+    pub padding: [u64; 10],
+}
+
+// the account size is fixed, new fields are carved out of `padding`
+const _: () = assert!(AmmConfig::LEN == 8 + AmmConfig::INIT_SPACE);
+
+impl AmmConfig {
+    pub const VERSION: u8 = 1;
+    pub const LEN: usize = 8 + 1 + 2 + 32 + 4 * 3 + 2 + 32 + 1 + 1 + 7 + 8 + 32 + 8 * 10;
 }
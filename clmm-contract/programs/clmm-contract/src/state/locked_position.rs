@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+pub const LOCKED_POSITION_SEED: &str = "locked_position";
+
+/// Escrow of a locked position nft. The holder of the `fee_key_mint` nft collects the fees of
+/// the position and takes the position nft back once `unlock_time` has passed.
+///
+/// The escrow is the lock. Every instruction that changes the liquidity of a position needs its
+/// nft held by the signer, or for compound an operator the holder approved, and lock_position
+/// drops the operator. The escrow's owner is this account, which only signs in
+/// collect_locked_position_fees and unlock_position. An instruction that touches a position
+/// without its nft has to check for this account itself.
+///
+/// The fee key collects trading fees only. The program has no reward emissions, so locked
+/// positions have no rewards to collect.
+#[account]
+#[derive(InitSpace)]
+pub struct LockedPositionState {
+    pub bump: [u8; 1],
+    pub position_nft_mint: Pubkey,
+    pub pool_id: Pubkey,
+    pub fee_key_mint: Pubkey,
+    /// Unix time from which the position can be unlocked, `None` locks it for good
+    pub unlock_time: Option<i64>,
+}
+
+impl LockedPositionState {
+    pub fn seeds(&self) -> [&[u8]; 3] {
+        [
+            LOCKED_POSITION_SEED.as_bytes(),
+            self.position_nft_mint.as_ref(),
+            self.bump.as_ref(),
+        ]
+    }
+
+    /// Whether the fee key holder may take the position nft back at `now`
+    pub fn is_unlocked(&self, now: i64) -> bool {
+        self.unlock_time
+            .is_some_and(|unlock_time| now >= unlock_time)
+    }
+}
@@ -0,0 +1,111 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+    Discriminator,
+};
+
+use crate::{
+    error::ErrorCode,
+    state::{AmmConfig, PersonalPositionState, PoolState, ProtocolPositionState},
+};
+
+/// An account with a `version` field that can be upgraded in place
+///
+/// `version` is the first field after the fields accounts had before versioning, so its offset
+/// is also the size of those accounts.
+pub trait Versioned: Discriminator {
+    /// Size of the current layout, discriminator included
+    const ACCOUNT_LEN: usize;
+    /// Version of the current layout
+    const LAYOUT_VERSION: u8;
+    /// Offset of `version` in the raw account data
+    const VERSION_OFFSET: usize;
+}
+
+impl Versioned for AmmConfig {
+    const ACCOUNT_LEN: usize = AmmConfig::LEN;
+    const LAYOUT_VERSION: u8 = AmmConfig::VERSION;
+    const VERSION_OFFSET: usize = 8 + 1 + 2 + 32 + 4 * 3 + 2 + 32;
+}
+
+impl Versioned for PoolState {
+    const ACCOUNT_LEN: usize = PoolState::LEN;
+    const LAYOUT_VERSION: u8 = PoolState::VERSION;
+    const VERSION_OFFSET: usize =
+        8 + 1 + 32 * 6 + 1 + 1 + 2 + 16 * 2 + 4 + 16 * 2 + 8 * 2 + 16 * 4 + 8 * 16 + 8 * 8;
+}
+
+impl Versioned for ProtocolPositionState {
+    const ACCOUNT_LEN: usize = ProtocolPositionState::LEN;
+    const LAYOUT_VERSION: u8 = ProtocolPositionState::VERSION;
+    const VERSION_OFFSET: usize = 8 + 1 + 32 + 4 + 4 + 16 * 3 + 8 * 3;
+}
+
+impl Versioned for PersonalPositionState {
+    const ACCOUNT_LEN: usize = PersonalPositionState::LEN;
+    const LAYOUT_VERSION: u8 = PersonalPositionState::VERSION;
+    const VERSION_OFFSET: usize = 8 + 1 + 32 + 32 + 4 + 4 + 16 * 3 + 8 * 2;
+}
+
+/// Layout version of raw account data, accounts created before versioning are version 0
+pub fn layout_version<T: Versioned>(data: &[u8]) -> Result<u8> {
+    if !data.starts_with(T::DISCRIMINATOR) {
+        return err!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+    }
+    let version = match data.len() {
+        len if len == T::VERSION_OFFSET => 0,
+        len if len == T::ACCOUNT_LEN => data[T::VERSION_OFFSET],
+        _ => return err!(ErrorCode::InvalidAccountLayout),
+    };
+    require!(
+        version <= T::LAYOUT_VERSION,
+        ErrorCode::UnsupportedLayoutVersion
+    );
+    Ok(version)
+}
+
+/// Rewrites account data of layout `from` into the current layout
+///
+/// `data` must already have the size of the current layout.
+pub fn upgrade_layout<T: Versioned>(data: &mut [u8], from: u8) {
+    assert_eq!(data.len(), T::ACCOUNT_LEN);
+    if from == 0 {
+        // version 1 appended `version` and `padding` after the original fields
+        data[T::VERSION_OFFSET..].fill(0);
+    }
+    data[T::VERSION_OFFSET] = T::LAYOUT_VERSION;
+}
+
+/// Upgrades an account to the current layout of `T`, topping up its rent from `payer`
+///
+/// Returns the version the account had. Accounts already on the current layout are left
+/// untouched, so migrating twice is a no-op.
+pub fn migrate_account<'info, T: Versioned>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<u8> {
+    let version = layout_version::<T>(&account.try_borrow_data()?)?;
+    if version == T::LAYOUT_VERSION {
+        return Ok(version);
+    }
+
+    let minimum_balance = Rent::get()?.minimum_balance(T::ACCOUNT_LEN);
+    let lamports = account.lamports();
+    if minimum_balance > lamports {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            minimum_balance - lamports,
+        )?;
+    }
+    account.realloc(T::ACCOUNT_LEN, false)?;
+    upgrade_layout::<T>(&mut account.try_borrow_mut_data()?, version);
+
+    Ok(version)
+}
@@ -1,3 +1,22 @@
+//! Program accounts
+//!
+//! # Upgrade path
+//!
+//! `AmmConfig`, `PoolState`, `ProtocolPositionState` and `PersonalPositionState` carry a
+//! `version` and a reserved `padding` array, and their `LEN` is checked against the struct
+//! layout at compile time.
+//!
+//! - A new field is carved out of the front of `padding`, shrinking it by the same number of
+//!   bytes. The account size and every existing offset stay the same, and since the reserved
+//!   bytes are zero the new field must treat zero as its default. `VERSION` is not bumped.
+//! - Any other change (resizing or reordering fields, growing past `padding`) bumps `VERSION`,
+//!   updates `LEN`, teaches `layout_version` the old size and adds a step to `upgrade_layout`
+//!   that rewrites the old layout in place.
+//! - Accounts created before `version` existed are shorter than `LEN` and read as version 0.
+//!
+//! The admin brings deployed accounts up to date with the `migrate_*` instructions, which
+//! realloc the account and run `upgrade_layout`. Up to date accounts are left untouched.
+
 pub mod amm_config;
 pub use amm_config::*;
 
@@ -7,6 +26,9 @@ pub use pool::*;
 pub mod support_mint;
 pub use support_mint::*;
 
+pub mod pool_creator;
+pub use pool_creator::*;
+
 pub mod tickarray_bitmap_extensions;
 pub use tickarray_bitmap_extensions::*;
 
@@ -16,5 +38,11 @@ pub use protocol_position::*;
 pub mod personal_position;
 pub use personal_position::*;
 
+pub mod locked_position;
+pub use locked_position::*;
+
 pub mod tick_array;
-pub use tick_array::*;
\ No newline at end of file
+pub use tick_array::*;
+
+pub mod migration;
+pub use migration::*;
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+pub const PERSONAL_POSITION_SEED: &str = "personal_position";
+
 #[account]
 #[derive(InitSpace)]
 pub struct PersonalPositionState {
@@ -22,4 +24,24 @@ pub struct PersonalPositionState {
     pub token_fees_owed_0: u64,
 
     pub token_fees_owed_1: u64,
+
+    // layout version, see the upgrade path in `state`
+    pub version: u8,
+
+    // may compound the fees of the position for the nft holder, default if none is approved
+    pub operator: Pubkey,
+
+    // nft holder that approved `operator`, the approval lapses once the nft changes hands
+    pub operator_approver: Pubkey,
+
+    // reserved for new fields
+    pub padding: [u64; 8],
+}
+
+// the account size is fixed, new fields are carved out of `padding`
+const _: () = assert!(PersonalPositionState::LEN == 8 + PersonalPositionState::INIT_SPACE);
+
+impl PersonalPositionState {
+    pub const VERSION: u8 = 1;
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 4 + 4 + 16 * 3 + 8 * 2 + 1 + 32 + 32 + 8 * 8;
 }
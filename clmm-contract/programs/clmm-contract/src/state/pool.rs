@@ -1,13 +1,21 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    helpers::{max_tick_in_tickarray_bitmap, MAX_TICK, TICK_ARRAY_BITMAP_SIZE, U1024},
+    error::ErrorCode,
+    helpers::{
+        check_current_tick_array_is_initialized, flip_bit, max_tick_in_tickarray_bitmap,
+        next_initialized_tick_array_start_index, MAX_TICK, MIN_TICK, TICK_ARRAY_BITMAP_SIZE,
+    },
     state::{TickArrayBitmapExtension, TickArrayState},
 };
 
+pub const POOL_SEED: &str = "pool";
+pub const POOL_VAULT_SEED: &str = "vault";
+
 // #[repr(C)] ensures a predictable, C-style memory layout for your struct
 #[account(zero_copy)]
 #[repr(C, packed)]
+#[derive(InitSpace)]
 pub struct PoolState {
     pub bump: [u8; 1],
     pub amm_config: Pubkey,
@@ -54,15 +62,38 @@ pub struct PoolState {
 
     pub open_time: u64,
     pub recent_epoch: u64,
+
+    // layout version, see the upgrade path in `state`
+    pub version: u8,
+    // reserved for new fields
+    pub padding: [u64; 32],
 }
 
+// the account size is fixed, new fields are carved out of `padding`
+const _: () = assert!(PoolState::LEN == 8 + PoolState::INIT_SPACE);
+const _: () = assert!(PoolState::INIT_SPACE == std::mem::size_of::<PoolState>());
+
 impl PoolState {
-    pub const LEN: usize =
-        8 + 1 + 32 * 6 + 1 + 1 + 2 + 16 + 16 + 4 + 16 + 16 + 8 * 2 + 16 * 4 + 8 * 16 + 8 * 8;
+    pub const VERSION: u8 = 1;
+    pub const LEN: usize = 8
+        + 1
+        + 32 * 6
+        + 1
+        + 1
+        + 2
+        + 16 * 2
+        + 4
+        + 16 * 2
+        + 8 * 2
+        + 16 * 4
+        + 8 * 16
+        + 8 * 8
+        + 1
+        + 8 * 32;
 
     pub fn seeds(&self) -> [&[u8]; 5] {
         [
-            &POOL_SEED.as_bytes(),
+            POOL_SEED.as_bytes(),
             self.amm_config.as_ref(),
             self.token_mint_0.as_ref(),
             self.token_mint_1.as_ref(),
@@ -70,8 +101,9 @@ impl PoolState {
         ]
     }
 
-    pub fn key(&self) -> Pubkey {
-        Pubkey::create_program_address(&self.seeds(), &crate::id()).unwrap()
+    pub fn key(&self) -> Result<Pubkey> {
+        Ok(Pubkey::create_program_address(&self.seeds(), &crate::id())
+            .map_err(ProgramError::from)?)
     }
 
     pub fn is_overflow_default_tickarray_bitmap(&self, tick_indexes: Vec<i32>) -> bool {
@@ -97,7 +129,7 @@ impl PoolState {
         if max_tick_boundary > MAX_TICK {
             max_tick_boundary = TickArrayState::get_array_start_index(MAX_TICK, self.tick_spacing);
             // next tick array start index its will be exclusive upperbound
-            max_tick_boundary = max_tick_boundary + TickArrayState::tick_count(self.tick_spacing);
+            max_tick_boundary += TickArrayState::tick_count(self.tick_spacing);
         }
         if min_tick_boundary < MIN_TICK {
             min_tick_boundary = TickArrayState::get_array_start_index(MIN_TICK, self.tick_spacing);
@@ -105,21 +137,21 @@ impl PoolState {
         (min_tick_boundary, max_tick_boundary)
     }
 
-    pub fn flip_tick_array_bit<'c: 'info, 'info>(
+    pub fn flip_tick_array_bit<'info>(
         &mut self,
-        tickarray_bitmap_extension: Option<&'c AccountInfo<'info>>,
+        tickarray_bitmap_extension: Option<&'info AccountInfo<'info>>,
         tick_array_start_index: i32,
     ) -> Result<()> {
         if self.is_overflow_default_tickarray_bitmap(vec![tick_array_start_index]) {
+            let tickarray_bitmap_extension = tickarray_bitmap_extension
+                .ok_or(ErrorCode::MissingTickArrayBitmapExtensionAccount)?;
             require_keys_eq!(
-                tickarray_bitmap_extension.unwrap().key(),
-                TickArrayBitmapExtension::key(self.key())
+                tickarray_bitmap_extension.key(),
+                TickArrayBitmapExtension::key(self.key()?)
             );
-            AccountLoader::<TickArrayBitmapExtension>::try_from(
-                tickarray_bitmap_extension.unwrap(),
-            )?
-            .load_mut()?
-            .flip_tick_array_bit(tick_array_start_index, self.tick_spacing)
+            AccountLoader::<TickArrayBitmapExtension>::try_from(tickarray_bitmap_extension)?
+                .load_mut()?
+                .flip_tick_array_bit(tick_array_start_index, self.tick_spacing)
         } else {
             self.flip_tick_array_bit_internal(tick_array_start_index)
         }
@@ -128,12 +160,90 @@ impl PoolState {
     fn flip_tick_array_bit_internal(&mut self, tick_array_start_index: i32) -> Result<()> {
         let tick_array_offset_in_bitmap = self.get_tick_array_offset(tick_array_start_index)?;
 
-        let tick_array_bitmap = U1024(self.tick_array_bitmap);
-        let mask = U1024::one() << tick_array_offset_in_bitmap.try_into().unwrap();
-        self.tick_array_bitmap = tick_array_bitmap.bitxor(mask).0;
+        let mut tick_array_bitmap = self.tick_array_bitmap;
+        flip_bit(&mut tick_array_bitmap, tick_array_offset_in_bitmap);
+        self.tick_array_bitmap = tick_array_bitmap;
         Ok(())
     }
 
+    /// Get the tick array a swap starts in. If the tick array holding the current tick is not
+    /// initialized, the next initialized one in the swap direction is returned instead.
+    pub fn get_first_initialized_tick_array(
+        &self,
+        tickarray_bitmap_extension: Option<&TickArrayBitmapExtension>,
+        zero_for_one: bool,
+    ) -> Result<(bool, i32)> {
+        let current_tick_array_start_index =
+            TickArrayState::get_array_start_index(self.tick_current, self.tick_spacing);
+        let (is_initialized, start_index) =
+            if self.is_overflow_default_tickarray_bitmap(vec![self.tick_current]) {
+                tickarray_bitmap_extension
+                    .ok_or(ErrorCode::MissingTickArrayBitmapExtensionAccount)?
+                    .check_tick_array_is_initialized(
+                        current_tick_array_start_index,
+                        self.tick_spacing,
+                    )?
+            } else {
+                check_current_tick_array_is_initialized(
+                    self.tick_array_bitmap,
+                    self.tick_current,
+                    self.tick_spacing,
+                )?
+            };
+        if is_initialized {
+            return Ok((true, start_index));
+        }
+        let next_start_index = self
+            .next_initialized_tick_array_start_index(
+                tickarray_bitmap_extension,
+                current_tick_array_start_index,
+                zero_for_one,
+            )?
+            .ok_or(ErrorCode::InsufficientLiquidityForDirection)?;
+        Ok((false, next_start_index))
+    }
+
+    /// Search the default bitmap and then the bitmap extension for the next initialized tick array
+    /// after `last_tick_array_start_index` in the swap direction
+    pub fn next_initialized_tick_array_start_index(
+        &self,
+        tickarray_bitmap_extension: Option<&TickArrayBitmapExtension>,
+        last_tick_array_start_index: i32,
+        zero_for_one: bool,
+    ) -> Result<Option<i32>> {
+        let mut last_tick_array_start_index =
+            TickArrayState::get_array_start_index(last_tick_array_start_index, self.tick_spacing);
+
+        loop {
+            let (is_found, start_index) = next_initialized_tick_array_start_index(
+                self.tick_array_bitmap,
+                last_tick_array_start_index,
+                self.tick_spacing,
+                zero_for_one,
+            );
+            if is_found {
+                return Ok(Some(start_index));
+            }
+            last_tick_array_start_index = start_index;
+
+            let (is_found, start_index) = tickarray_bitmap_extension
+                .ok_or(ErrorCode::MissingTickArrayBitmapExtensionAccount)?
+                .next_initialized_tick_array_from_one_bitmap(
+                    last_tick_array_start_index,
+                    self.tick_spacing,
+                    zero_for_one,
+                )?;
+            if is_found {
+                return Ok(Some(start_index));
+            }
+            last_tick_array_start_index = start_index;
+
+            if !(MIN_TICK..=MAX_TICK).contains(&last_tick_array_start_index) {
+                return Ok(None);
+            }
+        }
+    }
+
     pub fn get_tick_array_offset(&self, tick_array_start_index: i32) -> Result<usize> {
         require!(
             TickArrayState::check_is_valid_start_index(tick_array_start_index, self.tick_spacing),
@@ -145,9 +255,3 @@ impl PoolState {
         Ok(tick_array_offset_in_bitmap as usize)
     }
 }
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("invalid tick index")]
-    InvalidTickIndex,
-}
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+pub const POOL_CREATOR_SEED: &str = "pool_creator";
+
+/// Allows `creator` to create pools with an amm config in creator allowlist mode
+#[account]
+#[derive(InitSpace)]
+pub struct PoolCreator {
+    pub bump: u8,
+    pub amm_config: Pubkey,
+    pub creator: Pubkey,
+}
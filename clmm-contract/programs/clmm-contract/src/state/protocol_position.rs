@@ -1,10 +1,13 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    helpers::{MAX_TICK, MIN_TICK},
+    error::ErrorCode,
+    helpers::{fixed_point_64, MulDiv, MAX_TICK, MIN_TICK},
     util::get_recent_epoch,
 };
 
+pub const PROTOCOL_POSITION_SEED: &str = "protocol_position";
+
 #[account]
 #[derive(InitSpace)]
 pub struct ProtocolPositionState {
@@ -18,9 +21,20 @@ pub struct ProtocolPositionState {
     pub token_fees_owed_0: u64,
     pub token_fees_owed_1: u64,
     pub recent_epoch: u64,
+
+    // layout version, see the upgrade path in `state`
+    pub version: u8,
+    // reserved for new fields
+    pub padding: [u64; 8],
 }
 
+// the account size is fixed, new fields are carved out of `padding`
+const _: () = assert!(ProtocolPositionState::LEN == 8 + ProtocolPositionState::INIT_SPACE);
+
 impl ProtocolPositionState {
+    pub const VERSION: u8 = 1;
+    pub const LEN: usize = 8 + 1 + 32 + 4 + 4 + 16 * 3 + 8 * 3 + 1 + 8 * 8;
+
     pub fn update(
         &mut self,
         tick_lower_index: i32,
@@ -34,30 +48,36 @@ impl ProtocolPositionState {
         }
 
         require!(
-            tick_lower_index >= MIN_TICK && tick_lower_index <= MAX_TICK,
+            (MIN_TICK..=MAX_TICK).contains(&tick_lower_index),
             ErrorCode::InvalidTickRange
         );
         require!(
-            tick_upper_index >= MIN_TICK && tick_upper_index <= MAX_TICK,
+            (MIN_TICK..=MAX_TICK).contains(&tick_upper_index),
             ErrorCode::InvalidTickRange
         );
 
         // calculate accumulated Fees
-        let tokens_owed_0 =
-            U128::from(fee_growth_inside_0_x64.saturating_sub(self.fee_growth_inside_0_last_x64))
-                .mul_div_floor(U128::from(self.liquidity), U128::from(fixed_point_64::Q64))
-                .unwrap()
-                .to_underflow_u64();
-        let tokens_owed_1 =
-            U128::from(fee_growth_inside_1_x64.saturating_sub(self.fee_growth_inside_1_last_x64))
-                .mul_div_floor(U128::from(self.liquidity), U128::from(fixed_point_64::Q64))
-                .unwrap()
-                .to_underflow_u64();
+        let tokens_owed_0 = fixed_point_64::mul_floor(
+            fee_growth_inside_0_x64.saturating_sub(self.fee_growth_inside_0_last_x64),
+            self.liquidity,
+        )
+        .ok_or(ErrorCode::FeeAmountOverflow)?
+        .to_underflow_u64();
+        let tokens_owed_1 = fixed_point_64::mul_floor(
+            fee_growth_inside_1_x64.saturating_sub(self.fee_growth_inside_1_last_x64),
+            self.liquidity,
+        )
+        .ok_or(ErrorCode::FeeAmountOverflow)?
+        .to_underflow_u64();
 
         self.liquidity = if liquidity_delta < 0 {
-            self.liquidity - u128::try_from(-liquidity_delta).unwrap()
+            self.liquidity
+                .checked_sub(liquidity_delta.unsigned_abs())
+                .ok_or(ErrorCode::LiquidityUnderflow)?
         } else {
-            self.liquidity + u128::try_from(liquidity_delta).unwrap()
+            self.liquidity
+                .checked_add(liquidity_delta.unsigned_abs())
+                .ok_or(ErrorCode::LiquidityOverflow)?
         };
 
         self.fee_growth_inside_0_last_x64 = fee_growth_inside_0_x64;
@@ -66,17 +86,17 @@ impl ProtocolPositionState {
         self.tick_upper_index = tick_upper_index;
 
         if tokens_owed_0 > 0 || tokens_owed_1 > 0 {
-            self.token_fees_owed_0 = self.token_fees_owed_0.checked_add(tokens_owed_0).unwrap();
-            self.token_fees_owed_1 = self.token_fees_owed_1.checked_add(tokens_owed_1).unwrap();
+            self.token_fees_owed_0 = self
+                .token_fees_owed_0
+                .checked_add(tokens_owed_0)
+                .ok_or(ErrorCode::FeeAmountOverflow)?;
+            self.token_fees_owed_1 = self
+                .token_fees_owed_1
+                .checked_add(tokens_owed_1)
+                .ok_or(ErrorCode::FeeAmountOverflow)?;
         }
         self.recent_epoch = get_recent_epoch()?;
 
         Ok(())
     }
 }
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Invalid tick range")]
-    InvalidTickRange,
-}
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+pub const SUPPORT_MINT_SEED: &str = "support_mint";
+
 #[account]
 #[derive(InitSpace)]
 pub struct SupportMint {
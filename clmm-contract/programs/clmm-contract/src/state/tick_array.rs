@@ -1,15 +1,16 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    helpers::{create_or_allocate_account, TICK_ARRAY_SIZE},
+    error::ErrorCode,
+    helpers::{create_or_allocate_account, fee_math},
     state::PoolState,
-    util::get_recent_epoch,
+    util::{get_recent_epoch, AccountLoad},
 };
 
-pub const TICK_ARRAY_SIZE_USIZE: usize = 60;
-pub const TICK_ARRAY_SIZE: i32 = TICK_ARRAY_SIZE_USIZE as i32;
-pub const MIN_TICK: i32 = -443636;
-pub const MAX_TICK: i32 = -MIN_TICK;
+pub use crate::helpers::{MAX_TICK, MIN_TICK, TICK_ARRAY_SIZE};
+
+pub const TICK_ARRAY_SEED: &str = "tick_array";
+pub const TICK_ARRAY_SIZE_USIZE: usize = TICK_ARRAY_SIZE as usize;
 
 #[zero_copy(unsafe)]
 #[repr(C, packed)]
@@ -28,7 +29,7 @@ impl TickState {
     pub const LEN: usize = 4 + 16 + 16 + 16 + 16;
 
     pub fn check_is_out_of_bounds(tick: i32) -> bool {
-        tick < MIN_TICK || tick > MAX_TICK
+        !(MIN_TICK..=MAX_TICK).contains(&tick)
     }
 
     pub fn update(
@@ -41,9 +42,13 @@ impl TickState {
     ) -> Result<bool> {
         let liquidity_gross_before = self.liquidity_gross;
         let liquidity_gross_after = if liquidity_delta > 0 {
-            liquidity_gross_before + liquidity_delta as u128
+            liquidity_gross_before
+                .checked_add(liquidity_delta.unsigned_abs())
+                .ok_or(ErrorCode::LiquidityOverflow)?
         } else {
-            liquidity_gross_before - u128::try_from(-liquidity_delta).unwrap()
+            liquidity_gross_before
+                .checked_sub(liquidity_delta.unsigned_abs())
+                .ok_or(ErrorCode::LiquidityUnderflow)?
         };
 
         let flipped = (liquidity_gross_after == 0) != (liquidity_gross_before == 0);
@@ -63,13 +68,34 @@ impl TickState {
         } else {
             self.liquidity_net.checked_add(liquidity_delta)
         }
-        .unwrap();
+        .ok_or(ErrorCode::LiquidityNetOverflow)?;
 
         Ok(flipped)
     }
 
+    /// Transitions to the next tick as needed by price movement, returning the liquidity to apply
+    pub fn cross(&mut self, fee_growth_global_0_x64: u128, fee_growth_global_1_x64: u128) -> i128 {
+        self.fee_growth_outside_0_x64 =
+            fee_growth_global_0_x64.wrapping_sub(self.fee_growth_outside_0_x64);
+        self.fee_growth_outside_1_x64 =
+            fee_growth_global_1_x64.wrapping_sub(self.fee_growth_outside_1_x64);
+        self.liquidity_net
+    }
+
+    /// Reset a tick that no position references anymore, it keeps its index
+    pub fn clear(&mut self) {
+        self.liquidity_net = 0;
+        self.liquidity_gross = 0;
+        self.fee_growth_outside_0_x64 = 0;
+        self.fee_growth_outside_1_x64 = 0;
+    }
+
+    pub fn is_initialized(self) -> bool {
+        self.liquidity_gross != 0
+    }
+
     pub fn check_is_out_of_boundary(tick: i32) -> bool {
-        tick < MIN_TICK || tick > MAX_TICK
+        !(MIN_TICK..=MAX_TICK).contains(&tick)
     }
 }
 
@@ -92,7 +118,10 @@ impl TickArrayState {
         tick_spacing: u16,
         pool_key: Pubkey,
     ) -> Result<()> {
-        TickArrayState::check_is_valid_start_index(start_index, tick_spacing);
+        require!(
+            TickArrayState::check_is_valid_start_index(start_index, tick_spacing),
+            ErrorCode::InvalidTickArrayStartIndex
+        );
         self.start_tick_index = start_index;
         self.pool_id = pool_key;
         self.recent_epoch = get_recent_epoch()?;
@@ -106,13 +135,13 @@ impl TickArrayState {
         tick_spacing: u16,
         pool_state_loader: &AccountLoader<'info, PoolState>,
         system_program: AccountInfo<'info>,
-    ) -> Result<AccountLoader<'info, TickArrayState>> {
+    ) -> Result<AccountLoad<'info, TickArrayState>> {
         require!(
             TickArrayState::check_is_valid_start_index(tick_array_start_index, tick_spacing),
             ErrorCode::InvalidTickArrayStartIndex
         );
 
-        let tick_array_state = if tick_array_account_info.owner == &system_program::ID {
+        let tick_array_state = if tick_array_account_info.owner == &System::id() {
             let (expect_pda_address, bump) = Pubkey::find_program_address(
                 &[
                     b"tick_array",
@@ -137,7 +166,7 @@ impl TickArrayState {
                 ],
                 TickArrayState::LEN,
             )?;
-            let tick_array_state_loader = AccountLoader::<TickArrayState>::try_from_unchecked(
+            let tick_array_state_loader = AccountLoad::<TickArrayState>::try_from_unchecked(
                 &crate::id(),
                 &tick_array_account_info,
             )?;
@@ -151,7 +180,7 @@ impl TickArrayState {
             }
             tick_array_state_loader
         } else {
-            AccountLoader::<TickArrayState>::try_from(&tick_array_account_info)?;
+            AccountLoad::<TickArrayState>::try_from(&tick_array_account_info)?
         };
         Ok(tick_array_state)
     }
@@ -160,23 +189,12 @@ impl TickArrayState {
         let ticks_in_array = TickArrayState::tick_count(tick_spacing);
         let mut start = tick_index / ticks_in_array;
         if tick_index < 0 && tick_index % ticks_in_array != 0 {
-            start = start - 1;
+            start -= 1;
             // for negative division rust round toward 0
         }
         start * ticks_in_array
     }
 
-    pub fn check_is_valid_start_index(tick_index: i32, tick_spacing: u16) -> bool {
-        if TickState::check_is_out_of_bounds(tick) {
-            if tick_index > MAX_TICK {
-                return false;
-            }
-            let min_start_index = TickArrayState::get_array_start_index(MIN_TICK, tick_spacing);
-            return tick_index == min_start_index;
-        }
-        tick_index % TickArrayState::tick_count(tick_spacing) == 0
-    }
-
     pub fn tick_count(tick_spacing: u16) -> i32 {
         TICK_ARRAY_SIZE * i32::from(tick_spacing)
     }
@@ -195,6 +213,11 @@ impl TickArrayState {
         Ok(offset_in_array)
     }
 
+    pub fn get_tick_state(&self, tick_index: i32, tick_spacing: u16) -> Result<&TickState> {
+        let offset_in_array = self.get_tick_offset_in_array(tick_index, tick_spacing)?;
+        Ok(&self.ticks[offset_in_array])
+    }
+
     pub fn get_tick_state_mut(
         &mut self,
         tick_index: i32,
@@ -216,6 +239,48 @@ impl TickArrayState {
         Ok(())
     }
 
+    /// Get the first initialized tick in the array, scanning in the swap direction
+    pub fn first_initialized_tick(&self, zero_for_one: bool) -> Result<&TickState> {
+        let offset_in_array = if zero_for_one {
+            (0..TICK_ARRAY_SIZE_USIZE)
+                .rev()
+                .find(|&i| self.ticks[i].is_initialized())
+        } else {
+            (0..TICK_ARRAY_SIZE_USIZE).find(|&i| self.ticks[i].is_initialized())
+        };
+        match offset_in_array {
+            Some(i) => Ok(&self.ticks[i]),
+            None => err!(ErrorCode::InvalidTickArray),
+        }
+    }
+
+    /// Get the next initialized tick in the array after `current_tick_index` in the swap direction.
+    /// A zero_for_one swap includes the current tick itself, since price moves down through it.
+    pub fn next_initialized_tick(
+        &self,
+        current_tick_index: i32,
+        tick_spacing: u16,
+        zero_for_one: bool,
+    ) -> Result<Option<&TickState>> {
+        let current_tick_array_start_index =
+            TickArrayState::get_array_start_index(current_tick_index, tick_spacing);
+        if current_tick_array_start_index != self.start_tick_index {
+            return Ok(None);
+        }
+        let offset_in_array =
+            (current_tick_index - self.start_tick_index) / i32::from(tick_spacing);
+
+        let next_offset = if zero_for_one {
+            (0..=offset_in_array)
+                .rev()
+                .find(|&i| self.ticks[i as usize].is_initialized())
+        } else {
+            (offset_in_array + 1..TICK_ARRAY_SIZE)
+                .find(|&i| self.ticks[i as usize].is_initialized())
+        };
+        Ok(next_offset.map(|i| &self.ticks[i as usize]))
+    }
+
     pub fn update_initialized_tick_count(&mut self, add: bool) -> Result<()> {
         if add {
             self.initialized_tick_count += 1;
@@ -237,63 +302,33 @@ impl TickArrayState {
     }
 }
 
-// Calculates the fee growths inside of tick_lower and tick_upper based on their positions relative to tick_current.
+/// Calculates the fee growths inside of tick_lower and tick_upper based on their positions relative to tick_current.
 /// `fee_growth_inside = fee_growth_global - fee_growth_below(lower) - fee_growth_above(upper)`
-
 pub fn get_fee_growth_inside(
     tick_lower: &TickState,
     tick_upper: &TickState,
     tick_current: i32,
     fee_growth_global_0_x64: u128,
     fee_growth_global_1_x64: u128,
-) -> (u128, u128) {
-    // calculate fee growth below
-    let (fee_growth_below_0_x64, fee_growth_below_1_x64) = if tick_current >= tick_lower.tick {
-        (
-            tick_lower.fee_growth_outside_0_x64,
-            tick_lower.fee_growth_outside_1_x64,
-        )
-    } else {
-        (
-            fee_growth_global_0_x64
-                .checked_sub(tick_lower.fee_growth_outside_0_x64)
-                .unwrap(),
-            fee_growth_global_1_x64
-                .checked_sub(tick_lower.fee_growth_outside_1_x64)
-                .unwrap(),
-        )
-    };
-
-    // Calculate fee growth above
-    let (fee_growth_above_0_x64, fee_growth_above_1_x64) = if tick_current < tick_upper.tick {
-        (
-            tick_upper.fee_growth_outside_0_x64,
-            tick_upper.fee_growth_outside_1_x64,
-        )
-    } else {
-        (
-            fee_growth_global_0_x64
-                .checked_sub(tick_upper.fee_growth_outside_0_x64)
-                .unwrap(),
-            fee_growth_global_1_x64
-                .checked_sub(tick_upper.fee_growth_outside_1_x64)
-                .unwrap(),
-        )
-    };
-    let fee_growth_inside_0_x64 = fee_growth_global_0_x64
-        .wrapping_sub(fee_growth_below_0_x64)
-        .wrapping_sub(fee_growth_above_0_x64);
-    let fee_growth_inside_1_x64 = fee_growth_global_1_x64
-        .wrapping_sub(fee_growth_below_1_x64)
-        .wrapping_sub(fee_growth_above_1_x64);
-
-    (fee_growth_inside_0_x64, fee_growth_inside_1_x64)
-}
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Invalid tick array start index")]
-    InvalidTickArrayStartIndex,
-    #[msg("Invalid tick array")]
-    InvalidTickArray,
+) -> Result<(u128, u128)> {
+    let fee_growth_inside_0_x64 = fee_math::get_fee_growth_inside(
+        tick_lower.tick,
+        tick_lower.fee_growth_outside_0_x64,
+        tick_upper.tick,
+        tick_upper.fee_growth_outside_0_x64,
+        tick_current,
+        fee_growth_global_0_x64,
+    )
+    .map_err(ErrorCode::from)?;
+    let fee_growth_inside_1_x64 = fee_math::get_fee_growth_inside(
+        tick_lower.tick,
+        tick_lower.fee_growth_outside_1_x64,
+        tick_upper.tick,
+        tick_upper.fee_growth_outside_1_x64,
+        tick_current,
+        fee_growth_global_1_x64,
+    )
+    .map_err(ErrorCode::from)?;
+
+    Ok((fee_growth_inside_0_x64, fee_growth_inside_1_x64))
 }
@@ -1,10 +1,15 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    helpers::{max_tick_in_tickarray_bitmap, MAX_TICK, MIN_TICK, U512},
+    error::ErrorCode,
+    helpers::{
+        flip_bit, get_bitmap_tick_boundary, is_set, max_tick_in_tickarray_bitmap, next_set_bit,
+        prev_set_bit, MAX_TICK, MIN_TICK,
+    },
     state::TickArrayState,
 };
 
+pub const POOL_TICK_ARRAY_BITMAP_SEED: &str = "tick_array_bitmap";
 pub const TICK_ARRAY_BITMAP_SIZE: i32 = 512;
 
 const EXTENSION_TICKARRAY_BITMAP_SIZE: usize = 14; // number of tick array bitmap
@@ -27,7 +32,11 @@ impl TickArrayBitmapExtension {
     }
 
     pub fn key(pool_id: Pubkey) -> Pubkey {
-        Pubkey::find_program_address(&[b"tick_array_bitmap", pool_id.as_ref()], &crate::id()).0
+        Pubkey::find_program_address(
+            &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool_id.as_ref()],
+            &crate::id(),
+        )
+        .0
     }
 
     /// Flip the value of tick in the bitmap.
@@ -36,15 +45,15 @@ impl TickArrayBitmapExtension {
         tick_array_start_index: i32,
         tick_spacing: u16,
     ) -> Result<()> {
-        let (offset, tick_array_bitmap) = self.get_bitmap(tick_array_start_index, tick_spacing)?;
+        let (offset, mut tick_array_bitmap) =
+            self.get_bitmap(tick_array_start_index, tick_spacing)?;
         let tick_array_offset_in_bitmap =
             Self::tick_array_offset_in_bitmap(tick_array_start_index, tick_spacing);
-        let tick_array_bitmap = U512(tick_array_bitmap);
-        let mask = U512::one() << tick_array_offset_in_bitmap;
+        flip_bit(&mut tick_array_bitmap, tick_array_offset_in_bitmap as usize);
         if tick_array_start_index < 0 {
-            self.negative_tick_array_bitmap[offset as usize] = tick_array_bitmap.bitxor(mask).0;
+            self.negative_tick_array_bitmap[offset] = tick_array_bitmap;
         } else {
-            self.positive_tick_array_bitmap[offset as usize] = tick_array_bitmap.bitxor(mask).0;
+            self.positive_tick_array_bitmap[offset] = tick_array_bitmap;
         }
         Ok(())
     }
@@ -96,12 +105,89 @@ impl TickArrayBitmapExtension {
         let tick_array_offset_in_bitmap =
             Self::tick_array_offset_in_bitmap(tick_array_start_index, tick_spacing);
 
-        if U512(tickarray_bitmap).bit(tick_array_offset_in_bitmap as usize) {
+        if is_set(&tickarray_bitmap, tick_array_offset_in_bitmap as usize) {
             return Ok((true, tick_array_start_index));
         }
         Ok((false, tick_array_start_index))
     }
 
+    /// Search for the next initialized tick array in the extension bitmap that holds the tick array
+    /// after `last_tick_array_start_index`. When nothing is found, the boundary of that bitmap is
+    /// returned so the caller can continue with the adjacent one.
+    pub fn next_initialized_tick_array_from_one_bitmap(
+        &self,
+        last_tick_array_start_index: i32,
+        tick_spacing: u16,
+        zero_for_one: bool,
+    ) -> Result<(bool, i32)> {
+        let multiplier = TickArrayState::tick_count(tick_spacing);
+        let next_tick_array_start_index = if zero_for_one {
+            last_tick_array_start_index - multiplier
+        } else {
+            last_tick_array_start_index + multiplier
+        };
+        let min_tick_array_start_index =
+            TickArrayState::get_array_start_index(MIN_TICK, tick_spacing);
+        let max_tick_array_start_index =
+            TickArrayState::get_array_start_index(MAX_TICK, tick_spacing);
+
+        if next_tick_array_start_index < min_tick_array_start_index
+            || next_tick_array_start_index > max_tick_array_start_index
+        {
+            return Ok((false, next_tick_array_start_index));
+        }
+
+        let (_, tickarray_bitmap) = self.get_bitmap(next_tick_array_start_index, tick_spacing)?;
+
+        Ok(Self::next_initialized_tick_array_in_bitmap(
+            tickarray_bitmap,
+            next_tick_array_start_index,
+            tick_spacing,
+            zero_for_one,
+        ))
+    }
+
+    pub fn next_initialized_tick_array_in_bitmap(
+        tickarray_bitmap: TickArryBitmap,
+        next_tick_array_start_index: i32,
+        tick_spacing: u16,
+        zero_for_one: bool,
+    ) -> (bool, i32) {
+        let (bitmap_min_tick_boundary, bitmap_max_tick_boundary) =
+            get_bitmap_tick_boundary(next_tick_array_start_index, tick_spacing);
+
+        let tick_array_offset_in_bitmap =
+            Self::tick_array_offset_in_bitmap(next_tick_array_start_index, tick_spacing);
+        if zero_for_one {
+            // tick from upper to lower, find from higher bits to lower bits
+            let Some(bit) = prev_set_bit(&tickarray_bitmap, tick_array_offset_in_bitmap as usize)
+            else {
+                // not found til to the end
+                return (false, bitmap_min_tick_boundary);
+            };
+            let next_bit = tick_array_offset_in_bitmap - bit as i32;
+            (
+                true,
+                next_tick_array_start_index - next_bit * TickArrayState::tick_count(tick_spacing),
+            )
+        } else {
+            // tick from lower to upper, find from lower bits to higher bits
+            let Some(bit) = next_set_bit(&tickarray_bitmap, tick_array_offset_in_bitmap as usize)
+            else {
+                // not found til to the end
+                return (
+                    false,
+                    bitmap_max_tick_boundary - TickArrayState::tick_count(tick_spacing),
+                );
+            };
+            let next_bit = bit as i32 - tick_array_offset_in_bitmap;
+            (
+                true,
+                next_tick_array_start_index + next_bit * TickArrayState::tick_count(tick_spacing),
+            )
+        }
+    }
+
     // negative ticks from right to left
     pub fn tick_array_offset_in_bitmap(tick_array_start_index: i32, tick_spacing: u16) -> i32 {
         let m = tick_array_start_index.abs() % max_tick_in_tickarray_bitmap(tick_spacing);
@@ -112,11 +198,3 @@ impl TickArrayBitmapExtension {
         tick_array_offset_in_bitmap
     }
 }
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Invalid tick index")]
-    InvalidTickIndex,
-    #[msg("Invalid tick array boundary")]
-    InvalidTickArrayBoundary,
-}
@@ -0,0 +1,116 @@
+use std::{
+    cell::{Ref, RefMut},
+    marker::PhantomData,
+    mem,
+    ops::DerefMut,
+};
+
+use anchor_lang::{error::ErrorCode, prelude::*, ZeroCopy};
+
+/// Zero copy account loader like anchor's `AccountLoader`, but owning its `AccountInfo`
+///
+/// `AccountLoader` borrows its account info for `'info`, which the accounts of an instruction
+/// only hand out through `remaining_accounts`. This loader is built from any account info, such
+/// as tick arrays passed as `UncheckedAccount` and created in the instruction.
+#[derive(Clone)]
+pub struct AccountLoad<'info, T: ZeroCopy + Owner> {
+    acc_info: AccountInfo<'info>,
+    phantom: PhantomData<&'info T>,
+}
+
+impl<'info, T: ZeroCopy + Owner> AccountLoad<'info, T> {
+    fn new(acc_info: AccountInfo<'info>) -> AccountLoad<'info, T> {
+        Self {
+            acc_info,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Constructs a new `AccountLoad` from a previously initialized account
+    pub fn try_from(acc_info: &AccountInfo<'info>) -> Result<AccountLoad<'info, T>> {
+        if acc_info.owner != &T::owner() {
+            return Err(Error::from(ErrorCode::AccountOwnedByWrongProgram)
+                .with_pubkeys((*acc_info.owner, T::owner())));
+        }
+        let data = acc_info.try_borrow_data()?;
+        if data.len() < T::DISCRIMINATOR.len() {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        if &data[..T::DISCRIMINATOR.len()] != T::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        drop(data);
+        Ok(AccountLoad::new(acc_info.clone()))
+    }
+
+    /// Constructs a new `AccountLoad` from an account that is not initialized yet, for
+    /// [`AccountLoad::load_init`]
+    pub fn try_from_unchecked(
+        _program_id: &Pubkey,
+        acc_info: &AccountInfo<'info>,
+    ) -> Result<AccountLoad<'info, T>> {
+        if acc_info.owner != &T::owner() {
+            return Err(Error::from(ErrorCode::AccountOwnedByWrongProgram)
+                .with_pubkeys((*acc_info.owner, T::owner())));
+        }
+        Ok(AccountLoad::new(acc_info.clone()))
+    }
+
+    pub fn key(&self) -> Pubkey {
+        *self.acc_info.key
+    }
+
+    pub fn to_account_info(&self) -> AccountInfo<'info> {
+        self.acc_info.clone()
+    }
+
+    /// Returns a `Ref` to the account data structure for reading
+    pub fn load(&self) -> Result<Ref<'_, T>> {
+        let data = self.acc_info.try_borrow_data()?;
+        if data.len() < T::DISCRIMINATOR.len() {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        if &data[..T::DISCRIMINATOR.len()] != T::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        Ok(Ref::map(data, |data| {
+            bytemuck::from_bytes(&data[8..mem::size_of::<T>() + 8])
+        }))
+    }
+
+    /// Returns a `RefMut` to the account data structure for reading or writing
+    pub fn load_mut(&self) -> Result<RefMut<'_, T>> {
+        // AccountInfo api allows you to borrow mut even if the account isn't
+        // writable, so add this check for a better dev experience.
+        if !self.acc_info.is_writable {
+            return Err(ErrorCode::AccountNotMutable.into());
+        }
+        let data = self.acc_info.try_borrow_mut_data()?;
+        if data.len() < T::DISCRIMINATOR.len() {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        if &data[..T::DISCRIMINATOR.len()] != T::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        Ok(RefMut::map(data, |data| {
+            bytemuck::from_bytes_mut(&mut data.deref_mut()[8..mem::size_of::<T>() + 8])
+        }))
+    }
+
+    /// Returns a `RefMut` to the account data structure for reading or writing, for an account
+    /// whose discriminator is not set yet. Unlike `AccountLoader`, which sets it on exit, the
+    /// discriminator is written here since this loader is not part of the instruction accounts.
+    pub fn load_init(&self) -> Result<RefMut<'_, T>> {
+        if !self.acc_info.is_writable {
+            return Err(ErrorCode::AccountNotMutable.into());
+        }
+        let mut data = self.acc_info.try_borrow_mut_data()?;
+        if data[..T::DISCRIMINATOR.len()].iter().any(|byte| *byte != 0) {
+            return Err(ErrorCode::AccountDiscriminatorAlreadySet.into());
+        }
+        data[..T::DISCRIMINATOR.len()].copy_from_slice(T::DISCRIMINATOR);
+        Ok(RefMut::map(data, |data| {
+            bytemuck::from_bytes_mut(&mut data.deref_mut()[8..mem::size_of::<T>() + 8])
+        }))
+    }
+}
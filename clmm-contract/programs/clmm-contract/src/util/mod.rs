@@ -1,2 +1,5 @@
 pub mod system;
 pub use system::*;
+
+pub mod account_load;
+pub use account_load::*;
@@ -0,0 +1,1121 @@
+//! In-process test harness for the clmm program
+//!
+//! Runs the program built by `anchor build` in LiteSVM, together with the SPL Token,
+//! Token-2022 and associated token programs LiteSVM ships with, so the suite needs no
+//! validator and no network. Without an SBF build the program runs natively, see [`native`].
+
+#![allow(dead_code, clippy::result_large_err)]
+
+mod native;
+
+use std::path::PathBuf;
+
+use anchor_lang::{
+    prelude::{Clock, Pubkey},
+    solana_program::{instruction::Instruction, program_pack::Pack, system_instruction},
+};
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address_with_program_id,
+        spl_associated_token_account::instruction::create_associated_token_account,
+    },
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{transfer_fee, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    },
+};
+use clmm_client::{
+    check_solvency, decode_amm_config, decode_locked_position, decode_personal_position,
+    decode_pool_state, decode_protocol_position, decode_tick_array_bitmap_extension,
+    decode_tick_array_state,
+    instructions::{self, PriceBand, RepositionTarget},
+    pda,
+};
+use clmm_contract::{
+    context::create_amm_config::ID as ADMIN,
+    state::{
+        AmmConfig, LockedPositionState, PersonalPositionState, PoolState, ProtocolPositionState,
+        TickArrayBitmapExtension, TickArrayState, TickState,
+    },
+};
+use clmm_math::tick_math::get_sqrt_price_at_tick;
+use litesvm::{
+    types::{FailedTransactionMetadata, TransactionMetadata},
+    LiteSVM,
+};
+use solana_sdk::{
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+
+pub const DECIMALS: u8 = 6;
+pub const TICK_SPACING: u16 = 10;
+pub const TRADE_FEE_RATE: u32 = 2_500;
+pub const PROTOCOL_FEE_RATE: u32 = 120_000;
+pub const FUND_FEE_RATE: u32 = 40_000;
+/// Unix time of the test clock, pools are created with an earlier `open_time`
+pub const NOW: i64 = 1_700_000_000;
+/// Liquidity of each position [`pool_with_earned_fees`] opens
+pub const EARNED_FEES_LIQUIDITY: u128 = 1_000_000_000_000;
+/// Input of each zap [`pool_with_earned_fees`] makes
+pub const EARNED_FEES_ZAP_AMOUNT: u64 = 100_000_000;
+
+/// Token program and transfer fee of a test mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MintKind {
+    Spl,
+    Token2022,
+    /// Token-2022 mint charging `basis_points` of every transfer, capped at `maximum_fee`
+    Token2022TransferFee {
+        basis_points: u16,
+        maximum_fee: u64,
+    },
+}
+
+impl MintKind {
+    pub fn token_program(self) -> Pubkey {
+        match self {
+            MintKind::Spl => spl_token::ID,
+            _ => spl_token_2022::ID,
+        }
+    }
+}
+
+/// A created mint and the user's funded token account of it
+#[derive(Debug, Clone, Copy)]
+pub struct TestMint {
+    pub mint: Pubkey,
+    pub kind: MintKind,
+    pub user_account: Pubkey,
+}
+
+/// A pool created by [`TestEnv::create_pool`]
+#[derive(Debug, Clone, Copy)]
+pub struct TestPool {
+    pub address: Pubkey,
+    pub amm_config: Pubkey,
+    pub mint_0: TestMint,
+    pub mint_1: TestMint,
+}
+
+/// A position opened by [`TestEnv::open_position`]
+#[derive(Debug, Clone, Copy)]
+pub struct TestPosition {
+    pub nft_mint: Pubkey,
+    pub nft_account: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+}
+
+pub struct TestEnv {
+    pub svm: LiteSVM,
+    pub user: Keypair,
+}
+
+/// SBF build of the clmm program
+pub fn program_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/clmm_contract.so")
+}
+
+impl TestEnv {
+    /// Fresh SVM with the clmm program deployed and a funded user
+    ///
+    /// The SBF build of `anchor build` is deployed when there is one, otherwise the program
+    /// runs natively. Signature verification is disabled so the admin of `create_amm_config`
+    /// can sign without its private key.
+    pub fn new() -> Self {
+        let mut svm = LiteSVM::new().with_sigverify(false);
+        let path = program_path();
+        if path.exists() {
+            svm.add_program_from_file(clmm_contract::ID, &path).unwrap();
+        } else {
+            native::add_program(&mut svm);
+        }
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp = NOW;
+        svm.set_sysvar(&clock);
+
+        let user = Keypair::new();
+        svm.airdrop(&user.pubkey(), 100_000_000_000).unwrap();
+        svm.airdrop(&ADMIN, 100_000_000_000).unwrap();
+
+        Self { svm, user }
+    }
+
+    /// Send `instructions` paid by the user. Required signers missing from `signers` keep an
+    /// empty signature, which passes with signature verification disabled.
+    pub fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&self.user.pubkey()));
+        let blockhash = self.svm.latest_blockhash();
+        let mut keypairs = vec![&self.user];
+        keypairs.extend_from_slice(signers);
+        transaction.partial_sign(&keypairs, blockhash);
+        let result = self.svm.send_transaction(transaction);
+        // identical transactions in a row would otherwise be rejected as already processed
+        self.svm.expire_blockhash();
+        result
+    }
+
+    pub fn create_amm_config(&mut self, index: u16) -> Pubkey {
+        let instruction = instructions::create_amm_config(
+            ADMIN,
+            index,
+            TICK_SPACING,
+            PROTOCOL_FEE_RATE,
+            TRADE_FEE_RATE,
+            FUND_FEE_RATE,
+        );
+        self.send(&[instruction], &[]).unwrap();
+        pda::amm_config_address(index).0
+    }
+
+    /// Create a mint with the user as mint authority and mint `supply` to a user token account
+    pub fn create_mint(&mut self, kind: MintKind, supply: u64) -> TestMint {
+        let mint = Keypair::new();
+        let token_program = kind.token_program();
+        let user = self.user.pubkey();
+
+        let mut extensions = vec![];
+        if let MintKind::Token2022TransferFee { .. } = kind {
+            extensions.push(ExtensionType::TransferFeeConfig);
+        }
+        let space = if kind == MintKind::Spl {
+            spl_token::state::Mint::LEN
+        } else {
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&extensions)
+                .unwrap()
+        };
+
+        let mut instructions = vec![system_instruction::create_account(
+            &user,
+            &mint.pubkey(),
+            self.svm.minimum_balance_for_rent_exemption(space),
+            space as u64,
+            &token_program,
+        )];
+        if let MintKind::Token2022TransferFee {
+            basis_points,
+            maximum_fee,
+        } = kind
+        {
+            instructions.push(
+                transfer_fee::instruction::initialize_transfer_fee_config(
+                    &token_program,
+                    &mint.pubkey(),
+                    Some(&user),
+                    Some(&user),
+                    basis_points,
+                    maximum_fee,
+                )
+                .unwrap(),
+            );
+        }
+        instructions.push(
+            spl_token_2022::instruction::initialize_mint2(
+                &token_program,
+                &mint.pubkey(),
+                &user,
+                None,
+                DECIMALS,
+            )
+            .unwrap(),
+        );
+        self.send(&instructions, &[&mint]).unwrap();
+
+        let user_account = self.create_token_account(&mint.pubkey(), kind);
+        self.send(
+            &[spl_token_2022::instruction::mint_to(
+                &token_program,
+                &mint.pubkey(),
+                &user_account,
+                &user,
+                &[],
+                supply,
+            )
+            .unwrap()],
+            &[],
+        )
+        .unwrap();
+
+        TestMint {
+            mint: mint.pubkey(),
+            kind,
+            user_account,
+        }
+    }
+
+    /// Create a token account of `mint` owned by the user
+    pub fn create_token_account(&mut self, mint: &Pubkey, kind: MintKind) -> Pubkey {
+        let account = Keypair::new();
+        let token_program = kind.token_program();
+        let space = match kind {
+            MintKind::Spl => spl_token::state::Account::LEN,
+            MintKind::Token2022 => {
+                ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[])
+                    .unwrap()
+            }
+            MintKind::Token2022TransferFee { .. } => {
+                ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[
+                    ExtensionType::TransferFeeAmount,
+                ])
+                .unwrap()
+            }
+        };
+
+        self.send(
+            &[
+                system_instruction::create_account(
+                    &self.user.pubkey(),
+                    &account.pubkey(),
+                    self.svm.minimum_balance_for_rent_exemption(space),
+                    space as u64,
+                    &token_program,
+                ),
+                spl_token_2022::instruction::initialize_account3(
+                    &token_program,
+                    &account.pubkey(),
+                    mint,
+                    &self.user.pubkey(),
+                )
+                .unwrap(),
+            ],
+            &[&account],
+        )
+        .unwrap();
+        account.pubkey()
+    }
+
+    /// Create two mints and a pool of them at `sqrt_price_x64`. The mints are ordered by
+    /// address, so `kinds` is not necessarily the order of `mint_0` and `mint_1`.
+    pub fn create_pool(
+        &mut self,
+        amm_config: Pubkey,
+        kinds: [MintKind; 2],
+        sqrt_price_x64: u128,
+    ) -> TestPool {
+        let mut mints = kinds.map(|kind| self.create_mint(kind, 1_000_000_000_000));
+        mints.sort_by_key(|mint| mint.mint);
+        let [mint_0, mint_1] = mints;
+
+        let instruction = instructions::create_pool(
+            self.user.pubkey(),
+            amm_config,
+            self.create_pool_fee_receiver(&amm_config),
+            mint_0.mint,
+            mint_1.mint,
+            mint_0.kind.token_program(),
+            mint_1.kind.token_program(),
+            sqrt_price_x64,
+            0,
+            &[],
+        );
+        self.send(&[instruction], &[]).unwrap();
+
+        TestPool {
+            address: pda::pool_address(&amm_config, &mint_0.mint, &mint_1.mint).0,
+            amm_config,
+            mint_0,
+            mint_1,
+        }
+    }
+
+    pub fn open_position(
+        &mut self,
+        pool: &TestPool,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+    ) -> Result<TestPosition, FailedTransactionMetadata> {
+        self.open_position_in_band(
+            pool,
+            tick_lower_index,
+            tick_upper_index,
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            PriceBand::default(),
+        )
+    }
+
+    /// Open a position that fails unless the pool price is inside `price_band`
+    #[allow(clippy::too_many_arguments)]
+    pub fn open_position_in_band(
+        &mut self,
+        pool: &TestPool,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        price_band: PriceBand,
+    ) -> Result<TestPosition, FailedTransactionMetadata> {
+        let nft_mint = Keypair::new();
+        let pool_state = self.pool_state(&pool.address);
+        let instruction = instructions::open_position_with_token22_nft(
+            self.user.pubkey(),
+            self.user.pubkey(),
+            nft_mint.pubkey(),
+            &pool_state,
+            pool.mint_0.user_account,
+            pool.mint_1.user_account,
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            tick_lower_index,
+            tick_upper_index,
+            false,
+            None,
+            price_band,
+        );
+        self.send(&[instruction], &[&nft_mint])?;
+
+        Ok(TestPosition {
+            nft_mint: nft_mint.pubkey(),
+            nft_account: get_associated_token_address_with_program_id(
+                &self.user.pubkey(),
+                &nft_mint.pubkey(),
+                &spl_token_2022::ID,
+            ),
+            tick_lower_index,
+            tick_upper_index,
+        })
+    }
+
+    pub fn increase_liquidity(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        self.increase_liquidity_in_band(
+            pool,
+            position,
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            PriceBand::default(),
+        )
+    }
+
+    /// Increase the liquidity of a position, failing unless the pool price is inside `price_band`
+    pub fn increase_liquidity_in_band(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        price_band: PriceBand,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let pool_state = self.pool_state(&pool.address);
+        let personal_position = self.personal_position(&position.nft_mint);
+        let instruction = instructions::increase_liquidity_v2(
+            self.user.pubkey(),
+            position.nft_account,
+            &pool_state,
+            &personal_position,
+            pool.mint_0.user_account,
+            pool.mint_1.user_account,
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            None,
+            price_band,
+        );
+        self.send(&[instruction], &[])
+    }
+
+    /// Decrease the liquidity of a position, paying it out with the owed fees to the user
+    pub fn decrease_liquidity(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        liquidity: u128,
+        amount_0_min: u64,
+        amount_1_min: u64,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        self.decrease_liquidity_in_band(
+            pool,
+            position,
+            liquidity,
+            amount_0_min,
+            amount_1_min,
+            PriceBand::default(),
+        )
+    }
+
+    /// Decrease the liquidity of a position, failing unless the pool price is inside `price_band`
+    pub fn decrease_liquidity_in_band(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        liquidity: u128,
+        amount_0_min: u64,
+        amount_1_min: u64,
+        price_band: PriceBand,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let pool_state = self.pool_state(&pool.address);
+        let personal_position = self.personal_position(&position.nft_mint);
+        let instruction = instructions::decrease_liquidity_v2(
+            self.user.pubkey(),
+            position.nft_account,
+            &pool_state,
+            &personal_position,
+            pool.mint_0.user_account,
+            pool.mint_1.user_account,
+            liquidity,
+            amount_0_min,
+            amount_1_min,
+            price_band,
+        );
+        self.send(&[instruction], &[])
+    }
+
+    /// Zap `amount_in` of token 0 when `zero_for_one`, of token 1 otherwise, into `position`,
+    /// swapping `swap_amount` of it
+    #[allow(clippy::too_many_arguments)]
+    pub fn zap_in(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        zero_for_one: bool,
+        amount_in: u64,
+        swap_amount: u64,
+        liquidity_min: u128,
+        swap_tick_arrays: &[i32],
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        self.zap_in_in_band(
+            pool,
+            position,
+            zero_for_one,
+            amount_in,
+            swap_amount,
+            liquidity_min,
+            PriceBand::default(),
+            swap_tick_arrays,
+        )
+    }
+
+    /// Zap into `position`, failing unless the pool price is inside `price_band`
+    #[allow(clippy::too_many_arguments)]
+    pub fn zap_in_in_band(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        zero_for_one: bool,
+        amount_in: u64,
+        swap_amount: u64,
+        liquidity_min: u128,
+        price_band: PriceBand,
+        swap_tick_arrays: &[i32],
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let pool_state = self.pool_state(&pool.address);
+        let personal_position = self.personal_position(&position.nft_mint);
+        let instruction = instructions::zap_in(
+            self.user.pubkey(),
+            position.nft_account,
+            &pool_state,
+            &personal_position,
+            pool.mint_0.user_account,
+            pool.mint_1.user_account,
+            zero_for_one,
+            amount_in,
+            swap_amount,
+            liquidity_min,
+            price_band,
+            swap_tick_arrays,
+        );
+        self.send(&[instruction], &[])
+    }
+
+    /// Open a position in `[tick_lower_index, tick_upper_index)` from `amount_in` of token 0
+    /// when `zero_for_one`, of token 1 otherwise, swapping `swap_amount` of it
+    #[allow(clippy::too_many_arguments)]
+    pub fn zap_in_open_position(
+        &mut self,
+        pool: &TestPool,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        zero_for_one: bool,
+        amount_in: u64,
+        swap_amount: u64,
+        liquidity_min: u128,
+        swap_tick_arrays: &[i32],
+    ) -> Result<TestPosition, FailedTransactionMetadata> {
+        self.zap_in_open_position_in_band(
+            pool,
+            tick_lower_index,
+            tick_upper_index,
+            zero_for_one,
+            amount_in,
+            swap_amount,
+            liquidity_min,
+            PriceBand::default(),
+            swap_tick_arrays,
+        )
+    }
+
+    /// Open a position from a single token, failing unless the pool price is inside `price_band`
+    #[allow(clippy::too_many_arguments)]
+    pub fn zap_in_open_position_in_band(
+        &mut self,
+        pool: &TestPool,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        zero_for_one: bool,
+        amount_in: u64,
+        swap_amount: u64,
+        liquidity_min: u128,
+        price_band: PriceBand,
+        swap_tick_arrays: &[i32],
+    ) -> Result<TestPosition, FailedTransactionMetadata> {
+        let nft_mint = Keypair::new();
+        let pool_state = self.pool_state(&pool.address);
+        let instruction = instructions::zap_in_open_position(
+            self.user.pubkey(),
+            self.user.pubkey(),
+            nft_mint.pubkey(),
+            &pool_state,
+            pool.mint_0.user_account,
+            pool.mint_1.user_account,
+            tick_lower_index,
+            tick_upper_index,
+            zero_for_one,
+            amount_in,
+            swap_amount,
+            liquidity_min,
+            false,
+            price_band,
+            swap_tick_arrays,
+        );
+        self.send(&[instruction], &[&nft_mint])?;
+
+        Ok(TestPosition {
+            nft_mint: nft_mint.pubkey(),
+            nft_account: get_associated_token_address_with_program_id(
+                &self.user.pubkey(),
+                &nft_mint.pubkey(),
+                &spl_token_2022::ID,
+            ),
+            tick_lower_index,
+            tick_upper_index,
+        })
+    }
+
+    /// Move the liquidity of `position` to `[tick_lower_index, tick_upper_index)`, into
+    /// `new_position` when given, otherwise keeping the nft of `position`
+    #[allow(clippy::too_many_arguments)]
+    pub fn reposition(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        new_position: Option<&TestPosition>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        zero_for_one: bool,
+        swap_amount: u64,
+        liquidity_min: u128,
+        price_band: PriceBand,
+        swap_tick_arrays: &[i32],
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let target = match new_position {
+            Some(new_position) => RepositionTarget::Position {
+                nft_mint: new_position.nft_mint,
+                nft_account: new_position.nft_account,
+            },
+            None => RepositionTarget::InPlace,
+        };
+        let instruction = self.reposition_instruction(
+            pool,
+            position,
+            target,
+            tick_lower_index,
+            tick_upper_index,
+            zero_for_one,
+            swap_amount,
+            liquidity_min,
+            price_band,
+            swap_tick_arrays,
+        );
+        self.send(&[instruction], &[])
+    }
+
+    /// Move the liquidity of `position` to `[tick_lower_index, tick_upper_index)`, into a
+    /// position opened with a fresh nft of the user
+    #[allow(clippy::too_many_arguments)]
+    pub fn reposition_into_fresh_nft(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        zero_for_one: bool,
+        swap_amount: u64,
+        liquidity_min: u128,
+        swap_tick_arrays: &[i32],
+    ) -> Result<TestPosition, FailedTransactionMetadata> {
+        let nft_mint = Keypair::new();
+        let instruction = self.reposition_instruction(
+            pool,
+            position,
+            RepositionTarget::FreshNft {
+                nft_mint: nft_mint.pubkey(),
+                with_metadata: false,
+            },
+            tick_lower_index,
+            tick_upper_index,
+            zero_for_one,
+            swap_amount,
+            liquidity_min,
+            PriceBand::default(),
+            swap_tick_arrays,
+        );
+        self.send(&[instruction], &[&nft_mint])?;
+        Ok(TestPosition {
+            nft_mint: nft_mint.pubkey(),
+            nft_account: get_associated_token_address_with_program_id(
+                &self.user.pubkey(),
+                &nft_mint.pubkey(),
+                &spl_token_2022::ID,
+            ),
+            tick_lower_index,
+            tick_upper_index,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn reposition_instruction(
+        &self,
+        pool: &TestPool,
+        position: &TestPosition,
+        target: RepositionTarget,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        zero_for_one: bool,
+        swap_amount: u64,
+        liquidity_min: u128,
+        price_band: PriceBand,
+        swap_tick_arrays: &[i32],
+    ) -> Instruction {
+        let pool_state = self.pool_state(&pool.address);
+        let personal_position = self.personal_position(&position.nft_mint);
+        instructions::reposition(
+            self.user.pubkey(),
+            position.nft_account,
+            &pool_state,
+            &personal_position,
+            target,
+            pool.mint_0.user_account,
+            pool.mint_1.user_account,
+            tick_lower_index,
+            tick_upper_index,
+            zero_for_one,
+            swap_amount,
+            liquidity_min,
+            price_band,
+            swap_tick_arrays,
+        )
+    }
+
+    /// Transfer the nft of `position` to the associated token account of `new_owner`
+    pub fn transfer_position_nft(
+        &mut self,
+        position: &TestPosition,
+        new_owner: &Pubkey,
+    ) -> TestPosition {
+        let nft_account = get_associated_token_address_with_program_id(
+            new_owner,
+            &position.nft_mint,
+            &spl_token_2022::ID,
+        );
+        self.send(
+            &[
+                create_associated_token_account(
+                    &self.user.pubkey(),
+                    new_owner,
+                    &position.nft_mint,
+                    &spl_token_2022::ID,
+                ),
+                spl_token_2022::instruction::transfer_checked(
+                    &spl_token_2022::ID,
+                    &position.nft_account,
+                    &position.nft_mint,
+                    &nft_account,
+                    &self.user.pubkey(),
+                    &[],
+                    1,
+                    0,
+                )
+                .unwrap(),
+            ],
+            &[],
+        )
+        .unwrap();
+        TestPosition {
+            nft_account,
+            ..*position
+        }
+    }
+
+    /// Approve `operator` to compound the fees of `position`, the default pubkey revokes it
+    pub fn set_position_operator(
+        &mut self,
+        position: &TestPosition,
+        operator: Pubkey,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let personal_position = self.personal_position(&position.nft_mint);
+        let instruction = instructions::set_position_operator(
+            self.user.pubkey(),
+            position.nft_account,
+            &personal_position,
+            operator,
+        );
+        self.send(&[instruction], &[])
+    }
+
+    /// Compound the fees of `position`, signed by `operator` when given, by the holder otherwise
+    pub fn compound(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        operator: Option<&Keypair>,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        self.compound_in_band(pool, position, operator, PriceBand::default())
+    }
+
+    /// Compound the fees of `position`, failing unless the pool price is inside `price_band`
+    pub fn compound_in_band(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        operator: Option<&Keypair>,
+        price_band: PriceBand,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let pool_state = self.pool_state(&pool.address);
+        let personal_position = self.personal_position(&position.nft_mint);
+        let (authority, signers) = match operator {
+            Some(operator) => (operator.pubkey(), vec![operator]),
+            None => (self.user.pubkey(), vec![]),
+        };
+        let instruction = instructions::compound(
+            authority,
+            position.nft_account,
+            &pool_state,
+            &personal_position,
+            price_band,
+        );
+        self.send(&[instruction], &signers)
+    }
+
+    /// Lock `position` until `unlock_time`, for good when `None`, returning the fee key mint.
+    /// The fee key goes to the associated token account of the user.
+    pub fn lock_position(
+        &mut self,
+        position: &TestPosition,
+        unlock_time: Option<i64>,
+    ) -> Result<Pubkey, FailedTransactionMetadata> {
+        let personal_position = self.personal_position(&position.nft_mint);
+        let fee_key_mint = Keypair::new();
+        let instruction = instructions::lock_position(
+            self.user.pubkey(),
+            position.nft_account,
+            &personal_position,
+            fee_key_mint.pubkey(),
+            unlock_time,
+        );
+        self.send(&[instruction], &[&fee_key_mint])?;
+        Ok(fee_key_mint.pubkey())
+    }
+
+    /// Unlock `position` with the fee key of the user, the nft goes to its associated token
+    /// account
+    pub fn unlock_position(
+        &mut self,
+        position: &TestPosition,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let locked_position = self.locked_position(&position.nft_mint);
+        let instruction = instructions::unlock_position(
+            self.user.pubkey(),
+            locked_position.position_nft_mint,
+            locked_position.fee_key_mint,
+        );
+        self.send(&[instruction], &[])
+    }
+
+    /// Collect the fees of locked `position` into the user's token accounts of the pool for the
+    /// fee key in `fee_key_account`, signed by `fee_key_owner` when given, by the user otherwise
+    pub fn collect_locked_position_fees(
+        &mut self,
+        pool: &TestPool,
+        position: &TestPosition,
+        fee_key_owner: Option<&Keypair>,
+        fee_key_account: Pubkey,
+    ) -> Result<TransactionMetadata, FailedTransactionMetadata> {
+        let pool_state = self.pool_state(&pool.address);
+        let personal_position = self.personal_position(&position.nft_mint);
+        let (fee_key_owner, signers) = match fee_key_owner {
+            Some(owner) => (owner.pubkey(), vec![owner]),
+            None => (self.user.pubkey(), vec![]),
+        };
+        let instruction = instructions::collect_locked_position_fees(
+            fee_key_owner,
+            fee_key_account,
+            &pool_state,
+            &personal_position,
+            pool.mint_0.user_account,
+            pool.mint_1.user_account,
+        );
+        self.send(&[instruction], &signers)
+    }
+
+    /// Start indexes of the initialized tick arrays a swap from the pool price walks through
+    /// first, at most three
+    pub fn swap_tick_arrays(&self, pool: &TestPool, zero_for_one: bool) -> Vec<i32> {
+        let pool_state = self.pool_state(&pool.address);
+        let tickarray_bitmap_extension = self.tick_array_bitmap_extension(&pool.address);
+        let (_, mut start_index) = pool_state
+            .get_first_initialized_tick_array(Some(&tickarray_bitmap_extension), zero_for_one)
+            .unwrap();
+        let mut start_indexes = vec![start_index];
+        while start_indexes.len() < 3 {
+            match pool_state
+                .next_initialized_tick_array_start_index(
+                    Some(&tickarray_bitmap_extension),
+                    start_index,
+                    zero_for_one,
+                )
+                .unwrap()
+            {
+                Some(next_start_index) => {
+                    start_index = next_start_index;
+                    start_indexes.push(start_index);
+                }
+                None => break,
+            }
+        }
+        start_indexes
+    }
+
+    fn account_data(&self, address: &Pubkey) -> Vec<u8> {
+        self.svm
+            .get_account(address)
+            .unwrap_or_else(|| panic!("account {} not found", address))
+            .data
+    }
+
+    pub fn amm_config(&self, address: &Pubkey) -> AmmConfig {
+        decode_amm_config(&self.account_data(address)).unwrap()
+    }
+
+    /// Receiver `create_pool` has to pass for `amm_config`, `None` if it charges no fee
+    pub fn create_pool_fee_receiver(&self, amm_config: &Pubkey) -> Option<Pubkey> {
+        let amm_config = self.amm_config(amm_config);
+        (amm_config.create_pool_fee > 0).then_some(amm_config.create_pool_fee_receiver)
+    }
+
+    pub fn pool_state(&self, address: &Pubkey) -> PoolState {
+        decode_pool_state(&self.account_data(address)).unwrap()
+    }
+
+    pub fn tick_array(&self, pool: &Pubkey, start_tick_index: i32) -> TickArrayState {
+        let address = pda::tick_array_address(pool, start_tick_index).0;
+        decode_tick_array_state(&self.account_data(&address)).unwrap()
+    }
+
+    pub fn tick_array_bitmap_extension(&self, pool: &Pubkey) -> TickArrayBitmapExtension {
+        let address = pda::tick_array_bitmap_extension_address(pool).0;
+        decode_tick_array_bitmap_extension(&self.account_data(&address)).unwrap()
+    }
+
+    /// State of `tick` in its tick array
+    pub fn tick_state(&self, pool: &Pubkey, tick: i32) -> TickState {
+        let start_tick_index = TickArrayState::get_array_start_index(tick, TICK_SPACING);
+        let tick_array = self.tick_array(pool, start_tick_index);
+        let offset = tick_array
+            .get_tick_offset_in_array(tick, TICK_SPACING)
+            .unwrap();
+        tick_array.ticks[offset]
+    }
+
+    pub fn protocol_position(
+        &self,
+        pool: &Pubkey,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+    ) -> ProtocolPositionState {
+        let address = pda::protocol_position_address(pool, tick_lower_index, tick_upper_index).0;
+        decode_protocol_position(&self.account_data(&address)).unwrap()
+    }
+
+    pub fn personal_position(&self, position_nft_mint: &Pubkey) -> PersonalPositionState {
+        let address = pda::personal_position_address(position_nft_mint).0;
+        decode_personal_position(&self.account_data(&address)).unwrap()
+    }
+
+    pub fn locked_position(&self, position_nft_mint: &Pubkey) -> LockedPositionState {
+        let address = pda::locked_position_address(position_nft_mint).0;
+        decode_locked_position(&self.account_data(&address)).unwrap()
+    }
+
+    /// Move the clock to `unix_timestamp`
+    pub fn set_time(&mut self, unix_timestamp: i64) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp = unix_timestamp;
+        self.svm.set_sysvar(&clock);
+    }
+
+    /// Balance of an SPL Token or Token-2022 account, excluding withheld transfer fees
+    pub fn token_balance(&self, address: &Pubkey) -> u64 {
+        let data = self.account_data(address);
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)
+            .unwrap()
+            .base
+            .amount
+    }
+
+    /// Balances of the user's token 0 and token 1 accounts of `pool`
+    pub fn user_balances(&self, pool: &TestPool) -> (u64, u64) {
+        (
+            self.token_balance(&pool.mint_0.user_account),
+            self.token_balance(&pool.mint_1.user_account),
+        )
+    }
+
+    /// Balances of the token 0 and token 1 vaults of `pool`
+    pub fn vault_balances(&self, pool: &TestPool) -> (u64, u64) {
+        let pool_state = self.pool_state(&pool.address);
+        (
+            self.token_balance(&pool_state.token_vault_0),
+            self.token_balance(&pool_state.token_vault_1),
+        )
+    }
+
+    /// Panics unless the vaults of the pool cover `positions`, which must be all positions of
+    /// the pool, their fees and the protocol and fund fees
+    pub fn assert_solvent(&self, pool: &TestPool, positions: &[TestPosition]) {
+        let pool_state = self.pool_state(&pool.address);
+        let mut start_tick_indexes: Vec<i32> = positions
+            .iter()
+            .flat_map(|position| [position.tick_lower_index, position.tick_upper_index])
+            .map(|tick| TickArrayState::get_array_start_index(tick, TICK_SPACING))
+            .collect();
+        start_tick_indexes.sort();
+        start_tick_indexes.dedup();
+        let tick_arrays = start_tick_indexes
+            .into_iter()
+            .map(|start_tick_index| self.tick_array(&pool.address, start_tick_index));
+        let personal_positions: Vec<_> = positions
+            .iter()
+            .map(|position| self.personal_position(&position.nft_mint))
+            .collect();
+
+        let report = check_solvency(
+            &pool_state,
+            tick_arrays,
+            &personal_positions,
+            (
+                self.token_balance(&pool_state.token_vault_0),
+                self.token_balance(&pool_state.token_vault_1),
+            ),
+        )
+        .unwrap();
+        assert!(report.is_solvent(), "{:?}", report);
+        assert_eq!(report.liquidity_in_range, { pool_state.liquidity });
+    }
+
+    /// Transfer fee `mint` charges on a transfer of `amount` in the current epoch
+    pub fn transfer_fee(&self, mint: &TestMint, amount: u64) -> u64 {
+        if !matches!(mint.kind, MintKind::Token2022TransferFee { .. }) {
+            return 0;
+        }
+        let data = self.account_data(&mint.mint);
+        let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data).unwrap();
+        let config = state
+            .get_extension::<transfer_fee::TransferFeeConfig>()
+            .unwrap();
+        config
+            .calculate_epoch_fee(self.svm.get_sysvar::<Clock>().epoch, amount)
+            .unwrap()
+    }
+}
+
+/// Pool at tick 0 with `EARNED_FEES_LIQUIDITY` in [-6000, 6000) and in [-600, 600), the latter
+/// having earned fees in both tokens from a zap in each direction. Returns the wide position, the
+/// narrow one and the position the first zap opened in the same range.
+pub fn pool_with_earned_fees() -> (TestEnv, TestPool, [TestPosition; 3]) {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let pool = env.create_pool(
+        amm_config,
+        [MintKind::Spl, MintKind::Spl],
+        get_sqrt_price_at_tick(0).unwrap(),
+    );
+    let wide = env
+        .open_position(
+            &pool,
+            -6000,
+            6000,
+            EARNED_FEES_LIQUIDITY,
+            u64::MAX,
+            u64::MAX,
+        )
+        .unwrap();
+    let position = env
+        .open_position(&pool, -600, 600, EARNED_FEES_LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+
+    // the range is centered on the price, so about half of the input is swapped
+    let swap_tick_arrays = env.swap_tick_arrays(&pool, false);
+    let zap = env
+        .zap_in_open_position(
+            &pool,
+            -600,
+            600,
+            false,
+            EARNED_FEES_ZAP_AMOUNT,
+            EARNED_FEES_ZAP_AMOUNT / 2,
+            0,
+            &swap_tick_arrays,
+        )
+        .unwrap();
+    let swap_tick_arrays = env.swap_tick_arrays(&pool, true);
+    env.zap_in(
+        &pool,
+        &zap,
+        true,
+        EARNED_FEES_ZAP_AMOUNT,
+        EARNED_FEES_ZAP_AMOUNT / 2,
+        0,
+        &swap_tick_arrays,
+    )
+    .unwrap();
+
+    (env, pool, [wide, position, zap])
+}
+
+/// Whether the bit of the tick array starting at `start_tick_index` is set in the pool's
+/// default bitmap
+pub fn tick_array_bit_is_set(pool_state: &PoolState, start_tick_index: i32) -> bool {
+    let offset = pool_state.get_tick_array_offset(start_tick_index).unwrap();
+    let bitmap = pool_state.tick_array_bitmap;
+    bitmap[offset / 64] & (1u64 << (offset % 64)) != 0
+}
+
+/// Custom error code the first instruction of a failed transaction returned
+pub fn custom_error_code(failed: &FailedTransactionMetadata) -> Option<u32> {
+    match failed.err {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => Some(code),
+        _ => None,
+    }
+}
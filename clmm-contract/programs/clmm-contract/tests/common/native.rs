@@ -0,0 +1,309 @@
+//! Runs the clmm program as a native LiteSVM builtin, for when no SBF build is at hand
+//!
+//! The builtin serializes the instruction accounts like the SBF loader, calls the program's
+//! entrypoint and writes the changed accounts back. Cross-program invocations and sysvars go
+//! through `program_stubs` into the same invoke context, the way `solana-program-test` runs
+//! native processors. Compute units are not metered.
+
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    mem::transmute,
+    panic::AssertUnwindSafe,
+    ptr,
+    sync::Once,
+};
+
+use anchor_lang::{
+    prelude::{AccountInfo, ProgramError, Pubkey},
+    solana_program::{
+        entrypoint::{deserialize, ProgramResult, SUCCESS},
+        instruction::Instruction,
+        program_error::UNSUPPORTED_SYSVAR,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        stable_layout::stable_instruction::StableInstruction,
+        sysvar::Sysvar,
+    },
+};
+use litesvm::LiteSVM;
+use solana_log_collector::ic_msg;
+use solana_program_runtime::{
+    declare_process_instruction, invoke_context::InvokeContext,
+    serialization::serialize_parameters, stable_log,
+};
+use solana_sdk::{
+    account::Account, instruction::InstructionError, native_loader,
+    transaction_context::IndexOfAccount,
+};
+use solana_timings::ExecuteTimings;
+
+thread_local! {
+    static INVOKE_CONTEXT: Cell<*mut ()> = const { Cell::new(ptr::null_mut()) };
+}
+
+fn set_invoke_context(new: &mut InvokeContext) {
+    INVOKE_CONTEXT.with(|invoke_context| invoke_context.set(new as *mut InvokeContext as *mut ()));
+}
+
+fn get_invoke_context<'a, 'b>() -> &'a mut InvokeContext<'b> {
+    let ptr = INVOKE_CONTEXT.with(Cell::get);
+    assert!(!ptr.is_null(), "invoke context not set");
+    unsafe { &mut *(ptr as *mut InvokeContext) }
+}
+
+/// Deploy the clmm program into `svm` as a native builtin
+pub fn add_program(svm: &mut LiteSVM) {
+    static STUBS: Once = Once::new();
+    STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(NativeSyscallStubs));
+    });
+    svm.add_builtin(clmm_contract::ID, Entrypoint::vm);
+    // `add_builtin` leaves the program owned by the BPF loader, which refuses to run builtins,
+    // while programs owned by the native loader are looked up as builtins
+    svm.set_account(
+        clmm_contract::ID,
+        Account {
+            lamports: 1,
+            data: b"clmm_contract".to_vec(),
+            owner: native_loader::ID,
+            executable: true,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+}
+
+// the runtime rejects builtins that consume no compute units
+declare_process_instruction!(Entrypoint, 1, |invoke_context| {
+    process_instruction(invoke_context)
+});
+
+fn process_instruction(invoke_context: &mut InvokeContext) -> Result<(), InstructionError> {
+    set_invoke_context(invoke_context);
+
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let instruction_account_indexes: HashSet<IndexOfAccount> =
+        (0..instruction_context.get_number_of_instruction_accounts()).collect();
+
+    let (mut parameter_bytes, _, _) =
+        serialize_parameters(transaction_context, instruction_context, true, true)?;
+    let (program_id, account_infos, input) =
+        unsafe { deserialize(&mut parameter_bytes.as_slice_mut()[0] as *mut u8) };
+
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        clmm_contract::entry(program_id, &account_infos, input)
+    }));
+    match result {
+        Ok(result) => result.map_err(|error| InstructionError::from(u64::from(error)))?,
+        Err(_) => return Err(InstructionError::ProgramFailedToComplete),
+    }
+
+    // a cpi may have replaced the instruction context, so fetch it again
+    let account_infos: HashMap<_, _> = account_infos
+        .iter()
+        .map(|account_info| (account_info.key, account_info))
+        .collect();
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    for index in instruction_account_indexes {
+        let mut borrowed_account =
+            instruction_context.try_borrow_instruction_account(transaction_context, index)?;
+        if !borrowed_account.is_writable() {
+            continue;
+        }
+        let Some(account_info) = account_infos.get(borrowed_account.get_key()) else {
+            continue;
+        };
+        if borrowed_account.get_lamports() != account_info.lamports() {
+            borrowed_account.set_lamports(account_info.lamports())?;
+        }
+        if borrowed_account
+            .can_data_be_resized(account_info.data_len())
+            .is_ok()
+            && borrowed_account.can_data_be_changed().is_ok()
+        {
+            borrowed_account.set_data_from_slice(&account_info.data.borrow())?;
+        }
+        if borrowed_account.get_owner() != account_info.owner {
+            borrowed_account.set_owner(account_info.owner.as_ref())?;
+        }
+    }
+    Ok(())
+}
+
+fn get_sysvar<T: Sysvar + Clone>(
+    sysvar: Result<std::sync::Arc<T>, InstructionError>,
+    var_addr: *mut u8,
+) -> u64 {
+    match sysvar {
+        Ok(sysvar) => unsafe {
+            *(var_addr as *mut T) = T::clone(&sysvar);
+            SUCCESS
+        },
+        Err(_) => UNSUPPORTED_SYSVAR,
+    }
+}
+
+struct NativeSyscallStubs;
+
+impl SyscallStubs for NativeSyscallStubs {
+    fn sol_log(&self, message: &str) {
+        let invoke_context = get_invoke_context();
+        ic_msg!(invoke_context, "Program log: {}", message);
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let invoke_context = get_invoke_context();
+        stable_log::program_data(&invoke_context.get_log_collector(), fields);
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let instruction = StableInstruction::from(instruction.clone());
+        let invoke_context = get_invoke_context();
+        let transaction_context = &invoke_context.transaction_context;
+        let instruction_context = transaction_context
+            .get_current_instruction_context()
+            .unwrap();
+        let caller = *instruction_context
+            .get_last_program_key(transaction_context)
+            .unwrap();
+        let signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller).unwrap())
+            .collect::<Vec<_>>();
+        let (instruction_accounts, program_indices) = invoke_context
+            .prepare_instruction(&instruction, &signers)
+            .map_err(|error| ProgramError::try_from(error).unwrap())?;
+
+        // hand the caller's changes to the callee
+        let transaction_context = &invoke_context.transaction_context;
+        let instruction_context = transaction_context
+            .get_current_instruction_context()
+            .unwrap();
+        let mut writable_accounts = Vec::with_capacity(instruction_accounts.len());
+        for instruction_account in instruction_accounts.iter() {
+            let account_key = transaction_context
+                .get_key_of_account_at_index(instruction_account.index_in_transaction)
+                .unwrap();
+            let account_info_index = account_infos
+                .iter()
+                .position(|account_info| account_info.unsigned_key() == account_key)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            let account_info = &account_infos[account_info_index];
+            let mut borrowed_account = instruction_context
+                .try_borrow_instruction_account(
+                    transaction_context,
+                    instruction_account.index_in_caller,
+                )
+                .unwrap();
+            if borrowed_account.get_lamports() != account_info.lamports() {
+                borrowed_account
+                    .set_lamports(account_info.lamports())
+                    .unwrap();
+            }
+            let account_info_data = account_info.try_borrow_data()?;
+            match borrowed_account
+                .can_data_be_resized(account_info_data.len())
+                .and_then(|_| borrowed_account.can_data_be_changed())
+            {
+                Ok(()) => borrowed_account
+                    .set_data_from_slice(&account_info_data)
+                    .unwrap(),
+                Err(error) if borrowed_account.get_data() != *account_info_data => {
+                    panic!("{error:?}");
+                }
+                _ => {}
+            }
+            if borrowed_account.get_owner() != account_info.owner {
+                borrowed_account
+                    .set_owner(account_info.owner.as_ref())
+                    .unwrap();
+            }
+            if instruction_account.is_writable {
+                writable_accounts.push((instruction_account.index_in_caller, account_info_index));
+            }
+        }
+
+        let mut compute_units_consumed = 0;
+        invoke_context
+            .process_instruction(
+                &instruction.data,
+                &instruction_accounts,
+                &program_indices,
+                &mut compute_units_consumed,
+                &mut ExecuteTimings::default(),
+            )
+            .map_err(|error| ProgramError::try_from(error).unwrap_or_else(|e| panic!("{e}")))?;
+
+        // hand the callee's changes back to the caller
+        let transaction_context = &invoke_context.transaction_context;
+        let instruction_context = transaction_context
+            .get_current_instruction_context()
+            .unwrap();
+        for (index_in_caller, account_info_index) in writable_accounts {
+            let borrowed_account = instruction_context
+                .try_borrow_instruction_account(transaction_context, index_in_caller)
+                .unwrap();
+            let account_info = &account_infos[account_info_index];
+            **account_info.try_borrow_mut_lamports()? = borrowed_account.get_lamports();
+            if account_info.owner != borrowed_account.get_owner() {
+                // the system program assigns accounts the caller created
+                #[allow(mutable_transmutes)]
+                let owner = unsafe { transmute::<&Pubkey, &mut Pubkey>(account_info.owner) };
+                *owner = *borrowed_account.get_owner();
+            }
+            let data = borrowed_account.get_data();
+            if account_info.data_len() != data.len() {
+                account_info.realloc(data.len(), false)?;
+            }
+            account_info.try_borrow_mut_data()?.clone_from_slice(data);
+        }
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar(
+            get_invoke_context().get_sysvar_cache().get_clock(),
+            var_addr,
+        )
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar(
+            get_invoke_context().get_sysvar_cache().get_epoch_schedule(),
+            var_addr,
+        )
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar(get_invoke_context().get_sysvar_cache().get_rent(), var_addr)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        let (program_id, data) = get_invoke_context().transaction_context.get_return_data();
+        Some((*program_id, data.to_vec()))
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        let transaction_context = &mut get_invoke_context().transaction_context;
+        let caller = *transaction_context
+            .get_current_instruction_context()
+            .and_then(|instruction_context| {
+                instruction_context.get_last_program_key(transaction_context)
+            })
+            .unwrap();
+        transaction_context
+            .set_return_data(caller, data.to_vec())
+            .unwrap();
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        get_invoke_context().get_stack_height() as u64
+    }
+}
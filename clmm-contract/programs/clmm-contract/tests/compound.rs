@@ -0,0 +1,183 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use clmm_client::instructions::PriceBand;
+use clmm_contract::error::ErrorCode;
+use clmm_math::liquidity_math::get_delta_amounts_signed;
+use common::*;
+use solana_sdk::signature::{Keypair, Signer};
+
+#[test]
+fn compound_adds_fees_to_liquidity() {
+    let (mut env, pool, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    let vaults = env.vault_balances(&pool);
+    let pool_state = env.pool_state(&pool.address);
+
+    env.compound(&pool, &position, None).unwrap();
+
+    let personal_position = env.personal_position(&position.nft_mint);
+    let compounded = personal_position.liquidity - EARNED_FEES_LIQUIDITY;
+    assert!(compounded > 0);
+    let pool_liquidity = pool_state.liquidity;
+    assert_eq!(
+        { env.pool_state(&pool.address).liquidity },
+        pool_liquidity + compounded
+    );
+    assert_eq!(
+        { env.protocol_position(&pool.address, -600, 600).liquidity },
+        personal_position.liquidity + env.personal_position(&positions[2].nft_mint).liquidity
+    );
+    // no tokens move, the fees already sit in the vaults
+    assert_eq!(env.vault_balances(&pool), vaults);
+    env.assert_solvent(&pool, &positions);
+
+    // the fees the liquidity took count as claimed
+    let (amount_0, amount_1) = get_delta_amounts_signed(
+        pool_state.tick_current,
+        pool_state.sqrt_price_x64,
+        -600,
+        600,
+        compounded as i128,
+    )
+    .unwrap();
+    let pool_state_after = env.pool_state(&pool.address);
+    assert_eq!(
+        { pool_state_after.total_fees_claimed_token_0 },
+        pool_state.total_fees_claimed_token_0 + amount_0
+    );
+    assert_eq!(
+        { pool_state_after.total_fees_claimed_token_1 },
+        pool_state.total_fees_claimed_token_1 + amount_1
+    );
+
+    // the token the range needs more of is used up, the other one stays owed as dust
+    assert!(personal_position.token_fees_owed_0 > 0 || personal_position.token_fees_owed_1 > 0);
+    let failed = env.compound(&pool, &position, None).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::FeesTooSmallToCompound.into())
+    );
+}
+
+#[test]
+fn compound_rejects_price_outside_band() {
+    let (mut env, pool, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    let sqrt_price_x64 = env.pool_state(&pool.address).sqrt_price_x64;
+    let below = PriceBand {
+        sqrt_price_max_x64: Some(sqrt_price_x64 - 1),
+        ..PriceBand::default()
+    };
+    let above = PriceBand {
+        sqrt_price_min_x64: Some(sqrt_price_x64 + 1),
+        ..PriceBand::default()
+    };
+    let expired = PriceBand {
+        deadline: Some(NOW - 1),
+        ..PriceBand::default()
+    };
+    let around = PriceBand {
+        sqrt_price_min_x64: Some(sqrt_price_x64),
+        sqrt_price_max_x64: Some(sqrt_price_x64),
+        deadline: Some(NOW),
+    };
+
+    for (price_band, error) in [
+        (below, ErrorCode::PriceOutOfBand),
+        (above, ErrorCode::PriceOutOfBand),
+        (expired, ErrorCode::DeadlineExceeded),
+    ] {
+        let failed = env
+            .compound_in_band(&pool, &position, None, price_band)
+            .unwrap_err();
+        assert_eq!(custom_error_code(&failed), Some(error.into()));
+    }
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        EARNED_FEES_LIQUIDITY
+    );
+    env.compound_in_band(&pool, &position, None, around)
+        .unwrap();
+    assert!(env.personal_position(&position.nft_mint).liquidity > EARNED_FEES_LIQUIDITY);
+}
+
+#[test]
+fn compound_by_approved_operator() {
+    let (mut env, pool, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    let operator = Keypair::new();
+
+    let failed = env.compound(&pool, &position, Some(&operator)).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::NotApprovedOperator.into())
+    );
+
+    env.set_position_operator(&position, operator.pubkey())
+        .unwrap();
+    assert_eq!(
+        env.personal_position(&position.nft_mint).operator,
+        operator.pubkey()
+    );
+    env.compound(&pool, &position, Some(&operator)).unwrap();
+    assert!(env.personal_position(&position.nft_mint).liquidity > EARNED_FEES_LIQUIDITY);
+    env.assert_solvent(&pool, &positions);
+
+    // the approval only covers the position it was given for
+    let other = positions[0];
+    let failed = env.compound(&pool, &other, Some(&operator)).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::NotApprovedOperator.into())
+    );
+
+    env.set_position_operator(&position, Pubkey::default())
+        .unwrap();
+    let failed = env.compound(&pool, &position, Some(&operator)).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::NotApprovedOperator.into())
+    );
+}
+
+#[test]
+fn operator_approval_lapses_when_the_nft_changes_hands() {
+    let (mut env, pool, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    let operator = Keypair::new();
+    env.set_position_operator(&position, operator.pubkey())
+        .unwrap();
+
+    let transferred = env.transfer_position_nft(&position, &Pubkey::new_unique());
+    let failed = env
+        .compound(&pool, &transferred, Some(&operator))
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::NotApprovedOperator.into())
+    );
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        EARNED_FEES_LIQUIDITY
+    );
+}
+
+#[test]
+fn set_position_operator_requires_position_nft() {
+    let (mut env, _, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    let other_account = env.create_token_account(&position.nft_mint, MintKind::Token2022);
+    let forged = TestPosition {
+        nft_account: other_account,
+        ..position
+    };
+
+    assert!(env
+        .set_position_operator(&forged, Keypair::new().pubkey())
+        .is_err());
+    assert_eq!(
+        env.personal_position(&position.nft_mint).operator,
+        Pubkey::default()
+    );
+}
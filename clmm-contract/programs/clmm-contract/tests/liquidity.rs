@@ -0,0 +1,595 @@
+mod common;
+
+use clmm_client::instructions::PriceBand;
+use clmm_contract::{
+    error::ErrorCode,
+    state::{PersonalPositionState, ProtocolPositionState},
+};
+use clmm_math::{
+    liquidity_math::{get_delta_amounts_signed, get_liquidity_from_amounts},
+    tick_math::get_sqrt_price_at_tick,
+};
+use common::*;
+
+const LIQUIDITY: u128 = 1_000_000_000;
+
+const TRANSFER_FEE: MintKind = MintKind::Token2022TransferFee {
+    basis_points: 250,
+    maximum_fee: u64::MAX,
+};
+
+/// Pool at tick 0 with a fresh amm config
+fn setup(kinds: [MintKind; 2]) -> (TestEnv, TestPool) {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let pool = env.create_pool(amm_config, kinds, get_sqrt_price_at_tick(0).unwrap());
+    (env, pool)
+}
+
+/// Amounts the pool asks for to add `liquidity` to a range, rounded up
+fn deposit_amounts(
+    env: &TestEnv,
+    pool: &TestPool,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity: u128,
+) -> (u64, u64) {
+    let pool_state = env.pool_state(&pool.address);
+    get_delta_amounts_signed(
+        pool_state.tick_current,
+        pool_state.sqrt_price_x64,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity as i128,
+    )
+    .unwrap()
+}
+
+/// Amounts the pool pays out to remove `liquidity` from a range, rounded down
+fn withdrawal_amounts(
+    env: &TestEnv,
+    pool: &TestPool,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity: u128,
+) -> (u64, u64) {
+    let pool_state = env.pool_state(&pool.address);
+    get_delta_amounts_signed(
+        pool_state.tick_current,
+        pool_state.sqrt_price_x64,
+        tick_lower_index,
+        tick_upper_index,
+        -(liquidity as i128),
+    )
+    .unwrap()
+}
+
+/// The pool receives exactly `amount`, the user pays the transfer fee on top
+fn assert_paid(env: &TestEnv, mint: &TestMint, paid: u64, amount: u64) {
+    assert_eq!(paid - env.transfer_fee(mint, paid), amount);
+    if mint.kind == TRANSFER_FEE && amount > 0 {
+        assert!(paid > amount);
+    }
+}
+
+#[test]
+fn open_position_in_range() {
+    for kinds in [
+        [MintKind::Spl, MintKind::Spl],
+        [MintKind::Spl, TRANSFER_FEE],
+        [MintKind::Token2022, TRANSFER_FEE],
+    ] {
+        let (mut env, pool) = setup(kinds);
+        let (amount_0, amount_1) = deposit_amounts(&env, &pool, -600, 600, LIQUIDITY);
+        assert!(amount_0 > 0 && amount_1 > 0);
+        let balances_before = env.user_balances(&pool);
+
+        let position = env
+            .open_position(&pool, -600, 600, LIQUIDITY, u64::MAX, u64::MAX)
+            .unwrap();
+
+        let pool_state = env.pool_state(&pool.address);
+        assert_eq!({ pool_state.liquidity }, LIQUIDITY);
+        assert_eq!({ pool_state.tick_current }, 0);
+
+        // both bounds are initialized, with opposite net liquidity
+        let tick_lower = env.tick_state(&pool.address, -600);
+        assert_eq!({ tick_lower.tick }, -600);
+        assert_eq!({ tick_lower.liquidity_gross }, LIQUIDITY);
+        assert_eq!({ tick_lower.liquidity_net }, LIQUIDITY as i128);
+        let tick_upper = env.tick_state(&pool.address, 600);
+        assert_eq!({ tick_upper.tick }, 600);
+        assert_eq!({ tick_upper.liquidity_gross }, LIQUIDITY);
+        assert_eq!({ tick_upper.liquidity_net }, -(LIQUIDITY as i128));
+
+        for start_tick_index in [-600, 600] {
+            let tick_array = env.tick_array(&pool.address, start_tick_index);
+            assert_eq!(tick_array.pool_id, pool.address);
+            assert_eq!({ tick_array.start_tick_index }, start_tick_index);
+            assert_eq!(tick_array.initialized_tick_count, 1);
+            assert!(tick_array_bit_is_set(&pool_state, start_tick_index));
+        }
+        assert!(!tick_array_bit_is_set(&pool_state, 0));
+        assert!(!tick_array_bit_is_set(&pool_state, -1200));
+
+        let protocol_position = env.protocol_position(&pool.address, -600, 600);
+        assert_eq!(protocol_position.pool_id, pool.address);
+        assert_eq!(protocol_position.liquidity, LIQUIDITY);
+        assert_eq!(protocol_position.version, ProtocolPositionState::VERSION);
+
+        let personal_position = env.personal_position(&position.nft_mint);
+        assert_eq!(personal_position.nft_mint, position.nft_mint);
+        assert_eq!(personal_position.pool_id, pool.address);
+        assert_eq!(personal_position.tick_lower_index, -600);
+        assert_eq!(personal_position.tick_upper_index, 600);
+        assert_eq!(personal_position.liquidity, LIQUIDITY);
+        assert_eq!(personal_position.version, PersonalPositionState::VERSION);
+        assert_eq!(env.token_balance(&position.nft_account), 1);
+
+        assert_eq!(env.vault_balances(&pool), (amount_0, amount_1));
+        env.assert_solvent(&pool, &[position]);
+        let balances_after = env.user_balances(&pool);
+        assert_paid(
+            &env,
+            &pool.mint_0,
+            balances_before.0 - balances_after.0,
+            amount_0,
+        );
+        assert_paid(
+            &env,
+            &pool.mint_1,
+            balances_before.1 - balances_after.1,
+            amount_1,
+        );
+    }
+}
+
+#[test]
+fn open_position_above_price_deposits_token_0_only() {
+    let (mut env, pool) = setup([MintKind::Spl, TRANSFER_FEE]);
+    let (amount_0, amount_1) = deposit_amounts(&env, &pool, 600, 1190, LIQUIDITY);
+    assert!(amount_0 > 0);
+    assert_eq!(amount_1, 0);
+
+    let position = env
+        .open_position(&pool, 600, 1190, LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+
+    // out of range liquidity is not active
+    let pool_state = env.pool_state(&pool.address);
+    assert_eq!({ pool_state.liquidity }, 0);
+    assert_eq!(env.vault_balances(&pool), (amount_0, 0));
+    env.assert_solvent(&pool, &[position]);
+
+    // both bounds are in the same tick array
+    let tick_array = env.tick_array(&pool.address, 600);
+    assert_eq!(tick_array.initialized_tick_count, 2);
+    assert!(tick_array_bit_is_set(&pool_state, 600));
+    assert!(!tick_array_bit_is_set(&pool_state, 1200));
+}
+
+#[test]
+fn positions_sharing_a_tick() {
+    let (mut env, pool) = setup([MintKind::Spl, MintKind::Spl]);
+    let positions = [(-600, 0), (0, 600)].map(|(tick_lower_index, tick_upper_index)| {
+        env.open_position(
+            &pool,
+            tick_lower_index,
+            tick_upper_index,
+            LIQUIDITY,
+            u64::MAX,
+            u64::MAX,
+        )
+        .unwrap()
+    });
+    env.assert_solvent(&pool, &positions);
+
+    // only the range starting at the current tick is active
+    let pool_state = env.pool_state(&pool.address);
+    assert_eq!({ pool_state.liquidity }, LIQUIDITY);
+
+    let tick = env.tick_state(&pool.address, 0);
+    assert_eq!({ tick.liquidity_gross }, 2 * LIQUIDITY);
+    assert_eq!({ tick.liquidity_net }, 0);
+    assert!(tick_array_bit_is_set(&pool_state, -600));
+    assert!(tick_array_bit_is_set(&pool_state, 0));
+    assert!(tick_array_bit_is_set(&pool_state, 600));
+}
+
+#[test]
+fn open_position_rejects_slippage() {
+    let (mut env, pool) = setup([MintKind::Spl, TRANSFER_FEE]);
+    let (amount_0, amount_1) = deposit_amounts(&env, &pool, -600, 600, LIQUIDITY);
+    let (amount_0_max, amount_1_max) = if pool.mint_0.kind == TRANSFER_FEE {
+        (amount_0, u64::MAX)
+    } else {
+        (u64::MAX, amount_1)
+    };
+
+    // the maximum covers the amount but not the transfer fee
+    let failed = env
+        .open_position(&pool, -600, 600, LIQUIDITY, amount_0_max, amount_1_max)
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PriceSlippageCheck.into())
+    );
+}
+
+#[test]
+fn open_position_rejects_zero_liquidity() {
+    let (mut env, pool) = setup([MintKind::Spl, MintKind::Spl]);
+
+    // neither a liquidity nor amounts to derive it from
+    let failed = env.open_position(&pool, -600, 600, 0, 0, 0).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::LiquidityZero.into())
+    );
+}
+
+#[test]
+fn open_position_from_amounts() {
+    for kinds in [
+        [MintKind::Spl, MintKind::Spl],
+        [MintKind::Spl, TRANSFER_FEE],
+        [MintKind::Token2022, TRANSFER_FEE],
+    ] {
+        let (mut env, pool) = setup(kinds);
+        let (amount_0_max, amount_1_max) = (1_000_000, 3_000_000);
+        // the transfer fees come out of the maximums
+        let liquidity = get_liquidity_from_amounts(
+            env.pool_state(&pool.address).sqrt_price_x64,
+            get_sqrt_price_at_tick(-600).unwrap(),
+            get_sqrt_price_at_tick(600).unwrap(),
+            amount_0_max - env.transfer_fee(&pool.mint_0, amount_0_max),
+            amount_1_max - env.transfer_fee(&pool.mint_1, amount_1_max),
+        )
+        .unwrap();
+        assert!(liquidity > 0);
+        let (amount_0, amount_1) = deposit_amounts(&env, &pool, -600, 600, liquidity);
+        let balances_before = env.user_balances(&pool);
+
+        // zero liquidity without a base token takes the most liquidity both maximums pay for
+        let position = env
+            .open_position(&pool, -600, 600, 0, amount_0_max, amount_1_max)
+            .unwrap();
+        env.assert_solvent(&pool, &[position]);
+
+        assert_eq!(
+            env.personal_position(&position.nft_mint).liquidity,
+            liquidity
+        );
+        assert_eq!({ env.pool_state(&pool.address).liquidity }, liquidity);
+        assert_eq!(env.vault_balances(&pool), (amount_0, amount_1));
+        let balances_after = env.user_balances(&pool);
+        let (paid_0, paid_1) = (
+            balances_before.0 - balances_after.0,
+            balances_before.1 - balances_after.1,
+        );
+        assert_paid(&env, &pool.mint_0, paid_0, amount_0);
+        assert_paid(&env, &pool.mint_1, paid_1, amount_1);
+        // token 0 is the scarcer one at tick 0 and is used up, rounding aside
+        assert!(paid_0 <= amount_0_max && amount_0_max - paid_0 <= 10);
+        assert!(paid_1 < amount_1_max);
+    }
+}
+
+#[test]
+fn increase_liquidity_from_amounts() {
+    let (mut env, pool) = setup([MintKind::Spl, TRANSFER_FEE]);
+    let position = env
+        .open_position(&pool, -600, 600, LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    let liquidity = get_liquidity_from_amounts(
+        env.pool_state(&pool.address).sqrt_price_x64,
+        get_sqrt_price_at_tick(-600).unwrap(),
+        get_sqrt_price_at_tick(600).unwrap(),
+        2_000_000 - env.transfer_fee(&pool.mint_0, 2_000_000),
+        2_000_000 - env.transfer_fee(&pool.mint_1, 2_000_000),
+    )
+    .unwrap();
+
+    env.increase_liquidity(&pool, &position, 0, 2_000_000, 2_000_000)
+        .unwrap();
+    env.assert_solvent(&pool, &[position]);
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        LIQUIDITY + liquidity
+    );
+
+    // an in range position needs both tokens
+    let failed = env
+        .increase_liquidity(&pool, &position, 0, 2_000_000, 0)
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::LiquidityZero.into())
+    );
+}
+
+#[test]
+fn increase_liquidity() {
+    for kinds in [
+        [MintKind::Spl, MintKind::Spl],
+        [MintKind::Token2022, TRANSFER_FEE],
+    ] {
+        let (mut env, pool) = setup(kinds);
+        let position = env
+            .open_position(&pool, -600, 600, LIQUIDITY, u64::MAX, u64::MAX)
+            .unwrap();
+        env.assert_solvent(&pool, &[position]);
+        let vaults_before = env.vault_balances(&pool);
+        let balances_before = env.user_balances(&pool);
+        let (amount_0, amount_1) = deposit_amounts(&env, &pool, -600, 600, 2 * LIQUIDITY);
+
+        env.increase_liquidity(&pool, &position, 2 * LIQUIDITY, u64::MAX, u64::MAX)
+            .unwrap();
+        env.assert_solvent(&pool, &[position]);
+
+        let pool_state = env.pool_state(&pool.address);
+        assert_eq!({ pool_state.liquidity }, 3 * LIQUIDITY);
+        assert_eq!(
+            env.personal_position(&position.nft_mint).liquidity,
+            3 * LIQUIDITY
+        );
+        assert_eq!(
+            env.protocol_position(&pool.address, -600, 600).liquidity,
+            3 * LIQUIDITY
+        );
+        assert_eq!(
+            { env.tick_state(&pool.address, -600).liquidity_net },
+            3 * LIQUIDITY as i128
+        );
+        assert_eq!(
+            { env.tick_state(&pool.address, 600).liquidity_net },
+            -3 * LIQUIDITY as i128
+        );
+        // the ticks were already initialized
+        assert_eq!(
+            env.tick_array(&pool.address, -600).initialized_tick_count,
+            1
+        );
+        assert_eq!(env.tick_array(&pool.address, 600).initialized_tick_count, 1);
+
+        let vaults_after = env.vault_balances(&pool);
+        assert_eq!(vaults_after.0 - vaults_before.0, amount_0);
+        assert_eq!(vaults_after.1 - vaults_before.1, amount_1);
+        let balances_after = env.user_balances(&pool);
+        assert_paid(
+            &env,
+            &pool.mint_0,
+            balances_before.0 - balances_after.0,
+            amount_0,
+        );
+        assert_paid(
+            &env,
+            &pool.mint_1,
+            balances_before.1 - balances_after.1,
+            amount_1,
+        );
+    }
+}
+
+#[test]
+fn increase_liquidity_requires_position_nft() {
+    let (mut env, pool) = setup([MintKind::Spl, MintKind::Spl]);
+    let position = env
+        .open_position(&pool, -600, 600, LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+
+    // an empty account of the position nft mint
+    let other_account = env.create_token_account(&position.nft_mint, MintKind::Token2022);
+    let forged = TestPosition {
+        nft_account: other_account,
+        ..position
+    };
+    assert!(env
+        .increase_liquidity(&pool, &forged, LIQUIDITY, u64::MAX, u64::MAX)
+        .is_err());
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        LIQUIDITY
+    );
+}
+
+#[test]
+fn decrease_liquidity() {
+    for kinds in [
+        [MintKind::Spl, MintKind::Spl],
+        [MintKind::Token2022, TRANSFER_FEE],
+    ] {
+        let (mut env, pool) = setup(kinds);
+        let position = env
+            .open_position(&pool, -600, 600, 3 * LIQUIDITY, u64::MAX, u64::MAX)
+            .unwrap();
+        let vaults_before = env.vault_balances(&pool);
+        let balances_before = env.user_balances(&pool);
+        let (amount_0, amount_1) = withdrawal_amounts(&env, &pool, -600, 600, LIQUIDITY);
+        assert!(amount_0 > 0 && amount_1 > 0);
+        // the user receives the amounts less the transfer fees
+        let (received_0, received_1) = (
+            amount_0 - env.transfer_fee(&pool.mint_0, amount_0),
+            amount_1 - env.transfer_fee(&pool.mint_1, amount_1),
+        );
+
+        env.decrease_liquidity(&pool, &position, LIQUIDITY, received_0, received_1)
+            .unwrap();
+        env.assert_solvent(&pool, &[position]);
+
+        assert_eq!({ env.pool_state(&pool.address).liquidity }, 2 * LIQUIDITY);
+        assert_eq!(
+            env.personal_position(&position.nft_mint).liquidity,
+            2 * LIQUIDITY
+        );
+        assert_eq!(
+            env.protocol_position(&pool.address, -600, 600).liquidity,
+            2 * LIQUIDITY
+        );
+        let vaults_after = env.vault_balances(&pool);
+        assert_eq!(vaults_before.0 - vaults_after.0, amount_0);
+        assert_eq!(vaults_before.1 - vaults_after.1, amount_1);
+        let balances_after = env.user_balances(&pool);
+        assert_eq!(balances_after.0 - balances_before.0, received_0);
+        assert_eq!(balances_after.1 - balances_before.1, received_1);
+
+        // the last of the liquidity clears the ticks and flips their tick arrays
+        env.decrease_liquidity(&pool, &position, 2 * LIQUIDITY, 0, 0)
+            .unwrap();
+        env.assert_solvent(&pool, &[position]);
+
+        let pool_state = env.pool_state(&pool.address);
+        assert_eq!({ pool_state.liquidity }, 0);
+        assert_eq!(env.personal_position(&position.nft_mint).liquidity, 0);
+        for tick in [-600, 600] {
+            assert!(!env.tick_state(&pool.address, tick).is_initialized());
+            assert_eq!(
+                env.tick_array(&pool.address, tick).initialized_tick_count,
+                0
+            );
+            assert!(!tick_array_bit_is_set(&pool_state, tick));
+        }
+    }
+}
+
+#[test]
+fn decrease_liquidity_pays_out_owed_fees() {
+    let (mut env, pool, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    let (amount_0, amount_1) = withdrawal_amounts(&env, &pool, -600, 600, EARNED_FEES_LIQUIDITY);
+    let pool_state = env.pool_state(&pool.address);
+    let balances_before = env.user_balances(&pool);
+
+    env.decrease_liquidity(&pool, &position, EARNED_FEES_LIQUIDITY, amount_0, amount_1)
+        .unwrap();
+    env.assert_solvent(&pool, &positions);
+
+    let personal_position = env.personal_position(&position.nft_mint);
+    assert_eq!(personal_position.liquidity, 0);
+    assert_eq!(
+        (
+            personal_position.token_fees_owed_0,
+            personal_position.token_fees_owed_1
+        ),
+        (0, 0)
+    );
+    // the fees are paid out on top of the liquidity and count as claimed
+    let pool_state_after = env.pool_state(&pool.address);
+    let fee_amount_0 =
+        pool_state_after.total_fees_claimed_token_0 - pool_state.total_fees_claimed_token_0;
+    let fee_amount_1 =
+        pool_state_after.total_fees_claimed_token_1 - pool_state.total_fees_claimed_token_1;
+    assert!(fee_amount_0 > 0 && fee_amount_1 > 0);
+    let balances_after = env.user_balances(&pool);
+    assert_eq!(
+        balances_after.0 - balances_before.0,
+        amount_0 + fee_amount_0
+    );
+    assert_eq!(
+        balances_after.1 - balances_before.1,
+        amount_1 + fee_amount_1
+    );
+}
+
+#[test]
+fn decrease_liquidity_rejects_slippage() {
+    let (mut env, pool) = setup([MintKind::Spl, TRANSFER_FEE]);
+    let position = env
+        .open_position(&pool, -600, 600, LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    let (amount_0, amount_1) = withdrawal_amounts(&env, &pool, -600, 600, LIQUIDITY);
+    let (amount_0_min, amount_1_min) = if pool.mint_0.kind == TRANSFER_FEE {
+        (amount_0, 0)
+    } else {
+        (0, amount_1)
+    };
+
+    // the minimum covers the amount but not the transfer fee
+    let failed = env
+        .decrease_liquidity(&pool, &position, LIQUIDITY, amount_0_min, amount_1_min)
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PriceSlippageCheck.into())
+    );
+    // nor can more than the position holds be removed
+    assert!(env
+        .decrease_liquidity(&pool, &position, LIQUIDITY + 1, 0, 0)
+        .is_err());
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        LIQUIDITY
+    );
+}
+
+#[test]
+fn liquidity_changes_reject_price_outside_band() {
+    let (mut env, pool) = setup([MintKind::Spl, MintKind::Spl]);
+    let sqrt_price_x64 = env.pool_state(&pool.address).sqrt_price_x64;
+    let below = PriceBand {
+        sqrt_price_max_x64: Some(sqrt_price_x64 - 1),
+        ..PriceBand::default()
+    };
+    let above = PriceBand {
+        sqrt_price_min_x64: Some(sqrt_price_x64 + 1),
+        ..PriceBand::default()
+    };
+    let expired = PriceBand {
+        deadline: Some(NOW - 1),
+        ..PriceBand::default()
+    };
+    // the band and the deadline are inclusive
+    let around = PriceBand {
+        sqrt_price_min_x64: Some(sqrt_price_x64),
+        sqrt_price_max_x64: Some(sqrt_price_x64),
+        deadline: Some(NOW),
+    };
+
+    for (price_band, error) in [
+        (below, ErrorCode::PriceOutOfBand),
+        (above, ErrorCode::PriceOutOfBand),
+        (expired, ErrorCode::DeadlineExceeded),
+    ] {
+        let failed = env
+            .open_position_in_band(&pool, -600, 600, LIQUIDITY, u64::MAX, u64::MAX, price_band)
+            .unwrap_err();
+        assert_eq!(custom_error_code(&failed), Some(error.into()));
+    }
+    let position = env
+        .open_position_in_band(&pool, -600, 600, LIQUIDITY, u64::MAX, u64::MAX, around)
+        .unwrap();
+
+    for (price_band, error) in [
+        (below, ErrorCode::PriceOutOfBand),
+        (above, ErrorCode::PriceOutOfBand),
+        (expired, ErrorCode::DeadlineExceeded),
+    ] {
+        let failed = env
+            .increase_liquidity_in_band(&pool, &position, LIQUIDITY, u64::MAX, u64::MAX, price_band)
+            .unwrap_err();
+        assert_eq!(custom_error_code(&failed), Some(error.into()));
+    }
+    env.increase_liquidity_in_band(&pool, &position, LIQUIDITY, u64::MAX, u64::MAX, around)
+        .unwrap();
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        2 * LIQUIDITY
+    );
+
+    for (price_band, error) in [
+        (below, ErrorCode::PriceOutOfBand),
+        (above, ErrorCode::PriceOutOfBand),
+        (expired, ErrorCode::DeadlineExceeded),
+    ] {
+        let failed = env
+            .decrease_liquidity_in_band(&pool, &position, LIQUIDITY, 0, 0, price_band)
+            .unwrap_err();
+        assert_eq!(custom_error_code(&failed), Some(error.into()));
+    }
+    env.decrease_liquidity_in_band(&pool, &position, LIQUIDITY, 0, 0, around)
+        .unwrap();
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        LIQUIDITY
+    );
+}
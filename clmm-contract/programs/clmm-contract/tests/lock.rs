@@ -0,0 +1,256 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address_with_program_id,
+        spl_associated_token_account::instruction::create_associated_token_account,
+    },
+    token_2022::spl_token_2022,
+};
+use clmm_client::{instructions::PriceBand, pda};
+use clmm_contract::error::ErrorCode;
+use common::*;
+use solana_sdk::signature::{Keypair, Signer};
+
+const UNLOCK_TIME: i64 = NOW + 86_400;
+
+/// Associated Token-2022 account of `mint` for `owner`
+fn associated_account(owner: &Keypair, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(&owner.pubkey(), mint, &spl_token_2022::ID)
+}
+
+fn escrow_account(position: &TestPosition) -> Pubkey {
+    get_associated_token_address_with_program_id(
+        &pda::locked_position_address(&position.nft_mint).0,
+        &position.nft_mint,
+        &spl_token_2022::ID,
+    )
+}
+
+#[test]
+fn lock_position_escrows_nft() {
+    let (mut env, pool, positions) = pool_with_earned_fees();
+    let position = positions[1];
+
+    let fee_key_mint = env.lock_position(&position, Some(UNLOCK_TIME)).unwrap();
+
+    let locked_position = env.locked_position(&position.nft_mint);
+    assert_eq!(locked_position.position_nft_mint, position.nft_mint);
+    assert_eq!(locked_position.pool_id, pool.address);
+    assert_eq!(locked_position.fee_key_mint, fee_key_mint);
+    assert_eq!(locked_position.unlock_time, Some(UNLOCK_TIME));
+    assert_eq!(env.token_balance(&position.nft_account), 0);
+    assert_eq!(env.token_balance(&escrow_account(&position)), 1);
+    assert_eq!(
+        env.token_balance(&associated_account(&env.user, &fee_key_mint)),
+        1
+    );
+
+    // without the position nft the former holder can no longer touch the liquidity
+    assert!(env
+        .increase_liquidity(&pool, &position, 1_000, u64::MAX, u64::MAX)
+        .is_err());
+    assert!(env
+        .decrease_liquidity(&pool, &position, EARNED_FEES_LIQUIDITY, 0, 0)
+        .is_err());
+    assert!(env.compound(&pool, &position, None).is_err());
+    let swap_tick_arrays = env.swap_tick_arrays(&pool, true);
+    assert!(env
+        .zap_in(
+            &pool,
+            &position,
+            true,
+            EARNED_FEES_ZAP_AMOUNT,
+            EARNED_FEES_ZAP_AMOUNT / 2,
+            0,
+            &swap_tick_arrays
+        )
+        .is_err());
+    assert!(env
+        .set_position_operator(&position, Pubkey::new_unique())
+        .is_err());
+    assert!(env
+        .reposition(
+            &pool,
+            &position,
+            None,
+            -1200,
+            1200,
+            true,
+            0,
+            0,
+            PriceBand::default(),
+            &[],
+        )
+        .is_err());
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        EARNED_FEES_LIQUIDITY
+    );
+
+    // a position is locked once
+    assert!(env.lock_position(&position, None).is_err());
+}
+
+#[test]
+fn lock_position_takes_an_existing_escrow_and_drops_the_operator() {
+    let (mut env, _, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    env.set_position_operator(&position, Keypair::new().pubkey())
+        .unwrap();
+
+    // anyone may create the escrow account before the lock
+    let stranger = Keypair::new();
+    env.svm.airdrop(&stranger.pubkey(), 1_000_000_000).unwrap();
+    env.send(
+        &[create_associated_token_account(
+            &stranger.pubkey(),
+            &pda::locked_position_address(&position.nft_mint).0,
+            &position.nft_mint,
+            &spl_token_2022::ID,
+        )],
+        &[&stranger],
+    )
+    .unwrap();
+
+    env.lock_position(&position, None).unwrap();
+    assert_eq!(env.token_balance(&escrow_account(&position)), 1);
+    assert_eq!(
+        env.personal_position(&position.nft_mint).operator,
+        Pubkey::default()
+    );
+}
+
+#[test]
+fn lock_position_rejects_past_unlock_time() {
+    let (mut env, _, positions) = pool_with_earned_fees();
+    let position = positions[1];
+
+    let failed = env.lock_position(&position, Some(NOW)).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::InvalidUnlockTime.into())
+    );
+    assert_eq!(env.token_balance(&position.nft_account), 1);
+}
+
+#[test]
+fn fee_key_holder_collects_fees() {
+    let (mut env, pool, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    let fee_key_mint = env.lock_position(&position, None).unwrap();
+    let fee_key_account = associated_account(&env.user, &fee_key_mint);
+    let pool_state = env.pool_state(&pool.address);
+    let vaults = (
+        env.token_balance(&pool_state.token_vault_0),
+        env.token_balance(&pool_state.token_vault_1),
+    );
+    let users = (
+        env.token_balance(&pool.mint_0.user_account),
+        env.token_balance(&pool.mint_1.user_account),
+    );
+
+    // the fee key account has to be signed for by its owner
+    let stranger = Keypair::new();
+    assert!(env
+        .collect_locked_position_fees(&pool, &position, Some(&stranger), fee_key_account)
+        .is_err());
+
+    env.collect_locked_position_fees(&pool, &position, None, fee_key_account)
+        .unwrap();
+
+    let pool_state_after = env.pool_state(&pool.address);
+    let personal_position = env.personal_position(&position.nft_mint);
+    assert_eq!(personal_position.token_fees_owed_0, 0);
+    assert_eq!(personal_position.token_fees_owed_1, 0);
+    assert_eq!(personal_position.liquidity, EARNED_FEES_LIQUIDITY);
+    let collected_0 = env.token_balance(&pool.mint_0.user_account) - users.0;
+    let collected_1 = env.token_balance(&pool.mint_1.user_account) - users.1;
+    assert!(collected_0 > 0 && collected_1 > 0);
+    assert_eq!(
+        { pool_state_after.total_fees_claimed_token_0 },
+        pool_state.total_fees_claimed_token_0 + collected_0
+    );
+    assert_eq!(
+        { pool_state_after.total_fees_claimed_token_1 },
+        pool_state.total_fees_claimed_token_1 + collected_1
+    );
+    assert_eq!(
+        env.token_balance(&pool_state.token_vault_0),
+        vaults.0 - collected_0
+    );
+    assert_eq!(
+        env.token_balance(&pool_state.token_vault_1),
+        vaults.1 - collected_1
+    );
+    env.assert_solvent(&pool, &positions);
+
+    // the fees are paid once
+    env.collect_locked_position_fees(&pool, &position, None, fee_key_account)
+        .unwrap();
+    assert_eq!(
+        env.token_balance(&pool.mint_0.user_account) - users.0,
+        collected_0
+    );
+    assert_eq!(
+        env.token_balance(&pool.mint_1.user_account) - users.1,
+        collected_1
+    );
+}
+
+#[test]
+fn unlock_position_after_unlock_time() {
+    let (mut env, pool, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    let fee_key_mint = env.lock_position(&position, Some(UNLOCK_TIME)).unwrap();
+
+    let failed = env.unlock_position(&position).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PositionStillLocked.into())
+    );
+
+    env.set_time(UNLOCK_TIME);
+    env.unlock_position(&position).unwrap();
+
+    let nft_account = associated_account(&env.user, &position.nft_mint);
+    assert_eq!(env.token_balance(&nft_account), 1);
+    for closed in [
+        pda::locked_position_address(&position.nft_mint).0,
+        escrow_account(&position),
+        associated_account(&env.user, &fee_key_mint),
+    ] {
+        assert!(env.svm.get_account(&closed).is_none());
+    }
+
+    // the position is usable again from its new token account
+    let unlocked = TestPosition {
+        nft_account,
+        ..position
+    };
+    env.increase_liquidity(&pool, &unlocked, 1_000, u64::MAX, u64::MAX)
+        .unwrap();
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        EARNED_FEES_LIQUIDITY + 1_000
+    );
+    env.decrease_liquidity(&pool, &unlocked, EARNED_FEES_LIQUIDITY, 0, 0)
+        .unwrap();
+    assert_eq!(env.personal_position(&position.nft_mint).liquidity, 1_000);
+}
+
+#[test]
+fn permanent_lock_never_unlocks() {
+    let (mut env, _, positions) = pool_with_earned_fees();
+    let position = positions[1];
+    env.lock_position(&position, None).unwrap();
+
+    env.set_time(i64::MAX);
+    let failed = env.unlock_position(&position).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PositionStillLocked.into())
+    );
+    assert_eq!(env.token_balance(&escrow_account(&position)), 1);
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use anchor_lang::{error::ErrorCode as AnchorErrorCode, prelude::Pubkey};
+use clmm_client::{decode_pool_state, instructions, pda};
+use clmm_contract::{
+    context::create_amm_config::ID as ADMIN,
+    error::ErrorCode,
+    state::{
+        layout_version, upgrade_layout, AmmConfig, PersonalPositionState, PoolState,
+        ProtocolPositionState, Versioned,
+    },
+};
+use clmm_math::tick_math::get_sqrt_price_at_tick;
+use common::*;
+use solana_sdk::{account::Account, signature::Signer};
+
+/// A `PoolState` as written before accounts were versioned, see `pool_state_v0_fields`
+const POOL_STATE_V0: &[u8] = include_bytes!("fixtures/pool_state_v0.bin");
+
+/// Store `data` at `address` as a program account holding just enough rent for its size
+fn set_program_account(env: &mut TestEnv, address: &Pubkey, data: Vec<u8>) {
+    let lamports = env.svm.minimum_balance_for_rent_exemption(data.len());
+    env.svm
+        .set_account(
+            *address,
+            Account {
+                lamports,
+                data,
+                owner: clmm_contract::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        )
+        .unwrap();
+}
+
+/// Cut an account back to the size it had before versioning
+fn downgrade<T: Versioned>(env: &mut TestEnv, address: &Pubkey) {
+    let mut data = env.svm.get_account(address).unwrap().data;
+    data.truncate(T::VERSION_OFFSET);
+    set_program_account(env, address, data);
+}
+
+/// Asserts the account at `address` is on the current layout and rent exempt
+fn assert_migrated<T: Versioned>(env: &TestEnv, address: &Pubkey) {
+    let account = env.svm.get_account(address).unwrap();
+    assert_eq!(account.data.len(), T::ACCOUNT_LEN);
+    assert_eq!(
+        layout_version::<T>(&account.data).unwrap(),
+        T::LAYOUT_VERSION
+    );
+    assert!(account.lamports >= env.svm.minimum_balance_for_rent_exemption(T::ACCOUNT_LEN));
+}
+
+fn pool_state_v0_fields(pool_state: &PoolState) {
+    assert_eq!(pool_state.bump, [254]);
+    assert_eq!(pool_state.amm_config, Pubkey::new_from_array([1; 32]));
+    assert_eq!(pool_state.owner, Pubkey::new_from_array([2; 32]));
+    assert_eq!(pool_state.token_mint_0, Pubkey::new_from_array([3; 32]));
+    assert_eq!(pool_state.token_mint_1, Pubkey::new_from_array([4; 32]));
+    assert_eq!(pool_state.token_vault_0, Pubkey::new_from_array([5; 32]));
+    assert_eq!(pool_state.token_vault_1, Pubkey::new_from_array([6; 32]));
+    assert_eq!(pool_state.mint_decimals_0, 6);
+    assert_eq!(pool_state.mint_decimals_1, 9);
+    assert_eq!({ pool_state.tick_spacing }, 60);
+    assert_eq!({ pool_state.liquidity }, 0x0123_4567_89ab_cdef);
+    assert_eq!({ pool_state.sqrt_price_x64 }, 0x1_8000_0000_0000_0000);
+    assert_eq!({ pool_state.tick_current }, -12345);
+    assert_eq!(
+        { pool_state.fee_growth_global_0_x64 },
+        0x1111_1111_1111_1111_1111
+    );
+    assert_eq!(
+        { pool_state.fee_growth_global_1_x64 },
+        0x2222_2222_2222_2222_2222
+    );
+    assert_eq!({ pool_state.protocol_fees_token_0 }, 1001);
+    assert_eq!({ pool_state.protocol_fees_token_1 }, 1002);
+    assert_eq!({ pool_state.swap_in_amount_token_0 }, 2001);
+    assert_eq!({ pool_state.swap_out_amount_token_1 }, 2002);
+    assert_eq!({ pool_state.swap_in_amount_token_1 }, 2003);
+    assert_eq!({ pool_state.swap_out_amount_token_0 }, 2004);
+    assert_eq!(
+        { pool_state.tick_array_bitmap },
+        std::array::from_fn::<u64, 16, _>(|i| (i as u64 + 1) << (3 * i))
+    );
+    assert_eq!({ pool_state.total_fees_token_0 }, 3001);
+    assert_eq!({ pool_state.total_fees_claimed_token_0 }, 3002);
+    assert_eq!({ pool_state.total_fees_token_1 }, 3003);
+    assert_eq!({ pool_state.total_fees_claimed_token_1 }, 3004);
+    assert_eq!({ pool_state.fund_fees_token_0 }, 4001);
+    assert_eq!({ pool_state.fund_fees_token_1 }, 4002);
+    assert_eq!({ pool_state.open_time }, 1_650_000_000);
+    assert_eq!({ pool_state.recent_epoch }, 512);
+}
+
+#[test]
+fn upgrade_pool_state_fixture() {
+    assert_eq!(POOL_STATE_V0.len(), PoolState::VERSION_OFFSET);
+    assert_eq!(layout_version::<PoolState>(POOL_STATE_V0).unwrap(), 0);
+
+    let mut data = POOL_STATE_V0.to_vec();
+    data.resize(PoolState::LEN, 0xff);
+    upgrade_layout::<PoolState>(&mut data, 0);
+    assert_eq!(&data[..POOL_STATE_V0.len()], POOL_STATE_V0);
+
+    let pool_state = decode_pool_state(&data).unwrap();
+    pool_state_v0_fields(&pool_state);
+    assert_eq!(pool_state.version, PoolState::VERSION);
+    assert_eq!({ pool_state.padding }, [0; 32]);
+}
+
+#[test]
+fn layout_version_rejects_unknown_layouts() {
+    let mut data = POOL_STATE_V0.to_vec();
+    data.push(0);
+    assert_eq!(
+        layout_version::<PoolState>(&data).unwrap_err(),
+        ErrorCode::InvalidAccountLayout.into()
+    );
+
+    let mut data = POOL_STATE_V0.to_vec();
+    data.resize(PoolState::LEN, 0);
+    data[PoolState::VERSION_OFFSET] = PoolState::VERSION + 1;
+    assert_eq!(
+        layout_version::<PoolState>(&data).unwrap_err(),
+        ErrorCode::UnsupportedLayoutVersion.into()
+    );
+
+    assert_eq!(
+        layout_version::<AmmConfig>(POOL_STATE_V0).unwrap_err(),
+        AnchorErrorCode::AccountDiscriminatorMismatch.into()
+    );
+}
+
+#[test]
+fn migrate_pool_from_fixture() {
+    let mut env = TestEnv::new();
+    let address = Pubkey::new_unique();
+    set_program_account(&mut env, &address, POOL_STATE_V0.to_vec());
+
+    env.send(&[instructions::migrate_pool(ADMIN, address)], &[])
+        .unwrap();
+    assert_migrated::<PoolState>(&env, &address);
+    let pool_state = env.pool_state(&address);
+    pool_state_v0_fields(&pool_state);
+    assert_eq!({ pool_state.padding }, [0; 32]);
+
+    // migrating again leaves the account as it is
+    let migrated = env.svm.get_account(&address).unwrap();
+    env.send(&[instructions::migrate_pool(ADMIN, address)], &[])
+        .unwrap();
+    assert_eq!(env.svm.get_account(&address).unwrap(), migrated);
+}
+
+#[test]
+fn migrate_keeps_created_accounts() {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let pool = env.create_pool(
+        amm_config,
+        [MintKind::Spl, MintKind::Token2022],
+        get_sqrt_price_at_tick(0).unwrap(),
+    );
+    let position = env
+        .open_position(&pool, -600, 600, 1_000_000_000, u64::MAX, u64::MAX)
+        .unwrap();
+    let protocol_position = pda::protocol_position_address(&pool.address, -600, 600).0;
+    let personal_position = pda::personal_position_address(&position.nft_mint).0;
+
+    let accounts = [
+        amm_config,
+        pool.address,
+        protocol_position,
+        personal_position,
+    ];
+    let created = accounts.map(|address| env.svm.get_account(&address).unwrap().data);
+
+    downgrade::<AmmConfig>(&mut env, &amm_config);
+    downgrade::<PoolState>(&mut env, &pool.address);
+    downgrade::<ProtocolPositionState>(&mut env, &protocol_position);
+    downgrade::<PersonalPositionState>(&mut env, &personal_position);
+
+    // twice, the second round must not change anything
+    for _ in 0..2 {
+        env.send(
+            &[
+                instructions::migrate_amm_config(ADMIN, amm_config),
+                instructions::migrate_pool(ADMIN, pool.address),
+                instructions::migrate_protocol_position(ADMIN, protocol_position),
+                instructions::migrate_personal_position(ADMIN, personal_position),
+            ],
+            &[],
+        )
+        .unwrap();
+        assert_migrated::<AmmConfig>(&env, &amm_config);
+        assert_migrated::<PoolState>(&env, &pool.address);
+        assert_migrated::<ProtocolPositionState>(&env, &protocol_position);
+        assert_migrated::<PersonalPositionState>(&env, &personal_position);
+        for (address, data) in accounts.iter().zip(&created) {
+            assert_eq!(&env.svm.get_account(address).unwrap().data, data);
+        }
+    }
+
+    // the migrated accounts are usable
+    env.increase_liquidity(&pool, &position, 1_000, u64::MAX, u64::MAX)
+        .unwrap();
+    env.assert_solvent(&pool, &[position]);
+}
+
+#[test]
+fn migrate_requires_admin() {
+    let mut env = TestEnv::new();
+    let address = Pubkey::new_unique();
+    set_program_account(&mut env, &address, POOL_STATE_V0.to_vec());
+
+    let owner = env.user.pubkey();
+    let failed = env
+        .send(&[instructions::migrate_pool(owner, address)], &[])
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(AnchorErrorCode::ConstraintAddress.into())
+    );
+    assert_eq!(
+        env.svm.get_account(&address).unwrap().data,
+        POOL_STATE_V0.to_vec()
+    );
+}
+
+#[test]
+fn migrate_rejects_other_accounts() {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+
+    let failed = env
+        .send(&[instructions::migrate_pool(ADMIN, amm_config)], &[])
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(AnchorErrorCode::AccountDiscriminatorMismatch.into())
+    );
+}
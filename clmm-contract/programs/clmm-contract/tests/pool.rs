@@ -0,0 +1,350 @@
+mod common;
+
+use anchor_lang::error::ErrorCode as AnchorErrorCode;
+use clmm_client::{instructions, pda};
+use clmm_contract::{
+    context::create_amm_config::ID as ADMIN,
+    error::ErrorCode,
+    state::{AmmConfig, PoolState},
+};
+use clmm_math::tick_math::get_sqrt_price_at_tick;
+use common::*;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signer,
+};
+
+const TRANSFER_FEE: MintKind = MintKind::Token2022TransferFee {
+    basis_points: 100,
+    maximum_fee: u64::MAX,
+};
+
+#[test]
+fn create_amm_config() {
+    let mut env = TestEnv::new();
+    let address = env.create_amm_config(3);
+
+    let amm_config = env.amm_config(&address);
+    assert_eq!(amm_config.index, 3);
+    assert_eq!(amm_config.bump, pda::amm_config_address(3).1);
+    assert_eq!(amm_config.owner, ADMIN);
+    assert_eq!(amm_config.fund_owner, ADMIN);
+    assert_eq!(amm_config.tick_spacing, TICK_SPACING);
+    assert_eq!(amm_config.trade_fee_rate, TRADE_FEE_RATE);
+    assert_eq!(amm_config.protocol_fee_rate, PROTOCOL_FEE_RATE);
+    assert_eq!(amm_config.fund_fee_rate, FUND_FEE_RATE);
+    assert_eq!(amm_config.version, AmmConfig::VERSION);
+}
+
+#[test]
+fn create_amm_config_requires_admin() {
+    let mut env = TestEnv::new();
+    let owner = env.user.pubkey();
+    let instruction = instructions::create_amm_config(owner, 0, TICK_SPACING, 0, 0, 0);
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(AnchorErrorCode::ConstraintAddress.into())
+    );
+}
+
+#[test]
+fn create_pool() {
+    for kinds in [
+        [MintKind::Spl, MintKind::Spl],
+        [MintKind::Spl, MintKind::Token2022],
+        [MintKind::Token2022, TRANSFER_FEE],
+    ] {
+        let mut env = TestEnv::new();
+        let amm_config = env.create_amm_config(0);
+        let sqrt_price_x64 = get_sqrt_price_at_tick(-1234).unwrap();
+        let pool = env.create_pool(amm_config, kinds, sqrt_price_x64);
+
+        let pool_state = env.pool_state(&pool.address);
+        assert_eq!(
+            pool_state.bump,
+            [pda::pool_address(&amm_config, &pool.mint_0.mint, &pool.mint_1.mint).1]
+        );
+        assert_eq!(pool_state.amm_config, amm_config);
+        assert_eq!(pool_state.owner, env.user.pubkey());
+        assert_eq!(pool_state.token_mint_0, pool.mint_0.mint);
+        assert_eq!(pool_state.token_mint_1, pool.mint_1.mint);
+        assert_eq!(
+            pool_state.token_vault_0,
+            pda::pool_vault_address(&pool.address, &pool.mint_0.mint).0
+        );
+        assert_eq!(
+            pool_state.token_vault_1,
+            pda::pool_vault_address(&pool.address, &pool.mint_1.mint).0
+        );
+        assert_eq!(pool_state.mint_decimals_0, DECIMALS);
+        assert_eq!(pool_state.mint_decimals_1, DECIMALS);
+        assert_eq!({ pool_state.tick_spacing }, TICK_SPACING);
+        assert_eq!({ pool_state.sqrt_price_x64 }, sqrt_price_x64);
+        assert_eq!({ pool_state.tick_current }, -1234);
+        assert_eq!({ pool_state.liquidity }, 0);
+        assert_eq!({ pool_state.tick_array_bitmap }, [0; 16]);
+        assert_eq!(pool_state.version, PoolState::VERSION);
+        assert_eq!(
+            env.svm.get_account(&pool.address).unwrap().data.len(),
+            PoolState::LEN
+        );
+
+        // vaults are owned by the pool and empty
+        for (vault, mint) in [
+            (pool_state.token_vault_0, pool.mint_0),
+            (pool_state.token_vault_1, pool.mint_1),
+        ] {
+            let account = env.svm.get_account(&vault).unwrap();
+            assert_eq!(account.owner, mint.kind.token_program());
+            assert_eq!(env.token_balance(&vault), 0);
+        }
+
+        // the bitmap extension is created along with the pool
+        let extension = pda::tick_array_bitmap_extension_address(&pool.address).0;
+        assert_eq!(
+            env.svm.get_account(&extension).unwrap().owner,
+            clmm_contract::ID
+        );
+    }
+}
+
+#[test]
+fn create_pool_rejects_unsorted_mints() {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let mut mints = [MintKind::Spl, MintKind::Spl].map(|kind| env.create_mint(kind, 0));
+    mints.sort_by_key(|mint| mint.mint);
+    let [mint_0, mint_1] = mints;
+
+    let instruction = instructions::create_pool(
+        env.user.pubkey(),
+        amm_config,
+        env.create_pool_fee_receiver(&amm_config),
+        mint_1.mint,
+        mint_0.mint,
+        mint_1.kind.token_program(),
+        mint_0.kind.token_program(),
+        get_sqrt_price_at_tick(0).unwrap(),
+        0,
+        &[],
+    );
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(AnchorErrorCode::ConstraintRaw.into())
+    );
+}
+
+#[test]
+fn create_pool_rejects_future_open_time() {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let mut mints = [MintKind::Spl, MintKind::Spl].map(|kind| env.create_mint(kind, 0));
+    mints.sort_by_key(|mint| mint.mint);
+    let [mint_0, mint_1] = mints;
+
+    let instruction = instructions::create_pool(
+        env.user.pubkey(),
+        amm_config,
+        env.create_pool_fee_receiver(&amm_config),
+        mint_0.mint,
+        mint_1.mint,
+        mint_0.kind.token_program(),
+        mint_1.kind.token_program(),
+        get_sqrt_price_at_tick(0).unwrap(),
+        NOW as u64 + 60,
+        &[],
+    );
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(AnchorErrorCode::RequireGtViolated.into())
+    );
+}
+
+/// `create_pool` of two fresh SPL mints by the user, passing `remaining_accounts`
+fn create_pool_instruction(
+    env: &mut TestEnv,
+    amm_config: Pubkey,
+    remaining_accounts: &[Pubkey],
+) -> Instruction {
+    let mut mints = [MintKind::Spl, MintKind::Spl].map(|kind| env.create_mint(kind, 0));
+    mints.sort_by_key(|mint| mint.mint);
+    let [mint_0, mint_1] = mints;
+    instructions::create_pool(
+        env.user.pubkey(),
+        amm_config,
+        env.create_pool_fee_receiver(&amm_config),
+        mint_0.mint,
+        mint_1.mint,
+        mint_0.kind.token_program(),
+        mint_1.kind.token_program(),
+        get_sqrt_price_at_tick(0).unwrap(),
+        0,
+        remaining_accounts,
+    )
+}
+
+#[test]
+fn create_pool_with_creator_allowlist() {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let creator = env.user.pubkey();
+    let pool_creator = pda::pool_creator_address(&amm_config, &creator).0;
+    env.send(
+        &[instructions::set_creator_allowlist(ADMIN, amm_config, true)],
+        &[],
+    )
+    .unwrap();
+    assert!(env.amm_config(&amm_config).creator_allowlist);
+
+    // not allowlisted yet
+    let instruction = create_pool_instruction(&mut env, amm_config, &[]);
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PoolCreatorNotAllowed.into())
+    );
+
+    env.send(
+        &[instructions::add_pool_creator(ADMIN, amm_config, creator)],
+        &[],
+    )
+    .unwrap();
+    let instruction = create_pool_instruction(&mut env, amm_config, &[pool_creator]);
+    env.send(&[instruction], &[]).unwrap();
+
+    // removing the creator closes its account and revokes the permission
+    env.send(
+        &[instructions::remove_pool_creator(
+            ADMIN, amm_config, creator,
+        )],
+        &[],
+    )
+    .unwrap();
+    assert!(env.svm.get_account(&pool_creator).is_none());
+    let instruction = create_pool_instruction(&mut env, amm_config, &[pool_creator]);
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PoolCreatorNotAllowed.into())
+    );
+}
+
+#[test]
+fn create_pool_ignores_creators_of_other_configs() {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let other_amm_config = env.create_amm_config(1);
+    let creator = env.user.pubkey();
+    env.send(
+        &[
+            instructions::set_creator_allowlist(ADMIN, amm_config, true),
+            instructions::add_pool_creator(ADMIN, other_amm_config, creator),
+        ],
+        &[],
+    )
+    .unwrap();
+
+    let other_pool_creator = pda::pool_creator_address(&other_amm_config, &creator).0;
+    let instruction = create_pool_instruction(&mut env, amm_config, &[other_pool_creator]);
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PoolCreatorNotAllowed.into())
+    );
+
+    // permissionless configs accept anyone
+    let instruction = create_pool_instruction(&mut env, other_amm_config, &[]);
+    env.send(&[instruction], &[]).unwrap();
+}
+
+#[test]
+fn create_pool_charges_create_pool_fee() {
+    const FEE: u64 = 1_000_000_000;
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let receiver = Pubkey::new_unique();
+    env.send(
+        &[instructions::set_create_pool_fee(
+            ADMIN, amm_config, FEE, receiver,
+        )],
+        &[],
+    )
+    .unwrap();
+    let config = env.amm_config(&amm_config);
+    assert_eq!(config.create_pool_fee, FEE);
+    assert_eq!(config.create_pool_fee_receiver, receiver);
+
+    // the receiver has to be the one of the config, it can't be left out and must be writable
+    let mut instruction = create_pool_instruction(&mut env, amm_config, &[]);
+    instruction.accounts[2].pubkey = Pubkey::new_unique();
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::InvalidCreatePoolFeeReceiver.into())
+    );
+    let mut instruction = create_pool_instruction(&mut env, amm_config, &[]);
+    instruction.accounts[2] = AccountMeta::new_readonly(clmm_contract::ID, false);
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::InvalidCreatePoolFeeReceiver.into())
+    );
+    let mut instruction = create_pool_instruction(&mut env, amm_config, &[]);
+    instruction.accounts[2].is_writable = false;
+    let failed = env.send(&[instruction], &[]).unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(AnchorErrorCode::ConstraintMut.into())
+    );
+
+    let instruction = create_pool_instruction(&mut env, amm_config, &[]);
+    env.send(&[instruction], &[]).unwrap();
+    assert_eq!(env.svm.get_balance(&receiver), Some(FEE));
+}
+
+#[test]
+fn set_create_pool_fee_requires_receiver() {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let failed = env
+        .send(
+            &[instructions::set_create_pool_fee(
+                ADMIN,
+                amm_config,
+                1,
+                Pubkey::default(),
+            )],
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::InvalidCreatePoolFeeReceiver.into())
+    );
+}
+
+#[test]
+fn config_setters_require_admin() {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let user = env.user.pubkey();
+
+    for instruction in [
+        instructions::set_creator_allowlist(user, amm_config, true),
+        instructions::set_create_pool_fee(user, amm_config, 1, user),
+        instructions::add_pool_creator(user, amm_config, user),
+    ] {
+        let failed = env.send(&[instruction], &[]).unwrap_err();
+        assert_eq!(
+            custom_error_code(&failed),
+            Some(AnchorErrorCode::ConstraintAddress.into())
+        );
+    }
+    let config = env.amm_config(&amm_config);
+    assert!(!config.creator_allowlist);
+    assert_eq!(config.create_pool_fee, 0);
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use clmm_client::{ClientError, SwapQuote, SwapQuoter};
+use clmm_contract::state::TickArrayState;
+use clmm_math::tick_math::{get_sqrt_price_at_tick, MIN_SQRT_PRICE_X64};
+use common::*;
+
+/// Liquidity of the wide position and of each narrow one
+const LIQUIDITY: u128 = 10_000_000_000;
+/// Exact input that moves the price from tick 0 past -900
+const SWAP_AMOUNT: u64 = 1_000_000_000;
+/// Exact output that moves the price from tick 0 past 900
+const AMOUNT_OUT: u64 = 800_000_000;
+/// Input of a zap on top of its swap amount, deposited in a range the swap does not reach
+const DEPOSIT_AMOUNT: u64 = 1_000_000;
+
+/// Pool at tick 0 with `LIQUIDITY` in [-6000, 6000), [-900, -300) and [300, 900). Swaps away
+/// from tick 0 cross two initialized ticks and the tick array boundary between them.
+fn setup() -> (TestEnv, TestPool, [TestPosition; 3]) {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let pool = env.create_pool(
+        amm_config,
+        [MintKind::Spl, MintKind::Spl],
+        get_sqrt_price_at_tick(0).unwrap(),
+    );
+    let positions =
+        [(-6000, 6000), (-900, -300), (300, 900)].map(|(tick_lower_index, tick_upper_index)| {
+            env.open_position(
+                &pool,
+                tick_lower_index,
+                tick_upper_index,
+                LIQUIDITY,
+                u64::MAX,
+                u64::MAX,
+            )
+            .unwrap()
+        });
+    (env, pool, positions)
+}
+
+/// Start indexes of the tick arrays of `positions`
+fn tick_array_start_indexes(positions: &[TestPosition]) -> Vec<i32> {
+    let mut start_indexes: Vec<i32> = positions
+        .iter()
+        .flat_map(|position| [position.tick_lower_index, position.tick_upper_index])
+        .map(|tick| TickArrayState::get_array_start_index(tick, TICK_SPACING))
+        .collect();
+    start_indexes.sort();
+    start_indexes.dedup();
+    start_indexes
+}
+
+/// Quote a swap against the current pool state with the tick arrays starting at
+/// `start_indexes`
+fn quote_swap(
+    env: &TestEnv,
+    pool: &TestPool,
+    start_indexes: &[i32],
+    amount_specified: u64,
+    sqrt_price_limit_x64: Option<u128>,
+    is_base_input: bool,
+    zero_for_one: bool,
+) -> Result<SwapQuote, ClientError> {
+    let pool_state = env.pool_state(&pool.address);
+    let amm_config = env.amm_config(&pool.amm_config);
+    let tickarray_bitmap_extension = env.tick_array_bitmap_extension(&pool.address);
+    let tick_arrays = start_indexes
+        .iter()
+        .map(|start_index| env.tick_array(&pool.address, *start_index));
+    SwapQuoter::new(
+        &pool_state,
+        &amm_config,
+        Some(&tickarray_bitmap_extension),
+        tick_arrays,
+    )?
+    .quote(
+        amount_specified,
+        sqrt_price_limit_x64,
+        is_base_input,
+        zero_for_one,
+    )
+}
+
+/// Swap `swap_amount` on chain through a zap into a range the swap does not reach, so the pool
+/// is left with the state of the swap alone. Returns the swap output the user received.
+fn swap_through_zap(
+    env: &mut TestEnv,
+    pool: &TestPool,
+    positions: &[TestPosition],
+    zero_for_one: bool,
+    swap_amount: u64,
+    swap_tick_arrays: &[i32],
+) -> u64 {
+    // a range above the price takes token 0 only, one below it token 1 only
+    let (tick_lower_index, tick_upper_index) = if zero_for_one {
+        (6000, 6600)
+    } else {
+        (-6600, -6000)
+    };
+    let users = env.user_balances(pool);
+    let position = env
+        .zap_in_open_position(
+            pool,
+            tick_lower_index,
+            tick_upper_index,
+            zero_for_one,
+            swap_amount + DEPOSIT_AMOUNT,
+            swap_amount,
+            0,
+            swap_tick_arrays,
+        )
+        .unwrap();
+    let mut all_positions = positions.to_vec();
+    all_positions.push(position);
+    env.assert_solvent(pool, &all_positions);
+
+    let users_after = env.user_balances(pool);
+    if zero_for_one {
+        users_after.1 - users.1
+    } else {
+        users_after.0 - users.0
+    }
+}
+
+fn assert_pool_matches(env: &TestEnv, pool: &TestPool, quote: &SwapQuote) {
+    let pool_state = env.pool_state(&pool.address);
+    assert_eq!({ pool_state.sqrt_price_x64 }, quote.sqrt_price_x64_after);
+    assert_eq!({ pool_state.tick_current }, quote.tick_after);
+    assert_eq!({ pool_state.liquidity }, quote.liquidity_after);
+}
+
+#[test]
+fn quote_exact_input_matches_swap() {
+    let (mut env, pool, positions) = setup();
+    let start_indexes = tick_array_start_indexes(&positions);
+
+    let quote = quote_swap(&env, &pool, &start_indexes, SWAP_AMOUNT, None, true, true).unwrap();
+
+    // -300 and -900 are crossed, each in its own tick array, after the current tick array
+    assert_eq!(quote.amount_in, SWAP_AMOUNT);
+    assert!(quote.fee_amount > 0);
+    assert_eq!(quote.ticks_crossed, 2);
+    assert!(quote.tick_after < -900);
+    assert!(quote
+        .tick_array_start_indexes
+        .starts_with(&[0, -600, -1200]));
+    assert_eq!(quote.liquidity_after, LIQUIDITY);
+
+    let amount_out = swap_through_zap(
+        &mut env,
+        &pool,
+        &positions,
+        true,
+        SWAP_AMOUNT,
+        &quote.tick_array_start_indexes,
+    );
+    assert_eq!(amount_out, quote.amount_out);
+    assert_pool_matches(&env, &pool, &quote);
+}
+
+#[test]
+fn quote_exact_output_matches_swap() {
+    let (mut env, pool, positions) = setup();
+    let start_indexes = tick_array_start_indexes(&positions);
+
+    let quote = quote_swap(&env, &pool, &start_indexes, AMOUNT_OUT, None, false, false).unwrap();
+
+    // 300 and 900 are crossed, each in its own tick array
+    assert_eq!(quote.amount_out, AMOUNT_OUT);
+    assert_eq!(quote.ticks_crossed, 2);
+    assert!(quote.tick_after > 900);
+    assert!(quote.tick_array_start_indexes.starts_with(&[0, 600]));
+    assert_eq!(quote.liquidity_after, LIQUIDITY);
+
+    // the program only swaps exact inputs, the quoted input buys at least the quoted output
+    let exact_input = quote_swap(
+        &env,
+        &pool,
+        &start_indexes,
+        quote.amount_in,
+        None,
+        true,
+        false,
+    )
+    .unwrap();
+    let amount_out = swap_through_zap(
+        &mut env,
+        &pool,
+        &positions,
+        false,
+        quote.amount_in,
+        &exact_input.tick_array_start_indexes,
+    );
+    assert_eq!(amount_out, exact_input.amount_out);
+    assert!(amount_out >= AMOUNT_OUT);
+    assert_pool_matches(&env, &pool, &exact_input);
+    let sqrt_price_x64 = env.pool_state(&pool.address).sqrt_price_x64;
+    assert!(sqrt_price_x64 >= quote.sqrt_price_x64_after);
+    assert_eq!(quote.tick_after, exact_input.tick_after);
+}
+
+#[test]
+fn quote_stops_at_price_limit() {
+    let (env, pool, positions) = setup();
+    let start_indexes = tick_array_start_indexes(&positions);
+    let sqrt_price_limit_x64 = get_sqrt_price_at_tick(-600).unwrap();
+
+    let quote = quote_swap(
+        &env,
+        &pool,
+        &start_indexes,
+        SWAP_AMOUNT,
+        Some(sqrt_price_limit_x64),
+        true,
+        true,
+    )
+    .unwrap();
+
+    // only -300 is crossed before the limit
+    assert!(quote.amount_in < SWAP_AMOUNT);
+    assert_eq!(quote.sqrt_price_x64_after, sqrt_price_limit_x64);
+    assert_eq!(quote.ticks_crossed, 1);
+    assert_eq!(quote.liquidity_after, 2 * LIQUIDITY);
+
+    // a limit on the wrong side of the price or beyond the price range is rejected
+    let sqrt_price_x64 = env.pool_state(&pool.address).sqrt_price_x64;
+    for (sqrt_price_limit_x64, zero_for_one) in [
+        (sqrt_price_x64 + 1, true),
+        (sqrt_price_x64, true),
+        (MIN_SQRT_PRICE_X64, true),
+        (sqrt_price_x64 - 1, false),
+    ] {
+        assert!(matches!(
+            quote_swap(
+                &env,
+                &pool,
+                &start_indexes,
+                SWAP_AMOUNT,
+                Some(sqrt_price_limit_x64),
+                true,
+                zero_for_one,
+            ),
+            Err(ClientError::InvalidSqrtPriceLimit)
+        ));
+    }
+}
+
+#[test]
+fn quote_requires_tick_arrays_on_the_path() {
+    let (env, pool, positions) = setup();
+    let start_indexes: Vec<i32> = tick_array_start_indexes(&positions)
+        .into_iter()
+        .filter(|start_index| *start_index != -1200)
+        .collect();
+
+    // the swap crosses into the tick array of -900, which is not part of the snapshots
+    assert!(matches!(
+        quote_swap(&env, &pool, &start_indexes, SWAP_AMOUNT, None, true, true),
+        Err(ClientError::MissingTickArray(-1200))
+    ));
+    // a swap that stays in the tick arrays it has is quoted
+    assert!(quote_swap(&env, &pool, &start_indexes, SWAP_AMOUNT, None, true, false).is_ok());
+}
@@ -0,0 +1,366 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use clmm_client::instructions::PriceBand;
+use clmm_contract::{error::ErrorCode, state::TickArrayState};
+use clmm_math::{
+    liquidity_math::{get_delta_amounts_signed, get_liquidity_from_amounts},
+    tick_math::get_sqrt_price_at_tick,
+};
+use common::*;
+
+/// Liquidity of the wide position that keeps the pool price in place
+const POOL_LIQUIDITY: u128 = 1_000_000_000_000;
+const LIQUIDITY: u128 = 1_000_000_000;
+
+/// Pool at tick 0 with `POOL_LIQUIDITY` in [-6000, 6000) and `LIQUIDITY` in [-600, 600)
+fn setup() -> (TestEnv, TestPool, TestPosition, TestPosition) {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let pool = env.create_pool(
+        amm_config,
+        [MintKind::Spl, MintKind::Spl],
+        get_sqrt_price_at_tick(0).unwrap(),
+    );
+    let wide = env
+        .open_position(&pool, -6000, 6000, POOL_LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    let position = env
+        .open_position(&pool, -600, 600, LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    (env, pool, wide, position)
+}
+
+/// Amounts removing all liquidity of `position` returns, rounded down
+fn withdrawn_amounts(env: &TestEnv, pool: &TestPool, position: &TestPosition) -> (u64, u64) {
+    let pool_state = env.pool_state(&pool.address);
+    get_delta_amounts_signed(
+        pool_state.tick_current,
+        pool_state.sqrt_price_x64,
+        position.tick_lower_index,
+        position.tick_upper_index,
+        -(env.personal_position(&position.nft_mint).liquidity as i128),
+    )
+    .unwrap()
+}
+
+/// Liquidity `amount_0` and `amount_1` buy in a range at the current pool price
+fn liquidity_for_amounts(
+    env: &TestEnv,
+    pool: &TestPool,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    amount_0: u64,
+    amount_1: u64,
+) -> u128 {
+    get_liquidity_from_amounts(
+        env.pool_state(&pool.address).sqrt_price_x64,
+        get_sqrt_price_at_tick(tick_lower_index).unwrap(),
+        get_sqrt_price_at_tick(tick_upper_index).unwrap(),
+        amount_0,
+        amount_1,
+    )
+    .unwrap()
+}
+
+/// The old range of a moved position is left without liquidity
+fn assert_range_cleared(env: &TestEnv, pool: &TestPool, position: &TestPosition) {
+    let protocol_position = env.protocol_position(
+        &pool.address,
+        position.tick_lower_index,
+        position.tick_upper_index,
+    );
+    assert_eq!({ protocol_position.liquidity }, 0);
+    for tick in [position.tick_lower_index, position.tick_upper_index] {
+        assert!(!env.tick_state(&pool.address, tick).is_initialized());
+    }
+}
+
+#[test]
+fn reposition_keeps_position_nft() {
+    let (mut env, pool, wide, position) = setup();
+    let (withdrawn_0, withdrawn_1) = withdrawn_amounts(&env, &pool, &position);
+    let liquidity = liquidity_for_amounts(&env, &pool, -1200, 1200, withdrawn_0, withdrawn_1);
+    let users = env.user_balances(&pool);
+    let vaults = env.vault_balances(&pool);
+
+    env.reposition(
+        &pool,
+        &position,
+        None,
+        -1200,
+        1200,
+        true,
+        0,
+        liquidity,
+        PriceBand::default(),
+        &[],
+    )
+    .unwrap();
+
+    let personal_position = env.personal_position(&position.nft_mint);
+    assert_eq!(
+        (
+            personal_position.tick_lower_index,
+            personal_position.tick_upper_index
+        ),
+        (-1200, 1200)
+    );
+    assert_eq!(personal_position.liquidity, liquidity);
+    assert_range_cleared(&env, &pool, &position);
+    assert_eq!(
+        { env.tick_state(&pool.address, -1200).liquidity_gross },
+        liquidity
+    );
+    assert_eq!(
+        { env.pool_state(&pool.address).liquidity },
+        POOL_LIQUIDITY + liquidity
+    );
+
+    let moved = TestPosition {
+        tick_lower_index: -1200,
+        tick_upper_index: 1200,
+        ..position
+    };
+    env.assert_solvent(&pool, &[wide, moved]);
+
+    // the vaults only pay out what the new range does not take
+    let users_after = env.user_balances(&pool);
+    let vaults_after = env.vault_balances(&pool);
+    assert_eq!(users_after.0 - users.0, vaults.0 - vaults_after.0);
+    assert_eq!(users_after.1 - users.1, vaults.1 - vaults_after.1);
+    assert!(users_after.0 - users.0 <= withdrawn_0);
+    assert!(users_after.1 - users.1 <= withdrawn_1);
+}
+
+#[test]
+fn reposition_into_new_position() {
+    let (mut env, pool, wide, position) = setup();
+    // above the price the range holds token 0 only
+    let target = env
+        .open_position(&pool, 1200, 1800, LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    let (withdrawn_0, withdrawn_1) = withdrawn_amounts(&env, &pool, &position);
+    let liquidity = liquidity_for_amounts(&env, &pool, 1200, 1800, withdrawn_0, withdrawn_1);
+    let users = env.user_balances(&pool);
+
+    env.reposition(
+        &pool,
+        &position,
+        Some(&target),
+        1200,
+        1800,
+        true,
+        0,
+        liquidity,
+        PriceBand::default(),
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(env.personal_position(&position.nft_mint).liquidity, 0);
+    assert_eq!(
+        env.personal_position(&target.nft_mint).liquidity,
+        LIQUIDITY + liquidity
+    );
+    assert_range_cleared(&env, &pool, &position);
+    assert_eq!({ env.pool_state(&pool.address).liquidity }, POOL_LIQUIDITY);
+    env.assert_solvent(&pool, &[wide, position, target]);
+
+    // all token 1 goes back to the user
+    let users_after = env.user_balances(&pool);
+    assert_eq!(users_after.1 - users.1, withdrawn_1);
+    assert!(users_after.0 - users.0 < withdrawn_0);
+}
+
+#[test]
+fn reposition_into_fresh_nft() {
+    let (mut env, pool, wide, position) = setup();
+    let (withdrawn_0, withdrawn_1) = withdrawn_amounts(&env, &pool, &position);
+    let liquidity = liquidity_for_amounts(&env, &pool, -1200, 1200, withdrawn_0, withdrawn_1);
+
+    // no position of the new range exists before, the instruction opens it
+    let fresh = env
+        .reposition_into_fresh_nft(&pool, &position, -1200, 1200, true, 0, liquidity, &[])
+        .unwrap();
+
+    assert_eq!(env.token_balance(&fresh.nft_account), 1);
+    assert_eq!(env.token_balance(&position.nft_account), 1);
+    let personal_position = env.personal_position(&fresh.nft_mint);
+    assert_eq!(personal_position.nft_mint, fresh.nft_mint);
+    assert_eq!(personal_position.pool_id, pool.address);
+    assert_eq!(
+        (
+            personal_position.tick_lower_index,
+            personal_position.tick_upper_index
+        ),
+        (-1200, 1200)
+    );
+    assert_eq!(personal_position.liquidity, liquidity);
+    assert_eq!(env.personal_position(&position.nft_mint).liquidity, 0);
+    assert_range_cleared(&env, &pool, &position);
+    assert_eq!(
+        { env.pool_state(&pool.address).liquidity },
+        POOL_LIQUIDITY + liquidity
+    );
+    env.assert_solvent(&pool, &[wide, position, fresh]);
+}
+
+#[test]
+fn reposition_requires_new_position_nft() {
+    let (mut env, pool, _, position) = setup();
+    let target = env
+        .open_position(&pool, 1200, 1800, LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    let target = env.transfer_position_nft(&target, &Pubkey::new_unique());
+
+    // the signer holds the position but not the target
+    assert!(env
+        .reposition(
+            &pool,
+            &position,
+            Some(&target),
+            1200,
+            1800,
+            true,
+            0,
+            0,
+            PriceBand::default(),
+            &[],
+        )
+        .is_err());
+    // nor does an account of the target's mint it owns
+    let forged = TestPosition {
+        nft_account: env.create_token_account(&target.nft_mint, MintKind::Token2022),
+        ..target
+    };
+    let failed = env
+        .reposition(
+            &pool,
+            &position,
+            Some(&forged),
+            1200,
+            1800,
+            true,
+            0,
+            0,
+            PriceBand::default(),
+            &[],
+        )
+        .unwrap_err();
+    assert!(custom_error_code(&failed).is_some());
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        LIQUIDITY
+    );
+    assert_eq!(env.personal_position(&target.nft_mint).liquidity, LIQUIDITY);
+}
+
+#[test]
+fn reposition_swaps_surplus() {
+    let (mut env, pool, wide, position) = setup();
+    let (withdrawn_0, withdrawn_1) = withdrawn_amounts(&env, &pool, &position);
+    let liquidity_without_swap =
+        liquidity_for_amounts(&env, &pool, 600, 1200, withdrawn_0, withdrawn_1);
+    let pool_before = env.pool_state(&pool.address);
+    let users = env.user_balances(&pool);
+
+    // the new range above the price takes token 0 only, so all token 1 is swapped. With
+    // [-600, 600) removed first, the swap starts in the tick array of the wide position's upper
+    // tick
+    env.reposition(
+        &pool,
+        &position,
+        None,
+        600,
+        1200,
+        false,
+        withdrawn_1,
+        liquidity_without_swap,
+        PriceBand::default(),
+        &[TickArrayState::get_array_start_index(6000, TICK_SPACING)],
+    )
+    .unwrap();
+
+    let personal_position = env.personal_position(&position.nft_mint);
+    assert!(personal_position.liquidity > liquidity_without_swap);
+    let pool_state = env.pool_state(&pool.address);
+    assert!({ pool_state.sqrt_price_x64 } > { pool_before.sqrt_price_x64 });
+    assert!({ pool_state.protocol_fees_token_1 } > 0);
+    assert_eq!(env.user_balances(&pool).1, users.1);
+
+    let moved = TestPosition {
+        tick_lower_index: 600,
+        tick_upper_index: 1200,
+        ..position
+    };
+    env.assert_solvent(&pool, &[wide, moved]);
+}
+
+#[test]
+fn reposition_rejects_slippage() {
+    let (mut env, pool, _, position) = setup();
+    let (withdrawn_0, withdrawn_1) = withdrawn_amounts(&env, &pool, &position);
+    let liquidity = liquidity_for_amounts(&env, &pool, -1200, 1200, withdrawn_0, withdrawn_1);
+    let sqrt_price_x64 = env.pool_state(&pool.address).sqrt_price_x64;
+    let below = PriceBand {
+        sqrt_price_max_x64: Some(sqrt_price_x64 - 1),
+        ..PriceBand::default()
+    };
+    let expired = PriceBand {
+        deadline: Some(NOW - 1),
+        ..PriceBand::default()
+    };
+
+    for ((tick_lower_index, tick_upper_index), swap_amount, liquidity_min, price_band, error) in [
+        (
+            (-1200, 1200),
+            0,
+            liquidity + 1,
+            PriceBand::default(),
+            ErrorCode::PriceSlippageCheck,
+        ),
+        (
+            (-1200, 1200),
+            withdrawn_0 + 1,
+            0,
+            PriceBand::default(),
+            ErrorCode::InvalidRepositionSwapAmount,
+        ),
+        ((-1200, 1200), 0, 0, below, ErrorCode::PriceOutOfBand),
+        ((-1200, 1200), 0, 0, expired, ErrorCode::DeadlineExceeded),
+        (
+            (-600, 600),
+            0,
+            0,
+            PriceBand::default(),
+            ErrorCode::RepositionToSameRange,
+        ),
+    ] {
+        let failed = env
+            .reposition(
+                &pool,
+                &position,
+                None,
+                tick_lower_index,
+                tick_upper_index,
+                true,
+                swap_amount,
+                liquidity_min,
+                price_band,
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(custom_error_code(&failed), Some(error.into()));
+    }
+
+    let personal_position = env.personal_position(&position.nft_mint);
+    assert_eq!(personal_position.liquidity, LIQUIDITY);
+    assert_eq!(
+        (
+            personal_position.tick_lower_index,
+            personal_position.tick_upper_index
+        ),
+        (-600, 600)
+    );
+}
@@ -0,0 +1,427 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use clmm_client::{instructions::PriceBand, SwapQuoter, ZapQuote};
+use clmm_contract::{error::ErrorCode, state::TickArrayState};
+use clmm_math::tick_math::get_sqrt_price_at_tick;
+use common::*;
+
+/// Liquidity of the wide position the zaps swap against
+const POOL_LIQUIDITY: u128 = 1_000_000_000_000;
+const AMOUNT_IN: u64 = 1_000_000;
+
+const TRANSFER_FEE: MintKind = MintKind::Token2022TransferFee {
+    basis_points: 250,
+    maximum_fee: u64::MAX,
+};
+
+/// Liquidity of the positions opened directly in [-600, 600)
+const POSITION_LIQUIDITY: u128 = 1_000_000_000;
+
+/// Pool at tick 0 with `POOL_LIQUIDITY` in [-6000, 6000)
+fn setup(kinds: [MintKind; 2]) -> (TestEnv, TestPool, TestPosition) {
+    let mut env = TestEnv::new();
+    let amm_config = env.create_amm_config(0);
+    let pool = env.create_pool(amm_config, kinds, get_sqrt_price_at_tick(0).unwrap());
+    let wide = env
+        .open_position(&pool, -6000, 6000, POOL_LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    (env, pool, wide)
+}
+
+/// Quote a zap of `amount_in` into `[tick_lower_index, tick_upper_index)`, swapping through the
+/// tick arrays of `positions`
+fn quote_zap_in(
+    env: &TestEnv,
+    pool: &TestPool,
+    positions: &[TestPosition],
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    zero_for_one: bool,
+    amount_in: u64,
+) -> ZapQuote {
+    let pool_state = env.pool_state(&pool.address);
+    let amm_config = env.amm_config(&pool.amm_config);
+    let tickarray_bitmap_extension = env.tick_array_bitmap_extension(&pool.address);
+    let start_tick_indexes: BTreeSet<i32> = positions
+        .iter()
+        .flat_map(|position| [position.tick_lower_index, position.tick_upper_index])
+        .map(|tick| TickArrayState::get_array_start_index(tick, TICK_SPACING))
+        .collect();
+    let tick_arrays = start_tick_indexes
+        .into_iter()
+        .map(|start_tick_index| env.tick_array(&pool.address, start_tick_index));
+
+    let input_mint = if zero_for_one {
+        &pool.mint_0
+    } else {
+        &pool.mint_1
+    };
+    SwapQuoter::new(
+        &pool_state,
+        &amm_config,
+        Some(&tickarray_bitmap_extension),
+        tick_arrays,
+    )
+    .unwrap()
+    .quote_zap_in(
+        amount_in - env.transfer_fee(input_mint, amount_in),
+        zero_for_one,
+        tick_lower_index,
+        tick_upper_index,
+    )
+    .unwrap()
+}
+
+fn balances(env: &TestEnv, pool: &TestPool) -> [u64; 4] {
+    let pool_state = env.pool_state(&pool.address);
+    [
+        env.token_balance(&pool.mint_0.user_account),
+        env.token_balance(&pool.mint_1.user_account),
+        env.token_balance(&pool_state.token_vault_0),
+        env.token_balance(&pool_state.token_vault_1),
+    ]
+}
+
+#[test]
+fn zap_in_open_position_from_either_token() {
+    for kinds in [
+        [MintKind::Spl, MintKind::Spl],
+        [MintKind::Spl, TRANSFER_FEE],
+    ] {
+        for zero_for_one in [true, false] {
+            let (mut env, pool, wide) = setup(kinds);
+            let quote = quote_zap_in(&env, &pool, &[wide], -600, 600, zero_for_one, AMOUNT_IN);
+            let swap = quote.swap.clone().unwrap();
+            assert!(quote.swap_amount > 0 && quote.swap_amount < AMOUNT_IN);
+            assert!(quote.amount_0 > 0 && quote.amount_1 > 0);
+            let [user_0, user_1, vault_0, vault_1] = balances(&env, &pool);
+
+            let position = env
+                .zap_in_open_position(
+                    &pool,
+                    -600,
+                    600,
+                    zero_for_one,
+                    AMOUNT_IN,
+                    quote.swap_amount,
+                    quote.liquidity,
+                    &swap.tick_array_start_indexes,
+                )
+                .unwrap();
+            env.assert_solvent(&pool, &[wide, position]);
+
+            // the program swaps the quoted amount and adds the quoted liquidity
+            assert_eq!(
+                env.personal_position(&position.nft_mint).liquidity,
+                quote.liquidity
+            );
+            let pool_state = env.pool_state(&pool.address);
+            assert_eq!({ pool_state.sqrt_price_x64 }, swap.sqrt_price_x64_after);
+            assert_eq!({ pool_state.tick_current }, swap.tick_after);
+            assert_eq!(
+                { pool_state.liquidity },
+                swap.liquidity_after + quote.liquidity
+            );
+
+            // the pool keeps the swap input and the deposit, and returns the swap output the
+            // position does not take
+            let [user_0_after, user_1_after, vault_0_after, vault_1_after] = balances(&env, &pool);
+            let (input_mint, output_mint, paid, received, deposit_in, deposit_out) = if zero_for_one
+            {
+                assert_eq!(vault_0_after - vault_0, swap.amount_in + quote.amount_0);
+                assert_eq!(vault_1 - vault_1_after, swap.amount_out - quote.amount_1);
+                assert!({ pool_state.protocol_fees_token_0 } > 0);
+                (
+                    &pool.mint_0,
+                    &pool.mint_1,
+                    user_0 - user_0_after,
+                    user_1_after - user_1,
+                    quote.amount_0,
+                    quote.amount_1,
+                )
+            } else {
+                assert_eq!(vault_1_after - vault_1, swap.amount_in + quote.amount_1);
+                assert_eq!(vault_0 - vault_0_after, swap.amount_out - quote.amount_0);
+                assert!({ pool_state.protocol_fees_token_1 } > 0);
+                (
+                    &pool.mint_1,
+                    &pool.mint_0,
+                    user_1 - user_1_after,
+                    user_0_after - user_0,
+                    quote.amount_1,
+                    quote.amount_0,
+                )
+            };
+            assert!(paid <= AMOUNT_IN);
+            assert_eq!(
+                paid - env.transfer_fee(input_mint, paid),
+                swap.amount_in + deposit_in
+            );
+            let refund = swap.amount_out - deposit_out;
+            assert_eq!(received, refund - env.transfer_fee(output_mint, refund));
+        }
+    }
+}
+
+#[test]
+fn zap_in_adds_to_a_position() {
+    let (mut env, pool, wide) = setup([MintKind::Spl, MintKind::Spl]);
+    let position = env
+        .open_position(&pool, -600, 600, POSITION_LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    let quote = quote_zap_in(&env, &pool, &[wide, position], -600, 600, false, AMOUNT_IN);
+    let swap = quote.swap.clone().unwrap();
+
+    env.zap_in(
+        &pool,
+        &position,
+        false,
+        AMOUNT_IN,
+        quote.swap_amount,
+        quote.liquidity,
+        &swap.tick_array_start_indexes,
+    )
+    .unwrap();
+    env.assert_solvent(&pool, &[wide, position]);
+
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        POSITION_LIQUIDITY + quote.liquidity
+    );
+    assert_eq!(
+        { env.pool_state(&pool.address).sqrt_price_x64 },
+        swap.sqrt_price_x64_after
+    );
+}
+
+#[test]
+fn zap_in_out_of_range_takes_input_only() {
+    let (mut env, pool, wide) = setup([MintKind::Spl, MintKind::Spl]);
+    // above the price the range holds token 0 only
+    let quote = quote_zap_in(&env, &pool, &[wide], 600, 1200, true, AMOUNT_IN);
+    assert_eq!(quote.swap_amount, 0);
+    assert_eq!(quote.swap, None);
+    let pool_before = env.pool_state(&pool.address);
+
+    let position = env
+        .zap_in_open_position(
+            &pool,
+            600,
+            1200,
+            true,
+            AMOUNT_IN,
+            quote.swap_amount,
+            quote.liquidity,
+            &[],
+        )
+        .unwrap();
+    env.assert_solvent(&pool, &[wide, position]);
+
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        quote.liquidity
+    );
+    let pool_state = env.pool_state(&pool.address);
+    assert_eq!({ pool_state.sqrt_price_x64 }, {
+        pool_before.sqrt_price_x64
+    });
+    assert_eq!({ pool_state.liquidity }, { pool_before.liquidity });
+}
+
+#[test]
+fn zap_in_rejects_slippage() {
+    let (mut env, pool, wide) = setup([MintKind::Spl, MintKind::Spl]);
+    let quote = quote_zap_in(&env, &pool, &[wide], -600, 600, true, AMOUNT_IN);
+    let swap_tick_arrays = quote.swap.clone().unwrap().tick_array_start_indexes;
+
+    let failed = env
+        .zap_in_open_position(
+            &pool,
+            -600,
+            600,
+            true,
+            AMOUNT_IN,
+            quote.swap_amount,
+            quote.liquidity + 1,
+            &swap_tick_arrays,
+        )
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PriceSlippageCheck.into())
+    );
+
+    // the swap cannot take more than the input
+    let failed = env
+        .zap_in_open_position(
+            &pool,
+            -600,
+            600,
+            true,
+            AMOUNT_IN,
+            AMOUNT_IN + 1,
+            0,
+            &swap_tick_arrays,
+        )
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::InvalidZapSwapAmount.into())
+    );
+
+    // without its tick arrays the swap cannot run
+    let failed = env
+        .zap_in_open_position(&pool, -600, 600, true, AMOUNT_IN, quote.swap_amount, 0, &[])
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::NotEnoughTickArrayAccount.into())
+    );
+}
+
+#[test]
+fn zap_in_rejects_stale_quote() {
+    let (mut env, pool, wide) = setup([MintKind::Spl, MintKind::Spl]);
+    let quote = quote_zap_in(&env, &pool, &[wide], -600, 600, true, AMOUNT_IN);
+
+    // a larger zap in the same direction lands first and moves the price down
+    let swap_tick_arrays = env.swap_tick_arrays(&pool, true);
+    env.zap_in_open_position(
+        &pool,
+        -600,
+        600,
+        true,
+        AMOUNT_IN * 10_000,
+        AMOUNT_IN * 5_000,
+        0,
+        &swap_tick_arrays,
+    )
+    .unwrap();
+    assert!(env.pool_state(&pool.address).tick_current < quote.swap.unwrap().tick_after);
+
+    // the quoted swap no longer pays for the quoted liquidity
+    let swap_tick_arrays = env.swap_tick_arrays(&pool, true);
+    let failed = env
+        .zap_in_open_position(
+            &pool,
+            -600,
+            600,
+            true,
+            AMOUNT_IN,
+            quote.swap_amount,
+            quote.liquidity,
+            &swap_tick_arrays,
+        )
+        .unwrap_err();
+    assert_eq!(
+        custom_error_code(&failed),
+        Some(ErrorCode::PriceSlippageCheck.into())
+    );
+}
+
+#[test]
+fn zap_in_rejects_price_outside_band() {
+    let (mut env, pool, wide) = setup([MintKind::Spl, MintKind::Spl]);
+    let position = env
+        .open_position(&pool, -600, 600, POSITION_LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    let quote = quote_zap_in(&env, &pool, &[wide, position], -600, 600, true, AMOUNT_IN);
+    let swap_tick_arrays = quote.swap.unwrap().tick_array_start_indexes;
+    let sqrt_price_x64 = env.pool_state(&pool.address).sqrt_price_x64;
+    let below = PriceBand {
+        sqrt_price_max_x64: Some(sqrt_price_x64 - 1),
+        ..PriceBand::default()
+    };
+    let expired = PriceBand {
+        deadline: Some(NOW - 1),
+        ..PriceBand::default()
+    };
+    // the band bounds the price before the swap
+    let around = PriceBand {
+        sqrt_price_min_x64: Some(sqrt_price_x64),
+        sqrt_price_max_x64: Some(sqrt_price_x64),
+        deadline: Some(NOW),
+    };
+
+    for (price_band, error) in [
+        (below, ErrorCode::PriceOutOfBand),
+        (expired, ErrorCode::DeadlineExceeded),
+    ] {
+        let failed = env
+            .zap_in_open_position_in_band(
+                &pool,
+                -600,
+                600,
+                true,
+                AMOUNT_IN,
+                quote.swap_amount,
+                0,
+                price_band,
+                &swap_tick_arrays,
+            )
+            .unwrap_err();
+        assert_eq!(custom_error_code(&failed), Some(error.into()));
+
+        let failed = env
+            .zap_in_in_band(
+                &pool,
+                &position,
+                true,
+                AMOUNT_IN,
+                quote.swap_amount,
+                0,
+                price_band,
+                &swap_tick_arrays,
+            )
+            .unwrap_err();
+        assert_eq!(custom_error_code(&failed), Some(error.into()));
+    }
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        POSITION_LIQUIDITY
+    );
+
+    env.zap_in_in_band(
+        &pool,
+        &position,
+        true,
+        AMOUNT_IN,
+        quote.swap_amount,
+        quote.liquidity,
+        around,
+        &swap_tick_arrays,
+    )
+    .unwrap();
+}
+
+#[test]
+fn zap_in_requires_position_nft() {
+    let (mut env, pool, wide) = setup([MintKind::Spl, MintKind::Spl]);
+    let position = env
+        .open_position(&pool, -600, 600, POSITION_LIQUIDITY, u64::MAX, u64::MAX)
+        .unwrap();
+    let quote = quote_zap_in(&env, &pool, &[wide, position], -600, 600, true, AMOUNT_IN);
+    let other_account = env.create_token_account(&position.nft_mint, MintKind::Token2022);
+    let forged = TestPosition {
+        nft_account: other_account,
+        ..position
+    };
+
+    assert!(env
+        .zap_in(
+            &pool,
+            &forged,
+            true,
+            AMOUNT_IN,
+            quote.swap_amount,
+            0,
+            &quote.swap.unwrap().tick_array_start_indexes,
+        )
+        .is_err());
+    assert_eq!(
+        env.personal_position(&position.nft_mint).liquidity,
+        POSITION_LIQUIDITY
+    );
+}